@@ -16,7 +16,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         let mut incoming = listener.incoming()
             .log_warnings(|e| {
                 eprintln!("Accept error: {}. Paused listener for 0.5s. {}",
-                          e, error_hint(&e))
+                          e, error_hint(e))
             })
             .handle_errors(Duration::from_millis(500))
             .backpressure_wrapper(bp);
@@ -10,7 +10,7 @@ use async_listen::{ListenExt, ByteStream, backpressure, error_hint};
 
 
 fn main() -> Result<(), Box<dyn Error>> {
-    let (metrics, bp) = backpressure::new(10);
+    let (metrics, _) = backpressure::new(10);
     let metrics1 = metrics.clone();
     task::spawn(async move {
         loop {
@@ -24,10 +24,10 @@ fn main() -> Result<(), Box<dyn Error>> {
         let mut incoming = listener.incoming()
             .log_warnings(|e| {
                 eprintln!("Accept error: {}. Paused listener for 0.5s. {}",
-                          e, error_hint(&e))
+                          e, error_hint(e))
             })
             .handle_errors(Duration::from_millis(500))
-            .backpressure_wrapper(bp);
+            .backpressure_wrapper_with(&metrics);
         while let Some(stream) = incoming.next().await {
             task::spawn(connection_loop(stream, metrics.clone()));
         }
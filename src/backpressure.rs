@@ -5,50 +5,103 @@
 //! * [`backpressure`](../trait.ListenExt.html#method.backpressure)
 //! * [`apply_backpressure`](../trait.ListenExt.html#method.apply_backpressure)
 //! * [`backpressure_wrapper`](../trait.ListenExt.html#method.backpressure_wrapper)
+//! * [`backpressure_wrap`](../trait.ListenExt.html#method.backpressure_wrap)
+//! * [`reject_over_limit`](../trait.ListenExt.html#method.reject_over_limit)
 //!
 //! Also take a look at [`backpressure::new`](fn.new.html) for the low-level
 //! interface.
 //!
+//! For adjusting the limit dynamically based on the system load see
+//! [`LoadLimiter`](struct.LoadLimiter.html).
+//!
+use std::collections::HashMap;
 use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Mutex, TryLockError};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
 use async_std::stream::Stream;
-use async_std::future::Future;
+use async_std::future::{self, Future, TimeoutError};
 use async_std::task::{Poll, Context, Waker};
+use pin_project_lite::pin_project;
 
 use crate::byte_stream::ByteStream;
+use crate::histogram::Recorder;
+
+pub use crate::histogram::DurationHistogram;
+pub use crate::load_limit::{LoadLimiter, LoadSource};
+pub use crate::reject::RejectOverLimit;
 
 
 struct Inner {
     active: AtomicUsize,
     limit: AtomicUsize,
-    task: Mutex<Option<Waker>>,
+    tasks: Mutex<Vec<Waker>>,
+    tags: Mutex<HashMap<&'static str, usize>>,
+    pauses: Mutex<Pauses>,
+    drain: Mutex<Vec<Waker>>,
+    durations: Recorder,
 }
 
-/// A stream adapter that applies backpressure
-///
-/// See
-/// [`ListenExt::backpressure`](../trait.ListenExt.html#method.backpressure)
-/// for more info.
-pub struct BackpressureToken<S>(Backpressure<S>);
+#[derive(Default)]
+struct Pauses {
+    count: usize,
+    total: Duration,
+    since: Option<Instant>,
+    receivers: usize,
+}
 
-/// A stream adapter that applies backpressure and yields ByteStream
-///
-/// See
-/// [`ListenExt::backpressure_wrapper`](../trait.ListenExt.html#method.backpressure_wrapper)
-/// for more info.
-pub struct BackpressureWrapper<S>(Backpressure<S>);
+pin_project! {
+    /// A stream adapter that applies backpressure
+    ///
+    /// See
+    /// [`ListenExt::backpressure`](../trait.ListenExt.html#method.backpressure)
+    /// for more info.
+    pub struct BackpressureToken<S> {
+        #[pin]
+        inner: Backpressure<S>,
+    }
+}
 
-/// A stream adapter that applies backpressure and yields a token
-///
-/// See
-/// [`ListenExt::apply_backpressure`](../trait.ListenExt.html#method.apply_backpressure)
-/// for more info.
-pub struct Backpressure<S> {
-    stream: S,
-    backpressure: Receiver,
+pin_project! {
+    /// A stream adapter that applies backpressure and yields ByteStream
+    ///
+    /// See
+    /// [`ListenExt::backpressure_wrapper`](../trait.ListenExt.html#method.backpressure_wrapper)
+    /// for more info.
+    pub struct BackpressureWrapper<S> {
+        #[pin]
+        inner: Backpressure<S>,
+    }
+}
+
+pin_project! {
+    /// A stream adapter that applies backpressure and yields a user type
+    ///
+    /// See
+    /// [`ListenExt::backpressure_wrap`](../trait.ListenExt.html#method.backpressure_wrap)
+    /// for more info.
+    pub struct BackpressureWrap<S, W> {
+        #[pin]
+        inner: Backpressure<S>,
+        marker: PhantomData<fn() -> W>,
+    }
+}
+
+pin_project! {
+    /// A stream adapter that applies backpressure and yields a token
+    ///
+    /// See
+    /// [`ListenExt::apply_backpressure`](../trait.ListenExt.html#method.apply_backpressure)
+    /// for more info.
+    pub struct Backpressure<S> {
+        #[pin]
+        stream: S,
+        backpressure: Receiver,
+    }
 }
 
 /// The throttler of a stream
@@ -56,6 +109,7 @@ pub struct Backpressure<S> {
 /// See [`new`](fn.new.html) for more details
 pub struct Receiver {
     inner: Arc<Inner>,
+    paused: bool,
 }
 
 /// Future that resolves when there is less that limit tokens alive
@@ -63,6 +117,13 @@ pub struct HasCapacity<'a> {
     recv: &'a mut Receiver,
 }
 
+/// Future that resolves when all tokens are dropped
+///
+/// See [`Sender::drain`](struct.Sender.html#method.drain).
+pub struct Drain {
+    inner: Arc<Inner>,
+}
+
 /// The handle that controls backpressure
 ///
 /// It can be used to create tokens, changing limit and getting metrics.
@@ -77,16 +138,27 @@ pub struct Sender {
 ///
 /// # Notes on Cloning
 ///
-/// After cloning a `Token`, *both* clones have to be dropped to make
-/// backpressure slot available again.
-#[derive(Clone)]
+/// Cloning a `Token` acquires one more unit of resource, so after cloning
+/// *both* clones have to be dropped to make backpressure slots available
+/// again. Use [`share`](#method.share) if multiple tasks should hold onto
+/// the same single unit.
 pub struct Token {
     inner: Arc<Inner>,
+    tag: Option<&'static str>,
+    created: Instant,
 }
 
-impl<S: Unpin> Unpin for Backpressure<S> {}
-impl<S: Unpin> Unpin for BackpressureToken<S> {}
-impl<S: Unpin> Unpin for BackpressureWrapper<S> {}
+/// A reference-counted token that can be shared between tasks
+///
+/// Unlike cloning a [`Token`](struct.Token.html), cloning a `SharedToken`
+/// doesn't acquire new resource units: the single unit held is released
+/// when the last clone is dropped.
+///
+/// Created by [`Token::share`](struct.Token.html#method.share).
+#[derive(Clone)]
+pub struct SharedToken {
+    token: Arc<Token>,
+}
 
 impl Sender {
     /// Acquire a backpressure token
@@ -95,11 +167,65 @@ impl Sender {
     ///
     /// *Note:* You can always acquire a token, even if capacity limit reached.
     pub fn token(&self) -> Token {
-        self.inner.active.fetch_add(1, Ordering::SeqCst);
-        Token {
-            inner: self.inner.clone(),
+        new_token(&self.inner, None)
+    }
+
+    /// Acquire a backpressure token marked with a tag
+    ///
+    /// The token counts against the limit exactly like the one returned by
+    /// [`token`](#method.token). In addition, number of active tokens is
+    /// tracked for each tag, so metrics can show how capacity is split
+    /// between different kinds of connections (see
+    /// [`get_active_tagged`](#method.get_active_tagged)).
+    ///
+    /// Tags are expected to be a small fixed set of names.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use async_listen::backpressure;
+    ///
+    /// let (tx, _rx) = backpressure::new(10);
+    /// let _ws = tx.token_tagged("ws");
+    /// let _http = tx.token_tagged("http");
+    /// let _http2 = tx.token_tagged("http");
+    /// assert_eq!(tx.get_active_tokens(), 3);
+    /// assert_eq!(tx.get_active_tagged("ws"), 1);
+    /// assert_eq!(tx.get_active_tagged("http"), 2);
+    /// ```
+    pub fn token_tagged(&self, tag: &'static str) -> Token {
+        new_token(&self.inner, Some(tag))
+    }
+    /// Acquire a backpressure token only if the limit isn't reached yet
+    ///
+    /// Unlike [`token`](#method.token), which always succeeds, this returns
+    /// `None` if there are already `limit` tokens alive.
+    pub fn try_token(&self) -> Option<Token> {
+        let limit = self.inner.limit.load(Ordering::SeqCst);
+        let mut active = self.inner.active.load(Ordering::SeqCst);
+        loop {
+            if active >= limit {
+                return None;
+            }
+            match self.inner.active.compare_exchange_weak(active, active + 1,
+                Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => break,
+                Err(value) => active = value,
+            }
         }
+        Some(Token {
+            inner: self.inner.clone(),
+            tag: None,
+            created: Instant::now(),
+        })
     }
+
+    /// Returns current limit for the number of connections
+    pub fn get_limit(&self) -> usize {
+        self.inner.limit.load(Ordering::SeqCst)
+    }
+
     /// Change the limit for the number of connections
     ///
     /// If limit is increased it's applied immediately. If limit is lowered,
@@ -109,19 +235,7 @@ impl Sender {
     pub fn set_limit(&self, new_limit: usize) {
         let old_limit = self.inner.limit.swap(new_limit, Ordering::SeqCst);
         if old_limit < new_limit {
-            match self.inner.task.try_lock() {
-                Ok(mut guard) => {
-                    guard.take().map(|w| w.wake());
-                }
-                Err(TryLockError::WouldBlock) => {
-                    // This means either another token is currently waking
-                    // up a Receiver. Or Receiver is currently running.
-                    // Receiver will recheck values after releasing the Mutex.
-                }
-                Err(TryLockError::Poisoned(_)) => {
-                    unreachable!("backpressure lock should never be poisoned");
-                }
-            }
+            wake_receivers(&self.inner);
         }
     }
 
@@ -136,15 +250,140 @@ impl Sender {
     pub fn get_active_tokens(&self) -> usize {
         self.inner.active.load(Ordering::Relaxed)
     }
+
+    /// Returns the number of currently active tokens with the specified tag
+    ///
+    /// See [`token_tagged`](#method.token_tagged).
+    pub fn get_active_tagged(&self, tag: &str) -> usize {
+        lock_tags(&self.inner).get(tag).copied().unwrap_or(0)
+    }
+
+    /// Returns the number of currently active tokens for every tag
+    ///
+    /// Tags that have no active tokens are omitted. Untagged tokens are not
+    /// included either, but can be computed by subtracting the sum of these
+    /// values from [`get_active_tokens`](#method.get_active_tokens).
+    pub fn get_tag_counts(&self) -> HashMap<&'static str, usize> {
+        lock_tags(&self.inner).clone()
+    }
+
+    /// Returns the number of times the stream was paused due to the limit
+    ///
+    /// The counter is incremented each time the receiver starts waiting for
+    /// tokens to be released. Along with
+    /// [`get_paused_time`](#method.get_paused_time) this is useful for
+    /// capacity planning.
+    pub fn get_pause_count(&self) -> usize {
+        lock_pauses(&self.inner).count
+    }
+
+    /// Returns the total time the stream was paused due to the limit
+    ///
+    /// This includes the currently ongoing pause, if any.
+    pub fn get_paused_time(&self) -> Duration {
+        let pauses = lock_pauses(&self.inner);
+        match pauses.since {
+            Some(since) => pauses.total + since.elapsed(),
+            None => pauses.total,
+        }
+    }
+
+    /// Returns true if the stream is currently paused due to the limit
+    pub fn is_paused(&self) -> bool {
+        lock_pauses(&self.inner).since.is_some()
+    }
+
+    /// Returns histogram of lifetimes of all dropped tokens
+    ///
+    /// As tokens are usually held for the whole connection, this is
+    /// a histogram of connection durations. Percentiles of connection
+    /// lifetimes are useful for tuning both the limit and idle timeouts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use async_listen::backpressure;
+    ///
+    /// let (tx, _rx) = backpressure::new(10);
+    /// drop(tx.token());
+    /// let hist = tx.get_duration_histogram();
+    /// assert_eq!(hist.count(), 1);
+    /// println!("Median connection lifetime: {:?}", hist.quantile(0.5));
+    /// ```
+    pub fn get_duration_histogram(&self) -> DurationHistogram {
+        self.inner.durations.snapshot()
+    }
+
+    /// Return future which resolves when there are no active tokens
+    ///
+    /// This is useful for graceful shutdown: stop accepting connections
+    /// (i.e. using [`until`](../trait.ListenExt.html#method.until)) then
+    /// wait for all connections to finish. Tokens created after the future
+    /// is resolved are not tracked.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// # let shutdown = async {};
+    /// #
+    /// use async_listen::{ListenExt, ByteStream, backpressure};
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let (tx, _) = backpressure::new(10);
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .until(shutdown)
+    ///     .backpressure_wrapper_with(&tx);
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// drop(incoming);  // close listening socket
+    /// if tx.drain_timeout(Duration::from_secs(30)).await.is_err() {
+    ///     eprintln!("{} connections still active", tx.get_active_tokens());
+    /// }
+    /// # async fn connection_loop(_stream: ByteStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    pub fn drain(&self) -> Drain {
+        Drain { inner: self.inner.clone() }
+    }
+
+    /// Wait until there are no active tokens, but no longer than `timeout`
+    ///
+    /// Returns error if timeout has elapsed before all tokens are dropped.
+    /// See [`drain`](#method.drain) for more info.
+    pub async fn drain_timeout(&self, timeout: Duration)
+        -> Result<(), TimeoutError>
+    {
+        future::timeout(timeout, self.drain()).await
+    }
+
+    /// Create a receiver bound to this sender
+    ///
+    /// Any number of receivers can share the limit, all paused receivers
+    /// are woken up when capacity is available. The receiver can be passed
+    /// to [`backpressure`](../trait.ListenExt.html#method.backpressure) or
+    /// [`backpressure_wrapper`](../trait.ListenExt.html#method.backpressure_wrapper).
+    pub fn receiver(&self) -> Receiver {
+        Receiver {
+            inner: self.inner.clone(),
+            paused: false,
+        }
+    }
 }
 
 impl Receiver {
     /// Handy to create token in Backpressure wrapper
     fn token(&self) -> Token {
-        self.inner.active.fetch_add(1, Ordering::SeqCst);
-        Token {
-            inner: self.inner.clone(),
-        }
+        new_token(&self.inner, None)
     }
 
     /// Return future which resolves when the current number active of tokens
@@ -152,35 +391,51 @@ impl Receiver {
     ///
     /// If you create tokens in different task than the task that waits
     /// on `HasCapacity` there is a race condition.
-    pub fn has_capacity(&mut self) -> HasCapacity {
+    pub fn has_capacity(&mut self) -> HasCapacity<'_> {
         HasCapacity { recv: self }
     }
 
     fn poll(&mut self, cx: &mut Context) -> Poll<()> {
-        let limit = self.inner.limit.load(Ordering::Acquire);
-        loop {
-            let active = self.inner.active.load(Ordering::Acquire);
-            if active < limit {
-                return Poll::Ready(());
-            }
-            match self.inner.task.try_lock() {
-                Ok(mut guard) => {
-                    *guard = Some(cx.waker().clone());
-                    break;
-                }
-                Err(TryLockError::WouldBlock) => {
-                    // This means either another token is currently waking
-                    // up this receiver, retry
-                    //
-                    // Note: this looks like a busyloop, but we don't have
-                    // anything long/slow behind the mutex. And it's only
-                    // executed when limit is reached.
-                    continue;
-                }
-                Err(TryLockError::Poisoned(_)) => {
-                    unreachable!("backpressure lock should never be poisoned");
+        let result = self.poll_limit(cx);
+        match result {
+            Poll::Pending if !self.paused => {
+                self.paused = true;
+                let mut pauses = lock_pauses(&self.inner);
+                pauses.count += 1;
+                pauses.receivers += 1;
+                if pauses.since.is_none() {
+                    pauses.since = Some(Instant::now());
                 }
             }
+            Poll::Ready(()) if self.paused => self.finish_pause(),
+            _ => {}
+        }
+        result
+    }
+
+    fn finish_pause(&mut self) {
+        self.paused = false;
+        let mut pauses = lock_pauses(&self.inner);
+        pauses.receivers -= 1;
+        // pause lasts while at least one receiver is waiting
+        if pauses.receivers == 0 {
+            if let Some(since) = pauses.since.take() {
+                pauses.total += since.elapsed();
+            }
+        }
+    }
+
+    fn poll_limit(&mut self, cx: &mut Context) -> Poll<()> {
+        let limit = self.inner.limit.load(Ordering::Acquire);
+        let active = self.inner.active.load(Ordering::Acquire);
+        if active < limit {
+            return Poll::Ready(());
+        }
+        {
+            let mut tasks = lock_tasks(&self.inner);
+            if !tasks.iter().any(|w| w.will_wake(cx.waker())) {
+                tasks.push(cx.waker().clone());
+            }
         }
         // Reread the limit after lock is unlocked because
         // token Drop relies on that
@@ -193,26 +448,115 @@ impl Receiver {
     }
 }
 
+impl Token {
+    /// Convert token into a cloneable handle that holds the same unit
+    ///
+    /// This is useful when connection is handled by multiple tasks (i.e.
+    /// reader, writer and heartbeat task), and backpressure slot should
+    /// only be freed when all of them are finished.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use async_listen::backpressure;
+    ///
+    /// let (tx, _rx) = backpressure::new(10);
+    /// let reader = tx.token().share();
+    /// let writer = reader.clone();
+    /// assert_eq!(tx.get_active_tokens(), 1);
+    /// drop(reader);
+    /// assert_eq!(tx.get_active_tokens(), 1);
+    /// drop(writer);
+    /// assert_eq!(tx.get_active_tokens(), 0);
+    /// ```
+    pub fn share(self) -> SharedToken {
+        SharedToken { token: Arc::new(self) }
+    }
+}
+
+impl SharedToken {
+    /// Acquire a new token from the same backpressure object
+    pub(crate) fn acquire_another(&self) -> Token {
+        (*self.token).clone()
+    }
+}
+
+fn new_token(inner: &Arc<Inner>, tag: Option<&'static str>) -> Token {
+    inner.active.fetch_add(1, Ordering::SeqCst);
+    if let Some(tag) = tag {
+        *lock_tags(inner).entry(tag).or_insert(0) += 1;
+    }
+    Token {
+        inner: inner.clone(),
+        tag,
+        created: Instant::now(),
+    }
+}
+
+fn lock_tags(inner: &Inner) -> MutexGuard<'_, HashMap<&'static str, usize>> {
+    inner.tags.lock().expect("backpressure tags lock is not poisoned")
+}
+
+fn lock_tasks(inner: &Inner) -> MutexGuard<'_, Vec<Waker>> {
+    inner.tasks.lock().expect("backpressure lock is not poisoned")
+}
+
+fn lock_pauses(inner: &Inner) -> MutexGuard<'_, Pauses> {
+    inner.pauses.lock().expect("backpressure pauses lock is not poisoned")
+}
+
+fn lock_drain(inner: &Inner) -> MutexGuard<'_, Vec<Waker>> {
+    inner.drain.lock().expect("backpressure drain lock is not poisoned")
+}
+
+impl Clone for Token {
+    fn clone(&self) -> Token {
+        new_token(&self.inner, self.tag)
+    }
+}
+
 impl Drop for Token {
     fn drop(&mut self) {
+        self.inner.durations.record(self.created.elapsed());
+        if let Some(tag) = self.tag {
+            let mut tags = lock_tags(&self.inner);
+            if let Some(count) = tags.get_mut(tag) {
+                *count -= 1;
+                if *count == 0 {
+                    tags.remove(tag);
+                }
+            }
+        }
         // TODO(tailhook) we could use Acquire for old_ref,
         // but not sure how safe is it to compare it with a limit
         let old_ref = self.inner.active.fetch_sub(1, Ordering::SeqCst);
+        if old_ref == 1 {
+            for waker in lock_drain(&self.inner).drain(..) {
+                waker.wake();
+            }
+        }
         let limit = self.inner.limit.load(Ordering::SeqCst);
         if old_ref == limit {
-            match self.inner.task.try_lock() {
-                Ok(mut guard) => {
-                    guard.take().map(|w| w.wake());
-                }
-                Err(TryLockError::WouldBlock) => {
-                    // This means either another token is currently waking
-                    // up a Receiver. Or Receiver is currently running.
-                    // Receiver will recheck values after releasing the Mutex.
-                }
-                Err(TryLockError::Poisoned(_)) => {
-                    unreachable!("backpressure lock should never be poisoned");
-                }
-            }
+            wake_receivers(&self.inner);
+        }
+    }
+}
+
+fn wake_receivers(inner: &Inner) {
+    // Wakers are called outside of the lock. Receivers register under the
+    // lock and recheck the counter after, so wakeups are never lost.
+    let wakers = mem::take(&mut *lock_tasks(inner));
+    for waker in wakers {
+        waker.wake();
+    }
+}
+
+impl Drop for Receiver {
+    fn drop(&mut self) {
+        // stream may be dropped while waiting for capacity, i.e. stopped
+        // by `until()` when the limit is reached
+        if self.paused {
+            self.finish_pause();
         }
     }
 }
@@ -221,24 +565,31 @@ impl<S> BackpressureToken<S> {
     pub(crate) fn new(stream: S, backpressure: Receiver)
         -> BackpressureToken<S>
     {
-        BackpressureToken(Backpressure::new(stream, backpressure))
+        BackpressureToken { inner: Backpressure::new(stream, backpressure) }
     }
 
     /// Acquires a reference to the underlying stream that this adapter is
     /// pulling from.
     pub fn get_ref(&self) -> &S {
-        self.0.get_ref()
+        self.inner.get_ref()
     }
 
     /// Acquires a mutable reference to the underlying stream that this
     /// adapter is pulling from.
     pub fn get_mut(&mut self) -> &mut S {
-        self.0.get_mut()
+        self.inner.get_mut()
     }
 
     /// Consumes this adapter, returning the underlying stream.
     pub fn into_inner(self) -> S {
-        self.0.into_inner()
+        self.inner.into_inner()
+    }
+
+    /// Replaces the backpressure receiver, returning the old one
+    ///
+    /// See [`Backpressure::set_receiver`](struct.Backpressure.html#method.set_receiver).
+    pub fn set_receiver(&mut self, receiver: Receiver) -> Receiver {
+        self.inner.set_receiver(receiver)
     }
 }
 
@@ -246,24 +597,66 @@ impl<S> BackpressureWrapper<S> {
     pub(crate) fn new(stream: S, backpressure: Receiver)
         -> BackpressureWrapper<S>
     {
-        BackpressureWrapper(Backpressure::new(stream, backpressure))
+        BackpressureWrapper { inner: Backpressure::new(stream, backpressure) }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref()
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.inner.get_mut()
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.inner.into_inner()
+    }
+
+    /// Replaces the backpressure receiver, returning the old one
+    ///
+    /// See [`Backpressure::set_receiver`](struct.Backpressure.html#method.set_receiver).
+    pub fn set_receiver(&mut self, receiver: Receiver) -> Receiver {
+        self.inner.set_receiver(receiver)
+    }
+}
+
+impl<S, W> BackpressureWrap<S, W> {
+    pub(crate) fn new(stream: S, backpressure: Receiver)
+        -> BackpressureWrap<S, W>
+    {
+        BackpressureWrap {
+            inner: Backpressure::new(stream, backpressure),
+            marker: PhantomData,
+        }
     }
 
     /// Acquires a reference to the underlying stream that this adapter is
     /// pulling from.
     pub fn get_ref(&self) -> &S {
-        self.0.get_ref()
+        self.inner.get_ref()
     }
 
     /// Acquires a mutable reference to the underlying stream that this
     /// adapter is pulling from.
     pub fn get_mut(&mut self) -> &mut S {
-        self.0.get_mut()
+        self.inner.get_mut()
     }
 
     /// Consumes this adapter, returning the underlying stream.
     pub fn into_inner(self) -> S {
-        self.0.into_inner()
+        self.inner.into_inner()
+    }
+
+    /// Replaces the backpressure receiver, returning the old one
+    ///
+    /// See [`Backpressure::set_receiver`](struct.Backpressure.html#method.set_receiver).
+    pub fn set_receiver(&mut self, receiver: Receiver) -> Receiver {
+        self.inner.set_receiver(receiver)
     }
 }
 
@@ -288,6 +681,22 @@ impl<S> Backpressure<S> {
     pub fn into_inner(self) -> S {
         self.stream
     }
+
+    /// Replaces the backpressure receiver, returning the old one
+    ///
+    /// This allows changing the throttling object at runtime (i.e. on
+    /// configuration reload) without rebuilding the whole stream pipeline.
+    /// The new limit is applied starting from the next poll of the stream.
+    ///
+    /// Tokens that were created by the old receiver's `Sender` still count
+    /// against the old limit only.
+    pub fn set_receiver(&mut self, receiver: Receiver) -> Receiver {
+        let mut old = mem::replace(&mut self.backpressure, receiver);
+        if old.paused {
+            old.finish_pause();
+        }
+        old
+    }
 }
 
 /// Create a new pair of backpressure structures
@@ -340,14 +749,19 @@ pub fn new(initial_limit: usize) -> (Sender, Receiver) {
     let inner = Arc::new(Inner {
         limit: AtomicUsize::new(initial_limit),
         active: AtomicUsize::new(0),
-        task: Mutex::new(None),
+        tasks: Mutex::new(Vec::new()),
+        tags: Mutex::new(HashMap::new()),
+        pauses: Mutex::new(Pauses::default()),
+        drain: Mutex::new(Vec::new()),
+        durations: Recorder::new(),
     });
-    return (
+    (
         Sender {
             inner: inner.clone(),
         },
         Receiver {
             inner: inner.clone(),
+            paused: false,
         },
     )
 }
@@ -358,6 +772,12 @@ impl fmt::Debug for Token {
     }
 }
 
+impl fmt::Debug for SharedToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        debug("SharedToken", &self.token.inner, f)
+    }
+}
+
 impl fmt::Debug for Sender {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         debug("Sender", &self.inner, f)
@@ -370,6 +790,12 @@ impl fmt::Debug for Receiver {
     }
 }
 
+impl fmt::Debug for Drain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        debug("Drain", &self.inner, f)
+    }
+}
+
 impl<'a> fmt::Debug for HasCapacity<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         debug("HasCapacity", &self.recv.inner, f)
@@ -396,8 +822,8 @@ impl<S: fmt::Debug> fmt::Debug for Backpressure<S> {
 impl<S: fmt::Debug> fmt::Debug for BackpressureToken<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("BackpressureToken")
-            .field("stream", &self.0.stream)
-            .field("backpressure", &self.0.backpressure)
+            .field("stream", &self.inner.stream)
+            .field("backpressure", &self.inner.backpressure)
             .finish()
     }
 }
@@ -405,22 +831,32 @@ impl<S: fmt::Debug> fmt::Debug for BackpressureToken<S> {
 impl<S: fmt::Debug> fmt::Debug for BackpressureWrapper<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("BackpressureWrapper")
-            .field("stream", &self.0.stream)
-            .field("backpressure", &self.0.backpressure)
+            .field("stream", &self.inner.stream)
+            .field("backpressure", &self.inner.backpressure)
+            .finish()
+    }
+}
+
+impl<S: fmt::Debug, W> fmt::Debug for BackpressureWrap<S, W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BackpressureWrap")
+            .field("stream", &self.inner.stream)
+            .field("backpressure", &self.inner.backpressure)
             .finish()
     }
 }
 
 impl<I, S> Stream for Backpressure<S>
-    where S: Stream<Item=I> + Unpin
+    where S: Stream<Item=I>
 {
     type Item = I;
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
         -> Poll<Option<Self::Item>>
     {
-        match self.backpressure.poll(cx) {
+        let this = self.project();
+        match this.backpressure.poll(cx) {
             Poll::Pending => Poll::Pending,
-            Poll::Ready(()) => Pin::new(&mut self.stream).poll_next(cx),
+            Poll::Ready(()) => this.stream.poll_next(cx),
         }
     }
 }
@@ -432,31 +868,69 @@ impl<'a> Future for HasCapacity<'a> {
     }
 }
 
+impl Future for Drain {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        if self.inner.active.load(Ordering::SeqCst) == 0 {
+            return Poll::Ready(());
+        }
+        let mut waiters = lock_drain(&self.inner);
+        // Recheck under the lock, as token drop wakes up waiters under
+        // the same lock after counter reaches zero
+        if self.inner.active.load(Ordering::SeqCst) == 0 {
+            return Poll::Ready(());
+        }
+        if !waiters.iter().any(|w| w.will_wake(cx.waker())) {
+            waiters.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
 impl<I, S> Stream for BackpressureToken<S>
-    where S: Stream<Item=I> + Unpin
+    where S: Stream<Item=I>
 {
     type Item = (Token, I);
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
         -> Poll<Option<Self::Item>>
     {
-        Pin::new(&mut self.0)
+        let mut inner = self.project().inner;
+        inner.as_mut()
         .poll_next(cx)
-        .map(|opt| opt.map(|conn| (self.0.backpressure.token(), conn)))
+        .map(|opt| opt.map(|conn| (inner.backpressure.token(), conn)))
     }
 }
 
 impl<I, S> Stream for BackpressureWrapper<S>
-    where S: Stream<Item=I> + Unpin,
+    where S: Stream<Item=I>,
           ByteStream: From<(Token, I)>,
 {
     type Item = ByteStream;
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let mut inner = self.project().inner;
+        inner.as_mut()
+        .poll_next(cx)
+        .map(|opt| opt.map(|conn| {
+            ByteStream::from((inner.backpressure.token(), conn))
+        }))
+    }
+}
+
+impl<I, S, W> Stream for BackpressureWrap<S, W>
+    where S: Stream<Item=I>,
+          W: From<(Token, I)>,
+{
+    type Item = W;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
         -> Poll<Option<Self::Item>>
     {
-        Pin::new(&mut self.0)
+        let mut inner = self.project().inner;
+        inner.as_mut()
         .poll_next(cx)
         .map(|opt| opt.map(|conn| {
-            ByteStream::from((self.0.backpressure.token(), conn))
+            W::from((inner.backpressure.token(), conn))
         }))
     }
 }
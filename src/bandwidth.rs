@@ -0,0 +1,298 @@
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use async_std::future::Future;
+use async_std::io::{Read, Write};
+use async_std::stream::Stream;
+use async_std::task::{sleep, Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
+use crate::socket_options::{ConfigureSocket, SocketOptions};
+
+
+type Timer = Pin<Box<dyn Future<Output=()> + Send + 'static>>;
+
+/// Bandwidth limits for a single connection
+///
+/// Limits are in bytes per second, each direction is limited
+/// independently. By default nothing is limited. Connection may transfer
+/// up to a burst of bytes (one second worth of traffic by default) at full
+/// speed after being idle.
+///
+/// Used by
+/// [`ListenExt::limit_bandwidth`](trait.ListenExt.html#method.limit_bandwidth).
+///
+/// # Example
+///
+/// ```
+/// use async_listen::Bandwidth;
+///
+/// let limit = Bandwidth::new()
+///     .read(64 << 10)
+///     .write(1 << 20);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Bandwidth {
+    read: Option<u64>,
+    write: Option<u64>,
+    burst: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Limit {
+    rate: u64,
+    burst: u64,
+}
+
+struct Bucket {
+    limit: Limit,
+    tokens: f64,
+    updated: Instant,
+    timer: Option<Timer>,
+}
+
+/// A connection with limited read and write throughput
+///
+/// Yielded by
+/// [`ListenExt::limit_bandwidth`](trait.ListenExt.html#method.limit_bandwidth).
+/// Reads and writes are shortened to the number of bytes allowed at the
+/// moment, and wait when the limit is exhausted.
+pub struct Throttled<T> {
+    stream: T,
+    read: Option<Bucket>,
+    write: Option<Bucket>,
+}
+
+pin_project! {
+    /// A stream adapter that limits bandwidth of each connection
+    ///
+    /// See
+    /// [`ListenExt::limit_bandwidth`](../trait.ListenExt.html#method.limit_bandwidth)
+    /// for more info.
+    #[project = WithBandwidthLimitProj]
+    pub struct WithBandwidthLimit<S> {
+        #[pin]
+        stream: S,
+        limit: Bandwidth,
+    }
+}
+
+impl Bandwidth {
+    /// No limits, same as `Bandwidth::default()`
+    pub fn new() -> Bandwidth {
+        Bandwidth::default()
+    }
+
+    /// Limit reading to `bytes_per_sec`
+    pub fn read(mut self, bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "rate must be positive");
+        self.read = Some(bytes_per_sec);
+        self
+    }
+
+    /// Limit writing to `bytes_per_sec`
+    pub fn write(mut self, bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "rate must be positive");
+        self.write = Some(bytes_per_sec);
+        self
+    }
+
+    /// Set maximum number of bytes transferred at full speed
+    ///
+    /// Applies to both limited directions, defaults to the rate of each.
+    pub fn burst(mut self, bytes: u64) -> Self {
+        assert!(bytes > 0, "burst must be positive");
+        self.burst = Some(bytes);
+        self
+    }
+
+    fn limit(&self, rate: Option<u64>) -> Option<Limit> {
+        rate.map(|rate| Limit { rate, burst: self.burst.unwrap_or(rate) })
+    }
+}
+
+impl Bucket {
+    fn new(limit: Limit) -> Bucket {
+        Bucket {
+            limit,
+            tokens: limit.burst as f64,
+            updated: Instant::now(),
+            timer: None,
+        }
+    }
+
+    /// Returns number of bytes allowed now, or registers a timer
+    fn poll_allowed(&mut self, cx: &mut Context) -> Poll<usize> {
+        loop {
+            if let Some(ref mut timer) = self.timer {
+                match timer.as_mut().poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(()) => self.timer = None,
+                }
+            }
+            let now = Instant::now();
+            let elapsed = now.duration_since(self.updated).as_secs_f64();
+            self.updated = now;
+            self.tokens = (self.tokens + elapsed * self.limit.rate as f64)
+                .min(self.limit.burst as f64);
+            if self.tokens >= 1.0 {
+                return Poll::Ready(self.tokens as usize);
+            }
+            let wait = (1.0 - self.tokens) / self.limit.rate as f64;
+            self.timer = Some(Box::pin(sleep(Duration::from_secs_f64(wait))));
+        }
+    }
+
+    fn consume(&mut self, bytes: usize) {
+        self.tokens -= bytes as f64;
+    }
+}
+
+impl<T> Throttled<T> {
+    /// Wrap a connection limiting its bandwidth
+    pub fn new(stream: T, limit: Bandwidth) -> Throttled<T> {
+        Throttled {
+            stream,
+            read: limit.limit(limit.read).map(Bucket::new),
+            write: limit.limit(limit.write).map(Bucket::new),
+        }
+    }
+
+    /// Acquires a reference to the underlying connection
+    pub fn get_ref(&self) -> &T {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying connection
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.stream
+    }
+
+    /// Consumes the wrapper, returning the underlying connection
+    pub fn into_inner(self) -> T {
+        self.stream
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Throttled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Throttled")
+            .field("stream", &self.stream)
+            .field("read", &self.read.as_ref().map(|b| b.limit.rate))
+            .field("write", &self.write.as_ref().map(|b| b.limit.rate))
+            .finish()
+    }
+}
+
+impl<T: Read + Unpin> Read for Throttled<T> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        let this = self.get_mut();
+        let bucket = match this.read {
+            Some(ref mut bucket) => bucket,
+            None => return Pin::new(&mut this.stream).poll_read(cx, buf),
+        };
+        let allowed = match bucket.poll_allowed(cx) {
+            Poll::Ready(allowed) => allowed.min(buf.len()),
+            Poll::Pending => return Poll::Pending,
+        };
+        let res = Pin::new(&mut this.stream).poll_read(cx, &mut buf[..allowed]);
+        if let Poll::Ready(Ok(bytes)) = res {
+            bucket.consume(bytes);
+        }
+        res
+    }
+}
+
+impl<T: Write + Unpin> Write for Throttled<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        let this = self.get_mut();
+        let bucket = match this.write {
+            Some(ref mut bucket) => bucket,
+            None => return Pin::new(&mut this.stream).poll_write(cx, buf),
+        };
+        let allowed = match bucket.poll_allowed(cx) {
+            Poll::Ready(allowed) => allowed.min(buf.len()),
+            Poll::Pending => return Poll::Pending,
+        };
+        let res = Pin::new(&mut this.stream).poll_write(cx, &buf[..allowed]);
+        if let Poll::Ready(Ok(bytes)) = res {
+            bucket.consume(bytes);
+        }
+        res
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        Pin::new(&mut self.get_mut().stream).poll_close(cx)
+    }
+}
+
+impl<T: HasPeerAddr> HasPeerAddr for Throttled<T> {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.stream.get_peer_addr()
+    }
+}
+
+impl<T: ConfigureSocket> ConfigureSocket for Throttled<T> {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        self.stream.configure(options)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for WithBandwidthLimit<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithBandwidthLimit")
+            .field("stream", &self.stream)
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+impl<S> WithBandwidthLimit<S> {
+    pub(crate) fn new(stream: S, limit: Bandwidth) -> WithBandwidthLimit<S> {
+        WithBandwidthLimit { stream, limit }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S> Stream for WithBandwidthLimit<S>
+    where S: Stream<Item=I>,
+{
+    type Item = Throttled<I>;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let limit = self.limit;
+        self.as_mut().project().stream
+        .poll_next(cx)
+        .map(|opt| opt.map(|conn| Throttled::new(conn, limit)))
+    }
+}
@@ -0,0 +1,204 @@
+use std::io;
+use std::net::{SocketAddr, Ipv4Addr, Ipv6Addr};
+
+use async_std::net::TcpListener;
+
+#[cfg(unix)] use std::os::fd::OwnedFd;
+
+use crate::listener_set::ListenerSet;
+
+
+#[cfg(unix)]
+const BACKLOG: i32 = 1024;
+
+/// Bind `n` TCP listeners to the same address using `SO_REUSEPORT`
+///
+/// Accepting connections from a single socket is limited by a single core
+/// at high connection rates. With `SO_REUSEPORT` the kernel distributes
+/// incoming connections between all the sockets bound to the same address,
+/// so each listener can be served by a separate task (and thread of the
+/// executor). Listeners can also be combined into a single stream using
+/// [`ListenerSet`](struct.ListenerSet.html).
+///
+/// If the port is `0`, all the listeners are bound to the port chosen for
+/// the first one.
+///
+/// Connections are distributed between sockets on Linux and FreeBSD. On
+/// MacOS and other BSDs the option only allows binding the same address,
+/// and connections usually go to a single socket. Not supported on
+/// Windows and Solaris.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use async_std::prelude::*;
+/// # use async_std::task;
+/// # fn main() -> std::io::Result<()> {
+/// use async_listen::{ListenExt, bind_reuseport};
+///
+/// let addr = "0.0.0.0:8080".parse().unwrap();
+/// for listener in bind_reuseport(addr, 4)? {
+///     task::spawn(async move {
+///         let mut incoming = listener.incoming()
+///             .handle_errors(Duration::from_millis(500));
+///         while let Some(stream) = incoming.next().await {
+///             // ...
+///         }
+///     });
+/// }
+/// # Ok(()) }
+/// ```
+#[cfg(all(unix, not(any(target_os="solaris", target_os="illumos",
+                          target_os="cygwin"))))]
+pub fn bind_reuseport(addr: SocketAddr, n: usize)
+    -> io::Result<Vec<TcpListener>>
+{
+    use rustix::net::sockopt::{set_socket_reuseaddr, set_socket_reuseport};
+
+    let mut addr = addr;
+    let mut result = Vec::with_capacity(n);
+    for _ in 0..n {
+        let socket = tcp_socket(&addr)?;
+        // the same as the standard library does on unix
+        set_socket_reuseaddr(&socket, true)?;
+        set_socket_reuseport(&socket, true)?;
+        let listener = bind_listen(socket, &addr)?;
+        addr = listener.local_addr()?;
+        result.push(listener.into());
+    }
+    Ok(result)
+}
+
+/// Listen on the port on all IPv6 and IPv4 addresses
+///
+/// Where supported, a single dual-stack socket is bound to `[::]:port`,
+/// so IPv4 clients connect to the same socket and are seen as IPv4-mapped
+/// addresses (`::ffff:127.0.0.1`). [Filters](filter/index.html) treat
+/// mapped addresses as IPv4 ones. Otherwise, an IPv6-only socket and an
+/// IPv4 socket are bound to the same port. If IPv6 is not available on the
+/// host, only the IPv4 socket is bound.
+///
+/// Binding `[::]` directly gives different results depending on the
+/// default of the `IPV6_V6ONLY` option, which is off on Linux (unless
+/// changed by the `net.ipv6.bindv6only` sysctl) and on in Windows and some
+/// BSDs. This function sets the option explicitly on Unix. Windows
+/// always uses two sockets, and so does OpenBSD which has no dual-stack
+/// sockets.
+///
+/// Returns the set of listeners, yielding connections from all of them,
+/// and the addresses the sockets are bound to. If the port is `0`, both
+/// sockets are bound to the port chosen for the first one.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use async_std::prelude::*;
+/// # use async_std::task;
+/// # fn main() -> std::io::Result<()> { task::block_on(async {
+/// use async_listen::{ListenExt, bind_dual_stack};
+///
+/// let (listeners, addrs) = bind_dual_stack(8080)?;
+/// eprintln!("Listening on {:?}", addrs);
+/// let mut incoming = listeners.handle_errors(Duration::from_millis(500));
+/// while let Some(stream) = incoming.next().await {
+///     // ...
+/// }
+/// # Ok(()) }) }
+/// ```
+pub fn bind_dual_stack(port: u16)
+    -> io::Result<(ListenerSet, Vec<SocketAddr>)>
+{
+    let mut set = ListenerSet::new();
+    let mut addrs = Vec::with_capacity(2);
+    let mut v4 = SocketAddr::from((Ipv4Addr::UNSPECIFIED, port));
+    match bind_ipv6(SocketAddr::from((Ipv6Addr::UNSPECIFIED, port))) {
+        Ok((listener, dual)) => {
+            let addr = listener.local_addr()?;
+            set.add_tcp(listener);
+            addrs.push(addr);
+            if dual {
+                return Ok((set, addrs));
+            }
+            v4.set_port(addr.port());
+        }
+        Err(ref e) if no_ipv6(e) => {}
+        Err(e) => return Err(e),
+    }
+    let listener = std::net::TcpListener::bind(v4)?;
+    listener.set_nonblocking(true)?;
+    addrs.push(listener.local_addr()?);
+    set.add_tcp(listener.into());
+    Ok((set, addrs))
+}
+
+/// Bind IPv6 socket, returns `true` if it also accepts IPv4 connections
+#[cfg(unix)]
+fn bind_ipv6(addr: SocketAddr) -> io::Result<(TcpListener, bool)> {
+    use rustix::net::sockopt::{set_ipv6_v6only, set_socket_reuseaddr};
+
+    let socket = tcp_socket(&addr)?;
+    set_socket_reuseaddr(&socket, true)?;
+    // fails on systems without dual-stack sockets (OpenBSD)
+    let dual = set_ipv6_v6only(&socket, false).is_ok();
+    if !dual {
+        set_ipv6_v6only(&socket, true)?;
+    }
+    Ok((bind_listen(socket, &addr)?.into(), dual))
+}
+
+/// Bind IPv6 socket, returns `true` if it also accepts IPv4 connections
+#[cfg(not(unix))]
+fn bind_ipv6(addr: SocketAddr) -> io::Result<(TcpListener, bool)> {
+    // IPV6_V6ONLY is on by default on Windows, and can't be changed
+    // using the standard library
+    let listener = std::net::TcpListener::bind(addr)?;
+    listener.set_nonblocking(true)?;
+    Ok((listener.into(), false))
+}
+
+fn no_ipv6(e: &io::Error) -> bool {
+    #[cfg(unix)]
+    let code = Some(rustix::io::Errno::AFNOSUPPORT.raw_os_error());
+    #[cfg(windows)]
+    let code = Some(10047);  // WSAEAFNOSUPPORT
+    #[cfg(not(any(unix, windows)))]
+    let code = None;
+    e.kind() == io::ErrorKind::AddrNotAvailable
+        || (code.is_some() && e.raw_os_error() == code)
+}
+
+#[cfg(unix)]
+fn tcp_socket(addr: &SocketAddr) -> io::Result<OwnedFd> {
+    use rustix::net::{AddressFamily, SocketType};
+
+    let family = match addr {
+        SocketAddr::V4(_) => AddressFamily::INET,
+        SocketAddr::V6(_) => AddressFamily::INET6,
+    };
+    #[cfg(any(target_os="macos", target_os="ios"))] {
+        use rustix::io::{fcntl_setfd, FdFlags};
+
+        let socket = rustix::net::socket(family, SocketType::STREAM, None)?;
+        fcntl_setfd(&socket, FdFlags::CLOEXEC)?;
+        Ok(socket)
+    }
+    #[cfg(not(any(target_os="macos", target_os="ios")))] {
+        use rustix::net::{socket_with, SocketFlags};
+
+        Ok(socket_with(family, SocketType::STREAM, SocketFlags::CLOEXEC,
+                       None)?)
+    }
+}
+
+#[cfg(unix)]
+fn bind_listen(socket: OwnedFd, addr: &SocketAddr)
+    -> io::Result<std::net::TcpListener>
+{
+    rustix::net::bind(&socket, addr)?;
+    rustix::net::listen(&socket, BACKLOG)?;
+    let listener = std::net::TcpListener::from(socket);
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
@@ -0,0 +1,238 @@
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+
+use async_std::io::{BufRead, Read, Write};
+use async_std::task::{Poll, Context};
+
+use crate::backpressure::Token;
+use crate::byte_stream::{ByteStream, HasPeerAddr, PeerAddr};
+use crate::socket_options::{ConfigureSocket, SocketOptions};
+
+
+const DEFAULT_CAPACITY: usize = 8192;
+
+/// A `ByteStream` with buffered reads and writes
+///
+/// This is a single object implementing both `BufRead` and `Write`, so
+/// line- or frame-oriented protocols don't need to split the stream into
+/// a `BufReader` and `BufWriter` pair. Written data is kept in the buffer
+/// until it's full or [`flush`] is called, so don't forget to flush
+/// after each response.
+///
+/// Can be yielded directly by the listener using
+/// [`ListenExt::backpressure_wrap`](trait.ListenExt.html#method.backpressure_wrap):
+///
+/// ```ignore
+/// listener.incoming()
+///     .handle_errors(Duration::from_millis(500))
+///     .backpressure_wrap::<BufferedByteStream>(bp)
+/// ```
+///
+/// [`flush`]: https://docs.rs/async-std/1/async_std/io/trait.WriteExt.html#method.flush
+pub struct BufferedByteStream {
+    stream: ByteStream,
+    read_buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+    write_buf: Vec<u8>,
+}
+
+impl BufferedByteStream {
+    /// Wrap a stream with buffers of default size (8 KiB each)
+    pub fn new(stream: ByteStream) -> BufferedByteStream {
+        BufferedByteStream::with_capacity(
+            DEFAULT_CAPACITY, DEFAULT_CAPACITY, stream)
+    }
+
+    /// Wrap a stream with buffers of the specified size
+    pub fn with_capacity(read: usize, write: usize, stream: ByteStream)
+        -> BufferedByteStream
+    {
+        BufferedByteStream {
+            stream,
+            read_buf: vec![0; read].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+            write_buf: Vec::with_capacity(write),
+        }
+    }
+
+    /// Returns data that was read but not consumed yet
+    pub fn read_buffer(&self) -> &[u8] {
+        &self.read_buf[self.pos..self.cap]
+    }
+
+    /// Returns data that was written but not flushed yet
+    pub fn write_buffer(&self) -> &[u8] {
+        &self.write_buf
+    }
+
+    /// Acquires a reference to the underlying stream
+    pub fn get_ref(&self) -> &ByteStream {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream
+    ///
+    /// Reading or writing directly to the stream bypasses the buffers.
+    pub fn get_mut(&mut self) -> &mut ByteStream {
+        &mut self.stream
+    }
+
+    /// Consumes the wrapper, returning the underlying stream
+    ///
+    /// Buffered data is lost, so flush the stream before calling this and
+    /// check [`read_buffer`](#method.read_buffer) if needed.
+    pub fn into_inner(self) -> ByteStream {
+        self.stream
+    }
+
+    fn poll_flush_buf(&mut self, cx: &mut Context) -> Poll<io::Result<()>> {
+        let mut written = 0;
+        let mut result = Poll::Ready(Ok(()));
+        while written < self.write_buf.len() {
+            let buf = &self.write_buf[written..];
+            match Pin::new(&mut self.stream).poll_write(cx, buf) {
+                Poll::Ready(Ok(0)) => {
+                    result = Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        "failed to write the buffered data")));
+                    break;
+                }
+                Poll::Ready(Ok(bytes)) => written += bytes,
+                Poll::Ready(Err(e)) => {
+                    result = Poll::Ready(Err(e));
+                    break;
+                }
+                Poll::Pending => {
+                    result = Poll::Pending;
+                    break;
+                }
+            }
+        }
+        self.write_buf.drain(..written);
+        result
+    }
+}
+
+impl fmt::Debug for BufferedByteStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufferedByteStream")
+            .field("stream", &self.stream)
+            .field("read_buffered", &(self.cap - self.pos))
+            .field("write_buffered", &self.write_buf.len())
+            .finish()
+    }
+}
+
+impl From<ByteStream> for BufferedByteStream {
+    fn from(stream: ByteStream) -> BufferedByteStream {
+        BufferedByteStream::new(stream)
+    }
+}
+
+impl<I> From<(Token, I)> for BufferedByteStream
+    where ByteStream: From<(Token, I)>,
+{
+    fn from(pair: (Token, I)) -> BufferedByteStream {
+        BufferedByteStream::new(ByteStream::from(pair))
+    }
+}
+
+impl Read for BufferedByteStream {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        // large reads bypass the empty buffer
+        if self.pos >= self.cap && buf.len() >= self.read_buf.len() {
+            return Pin::new(&mut self.stream).poll_read(cx, buf);
+        }
+        let bytes = match self.as_mut().poll_fill_buf(cx) {
+            Poll::Ready(Ok(data)) => {
+                let bytes = data.len().min(buf.len());
+                buf[..bytes].copy_from_slice(&data[..bytes]);
+                bytes
+            }
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        };
+        self.consume(bytes);
+        Poll::Ready(Ok(bytes))
+    }
+}
+
+impl BufRead for BufferedByteStream {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<&[u8], io::Error>>
+    {
+        let this = self.get_mut();
+        if this.pos >= this.cap {
+            let res = Pin::new(&mut this.stream)
+                .poll_read(cx, &mut this.read_buf);
+            match res {
+                Poll::Ready(Ok(bytes)) => {
+                    this.pos = 0;
+                    this.cap = bytes;
+                }
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(&this.read_buf[this.pos..this.cap]))
+    }
+
+    fn consume(mut self: Pin<&mut Self>, amt: usize) {
+        self.pos = (self.pos + amt).min(self.cap);
+    }
+}
+
+impl Write for BufferedByteStream {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context, buf: &[u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        let capacity = self.write_buf.capacity();
+        if self.write_buf.len() + buf.len() > capacity {
+            match self.poll_flush_buf(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        if buf.len() >= capacity {
+            return Pin::new(&mut self.stream).poll_write(cx, buf);
+        }
+        self.write_buf.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        match self.poll_flush_buf(cx) {
+            Poll::Ready(Ok(())) => {}
+            other => return other,
+        }
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        match self.poll_flush_buf(cx) {
+            Poll::Ready(Ok(())) => {}
+            other => return other,
+        }
+        Pin::new(&mut self.stream).poll_close(cx)
+    }
+}
+
+impl HasPeerAddr for BufferedByteStream {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.stream.peer_addr()
+    }
+}
+
+impl ConfigureSocket for BufferedByteStream {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        self.stream.configure(options)
+    }
+}
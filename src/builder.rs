@@ -0,0 +1,171 @@
+use std::io;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use async_std::net::TcpListener;
+use socket2::{Domain, Protocol, Socket, TcpKeepalive, Type};
+
+
+const BACKLOG: i32 = 1024;
+
+/// Creates TCP listeners with options set before `bind()`
+///
+/// Some options only have effect if set before the socket is bound
+/// (`SO_REUSEADDR`, `SO_REUSEPORT`, `IPV6_V6ONLY`). Others are inherited by
+/// accepted connections from the listening socket on most systems (buffer
+/// sizes and keepalive). Receive buffer size must be set before `listen()`
+/// to have an effect on the TCP window scale of accepted connections.
+///
+/// Unset options are left at system defaults, except `SO_REUSEADDR` which
+/// is enabled on Unix, the same as the standard library does. The builder
+/// can be used to bind any number of listeners.
+///
+/// Only available with the `socket2` feature.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use async_std::prelude::*;
+/// # use async_std::task;
+/// # fn main() -> std::io::Result<()> { task::block_on(async {
+/// use async_listen::{ListenExt, ListenerBuilder};
+///
+/// let listener = ListenerBuilder::new()
+///     .recv_buffer_size(1 << 20)
+///     .keepalive(Duration::from_secs(60))
+///     .bind("0.0.0.0:8080".parse().unwrap())?;
+/// let mut incoming = listener.incoming()
+///     .handle_errors(Duration::from_millis(500));
+/// while let Some(stream) = incoming.next().await {
+///     // ...
+/// }
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ListenerBuilder {
+    reuse_address: Option<bool>,
+    reuse_port: Option<bool>,
+    only_v6: Option<bool>,
+    keepalive: Option<Option<Duration>>,
+    recv_buffer_size: Option<usize>,
+    send_buffer_size: Option<usize>,
+    backlog: i32,
+}
+
+impl Default for ListenerBuilder {
+    fn default() -> ListenerBuilder {
+        ListenerBuilder {
+            reuse_address: if cfg!(unix) { Some(true) } else { None },
+            reuse_port: None,
+            only_v6: None,
+            keepalive: None,
+            recv_buffer_size: None,
+            send_buffer_size: None,
+            backlog: BACKLOG,
+        }
+    }
+}
+
+impl ListenerBuilder {
+    /// Create a builder with default options
+    pub fn new() -> ListenerBuilder {
+        ListenerBuilder::default()
+    }
+
+    /// Set `SO_REUSEADDR` option
+    ///
+    /// Enabled by default on Unix, so the port can be bound again while
+    /// connections of the previous process are in `TIME_WAIT` state. On
+    /// Windows the option allows stealing the port from another socket, so
+    /// it's left disabled.
+    pub fn reuse_address(&mut self, value: bool) -> &mut ListenerBuilder {
+        self.reuse_address = Some(value);
+        self
+    }
+
+    /// Set `SO_REUSEPORT` option
+    ///
+    /// See [`bind_reuseport`](fn.bind_reuseport.html) for details.
+    #[cfg(all(unix, not(any(target_os="solaris", target_os="illumos",
+                              target_os="cygwin"))))]
+    pub fn reuse_port(&mut self, value: bool) -> &mut ListenerBuilder {
+        self.reuse_port = Some(value);
+        self
+    }
+
+    /// Set `IPV6_V6ONLY` option, ignored for IPv4 addresses
+    ///
+    /// See [`bind_dual_stack`](fn.bind_dual_stack.html) for details.
+    pub fn only_v6(&mut self, value: bool) -> &mut ListenerBuilder {
+        self.only_v6 = Some(value);
+        self
+    }
+
+    /// Enable `SO_KEEPALIVE` with the specified idle time before probes
+    pub fn keepalive(&mut self, idle: Duration) -> &mut ListenerBuilder {
+        self.keepalive = Some(Some(idle));
+        self
+    }
+
+    /// Disable `SO_KEEPALIVE`
+    pub fn no_keepalive(&mut self) -> &mut ListenerBuilder {
+        self.keepalive = Some(None);
+        self
+    }
+
+    /// Set `SO_RCVBUF` option
+    pub fn recv_buffer_size(&mut self, size: usize) -> &mut ListenerBuilder {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Set `SO_SNDBUF` option
+    pub fn send_buffer_size(&mut self, size: usize) -> &mut ListenerBuilder {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    /// Set the size of the queue of pending connections (default is 1024)
+    ///
+    /// The value is capped by the system (`net.core.somaxconn` on Linux).
+    pub fn backlog(&mut self, value: u32) -> &mut ListenerBuilder {
+        self.backlog = value.min(i32::MAX as u32) as i32;
+        self
+    }
+
+    /// Create a socket, apply the options, bind and start listening
+    pub fn bind(&self, addr: SocketAddr) -> io::Result<TcpListener> {
+        let socket = Socket::new(Domain::for_address(addr), Type::STREAM,
+                                 Some(Protocol::TCP))?;
+        if let Some(value) = self.reuse_address {
+            socket.set_reuse_address(value)?;
+        }
+        #[cfg(all(unix, not(any(target_os="solaris", target_os="illumos",
+                                target_os="cygwin"))))]
+        if let Some(value) = self.reuse_port {
+            socket.set_reuse_port(value)?;
+        }
+        if let (Some(value), SocketAddr::V6(_)) = (self.only_v6, addr) {
+            socket.set_only_v6(value)?;
+        }
+        match self.keepalive {
+            Some(Some(idle)) => {
+                socket.set_tcp_keepalive(
+                    &TcpKeepalive::new().with_time(idle))?;
+            }
+            Some(None) => socket.set_keepalive(false)?,
+            None => {}
+        }
+        if let Some(size) = self.recv_buffer_size {
+            socket.set_recv_buffer_size(size)?;
+        }
+        if let Some(size) = self.send_buffer_size {
+            socket.set_send_buffer_size(size)?;
+        }
+        socket.bind(&addr.into())?;
+        socket.listen(self.backlog)?;
+        socket.set_nonblocking(true)?;
+        Ok(std::net::TcpListener::from(socket).into())
+    }
+}
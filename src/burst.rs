@@ -0,0 +1,66 @@
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+type BurstCallback = Box<dyn FnMut(&io::Error, usize) + Send + 'static>;
+
+/// Counts transient errors in fixed windows and reports the start of a burst
+///
+/// The callback is called once when the number of errors in a window
+/// reaches the threshold. It's not called again until there is a window
+/// with fewer errors, so a long flood is reported once.
+pub(crate) struct Burst {
+    threshold: usize,
+    window: Duration,
+    window_start: Instant,
+    count: usize,
+    in_burst: bool,
+    callback: BurstCallback,
+}
+
+impl fmt::Debug for Burst {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Burst")
+            .field("threshold", &self.threshold)
+            .field("window", &self.window)
+            .field("count", &self.count)
+            .field("in_burst", &self.in_burst)
+            .finish()
+    }
+}
+
+impl Burst {
+    pub(crate) fn new<F>(threshold: usize, window: Duration, callback: F)
+        -> Burst
+        where F: FnMut(&io::Error, usize) + Send + 'static,
+    {
+        assert!(threshold > 0, "burst threshold must be positive");
+        Burst {
+            threshold,
+            window,
+            window_start: Instant::now(),
+            count: 0,
+            in_burst: false,
+            callback: Box::new(callback),
+        }
+    }
+
+    pub(crate) fn error(&mut self, e: &io::Error) {
+        let now = Instant::now();
+        let elapsed = now.saturating_duration_since(self.window_start);
+        if elapsed >= self.window {
+            // burst continues only if previous window was full and
+            // there was no quiet window in between
+            self.in_burst = self.in_burst
+                && self.count >= self.threshold
+                && elapsed < self.window * 2;
+            self.window_start = now;
+            self.count = 0;
+        }
+        self.count += 1;
+        if self.count >= self.threshold && !self.in_burst {
+            self.in_burst = true;
+            (self.callback)(e, self.count);
+        }
+    }
+}
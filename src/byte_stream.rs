@@ -1,22 +1,71 @@
 use std::fmt;
+use std::fs::File;
 use std::io;
 use std::net::SocketAddr;
+use std::ops::Range;
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::task::{Poll, Context};
+use std::time::{Duration, Instant};
 
+use async_std::future::Future;
 use async_std::io::{Read, Write, IoSlice, IoSliceMut};
+use async_std::io::{ReadExt, WriteExt};
 use async_std::net::{TcpStream, Shutdown};
 #[cfg(unix)] use async_std::os::unix::net::UnixStream;
+#[cfg(unix)] use async_std::os::unix::net::SocketAddr as UnixSocketAddr;
+use async_std::task::{sleep, spawn_blocking};
 
-use crate::backpressure::Token;
+use crate::backpressure::{Token, SharedToken};
+use crate::extensions::Extensions;
+use crate::socket_options::set_tos;
+use crate::tcp_info::{self, TcpInfo};
 
 
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+const SNIFF_INTERVAL: Duration = Duration::from_millis(10);
+const SEND_FILE_CHUNK: usize = 65536;
+const PROXY_CHUNK: usize = 65536;
+
+#[cfg(feature="rustls")]
+type TlsStream = Arc<Mutex<futures_rustls::server::TlsStream<ByteStream>>>;
+
 #[derive(Debug, Clone)]
 enum Stream {
     Tcp(TcpStream),
     #[cfg(unix)]
     Unix(UnixStream),
+    #[cfg(feature="rustls")]
+    Tls(TlsStream),
+    Boxed(Boxed),
+}
+
+trait Transport: Read + Write + Send + Unpin + 'static {}
+impl<T: Read + Write + Send + Unpin + 'static> Transport for T {}
+
+#[derive(Clone)]
+struct Boxed(Arc<Mutex<Box<dyn Transport>>>);
+
+impl Boxed {
+    fn lock(&self) -> MutexGuard<'_, Box<dyn Transport>> {
+        self.0.lock().expect("boxed stream lock is not poisoned")
+    }
+}
+
+impl fmt::Debug for Boxed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Boxed")
+    }
+}
+
+#[cfg(feature="rustls")]
+fn lock(stream: &TlsStream)
+    -> MutexGuard<'_, futures_rustls::server::TlsStream<ByteStream>>
+{
+    stream.lock().expect("tls stream lock is not poisoned")
 }
 
 /// A peer address for either Tcp or Unix socket
@@ -34,6 +83,22 @@ pub enum PeerAddr {
     Tcp(SocketAddr),
     /// The peer address is Unix socket path. `None` if socket is unnamed.
     Unix(Option<PathBuf>),
+    /// The peer address is a name in the Linux abstract socket namespace
+    ///
+    /// The name is stored without the leading NUL byte.
+    UnixAbstract(Vec<u8>),
+}
+
+/// A blocking socket of the standard library
+///
+/// Returned by [`ByteStream::into_std`](struct.ByteStream.html#method.into_std).
+#[derive(Debug)]
+pub enum StdStream {
+    /// A TCP connection
+    Tcp(std::net::TcpStream),
+    /// A Unix socket connection
+    #[cfg(unix)]
+    Unix(std::os::unix::net::UnixStream),
 }
 
 /// A wrapper around TcpStream and UnixStream
@@ -49,18 +114,52 @@ pub enum PeerAddr {
 /// The structure implements AsyncRead and AsyncWrite so can be used for
 /// protocol implementation directly.
 ///
-/// # Notes on Cloning
+/// With the `rustls` feature, `ByteStream` may also be an encrypted
+/// connection yielded by [`ListenExt::tls`](trait.ListenExt.html#method.tls).
 ///
-/// Cloning a `ByteStream` is a shallow clone, both resulting `ByteStream`
-/// structures hold the same backpressure token (and the same underlying OS socket).
-/// The backpressure slot will be freed (which means new connection can be accepted)
-/// when the last clone of `ByteStream` is dropped.
-#[derive(Debug, Clone)]
+/// # Notes on Duplicating
+///
+/// `ByteStream` isn't `Clone`, because it's not obvious how the copy should
+/// be counted by the backpressure. Use [`duplicate`](#method.duplicate) to
+/// get another handle to the same connection sharing the token, or
+/// [`duplicate_with_new_token`](#method.duplicate_with_new_token) to
+/// count the copy as a separate connection.
+#[derive(Debug)]
 pub struct ByteStream {
+    id: u64,
+    accepted_at: Instant,
     stream: Stream,
-    token: Option<Token>,
+    token: Option<SharedToken>,
+    peer_addr: Option<PeerAddr>,
+    // boxed to keep the stream small when timeouts aren't used
+    timeouts: Option<Box<Timeouts>>,
+    extensions: Extensions,
+}
+
+type Timer = Pin<Box<dyn Future<Output=()> + Send + 'static>>;
+
+#[derive(Default)]
+struct Timeouts {
+    read: Option<Duration>,
+    write: Option<Duration>,
+    // timers are started when an operation returns `Pending`
+    read_timer: Mutex<Option<Timer>>,
+    write_timer: Mutex<Option<Timer>>,
+}
+
+/// A connection that can report its peer address
+///
+/// This is implemented for async-std sockets, for
+/// [`ByteStream`](struct.ByteStream.html) and for `(Token, stream)` pairs
+/// yielded by [`ListenExt::backpressure`](trait.ListenExt.html#method.backpressure),
+/// so combinators inspecting peer address can be used at any stage of the
+/// pipeline.
+pub trait HasPeerAddr {
+    /// Returns the remote address that this connection is connected to
+    fn get_peer_addr(&self) -> io::Result<PeerAddr>;
 }
 
+#[allow(dead_code)]
 trait Assert: Read + Write + Send + Unpin + 'static { }
 impl Assert for ByteStream {}
 
@@ -70,19 +169,78 @@ impl fmt::Display for PeerAddr {
             PeerAddr::Tcp(s) => s.fmt(f),
             PeerAddr::Unix(None) => "<unnamed>".fmt(f),
             PeerAddr::Unix(Some(s)) => s.display().fmt(f),
+            PeerAddr::UnixAbstract(name) => {
+                write!(f, "@{}", String::from_utf8_lossy(name))
+            }
+        }
+    }
+}
+
+/// Parses the format produced by `Display`
+///
+/// Strings that are valid socket addresses (`127.0.0.1:80`, `[::1]:80`) are
+/// parsed as `Tcp`, strings containing a slash are unix socket paths,
+/// strings starting with `@` are abstract unix socket names and `<unnamed>`
+/// is an unnamed unix socket. Host names are not resolved.
+impl FromStr for PeerAddr {
+    type Err = io::Error;
+    fn from_str(s: &str) -> io::Result<PeerAddr> {
+        if let Ok(addr) = s.parse() {
+            Ok(PeerAddr::Tcp(addr))
+        } else if s == "<unnamed>" {
+            Ok(PeerAddr::Unix(None))
+        } else if let Some(name) = s.strip_prefix('@') {
+            Ok(PeerAddr::UnixAbstract(name.as_bytes().to_vec()))
+        } else if s.contains('/') {
+            Ok(PeerAddr::Unix(Some(PathBuf::from(s))))
+        } else {
+            Err(io::Error::new(io::ErrorKind::InvalidInput,
+                "peer address must be either socket address or a path"))
         }
     }
 }
 
+/// Serialized as a string, the same as `Display`
+///
+/// Non-UTF-8 unix socket paths are converted lossily.
+#[cfg(feature="serde")]
+impl serde::Serialize for PeerAddr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserialized from a string, see `FromStr` implementation
+#[cfg(feature="serde")]
+impl<'de> serde::Deserialize<'de> for PeerAddr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D)
+        -> Result<PeerAddr, D::Error>
+    {
+        let s = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 impl ByteStream {
-    /// Create a bytestream for a tcp socket
-    pub fn new_tcp(token: Token, stream: TcpStream) -> ByteStream {
+    fn from_parts(stream: Stream, token: Option<SharedToken>) -> ByteStream {
         ByteStream {
-            stream: Stream::Tcp(stream),
-            token: Some(token),
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            accepted_at: Instant::now(),
+            stream,
+            token,
+            peer_addr: None,
+            timeouts: None,
+            extensions: Extensions::new(),
         }
     }
 
+    /// Create a bytestream for a tcp socket
+    pub fn new_tcp(token: Token, stream: TcpStream) -> ByteStream {
+        ByteStream::from_parts(Stream::Tcp(stream), Some(token.share()))
+    }
+
     /// Create a bytestream for a tcp socket (without token)
     ///
     /// This can be used with interfaces that require a `ByteStream` but
@@ -90,19 +248,13 @@ impl ByteStream {
     /// example, if you have two listeners in the single app or even for
     /// client connections.
     pub fn new_tcp_detached(stream: TcpStream) -> ByteStream {
-        ByteStream {
-            stream: Stream::Tcp(stream),
-            token: None,
-        }
+        ByteStream::from_parts(Stream::Tcp(stream), None)
     }
 
     /// Create a bytestream for a unix socket
     #[cfg(unix)]
     pub fn new_unix(token: Token, stream: UnixStream) -> ByteStream {
-        ByteStream {
-            stream: Stream::Unix(stream),
-            token: Some(token),
-        }
+        ByteStream::from_parts(Stream::Unix(stream), Some(token.share()))
     }
 
     /// Create a bytestream for a unix socket (without token)
@@ -113,9 +265,258 @@ impl ByteStream {
     /// client connections.
     #[cfg(unix)]
     pub fn new_unix_detached(stream: UnixStream) -> ByteStream {
+        ByteStream::from_parts(Stream::Unix(stream), None)
+    }
+
+    /// Create a bytestream for an arbitrary transport
+    ///
+    /// This allows TLS streams of other libraries, test doubles, or
+    /// tunneled connections to be passed to the code that is written
+    /// against `ByteStream`. Socket-specific methods don't apply to such
+    /// streams: [`peer_addr`](#method.peer_addr),
+    /// [`peek`](#method.peek) and [`shutdown`](#method.shutdown) return
+    /// an `Unsupported` error, nodelay setting is ignored, and raw
+    /// descriptor is invalid (`-1`).
+    pub fn new_boxed<T>(token: Token, stream: T) -> ByteStream
+        where T: Read + Write + Send + Unpin + 'static,
+    {
+        let boxed = Boxed(Arc::new(Mutex::new(Box::new(stream))));
+        ByteStream::from_parts(Stream::Boxed(boxed), Some(token.share()))
+    }
+
+    /// Create a bytestream for an established TLS connection
+    ///
+    /// Backpressure token of the underlying stream is kept by the new one.
+    #[cfg(feature="rustls")]
+    pub(crate) fn new_tls(
+        mut stream: futures_rustls::server::TlsStream<ByteStream>)
+        -> ByteStream
+    {
+        let inner = &mut stream.get_mut().0;
+        let id = inner.id;
+        let accepted_at = inner.accepted_at;
+        let token = inner.token.clone();
+        let extensions = std::mem::take(&mut inner.extensions);
+        ByteStream {
+            id,
+            accepted_at,
+            extensions,
+            ..ByteStream::from_parts(Stream::Tls(Arc::new(Mutex::new(stream))),
+                                     token)
+        }
+    }
+
+    /// Returns the identifier of the connection
+    ///
+    /// Each `ByteStream` created gets an identifier which is unique within
+    /// the process and increases monotonically, so it can be used to
+    /// correlate log lines of the same connection (peer addresses repeat).
+    /// Duplicates and TLS streams wrapping the connection share its
+    /// identifier.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the time when the connection was accepted
+    ///
+    /// More precisely, the time `ByteStream` was created, which for the
+    /// listener pipelines is when the connection was accepted. TLS streams
+    /// report the time of the underlying connection, so handshake time is
+    /// included.
+    pub fn accepted_at(&self) -> Instant {
+        self.accepted_at
+    }
+
+    /// Returns time elapsed since the connection was accepted
+    pub fn age(&self) -> Duration {
+        self.accepted_at.elapsed()
+    }
+
+    /// Returns another handle to the same connection sharing the token
+    ///
+    /// This is a shallow copy: both streams refer to the same socket, have
+    /// the same identifier, and hold the same backpressure token. The
+    /// backpressure slot is freed (which means new connection can be
+    /// accepted) when the last copy is dropped. This is useful to read and
+    /// write the connection from different tasks.
+    ///
+    /// Read and write timeouts are copied, but can be changed
+    /// independently.
+    pub fn duplicate(&self) -> ByteStream {
+        ByteStream {
+            id: self.id,
+            accepted_at: self.accepted_at,
+            stream: self.stream.clone(),
+            token: self.token.clone(),
+            peer_addr: self.peer_addr.clone(),
+            timeouts: self.timeouts.clone(),
+            extensions: self.extensions.clone(),
+        }
+    }
+
+    /// Returns another handle to the same connection with its own token
+    ///
+    /// Same as [`duplicate`](#method.duplicate), but acquires a new token
+    /// from the same backpressure object, so each copy is counted as a
+    /// connection and each holds its slot until dropped. If the stream
+    /// holds no token, the copy holds none either.
+    pub fn duplicate_with_new_token(&self) -> ByteStream {
         ByteStream {
-            stream: Stream::Unix(stream),
-            token: None,
+            token: self.token.as_ref().map(|t| t.acquire_another().share()),
+            ..self.duplicate()
+        }
+    }
+
+    /// Returns values attached to the connection
+    ///
+    /// See [`Extensions`](struct.Extensions.html).
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Returns mutable reference to values attached to the connection
+    ///
+    /// Extensions are moved to the TLS stream by
+    /// [`ListenExt::tls`](trait.ListenExt.html#method.tls) and copied by
+    /// [`duplicate`](#method.duplicate).
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Returns the backpressure token held by the stream
+    ///
+    /// `None` means the stream isn't counted by any backpressure limit.
+    pub fn token(&self) -> Option<&SharedToken> {
+        self.token.as_ref()
+    }
+
+    /// Detaches the backpressure token from the stream
+    ///
+    /// The slot is released when the returned token is dropped (or right
+    /// away if discarded), unless duplicates of the stream still hold it. This
+    /// allows to transfer the token to a replacement connection, for
+    /// example after a protocol upgrade.
+    pub fn take_token(&mut self) -> Option<SharedToken> {
+        self.token.take()
+    }
+
+    /// Attaches a backpressure token to the stream
+    ///
+    /// Returns the previous token. Use
+    /// [`Token::share`](backpressure/struct.Token.html#method.share) to
+    /// attach a freshly acquired token.
+    pub fn set_token(&mut self, token: SharedToken) -> Option<SharedToken> {
+        self.token.replace(token)
+    }
+
+    /// Converts into the underlying TCP stream
+    ///
+    /// Returns the backpressure token along with the stream, so the caller
+    /// decides whether the connection is still counted. Returns the stream
+    /// unchanged if it isn't a plain TCP connection (including TLS). Any
+    /// configured timeouts are lost.
+    pub fn into_tcp(self) -> Result<(Option<SharedToken>, TcpStream), Self> {
+        match self.stream {
+            Stream::Tcp(stream) => Ok((self.token, stream)),
+            _ => Err(self),
+        }
+    }
+
+    /// Converts into the underlying unix stream
+    ///
+    /// See [`into_tcp`](#method.into_tcp) for details.
+    #[cfg(unix)]
+    pub fn into_unix(self) -> Result<(Option<SharedToken>, UnixStream), Self>
+    {
+        match self.stream {
+            Stream::Unix(stream) => Ok((self.token, stream)),
+            _ => Err(self),
+        }
+    }
+
+    /// Converts into a blocking socket of the standard library
+    ///
+    /// This is useful to hand the connection over to a blocking subsystem
+    /// or to a child process. The socket is switched back to blocking mode.
+    ///
+    /// The backpressure token is returned along with the socket, so the
+    /// connection is still counted until the token is dropped. Drop it
+    /// right away if the connection is passed to a child process and
+    /// shouldn't be counted anymore.
+    ///
+    /// The returned socket is a duplicate of the descriptor, so duplicates
+    /// of this stream (if any) keep working and the connection is not
+    /// closed until all of them are dropped. TLS and boxed streams return
+    /// `Unsupported` error, because their state can't be transferred.
+    pub fn into_std(self) -> io::Result<(Option<SharedToken>, StdStream)> {
+        let stream = match &self.stream {
+            Stream::Tcp(s) => StdStream::Tcp(std_tcp(s)?),
+            #[cfg(unix)]
+            Stream::Unix(s) => {
+                use std::os::unix::io::AsFd;
+                StdStream::Unix(s.as_fd().try_clone_to_owned()?.into())
+            }
+            #[cfg(feature="rustls")]
+            Stream::Tls(_) => return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "can't convert TLS stream to a blocking socket")),
+            Stream::Boxed(_) => return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "can't convert boxed stream to a blocking socket")),
+        };
+        match &stream {
+            StdStream::Tcp(s) => s.set_nonblocking(false)?,
+            #[cfg(unix)]
+            StdStream::Unix(s) => s.set_nonblocking(false)?,
+        }
+        Ok((self.token, stream))
+    }
+
+    /// Returns true if this is an encrypted (TLS) connection
+    pub fn is_tls(&self) -> bool {
+        match &self.stream {
+            #[cfg(feature="rustls")]
+            Stream::Tls(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the server name (SNI) requested by the TLS client
+    ///
+    /// Returns `None` for plain connections and when client didn't send
+    /// the name (i.e. connected by IP address).
+    #[cfg(feature="rustls")]
+    pub fn tls_server_name(&self) -> Option<String> {
+        match &self.stream {
+            Stream::Tls(s) => lock(s).get_ref().1.server_name()
+                .map(|name| name.to_owned()),
+            _ => None,
+        }
+    }
+
+    /// Returns the application protocol negotiated with ALPN
+    ///
+    /// For example `b"h2"` or `b"http/1.1"`. Returns `None` for plain
+    /// connections and when no protocol was negotiated.
+    #[cfg(feature="rustls")]
+    pub fn tls_alpn_protocol(&self) -> Option<Vec<u8>> {
+        match &self.stream {
+            Stream::Tls(s) => lock(s).get_ref().1.alpn_protocol()
+                .map(|proto| proto.to_vec()),
+            _ => None,
+        }
+    }
+
+    /// Returns the negotiated TLS protocol version
+    ///
+    /// Returns `None` for plain connections.
+    #[cfg(feature="rustls")]
+    pub fn tls_protocol_version(&self)
+        -> Option<futures_rustls::rustls::ProtocolVersion>
+    {
+        match &self.stream {
+            Stream::Tls(s) => lock(s).get_ref().1.protocol_version(),
+            _ => None,
         }
     }
 
@@ -125,6 +526,9 @@ impl ByteStream {
     /// [`PeerAddr`](enum.PeerAddr.html) still contains `Unix` option so you
     /// don't have to use conditional compilation when matching.
     ///
+    /// If the address was overridden by
+    /// [`set_peer_addr`](#method.set_peer_addr), that one is returned.
+    ///
     /// ## Examples
     ///
     /// ```ignore
@@ -133,18 +537,57 @@ impl ByteStream {
     ///     PeerAddr::Tcp(addr) => println!("TCP addr {}", addr),
     ///     PeerAddr::Unix(None) => println!("Unnamed unix socket"),
     ///     PeerAddr::Unix(Some(path)) => println!("Unix {}", path.display()),
+    ///     PeerAddr::UnixAbstract(name) => println!("Abstract {:?}", name),
     /// }
     /// ```
     pub fn peer_addr(&self) -> io::Result<PeerAddr> {
+        if let Some(addr) = &self.peer_addr {
+            return Ok(addr.clone());
+        }
         match &self.stream {
-            Stream::Tcp(s) => s.peer_addr().map(PeerAddr::Tcp),
+            Stream::Tcp(s) => s.get_peer_addr(),
             #[cfg(unix)]
-            Stream::Unix(s) => {
-                s.peer_addr()
-                .map(|a| a.as_pathname().map(|p| p.to_owned()))
-                .map(PeerAddr::Unix)
-            }
+            Stream::Unix(s) => s.get_peer_addr(),
+            #[cfg(feature="rustls")]
+            Stream::Tls(s) => lock(s).get_ref().0.peer_addr(),
+            Stream::Boxed(_) => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "peer address of a boxed stream is unknown")),
+        }
+    }
+
+    /// Overrides the peer address of the stream
+    ///
+    /// This allows middleware decoding PROXY protocol, or mapping addresses
+    /// behind NAT, to pass the real client address to the handlers:
+    /// [`peer_addr`](#method.peer_addr) and every combinator using it (i.e.
+    /// filters and tracing) see the new address. Also makes the address
+    /// known for boxed streams.
+    pub fn set_peer_addr(&mut self, addr: PeerAddr) {
+        self.peer_addr = Some(addr);
+    }
+
+    /// Queries the peer address and stores it in the stream
+    ///
+    /// After this call [`peer_addr`](#method.peer_addr) doesn't fail, even
+    /// when the client has already disconnected (which otherwise gives
+    /// `ENOTCONN` exactly when the address is needed to log an error).
+    /// Streams constructed from `(stream, address)` pairs returned by
+    /// `accept()` (including the ones yielded by
+    /// [`ListenerSet`](struct.ListenerSet.html)) have the address cached
+    /// already.
+    pub fn cache_peer_addr(&mut self) -> io::Result<&PeerAddr> {
+        if self.peer_addr.is_none() {
+            self.peer_addr = Some(self.peer_addr()?);
         }
+        Ok(self.peer_addr.as_ref().expect("address is cached"))
+    }
+
+    /// Returns the peer address if it's cached or overridden
+    ///
+    /// See [`cache_peer_addr`](#method.cache_peer_addr) and
+    /// [`set_peer_addr`](#method.set_peer_addr).
+    pub fn cached_peer_addr(&self) -> Option<&PeerAddr> {
+        self.peer_addr.as_ref()
     }
 
     /// Gets the value of the `TCP_NODELAY` option on this socket.
@@ -160,6 +603,9 @@ impl ByteStream {
             Stream::Tcp(s) => s.nodelay(),
             #[cfg(unix)]
             Stream::Unix(_) => Ok(true),
+            #[cfg(feature="rustls")]
+            Stream::Tls(s) => lock(s).get_ref().0.nodelay(),
+            Stream::Boxed(_) => Ok(true),
         }
     }
 
@@ -178,199 +624,960 @@ impl ByteStream {
             Stream::Tcp(s) => s.set_nodelay(nodelay),
             #[cfg(unix)]
             Stream::Unix(_) => Ok(()),
+            #[cfg(feature="rustls")]
+            Stream::Tls(s) => lock(s).get_ref().0.set_nodelay(nodelay),
+            Stream::Boxed(_) => Ok(()),
         }
     }
 
-    /// Shuts down the read, write, or both halves of this connection.
+    /// Sets the `IP_TOS` (or `IPV6_TCLASS`) value of packets sent
     ///
-    /// This function will cause all pending and future I/O calls on the
-    /// specified portions to immediately return with an appropriate value
-    /// (see the documentation of Shutdown).
-    pub fn shutdown(&self, how: Shutdown) -> Result<(), io::Error> {
+    /// This marks packets for QoS handling in the network, for example
+    /// for latency-sensitive responses. Note that the lowest two bits are
+    /// used for ECN and are usually overridden by the kernel, see
+    /// [`set_dscp`](#method.set_dscp).
+    ///
+    /// For Unix sockets this function does nothing. It's also a no-op on
+    /// platforms where the option isn't supported.
+    pub fn set_tos(&self, tos: u8) -> io::Result<()> {
         match &self.stream {
-            Stream::Tcp(s) => s.shutdown(how),
+            Stream::Tcp(s) => set_tos(s, tos),
             #[cfg(unix)]
-            Stream::Unix(s) => s.shutdown(how),
+            Stream::Unix(_) => Ok(()),
+            #[cfg(feature="rustls")]
+            Stream::Tls(s) => lock(s).get_ref().0.set_tos(tos),
+            Stream::Boxed(_) => Ok(()),
         }
     }
-}
 
-impl From<(Token, TcpStream)> for ByteStream {
-    fn from((token, stream): (Token, TcpStream)) -> ByteStream {
-        ByteStream::new_tcp(token, stream)
+    /// Sets the DSCP (Differentiated Services Code Point) of packets sent
+    ///
+    /// This is the upper six bits of the TOS value, see
+    /// [`set_tos`](#method.set_tos). For example, `46` is Expedited
+    /// Forwarding. Returns `InvalidInput` error if `dscp` doesn't fit in six
+    /// bits.
+    pub fn set_dscp(&self, dscp: u8) -> io::Result<()> {
+        if dscp >= 64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                "DSCP must be less than 64"));
+        }
+        self.set_tos(dscp << 2)
     }
-}
 
-#[cfg(unix)]
-impl From<(Token, UnixStream)> for ByteStream {
-    fn from((token, stream): (Token, UnixStream)) -> ByteStream {
-        ByteStream::new_unix(token, stream)
+    /// Returns kernel statistics of the TCP connection
+    ///
+    /// Includes round trip time, retransmits and congestion window, which
+    /// is useful for logging why a connection was slow. Returns `None` for
+    /// Unix sockets and on platforms other than Linux.
+    pub fn tcp_info(&self) -> io::Result<Option<TcpInfo>> {
+        match &self.stream {
+            Stream::Tcp(s) => tcp_info::query(s),
+            #[cfg(unix)]
+            Stream::Unix(_) => Ok(None),
+            #[cfg(feature="rustls")]
+            Stream::Tls(s) => lock(s).get_ref().0.tcp_info(),
+            Stream::Boxed(_) => Ok(None),
+        }
     }
-}
 
-impl Read for ByteStream {
+    /// Returns the read timeout of this stream
+    ///
+    /// See [`set_read_timeout`](#method.set_read_timeout).
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.timeouts.as_ref().and_then(|t| t.read)
+    }
 
-    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8])
-        -> Poll<Result<usize, io::Error>>
-    {
-        match self.stream {
-            Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_read(cx, buf)
-            }
+    /// Sets the read timeout of this stream
+    ///
+    /// Each read which doesn't complete within `timeout` fails with
+    /// `io::ErrorKind::TimedOut`. Timer starts when read operation has to
+    /// wait for data and is reset when it completes, so this limits time
+    /// of waiting for a single read, not of the whole connection. This
+    /// works for reads through `&ByteStream` too. `None` disables timeout
+    /// (default).
+    ///
+    /// The setting is copied by [`duplicate`](#method.duplicate), but
+    /// copies can change it independently.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        let timeouts = self.timeouts.get_or_insert_with(Default::default);
+        timeouts.read = timeout;
+        *timeouts.read_timer.get_mut()
+            .expect("timer lock is not poisoned") = None;
+    }
+
+    /// Returns the write timeout of this stream
+    ///
+    /// See [`set_write_timeout`](#method.set_write_timeout).
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.timeouts.as_ref().and_then(|t| t.write)
+    }
+
+    /// Sets the write timeout of this stream
+    ///
+    /// Each write or flush which doesn't complete within `timeout` fails
+    /// with `io::ErrorKind::TimedOut`, i.e. when peer doesn't read data and
+    /// the send buffer is full. See
+    /// [`set_read_timeout`](#method.set_read_timeout) for details.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) {
+        let timeouts = self.timeouts.get_or_insert_with(Default::default);
+        timeouts.write = timeout;
+        *timeouts.write_timer.get_mut()
+            .expect("timer lock is not poisoned") = None;
+    }
+
+    pub(crate) fn as_tcp(&self) -> Option<&TcpStream> {
+        match &self.stream {
+            Stream::Tcp(s) => Some(s),
             #[cfg(unix)]
-            Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_read(cx, buf)
-            }
+            Stream::Unix(_) => None,
+            // can't borrow through the lock, configure sockets before TLS
+            #[cfg(feature="rustls")]
+            Stream::Tls(_) => None,
+            Stream::Boxed(_) => None,
         }
     }
 
-    fn poll_read_vectored(self: Pin<&mut Self>, cx: &mut Context,
-        bufs: &mut [IoSliceMut])
-        -> Poll<Result<usize, io::Error>>
-    {
-        match self.stream {
-            Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_read_vectored(cx, bufs)
-            }
+    /// Receives data on the socket without removing it from the queue
+    ///
+    /// Waits until some data is available (or the peer closes the
+    /// connection, in which case `0` is returned). Successive calls return
+    /// the same data. Not supported for TLS streams because the decrypted
+    /// data can't be peeked.
+    pub async fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        match &self.stream {
+            Stream::Tcp(s) => s.peek(buf).await,
             #[cfg(unix)]
-            Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_read_vectored(cx, bufs)
-            }
+            Stream::Unix(s) => peek_unix(s, buf).await,
+            #[cfg(feature="rustls")]
+            Stream::Tls(_) => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "can't peek into a TLS stream")),
+            Stream::Boxed(_) => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "can't peek into a boxed stream")),
         }
     }
-}
 
-impl Read for &ByteStream {
-    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8])
-        -> Poll<Result<usize, io::Error>>
+    /// Returns up to `n` first bytes of the connection without consuming
+    ///
+    /// Waits until `n` bytes are received or `timeout` elapses, whichever
+    /// comes first. On timeout returns the data received so far, or
+    /// `TimedOut` error if there is none. Empty vector means the peer
+    /// closed the connection without sending anything. The data is still
+    /// returned by subsequent reads.
+    ///
+    /// Use this to detect the protocol when serving several of them on the
+    /// same port (see [`looks_like_tls`](fn.looks_like_tls.html)). Keep `n`
+    /// as small as needed for the decision, because a client sending a
+    /// shorter request and waiting for the response delays the decision
+    /// until the timeout. Like [`peek`](#method.peek), this isn't
+    /// supported for TLS streams.
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_listen::{ByteStream, looks_like_tls};
+    /// # async fn f(stream: ByteStream) -> std::io::Result<()> {
+    /// let prefix = stream.sniff(3, Duration::from_secs(5)).await?;
+    /// if looks_like_tls(&prefix) {
+    ///     // pass to TLS acceptor
+    /// } else {
+    ///     // serve plaintext
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn sniff(&self, n: usize, timeout: Duration)
+        -> io::Result<Vec<u8>>
     {
-        match self.stream {
-            Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_read(cx, buf)
+        let deadline = Instant::now() + timeout;
+        let mut buf = vec![0u8; n];
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let bytes = async_std::io::timeout(left, self.peek(&mut buf))
+                .await?;
+            if bytes == 0 || bytes >= n {
+                buf.truncate(bytes);
+                return Ok(buf);
             }
-            #[cfg(unix)]
-            Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_read(cx, buf)
+            // peek returns immediately while any data is buffered,
+            // so poll for the rest of the data
+            if Instant::now() + SNIFF_INTERVAL >= deadline {
+                sleep(deadline.saturating_duration_since(Instant::now()))
+                    .await;
+                let bytes = self.peek(&mut buf).await?;
+                buf.truncate(bytes);
+                return Ok(buf);
             }
+            sleep(SNIFF_INTERVAL).await;
         }
     }
-    fn poll_read_vectored(self: Pin<&mut Self>, cx: &mut Context,
-        bufs: &mut [IoSliceMut])
-        -> Poll<Result<usize, io::Error>>
+
+    /// Sends a file descriptor over a Unix socket
+    ///
+    /// The descriptor is sent as `SCM_RIGHTS` ancillary data along with a
+    /// single zero byte (ancillary data can't be sent alone). The peer
+    /// should receive it with [`recv_fd`](#method.recv_fd) or a `recvmsg`
+    /// call that consumes the byte. The descriptor can be closed locally
+    /// right after the call, the peer gets its own copy.
+    ///
+    /// Returns `Unsupported` error for other kinds of streams.
+    #[cfg(unix)]
+    pub async fn send_fd<F: std::os::unix::io::AsFd>(&self, fd: F)
+        -> io::Result<()>
     {
-        match self.stream {
-            Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_read_vectored(cx, bufs)
-            }
-            #[cfg(unix)]
-            Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_read_vectored(cx, bufs)
-            }
+        match &self.stream {
+            Stream::Unix(s) => send_fd_unix(s, fd.as_fd()).await,
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "file descriptors can only be passed over unix sockets")),
         }
     }
-}
 
-impl Write for ByteStream {
-    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8])
-        -> Poll<Result<usize, io::Error>>
-    {
-        match self.stream {
-            Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_write(cx, buf)
-            }
-            #[cfg(unix)]
-            Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_write(cx, buf)
-            }
+    /// Receives a file descriptor sent by [`send_fd`](#method.send_fd)
+    ///
+    /// Reads a single byte from the stream along with the descriptor, so
+    /// it must be called exactly when the peer sends a descriptor, not
+    /// when other data is pending. Returns `InvalidData` error if the byte
+    /// came without a descriptor, and `UnexpectedEof` if the connection is
+    /// closed.
+    ///
+    /// Returns `Unsupported` error for streams other than Unix sockets.
+    #[cfg(unix)]
+    pub async fn recv_fd(&self) -> io::Result<std::os::unix::io::OwnedFd> {
+        match &self.stream {
+            Stream::Unix(s) => recv_fd_unix(s).await,
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "file descriptors can only be passed over unix sockets")),
         }
     }
-    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context)
-        -> Poll<Result<(), io::Error>>
-    {
-        match self.stream {
-            Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_flush(cx)
-            }
+
+    /// Shuts down the read, write, or both halves of this connection.
+    ///
+    /// This function will cause all pending and future I/O calls on the
+    /// specified portions to immediately return with an appropriate value
+    /// (see the documentation of Shutdown).
+    pub fn shutdown(&self, how: Shutdown) -> Result<(), io::Error> {
+        match &self.stream {
+            Stream::Tcp(s) => s.shutdown(how),
             #[cfg(unix)]
-            Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_flush(cx)
-            }
+            Stream::Unix(s) => s.shutdown(how),
+            #[cfg(feature="rustls")]
+            Stream::Tls(s) => lock(s).get_ref().0.shutdown(how),
+            Stream::Boxed(_) => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "can't shut down a boxed stream, use `close` instead")),
         }
     }
-    fn poll_close(self: Pin<&mut Self>, cx: &mut Context)
-        -> Poll<Result<(), io::Error>>
+
+    /// Calls `f` with a reference to the underlying socket
+    ///
+    /// This is an escape hatch for socket options that aren't covered by
+    /// [`SocketOptions`](struct.SocketOptions.html), without unwrapping the
+    /// stream. For TLS streams the underlying TCP socket is passed. Boxed
+    /// streams return `Unsupported` error.
+    ///
+    /// Only available with `socket2` feature.
+    ///
+    /// ```no_run
+    /// # use async_listen::ByteStream;
+    /// # fn f(stream: &ByteStream) -> std::io::Result<()> {
+    /// stream.with_socket(|sock| sock.set_tos(0x10))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature="socket2")]
+    pub fn with_socket<F, R>(&self, f: F) -> io::Result<R>
+        where F: FnOnce(socket2::SockRef) -> io::Result<R>,
     {
-        match self.stream {
-            Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_close(cx)
-            }
+        match &self.stream {
+            Stream::Tcp(s) => f(socket2::SockRef::from(s)),
             #[cfg(unix)]
-            Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_close(cx)
+            Stream::Unix(s) => f(socket2::SockRef::from(s)),
+            #[cfg(feature="rustls")]
+            Stream::Tls(s) => lock(s).get_ref().0.with_socket(f),
+            Stream::Boxed(_) => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "boxed stream has no socket")),
+        }
+    }
+
+    /// Sends a range of the file to the connection
+    ///
+    /// On Linux uses `sendfile` system call for TCP and Unix sockets, so the
+    /// data is not copied through the userspace. Elsewhere (and for TLS or
+    /// boxed streams) the file is read in chunks in a thread pool and
+    /// written to the stream.
+    ///
+    /// Returns the number of bytes sent, which is less than the length of
+    /// the range if the file ends earlier. On Unix the file position isn't
+    /// changed. The write timeout, if set, applies to each wait for the socket to
+    /// become writable.
+    ///
+    /// ```no_run
+    /// # use async_listen::ByteStream;
+    /// # async fn f(mut stream: ByteStream) -> std::io::Result<()> {
+    /// let file = std::fs::File::open("index.html")?;
+    /// let len = file.metadata()?.len();
+    /// stream.send_file(&file, 0..len).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn send_file(&mut self, file: &File, range: Range<u64>)
+        -> io::Result<u64>
+    {
+        #[cfg(any(target_os="linux", target_os="android"))] {
+            use std::os::unix::io::AsFd;
+
+            let fd = match &self.stream {
+                Stream::Tcp(s) => Some(s.as_fd().try_clone_to_owned()?),
+                Stream::Unix(s) => Some(s.as_fd().try_clone_to_owned()?),
+                _ => None,
+            };
+            if let Some(fd) = fd {
+                return sendfile(fd, file, range, self.write_timeout()).await;
             }
         }
+        let mut file = file.try_clone()?;
+        let mut buf = vec![0u8; SEND_FILE_CHUNK];
+        let mut offset = range.start;
+        while offset < range.end {
+            let len = (range.end - offset).min(SEND_FILE_CHUNK as u64);
+            let (f, b, res) = spawn_blocking(move || {
+                let res = read_at(&file, &mut buf[..len as usize], offset);
+                (file, buf, res)
+            }).await;
+            file = f;
+            buf = b;
+            let bytes = res?;
+            if bytes == 0 {
+                break;
+            }
+            self.write_all(&buf[..bytes]).await?;
+            offset += bytes as u64;
+        }
+        Ok(offset - range.start)
     }
-    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context,
-        bufs: &[IoSlice])
-        -> Poll<Result<usize, io::Error>>
+
+    /// Copies data in both directions between this and the other stream
+    ///
+    /// When one side closes its write half (sends EOF), the write half of
+    /// the other stream is shut down, and data keeps flowing in the
+    /// opposite direction until it's closed too. So half-closed
+    /// connections are proxied correctly. Returns the number of bytes
+    /// copied from this stream to the other one and in reverse.
+    ///
+    /// On Linux, data between plain TCP and Unix sockets is moved using
+    /// `splice` without copying it through the userspace. Read and write
+    /// timeouts of the streams apply.
+    ///
+    /// Returns on the first error in either direction, the streams
+    /// are not closed in this case.
+    ///
+    /// ```no_run
+    /// # use async_std::net::TcpStream;
+    /// # use async_listen::ByteStream;
+    /// # async fn f(mut client: ByteStream) -> std::io::Result<()> {
+    /// let backend = TcpStream::connect("127.0.0.1:8080").await?;
+    /// let mut backend = ByteStream::new_tcp_detached(backend);
+    /// client.proxy_to(&mut backend).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn proxy_to(&mut self, other: &mut ByteStream)
+        -> io::Result<(u64, u64)>
     {
-        match self.stream {
-            Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_write_vectored(cx, bufs)
+        let mut forward = Box::pin(copy_half(self, other));
+        let mut backward = Box::pin(copy_half(other, self));
+        let mut forward_bytes = None;
+        let mut backward_bytes = None;
+        async_std::future::poll_fn(|cx| {
+            if forward_bytes.is_none() {
+                if let Poll::Ready(res) = forward.as_mut().poll(cx) {
+                    forward_bytes = Some(res?);
+                }
             }
-            #[cfg(unix)]
-            Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_write_vectored(cx, bufs)
+            if backward_bytes.is_none() {
+                if let Poll::Ready(res) = backward.as_mut().poll(cx) {
+                    backward_bytes = Some(res?);
+                }
             }
+            match (forward_bytes, backward_bytes) {
+                (Some(f), Some(b)) => Poll::Ready(Ok((f, b))),
+                _ => Poll::Pending,
+            }
+        }).await
+    }
+
+    /// Signals the end of data to the peer, leaving the stream readable
+    ///
+    /// Flushes the stream and shuts down its write half (for TLS, sends
+    /// `close_notify` first), so the peer receives EOF. Unlike dropping the
+    /// stream, the response of the peer can still be read, for example
+    /// using [`read_remaining`](#method.read_remaining). This is how
+    /// request/response protocols that delimit the request by EOF work.
+    ///
+    /// For boxed streams the wrapped stream is closed, which may close it
+    /// in both directions.
+    pub async fn close_write(&self) -> io::Result<()> {
+        let mut stream = self;
+        stream.flush().await?;
+        // sends `close_notify` for TLS, but doesn't shut down sockets
+        async_std::future::poll_fn(|cx| self.poll_close_raw(cx)).await?;
+        if !matches!(self.stream, Stream::Boxed(_)) {
+            self.shutdown(Shutdown::Write)?;
+        }
+        Ok(())
+    }
+
+    /// Reads all the data until the peer closes the connection
+    ///
+    /// Usually called after [`close_write`](#method.close_write) to consume
+    /// the reply. There is no limit on the size of the data, so set the
+    /// [read timeout](#method.set_read_timeout) or use `take()` from
+    /// `ReadExt` if the peer isn't trusted.
+    pub async fn read_remaining(&mut self) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        self.read_to_end(&mut buf).await?;
+        Ok(buf)
+    }
+
+    #[cfg(any(target_os="linux", target_os="android"))]
+    fn splice_fd(&self) -> io::Result<Option<std::os::unix::io::OwnedFd>> {
+        use std::os::unix::io::AsFd;
+
+        match &self.stream {
+            Stream::Tcp(s) => s.as_fd().try_clone_to_owned().map(Some),
+            Stream::Unix(s) => s.as_fd().try_clone_to_owned().map(Some),
+            _ => Ok(None),
         }
     }
+
+    /// Closes the connection so that the peer receives all the data
+    ///
+    /// Flushes the stream, shuts down the write half (for TLS, sends
+    /// `close_notify` first), then reads and discards incoming data until
+    /// the peer closes its side or `timeout` elapses. Closing a socket
+    /// with unread data makes the kernel send RST, and the peer may lose
+    /// the last response, which this function avoids.
+    ///
+    /// The socket is closed when the function returns (unless there are
+    /// duplicates of the stream). Returns `TimedOut` error if the peer didn't
+    /// close the connection in time.
+    pub async fn graceful_close(mut self, timeout: Duration)
+        -> io::Result<()>
+    {
+        self.close_write().await?;
+        async_std::io::timeout(timeout, async {
+            let mut buf = [0u8; 4096];
+            while self.read(&mut buf).await? > 0 {}
+            Ok(())
+        }).await
+        // connection is closed on drop
+    }
 }
 
-impl Write for &ByteStream {
-    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8])
-        -> Poll<Result<usize, io::Error>>
+fn std_tcp(stream: &TcpStream) -> io::Result<std::net::TcpStream> {
+    #[cfg(unix)] {
+        use std::os::unix::io::AsFd;
+        Ok(stream.as_fd().try_clone_to_owned()?.into())
+    }
+    #[cfg(windows)] {
+        use std::os::windows::io::AsSocket;
+        Ok(stream.as_socket().try_clone_to_owned()?.into())
+    }
+}
+
+#[cfg(unix)]
+impl ByteStream {
+    /// Returns the descriptor of the socket
+    ///
+    /// For TLS streams the descriptor of the underlying socket is returned.
+    /// Boxed streams return `Unsupported` error.
+    pub fn try_as_raw_fd(&self) -> io::Result<std::os::unix::io::RawFd> {
+        use std::os::unix::io::AsRawFd;
+
+        match &self.stream {
+            Stream::Boxed(_) => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "boxed stream has no descriptor")),
+            _ => Ok(self.as_raw_fd()),
+        }
+    }
+
+    /// Converts the stream into the socket descriptor
+    ///
+    /// The backpressure token is released, i.e. the connection doesn't
+    /// count towards the limit anymore. The socket is left in non-blocking
+    /// mode.
+    ///
+    /// The descriptor is duplicated and the original one is closed, so the
+    /// returned descriptor is the only one referring to the socket, unless
+    /// the stream was duplicated with [`duplicate`](#method.duplicate).
+    /// Other duplicates share the socket and keep the original descriptor
+    /// open until they are dropped. If duplicating fails (i.e. `EMFILE`),
+    /// the error is returned and the stream is closed.
+    ///
+    /// TLS and boxed streams return `Unsupported` error, because their
+    /// state can't be transferred.
+    pub fn try_into_raw_fd(self)
+        -> io::Result<std::os::unix::io::OwnedFd>
     {
-        match self.stream {
-            Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_write(cx, buf)
+        use std::os::unix::io::AsFd;
+
+        match &self.stream {
+            Stream::Tcp(s) => s.as_fd().try_clone_to_owned(),
+            Stream::Unix(s) => s.as_fd().try_clone_to_owned(),
+            #[cfg(feature="rustls")]
+            Stream::Tls(_) => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "can't convert TLS stream to a descriptor")),
+            Stream::Boxed(_) => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "can't convert boxed stream to a descriptor")),
+        }
+    }
+}
+
+#[cfg(windows)]
+impl ByteStream {
+    /// Returns the socket handle
+    ///
+    /// For TLS streams the handle of the underlying socket is returned.
+    /// Boxed streams return `Unsupported` error.
+    pub fn try_as_raw_socket(&self)
+        -> io::Result<std::os::windows::io::RawSocket>
+    {
+        use std::os::windows::io::AsRawSocket;
+
+        match &self.stream {
+            Stream::Boxed(_) => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "boxed stream has no socket")),
+            _ => Ok(self.as_raw_socket()),
+        }
+    }
+}
+
+/// Returns true if data looks like the start of a TLS connection
+///
+/// Checks that the data starts with a TLS handshake record (`0x16`) of
+/// version 3.x, which is what `ClientHello` of all SSL 3.0 to TLS 1.3
+/// versions looks like. Plaintext protocols like HTTP, RESP or SMTP never
+/// start with these bytes. It's enough to pass a single byte, three bytes
+/// make detection more reliable.
+///
+/// Usually used with [`ByteStream::sniff`](struct.ByteStream.html#method.sniff).
+pub fn looks_like_tls(data: &[u8]) -> bool {
+    match data {
+        [] => false,
+        [0x16] => true,
+        [0x16, 0x03] => true,
+        [0x16, 0x03, minor, ..] => *minor <= 0x04,
+        _ => false,
+    }
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+async fn sendfile(fd: std::os::unix::io::OwnedFd, file: &File,
+    range: Range<u64>, timeout: Option<Duration>)
+    -> io::Result<u64>
+{
+    // same trick as in `peek_unix`
+    let sock = async_io::Async::new(fd)?;
+    let mut offset = range.start;
+    while offset < range.end {
+        let len = (range.end - offset).min(usize::MAX as u64) as usize;
+        let write = sock.write_with(|s| {
+            rustix::fs::sendfile(s, file, Some(&mut offset), len)
+                .map_err(io::Error::from)
+        });
+        if with_timeout(timeout, write).await? == 0 {
+            break;
+        }
+    }
+    Ok(offset - range.start)
+}
+
+async fn copy_half(from: &ByteStream, to: &ByteStream) -> io::Result<u64> {
+    #[cfg(any(target_os="linux", target_os="android"))] {
+        if let (Some(src), Some(dst)) = (from.splice_fd()?, to.splice_fd()?) {
+            let bytes = splice(src, dst,
+                from.read_timeout(), to.write_timeout()).await?;
+            to.close_write().await?;
+            return Ok(bytes);
+        }
+    }
+    let mut reader = from;
+    let mut writer = to;
+    let mut buf = vec![0u8; PROXY_CHUNK];
+    let mut total = 0;
+    loop {
+        let bytes = reader.read(&mut buf).await?;
+        if bytes == 0 {
+            break;
+        }
+        writer.write_all(&buf[..bytes]).await?;
+        total += bytes as u64;
+    }
+    to.close_write().await?;
+    Ok(total)
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+async fn splice(from: std::os::unix::io::OwnedFd,
+    to: std::os::unix::io::OwnedFd,
+    read_timeout: Option<Duration>, write_timeout: Option<Duration>)
+    -> io::Result<u64>
+{
+    use rustix::pipe::{pipe_with, splice, PipeFlags, SpliceFlags};
+
+    let from = async_io::Async::new(from)?;
+    let to = async_io::Async::new(to)?;
+    let (pipe_read, pipe_write) = pipe_with(
+        PipeFlags::CLOEXEC | PipeFlags::NONBLOCK)?;
+    let flags = SpliceFlags::MOVE | SpliceFlags::NONBLOCK;
+    let mut total = 0;
+    loop {
+        // pipe is always drained before reading more, so `EAGAIN` here
+        // means there is no data in the socket
+        let read = from.read_with(|s| {
+            splice(s, None, &pipe_write, None, PROXY_CHUNK, flags)
+                .map_err(io::Error::from)
+        });
+        let mut in_pipe = with_timeout(read_timeout, read).await?;
+        if in_pipe == 0 {
+            return Ok(total);
+        }
+        total += in_pipe as u64;
+        while in_pipe > 0 {
+            let write = to.write_with(|s| {
+                splice(&pipe_read, None, s, None, in_pipe, flags)
+                    .map_err(io::Error::from)
+            });
+            in_pipe -= with_timeout(write_timeout, write).await?;
+        }
+    }
+}
+
+#[cfg(any(target_os="linux", target_os="android"))]
+async fn with_timeout<F, T>(timeout: Option<Duration>, f: F) -> io::Result<T>
+    where F: Future<Output=io::Result<T>>,
+{
+    match timeout {
+        Some(timeout) => async_std::io::timeout(timeout, f).await,
+        None => f.await,
+    }
+}
+
+fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<usize> {
+    #[cfg(unix)] {
+        std::os::unix::fs::FileExt::read_at(file, buf, offset)
+    }
+    #[cfg(windows)] {
+        std::os::windows::fs::FileExt::seek_read(file, buf, offset)
+    }
+}
+
+#[cfg(unix)]
+async fn peek_unix(stream: &UnixStream, buf: &mut [u8]) -> io::Result<usize> {
+    use std::os::unix::io::AsFd;
+    use rustix::net::{recv, RecvFlags};
+
+    // async-std has no `peek` for unix sockets, so register a duplicate
+    // of the descriptor to wait for readiness
+    let fd = stream.as_fd().try_clone_to_owned()?;
+    let sock = async_io::Async::new(std::os::unix::net::UnixStream::from(fd))?;
+    sock.read_with(|s| {
+        recv(s, &mut *buf, RecvFlags::PEEK)
+            .map(|(bytes, _)| bytes)
+            .map_err(io::Error::from)
+    }).await
+}
+
+#[cfg(unix)]
+async fn send_fd_unix(stream: &UnixStream,
+    fd: std::os::unix::io::BorrowedFd<'_>)
+    -> io::Result<()>
+{
+    use std::mem::MaybeUninit;
+    use std::os::unix::io::AsFd;
+    use rustix::net::{sendmsg, SendFlags};
+    use rustix::net::{SendAncillaryBuffer, SendAncillaryMessage};
+
+    // same trick as in `peek_unix`
+    let dup = stream.as_fd().try_clone_to_owned()?;
+    let sock = async_io::Async::new(dup)?;
+    let fds = [fd];
+    sock.write_with(|s| {
+        let mut space =
+            [MaybeUninit::uninit(); rustix::cmsg_space!(ScmRights(1))];
+        let mut control = SendAncillaryBuffer::new(&mut space);
+        control.push(SendAncillaryMessage::ScmRights(&fds));
+        sendmsg(s, &[IoSlice::new(&[0])], &mut control, SendFlags::empty())
+            .map_err(io::Error::from)
+    }).await?;
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn recv_fd_unix(stream: &UnixStream)
+    -> io::Result<std::os::unix::io::OwnedFd>
+{
+    use std::mem::MaybeUninit;
+    use std::os::unix::io::AsFd;
+    use rustix::net::{recvmsg, RecvFlags};
+    use rustix::net::{RecvAncillaryBuffer, RecvAncillaryMessage};
+
+    #[cfg(any(target_os="linux", target_os="android"))]
+    let flags = RecvFlags::CMSG_CLOEXEC;
+    #[cfg(not(any(target_os="linux", target_os="android")))]
+    let flags = RecvFlags::empty();
+
+    let dup = stream.as_fd().try_clone_to_owned()?;
+    let sock = async_io::Async::new(dup)?;
+    sock.read_with(|s| {
+        let mut byte = [0u8];
+        let mut space =
+            [MaybeUninit::uninit(); rustix::cmsg_space!(ScmRights(1))];
+        let mut control = RecvAncillaryBuffer::new(&mut space);
+        let msg = recvmsg(s, &mut [IoSliceMut::new(&mut byte)],
+                          &mut control, flags)?;
+        if msg.bytes == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        // extra descriptors, if any, are closed on drop
+        let mut result = None;
+        for message in control.drain() {
+            if let RecvAncillaryMessage::ScmRights(fds) = message {
+                for fd in fds {
+                    result.get_or_insert(fd);
+                }
             }
+        }
+        result.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+            "no file descriptor received"))
+    }).await
+}
+
+/// Returns the descriptor of the socket
+///
+/// Boxed streams have no descriptor, and `-1` is returned for them. Use
+/// [`ByteStream::try_as_raw_fd`](struct.ByteStream.html#method.try_as_raw_fd)
+/// if the stream can be boxed.
+#[cfg(unix)]
+impl std::os::unix::io::AsRawFd for ByteStream {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        match &self.stream {
+            Stream::Tcp(s) => s.as_raw_fd(),
+            Stream::Unix(s) => s.as_raw_fd(),
+            #[cfg(feature="rustls")]
+            Stream::Tls(s) => lock(s).get_ref().0.as_raw_fd(),
+            Stream::Boxed(_) => -1,
+        }
+    }
+}
+
+/// Returns the socket handle
+///
+/// Boxed streams have no socket, and `INVALID_SOCKET` is returned for them.
+/// Use
+/// [`ByteStream::try_as_raw_socket`](struct.ByteStream.html#method.try_as_raw_socket)
+/// if the stream can be boxed.
+#[cfg(windows)]
+impl std::os::windows::io::AsRawSocket for ByteStream {
+    fn as_raw_socket(&self) -> std::os::windows::io::RawSocket {
+        match &self.stream {
+            Stream::Tcp(s) => s.as_raw_socket(),
+            #[cfg(feature="rustls")]
+            Stream::Tls(s) => lock(s).get_ref().0.as_raw_socket(),
+            Stream::Boxed(_) => !0,  // INVALID_SOCKET
+        }
+    }
+}
+
+impl HasPeerAddr for ByteStream {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.peer_addr()
+    }
+}
+
+/// Convert address of a unix socket, including abstract names on Linux
+#[cfg(unix)]
+pub(crate) fn unix_peer_addr(addr: &UnixSocketAddr) -> PeerAddr {
+    #[cfg(any(target_os="linux", target_os="android"))] {
+        #[cfg(target_os="linux")]
+        use std::os::linux::net::SocketAddrExt;
+        #[cfg(target_os="android")]
+        use std::os::android::net::SocketAddrExt;
+
+        if let Some(name) = addr.as_abstract_name() {
+            return PeerAddr::UnixAbstract(name.to_vec());
+        }
+    }
+    PeerAddr::Unix(addr.as_pathname().map(|p| p.to_owned()))
+}
+
+impl HasPeerAddr for TcpStream {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.peer_addr().map(PeerAddr::Tcp)
+    }
+}
+
+#[cfg(unix)]
+impl HasPeerAddr for UnixStream {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.peer_addr().map(|a| unix_peer_addr(&a))
+    }
+}
+
+/// Uses the address captured at accept time
+///
+/// This is the item type of streams of `TcpListener::accept()` results
+/// produced by some listener wrappers. Peer address is known even if client
+/// has already disconnected.
+impl HasPeerAddr for (TcpStream, SocketAddr) {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        Ok(PeerAddr::Tcp(self.1))
+    }
+}
+
+/// Uses the address captured at accept time
+#[cfg(unix)]
+impl HasPeerAddr for (UnixStream, UnixSocketAddr) {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        Ok(unix_peer_addr(&self.1))
+    }
+}
+
+impl<T: HasPeerAddr> HasPeerAddr for (Token, T) {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.1.get_peer_addr()
+    }
+}
+
+/// Same as [`ByteStream::new_tcp_detached`](#method.new_tcp_detached)
+impl From<TcpStream> for ByteStream {
+    fn from(stream: TcpStream) -> ByteStream {
+        ByteStream::new_tcp_detached(stream)
+    }
+}
+
+/// Same as [`ByteStream::new_unix_detached`](#method.new_unix_detached)
+#[cfg(unix)]
+impl From<UnixStream> for ByteStream {
+    fn from(stream: UnixStream) -> ByteStream {
+        ByteStream::new_unix_detached(stream)
+    }
+}
+
+impl From<(Token, TcpStream)> for ByteStream {
+    fn from((token, stream): (Token, TcpStream)) -> ByteStream {
+        ByteStream::new_tcp(token, stream)
+    }
+}
+
+#[cfg(unix)]
+impl From<(Token, UnixStream)> for ByteStream {
+    fn from((token, stream): (Token, UnixStream)) -> ByteStream {
+        ByteStream::new_unix(token, stream)
+    }
+}
+
+/// Peer address captured at accept time is cached in the stream
+impl From<(Token, (TcpStream, SocketAddr))> for ByteStream {
+    fn from((token, (stream, addr)): (Token, (TcpStream, SocketAddr)))
+        -> ByteStream
+    {
+        let mut stream = ByteStream::new_tcp(token, stream);
+        stream.set_peer_addr(PeerAddr::Tcp(addr));
+        stream
+    }
+}
+
+/// Peer address captured at accept time is cached in the stream
+#[cfg(unix)]
+impl From<(Token, (UnixStream, UnixSocketAddr))> for ByteStream {
+    fn from((token, (stream, addr)): (Token, (UnixStream, UnixSocketAddr)))
+        -> ByteStream
+    {
+        let mut stream = ByteStream::new_unix(token, stream);
+        stream.set_peer_addr(unix_peer_addr(&addr));
+        stream
+    }
+}
+
+/// Attaches backpressure token to the stream
+///
+/// Any token already held by the stream is released. This is used to
+/// apply backpressure to streams yielded by
+/// [`ListenerSet`](struct.ListenerSet.html).
+impl From<(Token, ByteStream)> for ByteStream {
+    fn from((token, stream): (Token, ByteStream)) -> ByteStream {
+        ByteStream {
+            token: Some(token.share()),
+            ..stream
+        }
+    }
+}
+
+impl ByteStream {
+    fn poll_read_raw(&self, cx: &mut Context, buf: &mut [u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        let res = match self.stream {
+            Stream::Tcp(ref s) => Pin::new(&mut &*s).poll_read(cx, buf),
             #[cfg(unix)]
-            Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_write(cx, buf)
-            }
+            Stream::Unix(ref s) => Pin::new(&mut &*s).poll_read(cx, buf),
+            #[cfg(feature="rustls")]
+            Stream::Tls(ref s) => Pin::new(&mut *lock(s)).poll_read(cx, buf),
+            Stream::Boxed(ref s) => Pin::new(&mut *s.lock()).poll_read(cx, buf),
+        };
+        match self.timeouts {
+            Some(ref t) => t.read(cx, res),
+            None => res,
         }
     }
-    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context)
-        -> Poll<Result<(), io::Error>>
+
+    fn poll_read_vectored_raw(&self, cx: &mut Context,
+        bufs: &mut [IoSliceMut])
+        -> Poll<Result<usize, io::Error>>
     {
-        match self.stream {
+        let res = match self.stream {
             Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_flush(cx)
+                Pin::new(&mut &*s).poll_read_vectored(cx, bufs)
             }
             #[cfg(unix)]
             Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_flush(cx)
+                Pin::new(&mut &*s).poll_read_vectored(cx, bufs)
             }
+            #[cfg(feature="rustls")]
+            Stream::Tls(ref s) => {
+                Pin::new(&mut *lock(s)).poll_read_vectored(cx, bufs)
+            }
+            Stream::Boxed(ref s) => {
+                Pin::new(&mut *s.lock()).poll_read_vectored(cx, bufs)
+            }
+        };
+        match self.timeouts {
+            Some(ref t) => t.read(cx, res),
+            None => res,
         }
     }
-    fn poll_close(self: Pin<&mut Self>, cx: &mut Context)
-        -> Poll<Result<(), io::Error>>
+
+    fn poll_write_raw(&self, cx: &mut Context, buf: &[u8])
+        -> Poll<Result<usize, io::Error>>
     {
-        match self.stream {
-            Stream::Tcp(ref s) => {
-                Pin::new(&mut &*s).poll_close(cx)
-            }
+        let res = match self.stream {
+            Stream::Tcp(ref s) => Pin::new(&mut &*s).poll_write(cx, buf),
             #[cfg(unix)]
-            Stream::Unix(ref s) => {
-                Pin::new(&mut &*s).poll_close(cx)
+            Stream::Unix(ref s) => Pin::new(&mut &*s).poll_write(cx, buf),
+            #[cfg(feature="rustls")]
+            Stream::Tls(ref s) => Pin::new(&mut *lock(s)).poll_write(cx, buf),
+            Stream::Boxed(ref s) => {
+                Pin::new(&mut *s.lock()).poll_write(cx, buf)
             }
+        };
+        match self.timeouts {
+            Some(ref t) => t.write(cx, res),
+            None => res,
         }
     }
-    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context,
-        bufs: &[IoSlice])
+
+    fn poll_write_vectored_raw(&self, cx: &mut Context, bufs: &[IoSlice])
         -> Poll<Result<usize, io::Error>>
     {
-        match self.stream {
+        let res = match self.stream {
             Stream::Tcp(ref s) => {
                 Pin::new(&mut &*s).poll_write_vectored(cx, bufs)
             }
@@ -378,6 +1585,183 @@ impl Write for &ByteStream {
             Stream::Unix(ref s) => {
                 Pin::new(&mut &*s).poll_write_vectored(cx, bufs)
             }
+            #[cfg(feature="rustls")]
+            Stream::Tls(ref s) => {
+                Pin::new(&mut *lock(s)).poll_write_vectored(cx, bufs)
+            }
+            Stream::Boxed(ref s) => {
+                Pin::new(&mut *s.lock()).poll_write_vectored(cx, bufs)
+            }
+        };
+        match self.timeouts {
+            Some(ref t) => t.write(cx, res),
+            None => res,
         }
     }
+
+    fn poll_flush_raw(&self, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        let res = match self.stream {
+            Stream::Tcp(ref s) => Pin::new(&mut &*s).poll_flush(cx),
+            #[cfg(unix)]
+            Stream::Unix(ref s) => Pin::new(&mut &*s).poll_flush(cx),
+            #[cfg(feature="rustls")]
+            Stream::Tls(ref s) => Pin::new(&mut *lock(s)).poll_flush(cx),
+            Stream::Boxed(ref s) => Pin::new(&mut *s.lock()).poll_flush(cx),
+        };
+        match self.timeouts {
+            Some(ref t) => t.write(cx, res),
+            None => res,
+        }
+    }
+
+    fn poll_close_raw(&self, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        match self.stream {
+            Stream::Tcp(ref s) => Pin::new(&mut &*s).poll_close(cx),
+            #[cfg(unix)]
+            Stream::Unix(ref s) => Pin::new(&mut &*s).poll_close(cx),
+            #[cfg(feature="rustls")]
+            Stream::Tls(ref s) => Pin::new(&mut *lock(s)).poll_close(cx),
+            Stream::Boxed(ref s) => Pin::new(&mut *s.lock()).poll_close(cx),
+        }
+    }
+}
+
+impl Timeouts {
+    fn read<R>(&self, cx: &mut Context, res: Poll<io::Result<R>>)
+        -> Poll<io::Result<R>>
+    {
+        poll_timeout(&self.read_timer, self.read, cx, res)
+    }
+
+    fn write<R>(&self, cx: &mut Context, res: Poll<io::Result<R>>)
+        -> Poll<io::Result<R>>
+    {
+        poll_timeout(&self.write_timer, self.write, cx, res)
+    }
+}
+
+fn poll_timeout<R>(timer: &Mutex<Option<Timer>>, timeout: Option<Duration>,
+    cx: &mut Context, res: Poll<io::Result<R>>)
+    -> Poll<io::Result<R>>
+{
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return res,
+    };
+    let mut timer = timer.lock().expect("timer lock is not poisoned");
+    if res.is_ready() {
+        *timer = None;
+        return res;
+    }
+    let fut = timer.get_or_insert_with(|| Box::pin(sleep(timeout)));
+    match fut.as_mut().poll(cx) {
+        Poll::Ready(()) => {
+            *timer = None;
+            Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut,
+                "operation timed out")))
+        }
+        Poll::Pending => Poll::Pending,
+    }
+}
+
+/// Timers are not cloned, only the configured durations are
+impl Clone for Timeouts {
+    fn clone(&self) -> Timeouts {
+        Timeouts {
+            read: self.read,
+            write: self.write,
+            read_timer: Mutex::new(None),
+            write_timer: Mutex::new(None),
+        }
+    }
+}
+
+impl fmt::Debug for Timeouts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Timeouts")
+            .field("read", &self.read)
+            .field("write", &self.write)
+            .finish()
+    }
+}
+
+impl Read for ByteStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        self.poll_read_raw(cx, buf)
+    }
+
+    fn poll_read_vectored(self: Pin<&mut Self>, cx: &mut Context,
+        bufs: &mut [IoSliceMut])
+        -> Poll<Result<usize, io::Error>>
+    {
+        self.poll_read_vectored_raw(cx, bufs)
+    }
+}
+
+impl Read for &ByteStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        self.poll_read_raw(cx, buf)
+    }
+    fn poll_read_vectored(self: Pin<&mut Self>, cx: &mut Context,
+        bufs: &mut [IoSliceMut])
+        -> Poll<Result<usize, io::Error>>
+    {
+        self.poll_read_vectored_raw(cx, bufs)
+    }
+}
+
+impl Write for ByteStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        self.poll_write_raw(cx, buf)
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        self.poll_flush_raw(cx)
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        self.poll_close_raw(cx)
+    }
+    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context,
+        bufs: &[IoSlice])
+        -> Poll<Result<usize, io::Error>>
+    {
+        self.poll_write_vectored_raw(cx, bufs)
+    }
+}
+
+impl Write for &ByteStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        self.poll_write_raw(cx, buf)
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        self.poll_flush_raw(cx)
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        self.poll_close_raw(cx)
+    }
+    fn poll_write_vectored(self: Pin<&mut Self>, cx: &mut Context,
+        bufs: &[IoSlice])
+        -> Poll<Result<usize, io::Error>>
+    {
+        self.poll_write_vectored_raw(cx, bufs)
+    }
 }
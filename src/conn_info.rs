@@ -0,0 +1,135 @@
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::time::{Duration, Instant, SystemTime};
+
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
+
+
+/// Metadata of an accepted connection
+///
+/// Yielded by
+/// [`ListenExt::with_conn_info`](trait.ListenExt.html#method.with_conn_info)
+/// along with the connection itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnInfo {
+    id: u64,
+    accepted_at: SystemTime,
+    accepted_instant: Instant,
+}
+
+pin_project! {
+    /// A stream adapter that attaches connection id and accept time
+    ///
+    /// See
+    /// [`ListenExt::with_conn_info`](../trait.ListenExt.html#method.with_conn_info)
+    /// for more info.
+    #[project = WithConnInfoProj]
+    pub struct WithConnInfo<S> {
+        #[pin]
+        stream: S,
+        next_id: u64,
+    }
+}
+
+impl ConnInfo {
+    /// Create metadata for a connection accepted right now
+    pub fn new(id: u64) -> ConnInfo {
+        ConnInfo {
+            id,
+            accepted_at: SystemTime::now(),
+            accepted_instant: Instant::now(),
+        }
+    }
+
+    /// Connection id
+    ///
+    /// Ids start from `1` and are monotonically increasing within a single
+    /// stream, which makes them useful for correlating log lines related
+    /// to the same connection.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Wall clock time when connection was accepted
+    pub fn accepted_at(&self) -> SystemTime {
+        self.accepted_at
+    }
+
+    /// Time elapsed since connection was accepted
+    ///
+    /// This uses monotonic clock, so unlike computing it from
+    /// [`accepted_at`](#method.accepted_at) it's not affected by system
+    /// time changes.
+    pub fn age(&self) -> Duration {
+        self.accepted_instant.elapsed()
+    }
+}
+
+impl fmt::Display for ConnInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.id)
+    }
+}
+
+impl<T: HasPeerAddr> HasPeerAddr for (ConnInfo, T) {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.1.get_peer_addr()
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for WithConnInfo<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithConnInfo")
+            .field("stream", &self.stream)
+            .field("next_id", &self.next_id)
+            .finish()
+    }
+}
+
+impl<S> WithConnInfo<S> {
+    pub(crate) fn new(stream: S) -> WithConnInfo<S> {
+        WithConnInfo { stream, next_id: 1 }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S> Stream for WithConnInfo<S>
+    where S: Stream<Item=I>,
+{
+    type Item = (ConnInfo, I);
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        match this.stream.poll_next(cx) {
+            Poll::Ready(Some(conn)) => {
+                let info = ConnInfo::new(*this.next_id);
+                *this.next_id += 1;
+                Poll::Ready(Some((info, conn)))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
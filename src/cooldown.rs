@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
+use crate::filter::normalize;
+
+
+pin_project! {
+    /// A stream adapter that rejects too frequent reconnects from a peer
+    ///
+    /// See
+    /// [`ListenExt::reconnect_cooldown`](../trait.ListenExt.html#method.reconnect_cooldown)
+    /// for more info.
+    #[project = ReconnectCooldownProj]
+    pub struct ReconnectCooldown<S> {
+        #[pin]
+        stream: S,
+        cooldown: Duration,
+        last_seen: HashMap<IpAddr, Instant>,
+        last_cleanup: Instant,
+        rejected: u64,
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for ReconnectCooldown<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReconnectCooldown")
+            .field("stream", &self.stream)
+            .field("cooldown", &self.cooldown)
+            .field("peers", &self.last_seen.len())
+            .field("rejected", &self.rejected)
+            .finish()
+    }
+}
+
+impl<S> ReconnectCooldown<S> {
+    pub(crate) fn new(stream: S, cooldown: Duration) -> ReconnectCooldown<S> {
+        ReconnectCooldown {
+            stream,
+            cooldown,
+            last_seen: HashMap::new(),
+            last_cleanup: Instant::now(),
+            rejected: 0,
+        }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns number of connections rejected so far
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+}
+
+impl<S> ReconnectCooldownProj<'_, S> {
+    fn allow(&mut self, peer: PeerAddr) -> bool {
+        let addr = match peer {
+            PeerAddr::Tcp(addr) => normalize(addr.ip()),
+            PeerAddr::Unix(_) | PeerAddr::UnixAbstract(_) => return true,
+        };
+        let now = Instant::now();
+        let cooldown = *self.cooldown;
+        if now.duration_since(*self.last_cleanup) >= cooldown {
+            self.last_seen.retain(|_, seen| now.duration_since(*seen) < cooldown);
+            *self.last_cleanup = now;
+        }
+        match self.last_seen.get(&addr) {
+            Some(seen) if now.duration_since(*seen) < cooldown => false,
+            _ => {
+                self.last_seen.insert(addr, now);
+                true
+            }
+        }
+    }
+}
+
+impl<I, S> Stream for ReconnectCooldown<S>
+    where S: Stream<Item=I>,
+          I: HasPeerAddr,
+{
+    type Item = I;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let mut this = self.project();
+        loop {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(conn)) => {
+                    let allowed = match conn.get_peer_addr() {
+                        Ok(peer) => this.allow(peer),
+                        Err(_) => false,
+                    };
+                    if allowed {
+                        return Poll::Ready(Some(conn));
+                    }
+                    // connection is closed on drop
+                    *this.rejected += 1;
+                }
+                other => return other,
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use async_std::future::Future;
+use async_std::io::{Read, Write};
+use async_std::stream::Stream;
+use async_std::task::{sleep, Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
+use crate::socket_options::{ConfigureSocket, SocketOptions};
+
+
+type Timer = Pin<Box<dyn Future<Output=()> + Send + 'static>>;
+
+/// A connection which is closed after a deadline
+///
+/// After the deadline, the underlying connection is dropped (which closes
+/// the socket and releases backpressure token held by it) and all reads and
+/// writes fail with `io::ErrorKind::TimedOut`.
+///
+/// Deadline is checked when connection is read or written, including
+/// when a read is pending, so a handler waiting for data from a forgotten
+/// peer is woken up at the deadline.
+///
+/// Yielded by
+/// [`ListenExt::with_deadline`](trait.ListenExt.html#method.with_deadline).
+pub struct Deadline<T> {
+    stream: Option<T>,
+    deadline: Instant,
+    timer: Timer,
+}
+
+pin_project! {
+    /// A stream adapter that limits lifetime of each connection
+    ///
+    /// See
+    /// [`ListenExt::with_deadline`](../trait.ListenExt.html#method.with_deadline)
+    /// for more info.
+    #[project = WithDeadlineProj]
+    pub struct WithDeadline<S> {
+        #[pin]
+        stream: S,
+        lifetime: Duration,
+    }
+}
+
+fn expired() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "connection deadline expired")
+}
+
+impl<T> Deadline<T> {
+    /// Wrap a connection so that it's closed after `lifetime` from now
+    pub fn new(stream: T, lifetime: Duration) -> Deadline<T> {
+        Deadline {
+            stream: Some(stream),
+            deadline: Instant::now() + lifetime,
+            timer: Box::pin(sleep(lifetime)),
+        }
+    }
+
+    /// Returns the time when connection is closed
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Returns time left before connection is closed
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(Instant::now())
+    }
+
+    /// Returns true if deadline has passed and connection is closed
+    pub fn is_expired(&self) -> bool {
+        self.stream.is_none()
+    }
+
+    /// Acquires a reference to the underlying connection
+    ///
+    /// Returns `None` if the deadline has expired.
+    pub fn get_ref(&self) -> Option<&T> {
+        self.stream.as_ref()
+    }
+
+    /// Acquires a mutable reference to the underlying connection
+    ///
+    /// Returns `None` if the deadline has expired.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.stream.as_mut()
+    }
+
+    /// Consumes the wrapper, returning the underlying connection
+    ///
+    /// Returns `None` if the deadline has expired.
+    pub fn into_inner(self) -> Option<T> {
+        self.stream
+    }
+
+    fn poll_stream(&mut self, cx: &mut Context) -> io::Result<&mut T> {
+        if self.stream.is_some() && self.timer.as_mut().poll(cx).is_ready() {
+            self.stream = None;
+        }
+        self.stream.as_mut().ok_or_else(expired)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Deadline<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Deadline")
+            .field("stream", &self.stream)
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+impl<T: Read + Unpin> Read for Deadline<T> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        match self.get_mut().poll_stream(cx) {
+            Ok(stream) => Pin::new(stream).poll_read(cx, buf),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+}
+
+impl<T: Write + Unpin> Write for Deadline<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        match self.get_mut().poll_stream(cx) {
+            Ok(stream) => Pin::new(stream).poll_write(cx, buf),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        match self.get_mut().poll_stream(cx) {
+            Ok(stream) => Pin::new(stream).poll_flush(cx),
+            Err(e) => Poll::Ready(Err(e)),
+        }
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        match self.get_mut().stream {
+            Some(ref mut stream) => Pin::new(stream).poll_close(cx),
+            // already closed
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl<T: HasPeerAddr> HasPeerAddr for Deadline<T> {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.stream.as_ref().ok_or_else(expired)?.get_peer_addr()
+    }
+}
+
+impl<T: ConfigureSocket> ConfigureSocket for Deadline<T> {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        self.stream.as_ref().ok_or_else(expired)?.configure(options)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for WithDeadline<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithDeadline")
+            .field("stream", &self.stream)
+            .field("lifetime", &self.lifetime)
+            .finish()
+    }
+}
+
+impl<S> WithDeadline<S> {
+    pub(crate) fn new(stream: S, lifetime: Duration) -> WithDeadline<S> {
+        WithDeadline { stream, lifetime }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S> Stream for WithDeadline<S>
+    where S: Stream<Item=I>,
+{
+    type Item = Deadline<I>;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        self.as_mut().project().stream
+        .poll_next(cx)
+        .map(|opt| opt.map(|conn| Deadline::new(conn, self.lifetime)))
+    }
+}
@@ -1,7 +1,12 @@
 #![deny(meta_variable_misuse)]
 
+use std::borrow::Cow;
 use std::fmt;
 use std::io;
+use std::sync::RwLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::fd_usage::{self, FdUsage};
 
 /// Error hint that can be formatted
 ///
@@ -16,8 +21,10 @@ use std::io;
 /// See [error description](../errors/index.html) for a list of the errors that
 /// can return a hint.
 ///
-/// You can also apply a custom formatting (i.e. a different link) for the
-/// hint. Just replace an `error_hint` function with something of your own:
+/// To point all the links to your own docs, use
+/// [`set_link_base`](../fn.set_link_base.html). You can also apply a custom
+/// formatting for the hint. Just replace an `error_hint` function with
+/// something of your own:
 ///
 /// ```
 /// # use std::io;
@@ -33,13 +40,88 @@ use std::io;
 /// ```
 #[derive(Debug)]
 pub struct ErrorHint {
-    error: Option<KnownError>
+    error: Option<HintKind>,
+    custom: Option<CustomHint>,
+    transient: bool,
+    code: Option<i32>,
+    fd_usage: Option<FdUsage>,
+    link_base: Option<Cow<'static, str>>,
 }
 
-#[derive(Debug)]
-enum KnownError {
+/// Errors matched by a hint registered with
+/// [`register_hint`](fn.register_hint.html)
+///
+/// Usually created implicitly from a raw os error code (`i32`) or an
+/// `io::ErrorKind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HintMatch {
+    /// Matches errors having this raw os error code
+    OsError(i32),
+    /// Matches errors of this kind
+    Kind(io::ErrorKind),
+}
+
+#[derive(Debug, Clone)]
+struct CustomHint {
+    matcher: HintMatch,
+    text: Cow<'static, str>,
+    link: Cow<'static, str>,
+}
+
+static CUSTOM_HINTS: RwLock<Vec<CustomHint>> = RwLock::new(Vec::new());
+static LINK_BASE: RwLock<Option<Cow<'static, str>>> = RwLock::new(None);
+static FD_DIAGNOSTICS: AtomicBool = AtomicBool::new(false);
+
+/// How bad the error is
+///
+/// Returned by
+/// [`ErrorHint::severity`](wrapper_types/struct.ErrorHint.html#method.severity)
+/// to map errors to log levels and alert policies. Levels are ordered, so
+/// `severity >= Severity::Warning` works as expected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Error affects a single connection and can be ignored
+    Info,
+    /// Error may need attention if it repeats
+    Warning,
+    /// Error affects all connections (or the whole system) and needs an
+    /// action of the operator
+    Critical,
+}
+
+/// Kind of the error that has a built-in hint
+///
+/// Returned by [`ErrorHint::kind`](wrapper_types/struct.ErrorHint.html#method.kind),
+/// so programs can tell the errors apart without matching the text. Names
+/// follow POSIX error names (which are also the link hashes) and don't
+/// change in future versions, but new kinds may be added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HintKind {
+    /// Too many open files in system (`ENFILE`)
     Enfile,
+    /// Too many open files (`EMFILE`)
     Emfile,
+    /// Address already in use (`EADDRINUSE`)
+    Eaddrinuse,
+    /// No buffer space available (`ENOBUFS`)
+    Enobufs,
+    /// Cannot allocate memory (`ENOMEM`)
+    Enomem,
+    /// Cannot assign requested address (`EADDRNOTAVAIL`)
+    Eaddrnotavail,
+    /// Operation not permitted (`EPERM`)
+    Eperm,
+    /// Protocol error (`EPROTO`)
+    Eproto,
+    /// Permission denied on bind (`EACCES`)
+    Eacces,
+    /// Unix socket directory doesn't exist on bind (`ENOENT`)
+    Enoent,
+    /// Unix socket file already exists on bind (`EADDRINUSE`)
+    UnixEaddrinuse,
+    /// No permission to create Unix socket file on bind (`EACCES`)
+    UnixEacces,
 }
 
 /// Returns true if the error is transient
@@ -51,6 +133,13 @@ enum KnownError {
 /// system call means then next connection might be ready to be accepted
 /// immediately.
 ///
+/// Besides connection refused, aborted and reset errors, the following
+/// errors are transient: `EINTR`, `ENETDOWN`, `ENETUNREACH`, `EHOSTUNREACH`
+/// and `EPROTO` (and their Winsock equivalents). Linux may return network
+/// errors of the pending connection from `accept()`, and these errors
+/// don't have a stable `ErrorKind` on all Rust versions, so they are
+/// matched by code.
+///
 /// All other errors should incur a timeout before the next `accept()` is
 /// performed.  The timeout is useful to handle resource exhaustion errors
 /// like ENFILE and EMFILE: file descriptor might be released after some time
@@ -63,31 +152,90 @@ enum KnownError {
 pub fn is_transient_error(e: &io::Error) -> bool {
     e.kind() == io::ErrorKind::ConnectionRefused ||
     e.kind() == io::ErrorKind::ConnectionAborted ||
-    e.kind() == io::ErrorKind::ConnectionReset
+    e.kind() == io::ErrorKind::ConnectionReset ||
+    is_transient_code(e.raw_os_error())
+}
+
+/// A policy deciding which accept errors are transient
+///
+/// Transient errors are skipped without a delay by
+/// [`handle_errors`](trait.ListenExt.html#method.handle_errors) and aren't
+/// reported by [`log_warnings`](trait.ListenExt.html#method.log_warnings).
+/// The default policy is [`is_transient_error`](fn.is_transient_error.html),
+/// use the `transient_policy` method of the adapters to change it.
+///
+/// The trait is implemented for functions and closures
+/// `Fn(&io::Error) -> bool`, so the default list can be extended like this:
+///
+/// ```
+/// # use std::io;
+/// use async_listen::is_transient_error;
+///
+/// fn is_transient(e: &io::Error) -> bool {
+///     // EPERM is returned when a firewall drops the pending connection
+///     is_transient_error(e) || e.kind() == io::ErrorKind::PermissionDenied
+/// }
+/// ```
+pub trait TransientPolicy {
+    /// Returns true if the error is transient
+    fn is_transient(&self, e: &io::Error) -> bool;
+}
+
+impl<F: Fn(&io::Error) -> bool> TransientPolicy for F {
+    fn is_transient(&self, e: &io::Error) -> bool {
+        self(e)
+    }
+}
+
+pub(crate) type Transient = Box<dyn TransientPolicy + Send + 'static>;
+
+pub(crate) fn default_transient() -> Transient {
+    Box::new(is_transient_error)
 }
 
+// Unix codes differ between systems, so they are taken from `rustix`
+// (i.e. `EADDRINUSE` is 98 on Linux, 48 on BSDs and 125 on Solaris)
 macro_rules! error_match {
     ($value:expr => {
         $(
-        ($n: pat | wasi: $wasi: pat | haiku: $haiku:pat) => $val: ident,
+        (unix: $unix:ident
+         $(| windows: $windows:pat)?
+         $(| wasi: $wasi:pat)?)
+            => $val: ident,
         )*
     }) => {
         match $value {$(
-            #[cfg(any(target_env="wasi", target_os="wasi"))]
+            #[cfg(unix)]
+            Some(code) if code == rustix::io::Errno::$unix.raw_os_error()
+                => Some($val),
+            $(
+            #[cfg(windows)]
+            Some($windows) => Some($val),
+            )?
+            $(
+            #[cfg(target_os="wasi")]
             Some($wasi) => Some($val),
-            #[cfg(target_os="haiku")]
-            Some($haiku) => Some($val),
-            #[cfg(all(
-                any(unix, windows, target_os="fuchsia"),
-                not(any(target_env="wasi", target_os="wasi",target_os="haiku"))
-            ))]
-            Some($n) => Some($val),
+            )?
         )*
             _ => None,
         }
     }
 }
 
+fn is_transient_code(code: Option<i32>) -> bool {
+    struct Matched;
+    error_match!(code => {
+        (unix: INTR | windows: 10004 | wasi: 27) => Matched,
+        (unix: NETDOWN | windows: 10050 | wasi: 38) => Matched,
+        (unix: NETUNREACH | windows: 10051 | wasi: 40) => Matched,
+        (unix: HOSTUNREACH | windows: 10065 | wasi: 23) => Matched,
+        (unix: CONNABORTED | windows: 10053 | wasi: 13) => Matched,
+        (unix: CONNRESET | windows: 10054 | wasi: 15) => Matched,
+        (unix: CONNREFUSED | windows: 10061 | wasi: 14) => Matched,
+        (unix: PROTO | wasi: 65) => Matched,
+    }).is_some()
+}
+
 /// Returns a hint structure that can be formatter to the log output
 ///
 /// # Example
@@ -110,16 +258,275 @@ macro_rules! error_match {
 ///
 /// [`ErrorHint`]: wrapper_types/struct.ErrorHint.html
 pub fn error_hint(e: &io::Error) -> ErrorHint {
-    use KnownError::*;
+    use HintKind::*;
     let error = error_match!(e.raw_os_error() => {
-        (24 | wasi: 33 | haiku: -2147459062) => Emfile,
-        (23 | wasi: 41 | haiku: -2147454970) => Enfile,
+        (unix: MFILE | windows: (24 | 10024) | wasi: 33) => Emfile,
+        (unix: NFILE | windows: 23 | wasi: 41) => Enfile,
+        (unix: ADDRINUSE | windows: 10048 | wasi: 3) => Eaddrinuse,
+        (unix: NOBUFS | windows: 10055 | wasi: 42) => Enobufs,
+        (unix: NOMEM | windows: 8 | wasi: 48) => Enomem,
+        (unix: ADDRNOTAVAIL | windows: 10049 | wasi: 4) => Eaddrnotavail,
+        // no equivalent error codes in Winsock
+        (unix: PERM | wasi: 63) => Eperm,
+        (unix: PROTO | wasi: 65) => Eproto,
     });
-    return ErrorHint { error }
+    ErrorHint::new(e, error)
+}
+
+/// Returns a hint for the error returned by `bind()`
+///
+/// This is the same as [`error_hint`](fn.error_hint.html), but also
+/// includes hints for errors which only make sense when creating a
+/// listening socket, like permission denied for privileged ports or a
+/// missing directory of the Unix socket path. These errors are common at
+/// startup, while in `accept()` the same error codes mean something else.
+///
+/// # Example
+/// ```no_run
+/// # use async_std::task;
+/// # use async_std::net::TcpListener;
+/// use async_listen::bind_error_hint;
+///
+/// # task::block_on(async {
+/// let listener = match TcpListener::bind("0.0.0.0:80").await {
+///     Ok(listener) => listener,
+///     Err(e) => {
+///         eprintln!("Can't listen: {}. {}", e, bind_error_hint(&e));
+///         std::process::exit(1);
+///     }
+/// };
+/// # });
+/// ```
+///
+/// Error message might look like:
+/// ```text
+/// Can't listen: Permission denied (os error 13). Use port 1024 or above, or allow binding privileged ports https://bit.ly/async-err#EACCES
+/// ```
+pub fn bind_error_hint(e: &io::Error) -> ErrorHint {
+    use HintKind::*;
+    let error = error_match!(e.raw_os_error() => {
+        (unix: ACCESS | windows: 10013 | wasi: 2) => Eacces,
+        (unix: NOENT | windows: (2 | 3) | wasi: 44) => Enoent,
+    });
+    match error {
+        Some(_) => ErrorHint::new(e, error),
+        None => error_hint(e),
+    }
+}
+
+/// Returns a hint for the error returned by `bind()` of a Unix socket
+///
+/// This is the same as [`bind_error_hint`](fn.bind_error_hint.html), but
+/// "address in use" and "permission denied" errors get hints about the
+/// socket file instead of the TCP port.
+///
+/// # Example
+/// ```no_run
+/// # #[cfg(unix)]
+/// # async_std::task::block_on(async {
+/// use async_std::os::unix::net::UnixListener;
+/// use async_listen::unix_bind_error_hint;
+///
+/// let listener = match UnixListener::bind("/run/app/app.sock").await {
+///     Ok(listener) => listener,
+///     Err(e) => {
+///         eprintln!("Can't listen: {}. {}", e, unix_bind_error_hint(&e));
+///         std::process::exit(1);
+///     }
+/// };
+/// # });
+/// ```
+pub fn unix_bind_error_hint(e: &io::Error) -> ErrorHint {
+    use HintKind::*;
+
+    let error = error_match!(e.raw_os_error() => {
+        (unix: ADDRINUSE | windows: 10048 | wasi: 3) => UnixEaddrinuse,
+        (unix: ACCESS | windows: 10013 | wasi: 2) => UnixEacces,
+    });
+    match error {
+        Some(_) => ErrorHint::new(e, error),
+        None => bind_error_hint(e),
+    }
+}
+
+/// Registers a hint for errors not covered by built-in hints
+///
+/// [`error_hint`](fn.error_hint.html) consults registered hints after the
+/// built-in ones, so this can be used to add hints for failures specific
+/// to your infrastructure, linking to internal runbooks. Hints are matched
+/// in the order of registration. Registering a hint for the same error
+/// again replaces the previous one.
+///
+/// The `link` is printed as is. If it contains a hash `#` sign, the part
+/// after the sign is returned by
+/// [`ErrorHint::link_hash`](wrapper_types/struct.ErrorHint.html#method.link_hash).
+///
+/// Both string literals and owned strings (i.e. read from a config file)
+/// are accepted.
+///
+/// # Example
+///
+/// ```
+/// # use std::io;
+/// use async_listen::{register_hint, error_hint};
+///
+/// register_hint(io::ErrorKind::PermissionDenied,
+///     "Check that the service runs as the right user",
+///     "https://wiki.example.org/runbooks/listen#permissions");
+///
+/// let e = io::Error::from(io::ErrorKind::PermissionDenied);
+/// assert_eq!(error_hint(&e).link_hash(), "permissions");
+/// ```
+pub fn register_hint<M, T, L>(error: M, text: T, link: L)
+    where M: Into<HintMatch>,
+          T: Into<Cow<'static, str>>,
+          L: Into<Cow<'static, str>>,
+{
+    let matcher = error.into();
+    let hint = CustomHint { matcher, text: text.into(), link: link.into() };
+    let mut hints = CUSTOM_HINTS.write()
+        .expect("hint registry is not poisoned");
+    match hints.iter_mut().find(|h| h.matcher == matcher) {
+        Some(existing) => *existing = hint,
+        None => hints.push(hint),
+    }
+}
+
+/// Sets the base link printed with built-in hints
+///
+/// By default links point to the [error description](errors/index.html)
+/// of this crate. Organizations having their own runbooks can set the base
+/// once at startup, and all the hints (including ones in
+/// [`ListenerError`](struct.ListenerError.html)) will link there. The
+/// [link hash](wrapper_types/struct.ErrorHint.html#method.link_hash) is
+/// appended after the `#` sign. Links of hints registered by
+/// [`register_hint`](fn.register_hint.html) aren't changed.
+///
+/// The base may be a string literal or an owned string (i.e. read from a
+/// config file).
+///
+/// # Example
+///
+/// ```
+/// use async_listen::set_link_base;
+///
+/// set_link_base("https://wiki.example.org/runbooks/listen");
+/// ```
+pub fn set_link_base<B: Into<Cow<'static, str>>>(base: B) {
+    *LINK_BASE.write().expect("link base is not poisoned") = Some(base.into());
+}
+
+/// Enables file descriptor diagnostics in the `EMFILE` hint
+///
+/// When enabled, [`error_hint`](fn.error_hint.html) reads the open file
+/// limit of the process and counts open descriptors at the time of the
+/// error, and the hint includes the numbers:
+/// ```text
+/// Increase per-process open file limit (open: 1024, limit: 1024, hard limit: 4096) https://bit.ly/async-err#EMFILE
+/// ```
+/// Diagnostics are only supported on Linux and MacOS. They are disabled by
+/// default, because counting descriptors requires listing a directory
+/// which is slow when there are many of them.
+pub fn set_fd_diagnostics(enabled: bool) {
+    FD_DIAGNOSTICS.store(enabled, Ordering::Relaxed);
+}
+
+impl HintMatch {
+    fn matches(&self, e: &io::Error) -> bool {
+        match *self {
+            HintMatch::OsError(code) => e.raw_os_error() == Some(code),
+            HintMatch::Kind(kind) => e.kind() == kind,
+        }
+    }
+}
+
+impl From<i32> for HintMatch {
+    fn from(code: i32) -> HintMatch {
+        HintMatch::OsError(code)
+    }
+}
+
+impl From<io::ErrorKind> for HintMatch {
+    fn from(kind: io::ErrorKind) -> HintMatch {
+        HintMatch::Kind(kind)
+    }
 }
 
 
 impl ErrorHint {
+    fn new(e: &io::Error, error: Option<HintKind>) -> ErrorHint {
+        let custom = if error.is_none() {
+            CUSTOM_HINTS.read().expect("hint registry is not poisoned")
+                .iter().find(|hint| hint.matcher.matches(e)).cloned()
+        } else {
+            None
+        };
+        let transient = is_transient_error(e);
+        let code = e.raw_os_error();
+        let fd_usage = if error == Some(HintKind::Emfile)
+            && FD_DIAGNOSTICS.load(Ordering::Relaxed)
+        {
+            fd_usage::query()
+        } else {
+            None
+        };
+        let link_base = if error.is_some() {
+            LINK_BASE.read().expect("link base is not poisoned").clone()
+        } else {
+            None
+        };
+        ErrorHint { error, custom, transient, code, fd_usage, link_base }
+    }
+
+    /// Kind of the error if it has a built-in hint
+    ///
+    /// Returns `None` if there is no hint or the hint is registered by
+    /// [`register_hint`](../fn.register_hint.html).
+    ///
+    /// ```
+    /// # use std::io;
+    /// use async_listen::{error_hint, HintKind};
+    ///
+    /// # #[cfg(unix)] {
+    /// let e = io::Error::from_raw_os_error(24);  // EMFILE on most systems
+    /// assert_eq!(error_hint(&e).kind(), Some(HintKind::Emfile));
+    /// # }
+    /// ```
+    pub fn kind(&self) -> Option<HintKind> {
+        self.error
+    }
+
+    /// Severity of the error
+    ///
+    /// Errors exhausting resources of the process or the system (`EMFILE`,
+    /// `ENFILE`, `ENOMEM`, `ENOBUFS`) and errors of `bind()` are
+    /// `Critical`. Transient errors (see
+    /// [`is_transient_error`](../fn.is_transient_error.html)) and `EPROTO`
+    /// are `Info`. Everything else, including errors with hints registered
+    /// by [`register_hint`](../fn.register_hint.html), is `Warning`.
+    ///
+    /// ```
+    /// # use std::io;
+    /// use async_listen::{error_hint, Severity};
+    ///
+    /// let e = io::ErrorKind::ConnectionAborted.into();
+    /// assert_eq!(error_hint(&e).severity(), Severity::Info);
+    /// ```
+    pub fn severity(&self) -> Severity {
+        use HintKind::*;
+        match self.error {
+            Some(Enfile) | Some(Emfile) | Some(Enobufs) | Some(Enomem)
+            | Some(Eaddrinuse) | Some(Eaddrnotavail)
+            | Some(Eacces) | Some(Enoent)
+            | Some(UnixEaddrinuse) | Some(UnixEacces)
+            => Severity::Critical,
+            Some(Eperm) => Severity::Warning,
+            Some(Eproto) => Severity::Info,
+            None if self.transient => Severity::Info,
+            None => Severity::Warning,
+        }
+    }
+
     /// Text of the hint
     ///
     /// Since the text is expected to be printed **after** the error message,
@@ -130,12 +537,28 @@ impl ErrorHint {
     ///
     /// Usually the hint is good enough to use search engine to find the
     /// solution to the problem. But usually link is printed too.
-    pub fn hint_text(&self) -> &'static str {
-        use KnownError::*;
+    pub fn hint_text(&self) -> &str {
+        use HintKind::*;
         match &self.error {
-            None => "",
+            None => self.custom.as_ref().map_or("", |c| &c.text),
             Some(Emfile) => "Increase per-process open file limit",
             Some(Enfile) => "Increase system open file limit",
+            Some(Eaddrinuse) => "Make sure no other process is listening \
+                                 on the same address",
+            Some(Enobufs) => "Increase network buffer memory limits",
+            Some(Enomem) => "Reduce memory usage or increase memory limit",
+            Some(Eaddrnotavail) => "Bind to an address of existing network \
+                                    interface or to 0.0.0.0 / [::]",
+            Some(Eperm) => "Check firewall and seccomp rules",
+            Some(Eproto) => "Client aborted connection during handshake, \
+                             usually safe to ignore",
+            Some(Eacces) => "Use port 1024 or above, or allow binding \
+                             privileged ports",
+            Some(Enoent) => "Create the directory of the Unix socket",
+            Some(UnixEaddrinuse) => "Remove stale socket file if no other \
+                                     process is listening on it",
+            Some(UnixEacces) => "Allow the user to write to the directory \
+                                 of the Unix socket",
         }
     }
 
@@ -144,7 +567,7 @@ impl ErrorHint {
     /// To make a link prepend with the base URL:
     /// ```
     /// # let h = async_listen::error_hint(&std::io::ErrorKind::Other.into());
-    /// println!("{}#{}", h.default_link_base(), h.link_hash())
+    /// println!("{}#{}", h.link_base(), h.link_hash())
     /// ```
     ///
     /// It's expected that implementation may customize base link. Mathing
@@ -152,12 +575,24 @@ impl ErrorHint {
     /// change the link only for one of few errors.
     ///
     /// Link hashes are stable (we don't change them in future versions).
-    pub fn link_hash(&self) -> &'static str {
-        use KnownError::*;
+    pub fn link_hash(&self) -> &str {
+        use HintKind::*;
         match &self.error {
-            None => "",
+            None => self.custom.as_ref()
+                .and_then(|c| c.link.split_once('#'))
+                .map_or("", |(_, hash)| hash),
             Some(Emfile) => "EMFILE",
             Some(Enfile) => "ENFILE",
+            Some(Eaddrinuse) => "EADDRINUSE",
+            Some(Enobufs) => "ENOBUFS",
+            Some(Enomem) => "ENOMEM",
+            Some(Eaddrnotavail) => "EADDRNOTAVAIL",
+            Some(Eperm) => "EPERM",
+            Some(Eproto) => "EPROTO",
+            Some(Eacces) => "EACCES",
+            Some(Enoent) => "ENOENT",
+            Some(UnixEaddrinuse) => "UNIX-EADDRINUSE",
+            Some(UnixEacces) => "UNIX-EACCES",
         }
     }
 
@@ -173,7 +608,44 @@ impl ErrorHint {
     /// println!("{}#{}", h.default_link_base(), h.link_hash())
     /// ```
     pub fn default_link_base(&self) -> &'static str {
-        return "https://bit.ly/async-err";
+        "https://bit.ly/async-err"
+    }
+
+    /// Returns the full link printed with the hint
+    ///
+    /// This is [`link_base`](#method.link_base) followed by
+    /// the [`link_hash`](#method.link_hash) for built-in hints and the link
+    /// as registered for custom ones. Returns an empty string if the hint
+    /// is empty.
+    pub fn link(&self) -> String {
+        if self.error.is_none() {
+            return self.custom.as_ref()
+                .map_or(String::new(), |c| c.link.to_string());
+        }
+        format!("{}#{}", self.link_base(), self.link_hash())
+    }
+
+    /// Returns base link printed with the hint
+    ///
+    /// This is the link set by [`set_link_base`](../fn.set_link_base.html)
+    /// (at the time the hint was created) or
+    /// [`default_link_base`](#method.default_link_base) if it isn't set.
+    pub fn link_base(&self) -> &str {
+        self.link_base.as_deref()
+            .unwrap_or_else(|| self.default_link_base())
+    }
+
+    /// File descriptor usage at the time of the error
+    ///
+    /// Only returned for `EMFILE` errors when enabled by
+    /// [`set_fd_diagnostics`](../fn.set_fd_diagnostics.html).
+    pub fn fd_usage(&self) -> Option<FdUsage> {
+        self.fd_usage
+    }
+
+    /// Raw os error code of the error the hint was created for
+    pub fn code(&self) -> Option<i32> {
+        self.code
     }
 
     /// Returns true if the hint is empty
@@ -183,16 +655,65 @@ impl ErrorHint {
     /// empty when displayed. This is a convenience in most cases, but you
     /// have to check for `is_empty` when formatting your own hint.
     pub fn is_empty(&self) -> bool {
-        self.error.is_some()
+        self.error.is_none() && self.custom.is_none()
+    }
+}
+
+/// Serialized as a structure with `code`, `hint_text`, `link` and
+/// `severity` fields
+///
+/// Text and link are `null` if the hint is empty, so hints can be emitted
+/// as structured log fields.
+#[cfg(feature="serde")]
+impl serde::Serialize for ErrorHint {
+    fn serialize<S: serde::Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        use serde::ser::SerializeStruct;
+
+        let empty = self.is_empty();
+        let mut s = serializer.serialize_struct("ErrorHint", 4)?;
+        s.serialize_field("code", &self.code)?;
+        s.serialize_field("hint_text",
+            &Some(self.hint_text()).filter(|_| !empty))?;
+        s.serialize_field("link", &Some(self.link()).filter(|_| !empty))?;
+        s.serialize_field("severity", &self.severity())?;
+        s.end()
+    }
+}
+
+/// Serialized as a lowercase string: `info`, `warning` or `critical`
+#[cfg(feature="serde")]
+impl serde::Serialize for Severity {
+    fn serialize<S: serde::Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => f.write_str("info"),
+            Severity::Warning => f.write_str("warning"),
+            Severity::Critical => f.write_str("critical"),
+        }
     }
 }
 
 impl fmt::Display for ErrorHint {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.error.is_none() {
+            if let Some(custom) = &self.custom {
+                return write!(f, "{} {}", custom.text, custom.link);
+            }
             return Ok(())
         }
-        write!(f, "{} {}#{}",
-            self.hint_text(), self.default_link_base(), self.link_hash())
+        f.write_str(self.hint_text())?;
+        if let Some(usage) = &self.fd_usage {
+            write!(f, " ({})", usage)?;
+        }
+        write!(f, " {}#{}", self.link_base(), self.link_hash())
     }
 }
@@ -7,12 +7,34 @@
 //!
 //! * [Too many open files](#EMFILE) / [EMFILE](#EMFILE)
 //! * [Too many open files in system](#ENFILE) / [ENFILE](#ENFILE)
+//! * [Address already in use](#EADDRINUSE) / [EADDRINUSE](#EADDRINUSE)
+//! * [No buffer space available](#ENOBUFS) / [ENOBUFS](#ENOBUFS)
+//! * [Cannot allocate memory](#ENOMEM) / [ENOMEM](#ENOMEM)
+//! * [Cannot assign requested address](#EADDRNOTAVAIL) /
+//!   [EADDRNOTAVAIL](#EADDRNOTAVAIL)
+//! * [Operation not permitted](#EPERM) / [EPERM](#EPERM)
+//! * [Protocol error](#EPROTO) / [EPROTO](#EPROTO)
+//!
+//! Errors having a hint only when returned by `bind()` (see
+//! [`bind_error_hint`](../fn.bind_error_hint.html)):
+//!
+//! * [Permission denied](#EACCES) / [EACCES](#EACCES)
+//! * [No such file or directory](#ENOENT) / [ENOENT](#ENOENT)
+//!
+//! Errors having a hint when returned by `bind()` of a Unix socket (see
+//! [`unix_bind_error_hint`](../fn.unix_bind_error_hint.html)):
+//!
+//! * [Address already in use](#UNIX-EADDRINUSE) /
+//!   [EADDRINUSE](#UNIX-EADDRINUSE)
+//! * [Permission denied](#UNIX-EACCES) / [EACCES](#UNIX-EACCES)
+//! * [No such file or directory](#ENOENT) / [ENOENT](#ENOENT)
 //!
 //!
 //! # Too Many Open Files <a name='EMFILE'></a>
 //!
 //! | Posix Name | EMFILE |
 //! |---|---|
+//! | Windows Name | WSAEMFILE (10024) |
 //! | Message | `Too many open files (os error 24)` |
 //! | Hint | `Increase per-process open file limit` |
 //! | Link | `https://bit.ly/async-err#EMFILE` |
@@ -27,6 +49,8 @@
 //! crate.
 //!
 //! The rest of this section discusses how to change file decriptor limit.
+//! To see the current limit and number of open descriptors right in the
+//! log message, enable [`set_fd_diagnostics`](../fn.set_fd_diagnostics.html).
 //!
 //! [`backpressure`]: ../backpressure/fn.new.html
 //!
@@ -89,6 +113,13 @@
 //!
 //! [More information](https://duckduckgo.com/?q=Increase+per-process+open+file+limit+macos)
 //!
+//! ## Windows
+//!
+//! On Windows the error is `WSAEMFILE` and there is no per-process limit to
+//! raise: the error means that the process has too many handles open. Most
+//! likely it's a handle leak or unlimited number of connections, see (2)
+//! above.
+//!
 //! # Too Many Open Files in System <a name='ENFILE'></a>
 //!
 //! | Posix Name | ENFILE |
@@ -158,3 +189,416 @@
 //! ```
 //!
 //! [More information](https://duckduckgo.com/?q=Increase+system+open+file+limit+macos)
+//!
+//! # Address Already in Use <a name='EADDRINUSE'></a>
+//!
+//! | Posix Name | EADDRINUSE |
+//! |---|---|
+//! | Windows Name | WSAEADDRINUSE (10048) |
+//! | Message | `Address already in use (os error 98)` |
+//! | Hint | `Make sure no other process is listening on the same address` |
+//! | Link | `https://bit.ly/async-err#EADDRINUSE` |
+//!
+//! The error is returned by `bind()` rather than `accept()`. Error code is
+//! `98` on Linux, `48` on MacOS and BSDs.
+//!
+//! ## Common Causes
+//!
+//! 1. Another instance of the application is already running
+//! 2. Some other application uses the same port
+//! 3. Previous instance has just exited and its connections are in
+//!    `TIME_WAIT` state, while the socket is bound without `SO_REUSEADDR`
+//! 4. For Unix sockets: socket file is left by the previous instance
+//!
+//! The (3) doesn't happen with `TcpListener` of the standard library and
+//! `async-std` on Unix systems, as they set `SO_REUSEADDR` option
+//! automatically. But it may happen if socket is created by other means.
+//!
+//! ## Finding the Process
+//!
+//! On Linux (replace `8080` with your port):
+//! ```console
+//! $ sudo ss -ltnp 'sport = :8080'
+//! ```
+//!
+//! On MacOS and BSDs:
+//! ```console
+//! $ sudo lsof -nP -iTCP:8080 -sTCP:LISTEN
+//! ```
+//!
+//! On Windows:
+//! ```console
+//! > netstat -ano | findstr :8080
+//! ```
+//! (the last column is process id, look it up in Task Manager)
+//!
+//! If it's an old instance of your application, stop it before starting
+//! a new one, or consider using a supervisor that does this for you.
+//!
+//! ## Unix Sockets
+//!
+//! Unlike TCP, Unix socket leaves a file on disk, which isn't removed when
+//! the process exits. Binding to an existing file fails even if no process
+//! listens on it. The usual approach is to remove the file before binding:
+//! ```rust,no_run
+//! # async fn f() -> std::io::Result<()> {
+//! # use async_std::os::unix::net::UnixListener;
+//! std::fs::remove_file("./app.sock").ok();
+//! let listener = UnixListener::bind("./app.sock").await?;
+//! # Ok(())
+//! # }
+//! ```
+//! But make sure that no other instance is running, as removing the file
+//! of a running instance makes it silently unreachable.
+//!
+//! ## Windows
+//!
+//! On Windows, `SO_REUSEADDR` allows binding to the port used by **any**
+//! other socket, which is a security issue. So the option isn't set by
+//! default, and the port of a recently stopped process might be
+//! unavailable for some time. Use `SO_EXCLUSIVEADDRUSE` instead, if you
+//! set socket options yourself.
+//!
+//! [More information](https://duckduckgo.com/?q=Address+already+in+use+bind)
+//!
+//! # No Buffer Space Available <a name='ENOBUFS'></a>
+//!
+//! | Posix Name | ENOBUFS |
+//! |---|---|
+//! | Windows Name | WSAENOBUFS (10055) |
+//! | Message | `No buffer space available (os error 105)` |
+//! | Hint | `Increase network buffer memory limits` |
+//! | Link | `https://bit.ly/async-err#ENOBUFS` |
+//!
+//! ## Common Causes
+//!
+//! 1. Memory allowed for network buffers in the kernel is exhausted
+//! 2. Too many simultaneous connections, each of them holding buffers
+//! 3. System is low on memory in general
+//!
+//! The (2) can be fixed by applying [`backpressure`] abstraction from this
+//! crate. Also consider lowering buffer sizes of each socket (see
+//! [`SocketOptions`](../struct.SocketOptions.html)) if connections are
+//! mostly idle.
+//!
+//! ## Linux
+//!
+//! Memory used by TCP sockets (in pages) and the limits are shown by:
+//! ```console
+//! $ cat /proc/net/sockstat
+//! $ sysctl net.ipv4.tcp_mem
+//! net.ipv4.tcp_mem = 188508 251347 377016
+//! ```
+//! The third number is the hard limit. Other relevant settings are
+//! `net.core.rmem_max`, `net.core.wmem_max` (maximum buffer size of a
+//! single socket) and `net.core.optmem_max`. For example:
+//! ```console
+//! $ sudo sysctl -w net.ipv4.tcp_mem="377016 502694 754032"
+//! ```
+//! Add the same setting to `/etc/sysctl.conf` to make it persistent.
+//!
+//! [More information](https://duckduckgo.com/?q=linux+tcp_mem+No+buffer+space+available)
+//!
+//! ## MacOS and BSDs
+//!
+//! Usually means that network memory buffers (mbufs) are exhausted. Check
+//! the usage with:
+//! ```console
+//! $ netstat -m
+//! ```
+//! On FreeBSD the limit is `kern.ipc.nmbclusters` (set in
+//! `/boot/loader.conf`), on MacOS see `kern.ipc.maxsockbuf` and
+//! `kern.ipc.nmbclusters`.
+//!
+//! [More information](https://duckduckgo.com/?q=No+buffer+space+available+mbuf+nmbclusters)
+//!
+//! # Cannot Allocate Memory <a name='ENOMEM'></a>
+//!
+//! | Posix Name | ENOMEM |
+//! |---|---|
+//! | Message | `Cannot allocate memory (os error 12)` |
+//! | Hint | `Reduce memory usage or increase memory limit` |
+//! | Link | `https://bit.ly/async-err#ENOMEM` |
+//!
+//! ## Common Causes
+//!
+//! 1. The system is out of memory
+//! 2. Memory limit of the container (cgroup) is reached
+//! 3. Too many simultaneous connections
+//!
+//! Like with [`ENOBUFS`](#ENOBUFS), kernel memory is needed for each
+//! accepted socket, so the error may be returned by `accept()` under memory
+//! pressure. The (3) can be fixed by applying [`backpressure`]. Also check
+//! that the application itself doesn't leak memory.
+//!
+//! ## Linux
+//!
+//! Check free memory and kernel socket memory:
+//! ```console
+//! $ free -m
+//! $ cat /proc/net/sockstat
+//! ```
+//! In a container check memory limit of the cgroup:
+//! ```console
+//! $ cat /sys/fs/cgroup/memory.max
+//! $ cat /sys/fs/cgroup/memory.events
+//! ```
+//!
+//! Docker containers' limit can be changed with `--memory` option, in
+//! Kubernetes change `resources.limits.memory` of the container.
+//!
+//! [More information](https://duckduckgo.com/?q=accept+Cannot+allocate+memory+linux)
+//!
+//! # Cannot Assign Requested Address <a name='EADDRNOTAVAIL'></a>
+//!
+//! | Posix Name | EADDRNOTAVAIL |
+//! |---|---|
+//! | Windows Name | WSAEADDRNOTAVAIL (10049) |
+//! | Message | `Cannot assign requested address (os error 99)` |
+//! | Hint | `Bind to an address of existing network interface or to 0.0.0.0 / [::]` |
+//! | Link | `https://bit.ly/async-err#EADDRNOTAVAIL` |
+//!
+//! The error is returned by `bind()` when the IP address isn't assigned to
+//! any network interface of the machine. Error code is `49` on MacOS and
+//! BSDs.
+//!
+//! ## Common Causes
+//!
+//! 1. IP address is hardcoded in the config, but the application runs on
+//!    a different machine or in a container (containers get their own
+//!    addresses)
+//! 2. The application starts before the network is configured, for example
+//!    early at boot or before DHCP assigned the address
+//! 3. IPv6 is disabled on the machine, but an IPv6 address (including
+//!    `[::1]`) is used
+//! 4. A typo in the address
+//!
+//! In most cases binding to `0.0.0.0` (all IPv4 interfaces) or `[::]`
+//! (all interfaces) fixes (1). In containers, use the port mapping of
+//! the container runtime to choose which addresses of the host are
+//! exposed.
+//!
+//! ## Listing Addresses
+//!
+//! On Linux:
+//! ```console
+//! $ ip -brief address
+//! ```
+//!
+//! On MacOS and BSDs:
+//! ```console
+//! $ ifconfig
+//! ```
+//!
+//! On Windows:
+//! ```console
+//! > ipconfig
+//! ```
+//!
+//! ## Starting Before the Network
+//!
+//! With systemd, order the service after the network is configured:
+//! ```ini
+//! [Unit]
+//! Wants=network-online.target
+//! After=network-online.target
+//! ```
+//!
+//! Alternatively, on Linux binding to an address that isn't assigned yet
+//! can be allowed by `sysctl net.ipv4.ip_nonlocal_bind=1` (and
+//! `net.ipv6.ip_nonlocal_bind=1` for IPv6).
+//!
+//! [More information](https://duckduckgo.com/?q=bind+Cannot+assign+requested+address)
+//!
+//! # Operation Not Permitted <a name='EPERM'></a>
+//!
+//! | Posix Name | EPERM |
+//! |---|---|
+//! | Message | `Operation not permitted (os error 1)` |
+//! | Hint | `Check firewall and seccomp rules` |
+//! | Link | `https://bit.ly/async-err#EPERM` |
+//!
+//! On Linux `accept()` returns the error when firewall rules forbid the
+//! connection. There is no such error on Windows.
+//!
+//! ## Common Causes
+//!
+//! 1. A firewall rule (iptables, nftables) rejects the connection after it
+//!    was queued, for example connection tracking or a rate limit rule
+//! 2. A seccomp filter (set up by a container runtime or systemd's
+//!    `SystemCallFilter=`) denies `accept4()` or the socket options used
+//! 3. A security module (SELinux, AppArmor) forbids the operation
+//!
+//! The error is per-connection in case of (1), but for (2) and (3) every
+//! connection will fail, so the error shouldn't be ignored.
+//!
+//! ## Linux
+//!
+//! Check firewall rules and kernel log for denials:
+//! ```console
+//! $ sudo nft list ruleset
+//! $ sudo iptables -L -n -v
+//! $ sudo dmesg | grep -i -e denied -e audit
+//! ```
+//!
+//! In docker, check whether the error goes away with a default seccomp
+//! profile (`--security-opt seccomp=unconfined` for testing only).
+//!
+//! [More information](https://duckduckgo.com/?q=accept+Operation+not+permitted+linux)
+//!
+//! # Protocol Error <a name='EPROTO'></a>
+//!
+//! | Posix Name | EPROTO |
+//! |---|---|
+//! | Message | `Protocol error (os error 71)` |
+//! | Hint | `Client aborted connection during handshake, usually safe to ignore` |
+//! | Link | `https://bit.ly/async-err#EPROTO` |
+//!
+//! Some systems (older Linux, Solaris, some BSDs) return the error from
+//! `accept()` when the client has sent a reset before the connection was
+//! accepted. Error code is `100` on MacOS and BSDs.
+//!
+//! ## Common Causes
+//!
+//! 1. A client closed the connection while it was waiting in the backlog
+//! 2. A load balancer or port scanner opening and immediately resetting
+//!    connections
+//!
+//! The error is specific to a single connection, so it's safe to continue
+//! accepting. If errors are frequent, check health checks of the load
+//! balancer, as they often connect and reset without sending anything.
+//!
+//! [More information](https://duckduckgo.com/?q=accept+EPROTO+Protocol+error)
+//!
+//! # Permission Denied <a name='EACCES'></a>
+//!
+//! | Posix Name | EACCES |
+//! |---|---|
+//! | Windows Name | WSAEACCES (10013) |
+//! | Message | `Permission denied (os error 13)` |
+//! | Hint | `Use port 1024 or above, or allow binding privileged ports` |
+//! | Link | `https://bit.ly/async-err#EACCES` |
+//!
+//! ## Common Causes
+//!
+//! 1. Binding a port below 1024 (like `80` or `443`) by non-root user
+//! 2. For Unix sockets: no write permission on the directory of the socket
+//! 3. On Windows: the port is used by another socket exclusively, or is
+//!    reserved by the system (see `netsh int ipv4 show excludedportrange
+//!    protocol=tcp`)
+//!
+//! ## Privileged Ports on Linux
+//!
+//! The best option is to use a port above 1024 and put a load balancer
+//! or port forwarding in front of the application. Otherwise, grant the
+//! capability to bind privileged ports to the binary:
+//! ```console
+//! $ sudo setcap cap_net_bind_service=+ep ./your_app
+//! ```
+//! Or with systemd:
+//! ```ini
+//! [Service]
+//! AmbientCapabilities=CAP_NET_BIND_SERVICE
+//! ```
+//! Or lower the range of privileged ports for the whole system:
+//! ```console
+//! $ sudo sysctl -w net.ipv4.ip_unprivileged_port_start=80
+//! ```
+//!
+//! Docker containers allow binding privileged ports by default.
+//!
+//! [More information](https://duckduckgo.com/?q=linux+bind+privileged+port+non-root)
+//!
+//! ## Privileged Ports on MacOS
+//!
+//! Since MacOS 10.14 binding privileged ports is allowed for any user when
+//! binding to all interfaces (`0.0.0.0` or `[::]`), but not for specific
+//! addresses like `127.0.0.1`.
+//!
+//! # No Such File or Directory <a name='ENOENT'></a>
+//!
+//! | Posix Name | ENOENT |
+//! |---|---|
+//! | Message | `No such file or directory (os error 2)` |
+//! | Hint | `Create the directory of the Unix socket` |
+//! | Link | `https://bit.ly/async-err#ENOENT` |
+//!
+//! Binding a Unix socket creates a file, but doesn't create the directory
+//! it's in. Common locations are `/run/your_app/` (usually created by the
+//! service manager) or the working directory of the application.
+//!
+//! With systemd, the directory in `/run` can be created by:
+//! ```ini
+//! [Service]
+//! RuntimeDirectory=your_app
+//! ```
+//!
+//! Or create it at startup:
+//! ```rust,no_run
+//! # async fn f() -> std::io::Result<()> {
+//! # use async_std::os::unix::net::UnixListener;
+//! std::fs::create_dir_all("/run/your_app")?;
+//! let listener = UnixListener::bind("/run/your_app/app.sock").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Unix Socket Address Already in Use <a name='UNIX-EADDRINUSE'></a>
+//!
+//! | Posix Name | EADDRINUSE |
+//! |---|---|
+//! | Message | `Address already in use (os error 98)` |
+//! | Hint | `Remove stale socket file if no other process is listening on it` |
+//! | Link | `https://bit.ly/async-err#UNIX-EADDRINUSE` |
+//!
+//! Binding a Unix socket fails if the file at the path exists, whether it's
+//! a socket of a running process, a socket left by a previous instance
+//! which has crashed or exited without cleanup, or any other file.
+//!
+//! ## Checking the Socket
+//!
+//! On Linux, find out whether some process listens on the socket:
+//! ```console
+//! $ sudo ss -lxp | grep /run/your_app/app.sock
+//! ```
+//! On MacOS and BSDs:
+//! ```console
+//! $ sudo lsof /run/your_app/app.sock
+//! ```
+//!
+//! If nothing listens, the file is stale and can be removed. Usually the
+//! application removes it before binding, see
+//! [Unix Sockets](#unix-sockets) section of `EADDRINUSE`. With systemd,
+//! `RuntimeDirectory=` is cleaned up when the service stops, so stale
+//! files don't survive a restart.
+//!
+//! [More information](https://duckduckgo.com/?q=unix+socket+bind+Address+already+in+use)
+//!
+//! # Unix Socket Permission Denied <a name='UNIX-EACCES'></a>
+//!
+//! | Posix Name | EACCES |
+//! |---|---|
+//! | Message | `Permission denied (os error 13)` |
+//! | Hint | `Allow the user to write to the directory of the Unix socket` |
+//! | Link | `https://bit.ly/async-err#UNIX-EACCES` |
+//!
+//! Creating a socket file requires write and search (`x`) permission on
+//! the directory, and search permission on all parent directories.
+//!
+//! ## Common Causes
+//!
+//! 1. The directory is owned by root or by another user, e.g. the
+//!    application is started as a non-root user, but `/run/your_app` was
+//!    created by root
+//! 2. A stale socket file left by another user can't be removed
+//! 3. A security module (SELinux, AppArmor) denies creating the file
+//!
+//! Check the permissions:
+//! ```console
+//! $ namei -l /run/your_app/app.sock
+//! ```
+//!
+//! Fix the owner of the directory, or, with systemd, let it create the
+//! directory with the right owner by `RuntimeDirectory=your_app`.
+//!
+//! [More information](https://duckduckgo.com/?q=unix+socket+bind+Permission+denied)
@@ -0,0 +1,126 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+
+/// A type map of values attached to a connection
+///
+/// Combinators and middleware can attach data to a
+/// [`ByteStream`](struct.ByteStream.html) (decoded PROXY header,
+/// authenticated user, etc.), and the connection handler retrieves it
+/// later, without changing the type of the stream. At most one value of
+/// each type is stored, so it's a good idea to wrap values into a newtype
+/// private to the crate that uses them.
+///
+/// Values must be `Clone`, because
+/// [`ByteStream::duplicate`](struct.ByteStream.html#method.duplicate)
+/// copies extensions.
+///
+/// # Example
+///
+/// ```
+/// use async_listen::Extensions;
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct User(String);
+///
+/// let mut ext = Extensions::new();
+/// ext.insert(User("admin".into()));
+/// assert_eq!(ext.get::<User>(), Some(&User("admin".into())));
+/// ```
+#[derive(Clone, Default)]
+pub struct Extensions {
+    // boxed so that empty extensions take a single pointer
+    #[allow(clippy::box_collection)]
+    map: Option<Box<HashMap<TypeId, Box<dyn AnyClone>>>>,
+}
+
+trait AnyClone: Any + Send + Sync {
+    fn clone_box(&self) -> Box<dyn AnyClone>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Clone + Send + Sync + 'static> AnyClone for T {
+    fn clone_box(&self) -> Box<dyn AnyClone> {
+        Box::new(self.clone())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl Clone for Box<dyn AnyClone> {
+    fn clone(&self) -> Self {
+        (**self).clone_box()
+    }
+}
+
+impl Extensions {
+    /// Create an empty map
+    pub fn new() -> Extensions {
+        Extensions::default()
+    }
+
+    /// Insert a value, returning the previous value of the same type
+    pub fn insert<T>(&mut self, value: T) -> Option<T>
+        where T: Clone + Send + Sync + 'static,
+    {
+        self.map.get_or_insert_with(Default::default)
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.into_any().downcast().ok())
+            .map(|old| *old)
+    }
+
+    /// Returns a reference to the value of type `T`
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.map.as_ref()?
+            .get(&TypeId::of::<T>())
+            .and_then(|value| (**value).as_any().downcast_ref())
+    }
+
+    /// Returns a mutable reference to the value of type `T`
+    pub fn get_mut<T: 'static>(&mut self) -> Option<&mut T> {
+        self.map.as_mut()?
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| (**value).as_any_mut().downcast_mut())
+    }
+
+    /// Removes and returns the value of type `T`
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.map.as_mut()?
+            .remove(&TypeId::of::<T>())
+            .and_then(|old| old.into_any().downcast().ok())
+            .map(|old| *old)
+    }
+
+    /// Returns true if the map contains no values
+    pub fn is_empty(&self) -> bool {
+        self.map.as_ref().is_none_or(|map| map.is_empty())
+    }
+
+    /// Returns number of values in the map
+    pub fn len(&self) -> usize {
+        self.map.as_ref().map_or(0, |map| map.len())
+    }
+
+    /// Removes all values
+    pub fn clear(&mut self) {
+        self.map = None;
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.len())
+            .finish()
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::time::Duration;
+
+use async_std::future::Future;
+use async_std::stream::Stream;
+use async_std::task::{sleep, Poll, Context};
+use pin_project_lite::pin_project;
+
+
+/// Faults to inject into a stream of accepted connections
+///
+/// This is a test helper, see
+/// [`ListenExt::inject_faults`](trait.ListenExt.html#method.inject_faults).
+///
+/// # Example
+///
+/// ```
+/// # use std::io;
+/// # use std::time::Duration;
+/// use async_listen::Faults;
+///
+/// let faults = Faults::new()
+///     .delay(Duration::from_millis(10))
+///     .error_every(3, io::ErrorKind::ConnectionAborted);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Faults {
+    delay: Option<Duration>,
+    error_every: Option<(u64, Error)>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Error {
+    Kind(io::ErrorKind),
+    Os(i32),
+}
+
+pin_project! {
+    /// A stream adapter that injects artificial delays and errors
+    ///
+    /// See
+    /// [`ListenExt::inject_faults`](../trait.ListenExt.html#method.inject_faults)
+    /// for more info.
+    #[project = InjectFaultsProj]
+    pub struct InjectFaults<S> {
+        #[pin]
+        stream: S,
+        faults: Faults,
+        yielded: u64,
+        delayed: bool,
+        timer: Option<Pin<Box<dyn Future<Output=()> + Send>>>,
+    }
+}
+
+impl Faults {
+    /// No faults, use builder methods to add some
+    pub fn new() -> Faults {
+        Faults::default()
+    }
+
+    /// Sleep for `delay` before yielding each item
+    pub fn delay(mut self, delay: Duration) -> Faults {
+        self.delay = Some(delay);
+        self
+    }
+
+    /// Yield an error of the specified `kind` in place of every `n`th item
+    ///
+    /// Injected errors don't consume connections of the underlying stream,
+    /// i.e. with `n = 3` the stream yields two connections then an error,
+    /// then the next two connections and so on.
+    pub fn error_every(mut self, n: u64, kind: io::ErrorKind) -> Faults {
+        assert!(n > 0, "error interval must be positive");
+        self.error_every = Some((n, Error::Kind(kind)));
+        self
+    }
+
+    /// Yield an OS error with the `code` in place of every `n`th item
+    ///
+    /// Same as [`error_every`](#method.error_every) but the error is created
+    /// with `io::Error::from_raw_os_error`, so it's handled exactly like
+    /// a real error from `accept()` (i.e. `EMFILE`), including
+    /// [`error_hint`](fn.error_hint.html).
+    pub fn os_error_every(mut self, n: u64, code: i32) -> Faults {
+        assert!(n > 0, "error interval must be positive");
+        self.error_every = Some((n, Error::Os(code)));
+        self
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for InjectFaults<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InjectFaults")
+            .field("stream", &self.stream)
+            .field("faults", &self.faults)
+            .field("yielded", &self.yielded)
+            .finish()
+    }
+}
+
+impl<S> InjectFaults<S> {
+    pub(crate) fn new(stream: S, faults: Faults) -> InjectFaults<S> {
+        InjectFaults {
+            stream,
+            faults,
+            yielded: 0,
+            delayed: false,
+            timer: None,
+        }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns a mutable reference to the faults (i.e. to disable them)
+    pub fn faults_mut(&mut self) -> &mut Faults {
+        &mut self.faults
+    }
+}
+
+impl<I, S> Stream for InjectFaults<S>
+    where S: Stream<Item=io::Result<I>>,
+{
+    type Item = io::Result<I>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        if let (Some(delay), false) = (this.faults.delay, *this.delayed) {
+            let timer = this.timer.get_or_insert_with(|| Box::pin(sleep(delay)));
+            match timer.as_mut().poll(cx) {
+                Poll::Ready(()) => {}
+                Poll::Pending => return Poll::Pending,
+            }
+            *this.timer = None;
+            *this.delayed = true;
+        }
+        if let Some((n, error)) = this.faults.error_every {
+            if (*this.yielded + 1).is_multiple_of(n) {
+                *this.yielded += 1;
+                *this.delayed = false;
+                let err = match error {
+                    Error::Kind(kind) => kind.into(),
+                    Error::Os(code) => io::Error::from_raw_os_error(code),
+                };
+                return Poll::Ready(Some(Err(err)));
+            }
+        }
+        let res = this.stream.poll_next(cx);
+        if let Poll::Ready(Some(_)) = res {
+            *this.yielded += 1;
+            *this.delayed = false;
+        }
+        res
+    }
+}
@@ -0,0 +1,84 @@
+use std::fmt;
+
+
+/// File descriptor usage of the process at the time of the error
+///
+/// Returned by
+/// [`ErrorHint::fd_usage`](wrapper_types/struct.ErrorHint.html#method.fd_usage)
+/// for `EMFILE` errors if enabled by
+/// [`set_fd_diagnostics`](fn.set_fd_diagnostics.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FdUsage {
+    open: Option<u64>,
+    soft_limit: Option<u64>,
+    hard_limit: Option<u64>,
+}
+
+impl FdUsage {
+    /// Estimated number of open file descriptors
+    ///
+    /// Descriptors are counted by listing `/proc/self/fd` on Linux and
+    /// `/dev/fd` on MacOS. Listing a directory needs a descriptor too, so
+    /// if no descriptors are left, the soft limit is returned as the
+    /// estimate. `None` if descriptors can't be counted.
+    pub fn open(&self) -> Option<u64> {
+        self.open
+    }
+
+    /// Current (soft) limit of open files, `None` if unlimited
+    pub fn soft_limit(&self) -> Option<u64> {
+        self.soft_limit
+    }
+
+    /// Maximum (hard) limit of open files, `None` if unlimited
+    ///
+    /// Soft limit can be raised up to this value without privileges.
+    pub fn hard_limit(&self) -> Option<u64> {
+        self.hard_limit
+    }
+}
+
+impl fmt::Display for FdUsage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn limit(value: Option<u64>) -> String {
+            value.map_or_else(|| "unlimited".into(), |v| v.to_string())
+        }
+        match self.open {
+            Some(open) => write!(f, "open: {}, ", open)?,
+            None => f.write_str("open: unknown, ")?,
+        }
+        write!(f, "limit: {}, hard limit: {}",
+               limit(self.soft_limit), limit(self.hard_limit))
+    }
+}
+
+#[cfg(not(any(target_os="linux", target_os="android", target_os="macos")))]
+pub(crate) fn query() -> Option<FdUsage> {
+    None
+}
+
+#[cfg(any(target_os="linux", target_os="android", target_os="macos"))]
+pub(crate) fn query() -> Option<FdUsage> {
+    use std::fs;
+    use rustix::process::{getrlimit, Resource};
+
+    #[cfg(target_os="macos")]
+    const FD_DIR: &str = "/dev/fd";
+    #[cfg(not(target_os="macos"))]
+    const FD_DIR: &str = "/proc/self/fd";
+
+    let limit = getrlimit(Resource::Nofile);
+    let open = match fs::read_dir(FD_DIR) {
+        // don't count descriptor of the directory itself
+        Ok(dir) => Some((dir.count() as u64).saturating_sub(1)),
+        Err(ref e) if e.raw_os_error()
+            == Some(rustix::io::Errno::MFILE.raw_os_error())
+        => limit.current,
+        Err(_) => None,
+    };
+    Some(FdUsage {
+        open,
+        soft_limit: limit.current,
+        hard_limit: limit.maximum,
+    })
+}
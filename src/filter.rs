@@ -0,0 +1,324 @@
+//! Filtering connections by peer address
+//!
+//! The usual way to filter connections is the
+//! [`ListenExt::filter_peers`](../trait.ListenExt.html#method.filter_peers)
+//! method with either a closure or one of the built-in policies:
+//! * [`AllowList`](struct.AllowList.html)
+//! * [`DenyList`](struct.DenyList.html)
+//!
+//! Also [`ListenExt::limit_per_peer`](../trait.ListenExt.html#method.limit_per_peer)
+//! rejects connections from peers having too many connections open, and
+//! [`ListenExt::tarpit`](../trait.ListenExt.html#method.tarpit) holds
+//! connections from flagged peers open instead of closing them.
+//! [`ListenExt::reconnect_cooldown`](../trait.ListenExt.html#method.reconnect_cooldown)
+//! rejects clients reconnecting in a tight loop.
+//!
+use std::error::Error;
+use std::fmt;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::str::FromStr;
+
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
+
+pub use crate::cooldown::ReconnectCooldown;
+pub use crate::peer_limit::{LimitPerPeer, PeerToken};
+pub use crate::tarpit::{Tarpit, DEFAULT_TARPIT_INTERVAL, DEFAULT_TARPIT_DURATION};
+
+
+/// A policy which decides whether connection from a peer is accepted
+///
+/// The trait is implemented for closures `Fn(&PeerAddr) -> bool`.
+pub trait PeerPolicy {
+    /// Returns true if connection from this peer should be accepted
+    fn allow(&self, peer: &PeerAddr) -> bool;
+}
+
+/// A network address with a prefix length (i.e. `10.0.0.0/8`)
+///
+/// Parsed from a string in usual CIDR notation. Plain IP address is parsed
+/// as a network containing this single address.
+///
+/// IPv4-mapped IPv6 addresses (`::ffff:10.1.2.3`) are matched as IPv4.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+/// Error parsing [`Cidr`](struct.Cidr.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCidrError {
+    _private: (),
+}
+
+/// Only accept connections from specified networks
+///
+/// Connections on Unix sockets are always accepted (their access is
+/// controlled by file permissions). Connections with an unknown peer address
+/// (i.e. disconnected before being inspected) are rejected.
+///
+/// # Example
+///
+/// ```
+/// use async_listen::filter::AllowList;
+///
+/// let policy: AllowList = "127.0.0.0/8, ::1, 10.0.0.0/8".parse().unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct AllowList {
+    networks: Vec<Cidr>,
+}
+
+/// Reject connections from specified networks
+///
+/// Connections on Unix sockets are always accepted.
+///
+/// # Example
+///
+/// ```
+/// use async_listen::filter::DenyList;
+///
+/// let mut policy = DenyList::new();
+/// policy.add("192.0.2.0/24".parse().unwrap());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DenyList {
+    networks: Vec<Cidr>,
+}
+
+pin_project! {
+    /// A stream adapter that drops connections rejected by a policy
+    ///
+    /// See
+    /// [`ListenExt::filter_peers`](../trait.ListenExt.html#method.filter_peers)
+    /// for more info.
+    #[project = FilterPeersProj]
+    pub struct FilterPeers<S, P> {
+        #[pin]
+        stream: S,
+        policy: P,
+    }
+}
+
+impl<F: Fn(&PeerAddr) -> bool> PeerPolicy for F {
+    fn allow(&self, peer: &PeerAddr) -> bool {
+        (self)(peer)
+    }
+}
+
+pub(crate) fn normalize(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(v6),
+        },
+        v4 => v4,
+    }
+}
+
+impl Cidr {
+    /// Create a network from address and prefix length
+    ///
+    /// Returns `None` if prefix is larger than number of bits in the address.
+    pub fn new(addr: IpAddr, prefix: u8) -> Option<Cidr> {
+        let addr = normalize(addr);
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return None;
+        }
+        Some(Cidr { addr, prefix })
+    }
+
+    /// Returns true if address belongs to this network
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, normalize(addr)) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = ParseCidrError;
+    fn from_str(s: &str) -> Result<Cidr, ParseCidrError> {
+        let err = ParseCidrError { _private: () };
+        let s = s.trim();
+        match s.find('/') {
+            Some(idx) => {
+                let addr = s[..idx].parse().map_err(|_| err.clone())?;
+                let prefix = s[idx+1..].parse().map_err(|_| err.clone())?;
+                Cidr::new(addr, prefix).ok_or(err)
+            }
+            None => {
+                let addr: IpAddr = s.parse().map_err(|_| err)?;
+                let addr = normalize(addr);
+                Ok(Cidr { addr, prefix: if addr.is_ipv4() { 32 } else { 128 }})
+            }
+        }
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl fmt::Display for ParseCidrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "invalid network address, expected `ip/prefix`".fmt(f)
+    }
+}
+
+impl Error for ParseCidrError {}
+
+fn parse_list(s: &str) -> Result<Vec<Cidr>, ParseCidrError> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|item| !item.is_empty())
+        .map(|item| item.parse())
+        .collect()
+}
+
+fn matches(networks: &[Cidr], peer: &PeerAddr) -> bool {
+    match peer {
+        PeerAddr::Tcp(addr) => networks.iter().any(|n| n.contains(addr.ip())),
+        PeerAddr::Unix(_) | PeerAddr::UnixAbstract(_) => false,
+    }
+}
+
+impl AllowList {
+    /// Create an empty allow list (which rejects all TCP connections)
+    pub fn new() -> AllowList {
+        AllowList::default()
+    }
+    /// Add a network to the list
+    pub fn add(&mut self, network: Cidr) -> &mut AllowList {
+        self.networks.push(network);
+        self
+    }
+    /// Returns the list of networks
+    pub fn networks(&self) -> &[Cidr] {
+        &self.networks
+    }
+}
+
+impl DenyList {
+    /// Create an empty deny list (which accepts all connections)
+    pub fn new() -> DenyList {
+        DenyList::default()
+    }
+    /// Add a network to the list
+    pub fn add(&mut self, network: Cidr) -> &mut DenyList {
+        self.networks.push(network);
+        self
+    }
+    /// Returns the list of networks
+    pub fn networks(&self) -> &[Cidr] {
+        &self.networks
+    }
+}
+
+impl FromStr for AllowList {
+    type Err = ParseCidrError;
+    fn from_str(s: &str) -> Result<AllowList, ParseCidrError> {
+        Ok(AllowList { networks: parse_list(s)? })
+    }
+}
+
+impl FromStr for DenyList {
+    type Err = ParseCidrError;
+    fn from_str(s: &str) -> Result<DenyList, ParseCidrError> {
+        Ok(DenyList { networks: parse_list(s)? })
+    }
+}
+
+impl PeerPolicy for AllowList {
+    fn allow(&self, peer: &PeerAddr) -> bool {
+        matches!(peer, PeerAddr::Unix(_) | PeerAddr::UnixAbstract(_))
+            || matches(&self.networks, peer)
+    }
+}
+
+impl PeerPolicy for DenyList {
+    fn allow(&self, peer: &PeerAddr) -> bool {
+        !matches(&self.networks, peer)
+    }
+}
+
+impl<S: fmt::Debug, P: fmt::Debug> fmt::Debug for FilterPeers<S, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FilterPeers")
+            .field("stream", &self.stream)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
+
+impl<S, P> FilterPeers<S, P> {
+    pub(crate) fn new(stream: S, policy: P) -> FilterPeers<S, P> {
+        FilterPeers { stream, policy }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns a mutable reference to the policy (i.e. to update lists)
+    pub fn policy_mut(&mut self) -> &mut P {
+        &mut self.policy
+    }
+}
+
+impl<I, S, P> Stream for FilterPeers<S, P>
+    where S: Stream<Item=I>,
+          I: HasPeerAddr,
+          P: PeerPolicy,
+{
+    type Item = I;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        loop {
+            match self.as_mut().project().stream.poll_next(cx) {
+                Poll::Ready(Some(conn)) => {
+                    match conn.get_peer_addr() {
+                        Ok(addr) if self.policy.allow(&addr) => {
+                            return Poll::Ready(Some(conn));
+                        }
+                        // connection is closed on drop
+                        _ => continue,
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::time::Duration;
+
+use async_std::future::{timeout, Future};
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::ByteStream;
+use crate::pending::Pending;
+
+
+type Task<T> = Pin<Box<dyn Future<Output=io::Result<T>> + Send>>;
+type Start<I, T> = Box<dyn FnMut(I) -> Task<T> + Send>;
+
+pin_project! {
+    /// A stream adapter that runs a handshake on each connection concurrently
+    ///
+    /// Connections for which the handshake has failed or hasn't completed
+    /// within the timeout are dropped (i.e. closed).
+    ///
+    /// See
+    /// [`ListenExt::handshake`](../trait.ListenExt.html#method.handshake)
+    /// and
+    /// [`ListenExt::first_byte_timeout`](../trait.ListenExt.html#method.first_byte_timeout)
+    /// for more info.
+    #[project = HandshakeProj]
+    pub struct Handshake<S, I, T> {
+        #[pin]
+        stream: S,
+        start: Start<I, T>,
+        pending: Pending<io::Result<T>>,
+        max_pending: Option<usize>,
+        stream_done: bool,
+    }
+}
+
+impl<S: fmt::Debug, I, T> fmt::Debug for Handshake<S, I, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Handshake")
+            .field("stream", &self.stream)
+            .field("pending", &self.pending.len())
+            .field("max_pending", &self.max_pending)
+            .finish()
+    }
+}
+
+impl<S, I: 'static, T: 'static> Handshake<S, I, T> {
+    pub(crate) fn new<F, Fut>(stream: S, mut f: F) -> Handshake<S, I, T>
+        where F: FnMut(I) -> Fut + Send + 'static,
+              Fut: Future<Output=io::Result<T>> + Send + 'static,
+    {
+        Handshake {
+            stream,
+            start: Box::new(move |conn| Box::pin(f(conn))),
+            pending: Pending::new(),
+            max_pending: None,
+            stream_done: false,
+        }
+    }
+
+    pub(crate) fn with_timeout<F, Fut>(stream: S, limit: Duration, mut f: F)
+        -> Handshake<S, I, T>
+        where F: FnMut(I) -> Fut + Send + 'static,
+              Fut: Future<Output=io::Result<T>> + Send + 'static,
+    {
+        Handshake::new(stream, move |conn| {
+            let fut = f(conn);
+            async move {
+                match timeout(limit, fut).await {
+                    Ok(res) => res,
+                    Err(_) => Err(io::ErrorKind::TimedOut.into()),
+                }
+            }
+        })
+    }
+}
+
+impl<S, I> Handshake<S, I, ByteStream>
+    where I: Into<ByteStream> + 'static,
+{
+    pub(crate) fn first_byte(stream: S, limit: Duration)
+        -> Handshake<S, I, ByteStream>
+    {
+        Handshake::with_timeout(stream, limit, |conn: I| {
+            let conn = conn.into();
+            async move {
+                if conn.peek(&mut [0u8]).await? == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+                Ok(conn)
+            }
+        })
+    }
+}
+
+impl<S, I, T> Handshake<S, I, T> {
+    /// Limit the number of handshakes running concurrently
+    ///
+    /// When the limit is reached no new connections are pulled from the
+    /// underlying stream (i.e. they wait in the listen backlog) until one
+    /// of the running handshakes is finished.
+    pub fn concurrency_limit(mut self, limit: usize) -> Self {
+        assert!(limit > 0, "concurrency limit must be positive");
+        self.max_pending = Some(limit);
+        self
+    }
+
+    fn is_full(&self) -> bool {
+        self.max_pending.map(|max| self.pending.len() >= max).unwrap_or(false)
+    }
+
+    /// Returns number of connections which haven't completed handshake yet
+    pub fn pending_handshakes(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    ///
+    /// Connections which haven't completed handshake yet are dropped.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S, T> Stream for Handshake<S, I, T>
+    where S: Stream<Item=I>,
+{
+    type Item = T;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        loop {
+            while !self.stream_done && !self.is_full() {
+                let this = self.as_mut().project();
+                match this.stream.poll_next(cx) {
+                    Poll::Ready(Some(conn)) => {
+                        let fut = (this.start)(conn);
+                        this.pending.push(fut);
+                    }
+                    Poll::Ready(None) => *this.stream_done = true,
+                    Poll::Pending => break,
+                }
+            }
+            let this = self.as_mut().project();
+            match this.pending.poll_next(cx) {
+                Poll::Ready(Some(Ok(item))) => return Poll::Ready(Some(item)),
+                // connection is closed on drop, and there might be a free
+                // slot for the next one now
+                Poll::Ready(Some(Err(_))) => continue,
+                Poll::Ready(None) if *this.stream_done => {
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
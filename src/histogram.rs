@@ -0,0 +1,102 @@
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+
+// Bucket `0` is for durations below 1ms, bucket `i` is for durations in
+// range `[2^(i-1), 2^i)` milliseconds, last bucket is for everything longer
+// (~2.3 hours and more)
+const BUCKETS: usize = 25;
+
+/// Lock-free histogram updated when tokens are dropped
+pub(crate) struct Recorder {
+    buckets: [AtomicU64; BUCKETS],
+}
+
+/// A snapshot of connection durations
+///
+/// Durations are collected into exponential buckets: the first one is for
+/// connections shorter than 1 millisecond, and each next one is twice as
+/// wide as the previous one. So the precision of percentiles is within
+/// a factor of two, which is usually enough for tuning limits and timeouts.
+///
+/// Returned by
+/// [`Sender::get_duration_histogram`](struct.Sender.html#method.get_duration_histogram).
+#[derive(Clone, PartialEq, Eq)]
+pub struct DurationHistogram {
+    buckets: [u64; BUCKETS],
+}
+
+fn upper_bound(idx: usize) -> Duration {
+    if idx + 1 >= BUCKETS {
+        return Duration::new(u64::MAX, 0);
+    }
+    Duration::from_millis(1 << idx)
+}
+
+impl Recorder {
+    pub(crate) fn new() -> Recorder {
+        Recorder { buckets: Default::default() }
+    }
+
+    pub(crate) fn record(&self, duration: Duration) {
+        let millis = duration.as_millis();
+        let idx = (128 - millis.leading_zeros()) as usize;
+        self.buckets[idx.min(BUCKETS - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> DurationHistogram {
+        let mut buckets = [0; BUCKETS];
+        for (dest, src) in buckets.iter_mut().zip(&self.buckets) {
+            *dest = src.load(Ordering::Relaxed);
+        }
+        DurationHistogram { buckets }
+    }
+}
+
+impl DurationHistogram {
+    /// Returns total number of connections recorded
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Returns approximate `q`-quantile of connection durations
+    ///
+    /// The `q` is a number between `0.0` and `1.0`, i.e. `0.99` for the 99th
+    /// percentile. Returned value is the upper bound of the bucket which
+    /// contains the quantile. Returns `None` if no connections are
+    /// recorded.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((q.clamp(0.0, 1.0) * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (idx, &num) in self.buckets.iter().enumerate() {
+            seen += num;
+            if seen >= rank {
+                return Some(upper_bound(idx));
+            }
+        }
+        unreachable!("rank is never larger than count");
+    }
+
+    /// Returns an iterator over `(upper_bound, count)` pairs of each bucket
+    ///
+    /// Upper bound of the last bucket is the maximum `Duration`.
+    pub fn buckets(&self) -> impl Iterator<Item=(Duration, u64)> + '_ {
+        self.buckets.iter().enumerate()
+            .map(|(idx, &num)| (upper_bound(idx), num))
+    }
+}
+
+impl fmt::Debug for DurationHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DurationHistogram")
+            .field("count", &self.count())
+            .field("p50", &self.quantile(0.5))
+            .field("p99", &self.quantile(0.99))
+            .finish()
+    }
+}
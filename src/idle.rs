@@ -0,0 +1,228 @@
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use async_std::future::Future;
+use async_std::io::{Read, Write};
+use async_std::stream::Stream;
+use async_std::task::{sleep, Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
+use crate::socket_options::{ConfigureSocket, SocketOptions};
+
+
+type Timer = Pin<Box<dyn Future<Output=()> + Send + 'static>>;
+
+/// A connection which is closed when idle for too long
+///
+/// Every successful read or write resets the idle timer. When no data was
+/// read or written for the idle timeout, the underlying connection is
+/// dropped (which closes the socket and releases backpressure token held by
+/// it) and all reads and writes fail with `io::ErrorKind::TimedOut`.
+///
+/// Yielded by
+/// [`ListenExt::idle_timeout`](trait.ListenExt.html#method.idle_timeout).
+pub struct IdleTimeout<T> {
+    stream: Option<T>,
+    timeout: Duration,
+    last_activity: Instant,
+    timer: Timer,
+}
+
+pin_project! {
+    /// A stream adapter that closes idle connections
+    ///
+    /// See
+    /// [`ListenExt::idle_timeout`](../trait.ListenExt.html#method.idle_timeout)
+    /// for more info.
+    #[project = WithIdleTimeoutProj]
+    pub struct WithIdleTimeout<S> {
+        #[pin]
+        stream: S,
+        timeout: Duration,
+    }
+}
+
+fn idle() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "connection idle timeout")
+}
+
+impl<T> IdleTimeout<T> {
+    /// Wrap a connection so that it's closed when idle for `timeout`
+    pub fn new(stream: T, timeout: Duration) -> IdleTimeout<T> {
+        IdleTimeout {
+            stream: Some(stream),
+            timeout,
+            last_activity: Instant::now(),
+            timer: Box::pin(sleep(timeout)),
+        }
+    }
+
+    /// Returns the time of last successful read or write
+    pub fn last_activity(&self) -> Instant {
+        self.last_activity
+    }
+
+    /// Returns true if connection was closed because of inactivity
+    pub fn is_expired(&self) -> bool {
+        self.stream.is_none()
+    }
+
+    /// Acquires a reference to the underlying connection
+    ///
+    /// Returns `None` if the connection was closed.
+    pub fn get_ref(&self) -> Option<&T> {
+        self.stream.as_ref()
+    }
+
+    /// Acquires a mutable reference to the underlying connection
+    ///
+    /// Returns `None` if the connection was closed.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        self.stream.as_mut()
+    }
+
+    /// Consumes the wrapper, returning the underlying connection
+    ///
+    /// Returns `None` if the connection was closed.
+    pub fn into_inner(self) -> Option<T> {
+        self.stream
+    }
+
+    fn poll_stream(&mut self, cx: &mut Context) -> io::Result<&mut T> {
+        while self.stream.is_some() && self.timer.as_mut().poll(cx).is_ready()
+        {
+            let elapsed = self.last_activity.elapsed();
+            if elapsed >= self.timeout {
+                self.stream = None;
+            } else {
+                // there was activity since the timer was started
+                self.timer = Box::pin(sleep(self.timeout - elapsed));
+            }
+        }
+        self.stream.as_mut().ok_or_else(idle)
+    }
+
+    fn activity<R>(&mut self, result: Poll<io::Result<R>>)
+        -> Poll<io::Result<R>>
+    {
+        if let Poll::Ready(Ok(_)) = result {
+            self.last_activity = Instant::now();
+        }
+        result
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for IdleTimeout<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IdleTimeout")
+            .field("stream", &self.stream)
+            .field("timeout", &self.timeout)
+            .field("last_activity", &self.last_activity)
+            .finish()
+    }
+}
+
+impl<T: Read + Unpin> Read for IdleTimeout<T> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        let this = self.get_mut();
+        let res = match this.poll_stream(cx) {
+            Ok(stream) => Pin::new(stream).poll_read(cx, buf),
+            Err(e) => return Poll::Ready(Err(e)),
+        };
+        this.activity(res)
+    }
+}
+
+impl<T: Write + Unpin> Write for IdleTimeout<T> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context, buf: &[u8])
+        -> Poll<Result<usize, io::Error>>
+    {
+        let this = self.get_mut();
+        let res = match this.poll_stream(cx) {
+            Ok(stream) => Pin::new(stream).poll_write(cx, buf),
+            Err(e) => return Poll::Ready(Err(e)),
+        };
+        this.activity(res)
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        let this = self.get_mut();
+        let res = match this.poll_stream(cx) {
+            Ok(stream) => Pin::new(stream).poll_flush(cx),
+            Err(e) => return Poll::Ready(Err(e)),
+        };
+        this.activity(res)
+    }
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Result<(), io::Error>>
+    {
+        match self.get_mut().stream {
+            Some(ref mut stream) => Pin::new(stream).poll_close(cx),
+            // already closed
+            None => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl<T: HasPeerAddr> HasPeerAddr for IdleTimeout<T> {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.stream.as_ref().ok_or_else(idle)?.get_peer_addr()
+    }
+}
+
+impl<T: ConfigureSocket> ConfigureSocket for IdleTimeout<T> {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        self.stream.as_ref().ok_or_else(idle)?.configure(options)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for WithIdleTimeout<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithIdleTimeout")
+            .field("stream", &self.stream)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl<S> WithIdleTimeout<S> {
+    pub(crate) fn new(stream: S, timeout: Duration) -> WithIdleTimeout<S> {
+        WithIdleTimeout { stream, timeout }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S> Stream for WithIdleTimeout<S>
+    where S: Stream<Item=I>,
+{
+    type Item = IdleTimeout<I>;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        self.as_mut().project().stream
+        .poll_next(cx)
+        .map(|opt| opt.map(|conn| IdleTimeout::new(conn, self.timeout)))
+    }
+}
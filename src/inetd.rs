@@ -0,0 +1,146 @@
+//! Running under inetd and other super-servers
+//!
+//! Only available on Unix systems. A super-server (inetd, xinetd, launchd
+//! with `inetdCompatibility`, or systemd with `Accept=yes`) binds the port
+//! itself and starts the service on demand, passing the socket as the
+//! standard input:
+//!
+//! * in `wait` mode (`Wait=true` in launchd) descriptor `0` is the
+//!   listening socket, the service accepts connections itself and the
+//!   super-server waits until it exits -- use [`listener`](fn.listener.html)
+//! * in `nowait` mode (`Wait=false`) a process is started for each
+//!   connection, and descriptor `0` (usually `1` too) is the accepted
+//!   connection -- use [`connection`](fn.connection.html)
+//!
+//! [`socket`](fn.socket.html) detects the mode by the kind of socket, so
+//! the same binary can be configured either way, or run standalone.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use async_std::prelude::*;
+//! # use async_std::task;
+//! # async fn serve(stream: async_listen::ByteStream) {}
+//! # fn main() -> std::io::Result<()> { task::block_on(async {
+//! use async_listen::{ListenExt, ListenerSet};
+//! use async_listen::inetd::{self, Socket};
+//!
+//! match inetd::socket()? {
+//!     Socket::Connection(stream) => serve(stream).await,
+//!     Socket::Listener(listener) => {
+//!         let mut listeners = ListenerSet::new();
+//!         listeners.add_listener(listener);
+//!         let mut incoming = listeners
+//!             .handle_errors(Duration::from_millis(500));
+//!         while let Some(stream) = incoming.next().await {
+//!             task::spawn(serve(stream));
+//!         }
+//!     }
+//! }
+//! # Ok(()) }) }
+//! ```
+use std::fs::File;
+use std::io;
+use std::os::fd::{AsFd, OwnedFd};
+
+use rustix::net::{getsockname, AddressFamily};
+use rustix::net::sockopt::socket_acceptconn;
+
+use crate::byte_stream::ByteStream;
+use crate::listen_addr::{Listener, take_fd, listener_from_fd};
+
+
+/// A socket passed by the super-server as standard input
+#[derive(Debug)]
+pub enum Socket {
+    /// Listening socket (`wait` mode)
+    Listener(Listener),
+    /// Accepted connection (`nowait` mode)
+    Connection(ByteStream),
+}
+
+/// Returns the socket passed as standard input, detecting the mode
+///
+/// Fails if standard input isn't a TCP or Unix socket, i.e. when the
+/// process is started from a terminal. See
+/// [`connection`](fn.connection.html) for how standard descriptors are
+/// changed in `nowait` mode.
+pub fn socket() -> io::Result<Socket> {
+    let fd = take_fd(0)?;
+    if socket_acceptconn(&fd).map_err(not_a_socket)? {
+        listener_from_fd(fd).map(Socket::Listener)
+    } else {
+        connection_from_fd(fd).map(Socket::Connection)
+    }
+}
+
+/// Returns the listening socket passed as standard input (`wait` mode)
+///
+/// The descriptor is duplicated, so standard input is left intact.
+pub fn listener() -> io::Result<Listener> {
+    let fd = take_fd(0)?;
+    socket_acceptconn(&fd).map_err(not_a_socket)?;
+    listener_from_fd(fd)
+}
+
+/// Returns the connection passed as standard input (`nowait` mode)
+///
+/// Standard input, and standard output if it refers to the same socket,
+/// are replaced by `/dev/null`. Otherwise the connection would stay open
+/// after the stream is dropped (the peer waits for the end of the response
+/// until the process exits), and writes to standard output would be sent
+/// to the peer.
+pub fn connection() -> io::Result<ByteStream> {
+    let fd = take_fd(0)?;
+    if socket_acceptconn(&fd).map_err(not_a_socket)? {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+            "standard input is a listening socket, not a connection"));
+    }
+    connection_from_fd(fd)
+}
+
+fn connection_from_fd(fd: OwnedFd) -> io::Result<ByteStream> {
+    let family = getsockname(&fd)?.address_family();
+    let stream = if family == AddressFamily::INET
+        || family == AddressFamily::INET6
+    {
+        let stream = std::net::TcpStream::from(fd);
+        stream.set_nonblocking(true)?;
+        ByteStream::new_tcp_detached(stream.into())
+    } else if family == AddressFamily::UNIX {
+        let stream = std::os::unix::net::UnixStream::from(fd);
+        stream.set_nonblocking(true)?;
+        ByteStream::new_unix_detached(stream.into())
+    } else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+            "standard input is neither TCP nor Unix socket"));
+    };
+    detach_stdio()?;
+    Ok(stream)
+}
+
+fn detach_stdio() -> io::Result<()> {
+    use rustix::fs::fstat;
+    use rustix::stdio::{dup2_stdin, dup2_stdout};
+
+    let stdin = fstat(io::stdin().as_fd())?;
+    let same_stdout = fstat(io::stdout().as_fd())
+        .map(|out| out.st_dev == stdin.st_dev && out.st_ino == stdin.st_ino)
+        .unwrap_or(false);
+    let null = File::open("/dev/null")?;
+    dup2_stdin(&null)?;
+    if same_stdout {
+        dup2_stdout(File::options().write(true).open("/dev/null")?)?;
+    }
+    Ok(())
+}
+
+fn not_a_socket(e: rustix::io::Errno) -> io::Error {
+    if e == rustix::io::Errno::NOTSOCK {
+        io::Error::new(io::ErrorKind::InvalidInput,
+            "standard input is not a socket, not started by inetd?")
+    } else {
+        e.into()
+    }
+}
@@ -0,0 +1,97 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<str>>> = const { RefCell::new(None) };
+}
+
+pin_project! {
+    /// A stream adapter that attaches a label to the pipeline
+    ///
+    /// See
+    /// [`ListenExt::labeled`](../trait.ListenExt.html#method.labeled)
+    /// for more info.
+    #[project = LabeledProj]
+    pub struct Labeled<S> {
+        #[pin]
+        stream: S,
+        label: Arc<str>,
+    }
+}
+
+struct Restore(Option<Arc<str>>);
+
+/// Returns the label of the pipeline which is being polled now
+///
+/// This can be used in callbacks of combinators (i.e. in
+/// [`log_warnings`](trait.ListenExt.html#method.log_warnings)) to find out
+/// which listener has produced the error. Returns `None` if called outside
+/// of a pipeline marked with
+/// [`labeled`](trait.ListenExt.html#method.labeled).
+pub fn current_label() -> Option<Arc<str>> {
+    CURRENT.with(|cur| cur.borrow().clone())
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let old = self.0.take();
+        CURRENT.with(|cur| *cur.borrow_mut() = old);
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for Labeled<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Labeled")
+            .field("stream", &self.stream)
+            .field("label", &self.label)
+            .finish()
+    }
+}
+
+impl<S> Labeled<S> {
+    pub(crate) fn new(stream: S, label: &str) -> Labeled<S> {
+        Labeled { stream, label: label.into() }
+    }
+
+    /// Returns the label of this pipeline
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S> Stream for Labeled<S>
+    where S: Stream<Item=I>,
+{
+    type Item = I;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let label = self.label.clone();
+        let _restore = Restore(CURRENT.with(|cur| cur.replace(Some(label))));
+        self.as_mut().project().stream.poll_next(cx)
+    }
+}
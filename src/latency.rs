@@ -0,0 +1,85 @@
+use std::fmt;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+
+pin_project! {
+    /// A stream adapter that measures how long connections wait to be accepted
+    ///
+    /// See
+    /// [`ListenExt::measure_accept_latency`](../trait.ListenExt.html#method.measure_accept_latency)
+    /// for more info.
+    #[project = AcceptLatencyProj]
+    pub struct AcceptLatency<S, F> {
+        #[pin]
+        stream: S,
+        callback: F,
+        last_return: Option<Instant>,
+        waiting: bool,
+    }
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for AcceptLatency<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AcceptLatency")
+            .field("stream", &self.stream)
+            .finish()
+    }
+}
+
+impl<S, F> AcceptLatency<S, F> {
+    pub(crate) fn new(stream: S, callback: F) -> AcceptLatency<S, F> {
+        AcceptLatency { stream, callback, last_return: None, waiting: true }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S, F> Stream for AcceptLatency<S, F>
+    where S: Stream<Item=I>,
+          F: FnMut(Duration),
+{
+    type Item = I;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        let res = this.stream.poll_next(cx);
+        match res {
+            Poll::Ready(Some(_)) => {
+                // If previous poll returned pending we were woken up as soon
+                // as the connection arrived. Otherwise it might have been
+                // waiting since the previous poll.
+                let latency = match *this.last_return {
+                    Some(time) if !*this.waiting => time.elapsed(),
+                    _ => Duration::new(0, 0),
+                };
+                (this.callback)(latency);
+                *this.waiting = false;
+            }
+            Poll::Pending => *this.waiting = true,
+            Poll::Ready(None) => {}
+        }
+        *this.last_return = Some(Instant::now());
+        res
+    }
+}
@@ -10,13 +10,61 @@
 //! # Utilities
 //! * [ListenExt](trait.ListenExt.html) -- extension trait for stream of
 //!   accepted sockets, provides useful conbinators for a stream
+//! * [ListenerSet](struct.ListenerSet.html) -- accepts connections from
+//!   multiple TCP and Unix listeners as a single stream
+//! * [ListenAddr](enum.ListenAddr.html) -- parses listener addresses like
+//!   `tcp://0.0.0.0:80` or `unix:/run/app.sock` and binds them
+//! * [bind_all](fn.bind_all.html) -- binds multiple addresses into a single
+//!   stream of connections
+//! * [bind_reuseport](fn.bind_reuseport.html) -- binds multiple sockets to
+//!   the same address to spread accepts across cores
+//! * [bind_dual_stack](fn.bind_dual_stack.html) -- listens on both IPv6
+//!   and IPv4 addresses regardless of the platform defaults
+//! * [upgrade](upgrade/index.html) -- passes listening sockets to a new
+//!   process for zero-downtime restart (Unix only)
+//! * [inetd](inetd/index.html) -- accepts the socket passed by inetd and
+//!   similar super-servers as standard input (Unix only)
 //! * [error_hint](fn.error_hint.html) -- shows end-user hints no how to fix
 //!   [the most imporant errors](errors/index.html)
+//! * [bind_error_hint](fn.bind_error_hint.html) -- same for errors of
+//!   `bind()`, i.e. port already in use or permission denied
+//! * [unix_bind_error_hint](fn.unix_bind_error_hint.html) -- same for
+//!   `bind()` of Unix sockets, i.e. stale socket file
+//! * [register_hint](fn.register_hint.html) -- adds hints for errors
+//!   specific to your infrastructure
+//! * [set_link_base](fn.set_link_base.html) -- points links of hints to
+//!   your own runbooks
+//! * [ListenerError](struct.ListenerError.html) -- an error with the
+//!   listener label, address and hint, suitable for alerts
+//!
+//! # Features
+//!
+//! * `tracing` -- enables
+//!   [`trace_errors`](trait.ListenExt.html#method.trace_errors) and
+//!   [`trace_connections`](trait.ListenExt.html#method.trace_connections)
+//!   combinators integrating with the [tracing](https://docs.rs/tracing)
+//!   crate
+//! * `rustls` -- enables [`tls`](trait.ListenExt.html#method.tls)
+//!   combinator doing TLS termination using
+//!   [rustls](https://docs.rs/rustls), see [`tls`](tls/index.html) module
+//! * `serde` -- implements `Serialize` and `Deserialize` for
+//!   [`PeerAddr`](enum.PeerAddr.html) and
+//!   [`ListenAddr`](enum.ListenAddr.html), and `Serialize` for
+//!   [`ErrorHint`](wrapper_types/struct.ErrorHint.html)
+//! * `systemd` -- enables [`systemd`](systemd/index.html) module for
+//!   socket activation (Unix only)
+//! * `socket2` -- enables
+//!   [`ByteStream::with_socket`](struct.ByteStream.html#method.with_socket)
+//!   to set arbitrary socket options using [socket2](https://docs.rs/socket2)
+//!   and [`ListenerBuilder`](struct.ListenerBuilder.html) to set options
+//!   of listening sockets before `bind()`
 //!
 //! # Low-Level Utilities
 //!
 //! * [is_transient_error](fn.is_transient_error.html) -- determines if the
 //!   error returned from `accept()` can be ignored
+//! * [looks_like_tls](fn.looks_like_tls.html) -- detects TLS connections
+//!   for serving TLS and plaintext on the same port
 //!
 //! # Example
 //!
@@ -63,17 +111,13 @@
 //!     task::block_on(async {
 //!         let listener = TcpListener::bind("localhost:8080").await?;
 //!         eprintln!("Accepting connections on localhost:8080");
-//!         let mut incoming = listener.incoming()
+//!         listener.incoming()
 //!             .log_warnings(log_accept_error)
 //!             .handle_errors(Duration::from_millis(500))
-//!             .backpressure_wrapper(bp);
-//!         while let Some(stream) = incoming.next().await {
-//!             task::spawn(async {
-//!                 if let Err(e) = connection_loop(stream).await {
-//!                     eprintln!("Error: {}", e);
-//!                 }
-//!             });
-//!         }
+//!             .backpressure_wrapper(bp)
+//!             .serve(connection_loop)
+//!             .on_error(|e| eprintln!("Error: {}", e))
+//!             .await;
 //!         Ok(())
 //!     })
 //! }
@@ -96,12 +140,77 @@
 mod error;
 mod listen_ext;
 mod log;
+mod peer_limit;
+mod reject;
+mod load_limit;
+mod pause;
+mod pending;
+mod serve;
 mod sleep;
+mod socket_options;
+mod tarpit;
+mod tcp_info;
+mod throttle;
+mod until;
+mod watchdog;
+#[cfg(feature="tracing")] mod trace;
+mod bandwidth;
+mod bind;
+mod buffered;
+#[cfg(feature="socket2")] mod builder;
+mod burst;
 mod byte_stream;
+mod conn_info;
+mod cooldown;
+mod deadline;
+mod extensions;
+mod fd_usage;
+mod fault;
+mod handshake;
+mod histogram;
+mod idle;
+mod label;
+mod latency;
+mod listen_addr;
+mod listener_error;
+mod listener_set;
 pub mod backpressure;
+pub mod filter;
+#[cfg(unix)] pub mod inetd;
+pub mod proxy;
+pub mod shard;
+#[cfg(all(feature="systemd", unix))] pub mod systemd;
+#[cfg(feature="rustls")] pub mod tls;
+#[cfg(unix)] pub mod upgrade;
 pub mod wrapper_types;
 pub mod errors;
 
-pub use byte_stream::{ByteStream, PeerAddr};
-pub use error::{is_transient_error, error_hint};
+pub use bandwidth::{Bandwidth, Throttled};
+pub use bind::bind_dual_stack;
+#[cfg(all(unix, not(any(target_os="solaris", target_os="illumos",
+                        target_os="cygwin"))))]
+pub use bind::bind_reuseport;
+pub use buffered::BufferedByteStream;
+#[cfg(feature="socket2")] pub use builder::ListenerBuilder;
+pub use byte_stream::{ByteStream, PeerAddr, HasPeerAddr, StdStream};
+pub use byte_stream::looks_like_tls;
+pub use conn_info::ConnInfo;
+pub use deadline::Deadline;
+pub use extensions::Extensions;
+pub use fd_usage::FdUsage;
+pub use fault::Faults;
+pub use idle::IdleTimeout;
+pub use label::current_label;
+pub use log::AsyncLogger;
+pub use listen_addr::{ListenAddr, Listener, bind_all};
+pub use listener_error::{ListenerError, ErrorPhase};
+pub use listener_set::ListenerSet;
+pub use pause::PauseHandle;
+pub use socket_options::{SocketOptions, ConfigureSocket};
+pub use tcp_info::TcpInfo;
+pub use sleep::{Backoff, ErrorAction, ErrorPolicy, ErrorStats, PauseEvent};
+pub use error::{is_transient_error, error_hint, bind_error_hint};
+pub use error::unix_bind_error_hint;
+pub use error::{register_hint, set_link_base, set_fd_diagnostics};
+pub use error::{HintKind, HintMatch, Severity, TransientPolicy};
 pub use listen_ext::ListenExt;
@@ -0,0 +1,359 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use async_std::net::TcpListener;
+#[cfg(unix)] use async_std::os::unix::net::UnixListener;
+
+#[cfg(unix)] use std::os::fd::OwnedFd;
+
+use crate::byte_stream::PeerAddr;
+#[cfg(unix)] use crate::byte_stream::unix_peer_addr;
+use crate::listener_error::{ListenerError, ErrorPhase};
+use crate::listener_set::ListenerSet;
+
+
+/// An address to listen on, usually read from configuration
+///
+/// Parsed from strings like:
+///
+/// * `tcp://0.0.0.0:8080` or just `0.0.0.0:8080` -- TCP socket,
+///   host names are not resolved
+/// * `unix:/run/app.sock` (also `unix:///run/app.sock`), or just a path
+///   containing a slash -- Unix socket
+/// * `unix:@app` or just `@app` -- Unix socket in the abstract namespace
+///   (Linux only), which has no file and disappears with the last socket
+/// * `fd://3` -- already open listening socket, inherited from the parent
+///   process
+///
+/// `Display` prints the canonical form (with a scheme), which can be parsed
+/// back.
+///
+/// # Example
+///
+/// ```no_run
+/// # use async_std::task;
+/// # fn main() -> std::io::Result<()> { task::block_on(async {
+/// use async_listen::{ListenAddr, ListenerSet};
+///
+/// let addr: ListenAddr = "tcp://0.0.0.0:8080".parse()?;
+/// let mut listeners = ListenerSet::new();
+/// listeners.add_listener(addr.bind().await?);
+/// # Ok(()) }) }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ListenAddr {
+    /// TCP socket address
+    Tcp(SocketAddr),
+    /// Path of the Unix socket
+    Unix(PathBuf),
+    /// Name of the Unix socket in the Linux abstract namespace
+    ///
+    /// The name is stored without the leading NUL byte.
+    UnixAbstract(Vec<u8>),
+    /// File descriptor of an inherited listening socket
+    Fd(i32),
+}
+
+/// A listening socket of any supported kind
+///
+/// Returned by [`ListenAddr::bind`](enum.ListenAddr.html#method.bind), can
+/// be added to a [`ListenerSet`](struct.ListenerSet.html) using
+/// [`add_listener`](struct.ListenerSet.html#method.add_listener), or
+/// matched to use the socket directly.
+#[derive(Debug)]
+pub enum Listener {
+    /// TCP listening socket
+    Tcp(TcpListener),
+    /// Unix listening socket
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl ListenAddr {
+    /// Create a listening socket for this address
+    ///
+    /// For Unix sockets the socket file must not exist. The file isn't
+    /// removed automatically, because removing a socket of a running
+    /// instance makes it silently unreachable (see
+    /// [`EADDRINUSE`](errors/index.html#UNIX-EADDRINUSE)). Abstract Unix
+    /// sockets have no file, so this doesn't apply to them. They are only
+    /// supported on Linux and Android.
+    ///
+    /// For `fd://` addresses the descriptor is duplicated (so the original
+    /// descriptor stays open) and must be a TCP or Unix socket in listening
+    /// state. This is supported on Linux (kernel 5.6 or later) and on
+    /// systems providing `/dev/fd` (MacOS and BSDs).
+    pub async fn bind(&self) -> io::Result<Listener> {
+        match self {
+            ListenAddr::Tcp(addr) => {
+                Ok(Listener::Tcp(TcpListener::bind(addr).await?))
+            }
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => {
+                Ok(Listener::Unix(UnixListener::bind(path).await?))
+            }
+            #[cfg(any(target_os="linux", target_os="android"))]
+            ListenAddr::UnixAbstract(name) => {
+                Ok(Listener::Unix(bind_abstract(name)?))
+            }
+            #[cfg(all(unix,
+                      not(any(target_os="linux", target_os="android"))))]
+            ListenAddr::UnixAbstract(_) => {
+                Err(io::Error::new(io::ErrorKind::Unsupported,
+                    "abstract unix sockets are only supported on Linux"))
+            }
+            #[cfg(unix)]
+            ListenAddr::Fd(fd) => listener_from_fd(take_fd(*fd)?),
+            #[cfg(not(unix))]
+            _ => Err(io::Error::new(io::ErrorKind::Unsupported,
+                "only TCP sockets are supported on this platform")),
+        }
+    }
+}
+
+/// Bind all the addresses and accept connections from them as one stream
+///
+/// Returns a [`ListenerSet`](struct.ListenerSet.html) yielding connections
+/// from all the listeners, so the same error handling and backpressure
+/// apply to all of them, and the addresses the sockets are actually bound
+/// to, in the same order as `addrs` (i.e. with the port chosen by the
+/// system for port `0`).
+///
+/// If any address fails to bind, the error tells which one, and Unix
+/// sockets already created by this call are removed.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use async_std::prelude::*;
+/// # use async_std::task;
+/// # fn main() -> std::io::Result<()> { task::block_on(async {
+/// use async_listen::{ListenExt, ListenAddr, bind_all};
+///
+/// let addrs = vec![
+///     "tcp://0.0.0.0:8080".parse::<ListenAddr>()?,
+///     "unix:/run/app/app.sock".parse()?,
+/// ];
+/// let (listeners, bound) = bind_all(&addrs).await?;
+/// eprintln!("Listening on {:?}", bound);
+/// let mut incoming = listeners.handle_errors(Duration::from_millis(500));
+/// while let Some(stream) = incoming.next().await {
+///     // ...
+/// }
+/// # Ok(()) }) }
+/// ```
+pub async fn bind_all(addrs: &[ListenAddr])
+    -> Result<(ListenerSet, Vec<ListenAddr>), ListenerError>
+{
+    let mut set = ListenerSet::new();
+    let mut bound = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        match addr.bind().await {
+            Ok(listener) => {
+                bound.push(listener.local_addr()
+                    .unwrap_or_else(|_| addr.clone()));
+                set.add_listener(listener);
+            }
+            Err(e) => {
+                for (orig, addr) in addrs.iter().zip(&bound) {
+                    if let (ListenAddr::Unix(_), ListenAddr::Unix(path))
+                        = (orig, addr)
+                    {
+                        std::fs::remove_file(path).ok();
+                    }
+                }
+                let mut err = ListenerError::new(ErrorPhase::Bind, e);
+                if let Some(peer) = addr.peer_addr() {
+                    err = err.with_address(peer);
+                }
+                return Err(err);
+            }
+        }
+    }
+    Ok((set, bound))
+}
+
+impl ListenAddr {
+    fn peer_addr(&self) -> Option<PeerAddr> {
+        match self {
+            ListenAddr::Tcp(addr) => Some(PeerAddr::Tcp(*addr)),
+            ListenAddr::Unix(path) => Some(PeerAddr::Unix(Some(path.clone()))),
+            ListenAddr::UnixAbstract(name) => {
+                Some(PeerAddr::UnixAbstract(name.clone()))
+            }
+            ListenAddr::Fd(_) => None,
+        }
+    }
+}
+
+impl Listener {
+    /// Returns the address this listener is bound to
+    ///
+    /// Fails for unnamed Unix sockets.
+    pub fn local_addr(&self) -> io::Result<ListenAddr> {
+        match self {
+            Listener::Tcp(listener) => {
+                listener.local_addr().map(ListenAddr::Tcp)
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                match unix_peer_addr(&listener.local_addr()?) {
+                    PeerAddr::Unix(Some(path)) => Ok(ListenAddr::Unix(path)),
+                    PeerAddr::UnixAbstract(name) => {
+                        Ok(ListenAddr::UnixAbstract(name))
+                    }
+                    _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                        "unix socket is unnamed")),
+                }
+            }
+        }
+    }
+}
+
+impl From<TcpListener> for Listener {
+    fn from(listener: TcpListener) -> Listener {
+        Listener::Tcp(listener)
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixListener> for Listener {
+    fn from(listener: UnixListener) -> Listener {
+        Listener::Unix(listener)
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => write!(f, "tcp://{}", addr),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+            ListenAddr::UnixAbstract(name) => {
+                write!(f, "unix:@{}", String::from_utf8_lossy(name))
+            }
+            ListenAddr::Fd(fd) => write!(f, "fd://{}", fd),
+        }
+    }
+}
+
+/// Parses the formats described in the [type docs](enum.ListenAddr.html)
+impl FromStr for ListenAddr {
+    type Err = io::Error;
+    fn from_str(s: &str) -> io::Result<ListenAddr> {
+        fn invalid(msg: &'static str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidInput, msg)
+        }
+        if let Some(addr) = s.strip_prefix("tcp://") {
+            addr.parse().map(ListenAddr::Tcp)
+                .map_err(|_| invalid("invalid TCP socket address"))
+        } else if let Some(path) = s.strip_prefix("unix:") {
+            let path = path.strip_prefix("//").unwrap_or(path);
+            if path.is_empty() {
+                return Err(invalid("unix socket path is empty"));
+            }
+            if let Some(name) = path.strip_prefix('@') {
+                return Ok(ListenAddr::UnixAbstract(name.as_bytes().to_vec()));
+            }
+            Ok(ListenAddr::Unix(PathBuf::from(path)))
+        } else if let Some(fd) = s.strip_prefix("fd://") {
+            fd.parse().ok().filter(|fd| *fd >= 0).map(ListenAddr::Fd)
+                .ok_or_else(|| invalid("invalid file descriptor number"))
+        } else if let Ok(addr) = s.parse() {
+            Ok(ListenAddr::Tcp(addr))
+        } else if let Some(name) = s.strip_prefix('@') {
+            Ok(ListenAddr::UnixAbstract(name.as_bytes().to_vec()))
+        } else if s.contains('/') {
+            Ok(ListenAddr::Unix(PathBuf::from(s)))
+        } else {
+            Err(invalid("listen address must be either socket address, \
+                         unix:PATH, unix:@NAME or fd://NUMBER"))
+        }
+    }
+}
+
+/// Serialized as a string, the same as `Display`
+///
+/// Non-UTF-8 unix socket paths and abstract names are converted lossily.
+#[cfg(feature="serde")]
+impl serde::Serialize for ListenAddr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S)
+        -> Result<S::Ok, S::Error>
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserialized from a string, see `FromStr` implementation
+#[cfg(feature="serde")]
+impl<'de> serde::Deserialize<'de> for ListenAddr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D)
+        -> Result<ListenAddr, D::Error>
+    {
+        let s = <std::borrow::Cow<str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Bind a listener in the abstract namespace
+#[cfg(any(target_os="linux", target_os="android"))]
+pub(crate) fn bind_abstract(name: &[u8]) -> io::Result<UnixListener> {
+    #[cfg(target_os="linux")]
+    use std::os::linux::net::SocketAddrExt;
+    #[cfg(target_os="android")]
+    use std::os::android::net::SocketAddrExt;
+    use std::os::unix::net::SocketAddr;
+
+    let addr = SocketAddr::from_abstract_name(name)?;
+    let listener = std::os::unix::net::UnixListener::bind_addr(&addr)?;
+    listener.set_nonblocking(true)?;
+    Ok(listener.into())
+}
+
+/// Duplicate a raw file descriptor into an owned one
+///
+/// Wrapping a raw descriptor requires unsafe code, so the descriptor is
+/// duplicated by means of the kernel instead.
+#[cfg(target_os="linux")]
+pub(crate) fn take_fd(fd: i32) -> io::Result<OwnedFd> {
+    use rustix::process::{getpid, pidfd_open, pidfd_getfd};
+    use rustix::process::{PidfdFlags, PidfdGetfdFlags};
+
+    let pidfd = pidfd_open(getpid(), PidfdFlags::empty())?;
+    Ok(pidfd_getfd(&pidfd, fd, PidfdGetfdFlags::empty())?)
+}
+
+#[cfg(all(unix, not(target_os="linux")))]
+pub(crate) fn take_fd(fd: i32) -> io::Result<OwnedFd> {
+    // opening `/dev/fd/N` is equivalent to `dup(N)` on these systems
+    let file = std::fs::File::open(format!("/dev/fd/{}", fd))?;
+    Ok(file.into())
+}
+
+/// Create a non-blocking listener of the right kind from a socket
+#[cfg(unix)]
+pub(crate) fn listener_from_fd(fd: OwnedFd) -> io::Result<Listener> {
+    use rustix::net::{getsockname, AddressFamily};
+    use rustix::net::sockopt::socket_acceptconn;
+
+    if !socket_acceptconn(&fd)? {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput,
+            "file descriptor is not a listening socket"));
+    }
+    let family = getsockname(&fd)?.address_family();
+    if family == AddressFamily::INET || family == AddressFamily::INET6 {
+        let listener = std::net::TcpListener::from(fd);
+        listener.set_nonblocking(true)?;
+        Ok(Listener::Tcp(listener.into()))
+    } else if family == AddressFamily::UNIX {
+        let listener = std::os::unix::net::UnixListener::from(fd);
+        listener.set_nonblocking(true)?;
+        Ok(Listener::Unix(listener.into()))
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput,
+            "file descriptor is neither TCP nor Unix socket"))
+    }
+}
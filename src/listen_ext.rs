@@ -1,17 +1,43 @@
 use std::io;
 use std::time::Duration;
 
+use async_std::future::Future;
 use async_std::stream::Stream;
 
 use crate::log;
+use crate::serve;
 use crate::sleep;
+use crate::socket_options::{self, SocketOptions, ConfigureSocket};
+use crate::throttle;
+use crate::until;
+use crate::pause;
+#[cfg(feature="tracing")] use crate::trace;
+#[cfg(feature="rustls")] use crate::tls;
 use crate::backpressure::{self, Token};
-use crate::byte_stream::ByteStream;
+use crate::bandwidth::{self, Bandwidth};
+use crate::conn_info;
+use crate::deadline;
+use crate::fault::{self, Faults};
+use crate::handshake;
+use crate::idle;
+use crate::label;
+use crate::latency;
+use crate::watchdog;
+use crate::byte_stream::{ByteStream, HasPeerAddr, PeerAddr};
+use crate::pause::PauseHandle;
+use crate::filter;
+use crate::proxy;
+use crate::shard;
 
 
 /// An extension trait that provides necessary adapters for turning
 /// a stream of `accept()` events into a full-featured connection listener
 ///
+/// Adapters don't require the underlying stream to be `Unpin`, so streams
+/// built from async blocks can be used directly. An adapter is `Unpin`
+/// whenever the stream it wraps is, which is true for `incoming()` of all
+/// the listeners. Otherwise, pin the resulting stream before polling it
+/// (i.e. with `std::pin::pin!` or `Box::pin`).
 pub trait ListenExt: Stream {
     /// Log errors which aren't transient using user-specified function
     ///
@@ -58,6 +84,209 @@ pub trait ListenExt: Stream {
         log::LogWarnings::new(self, f)
     }
 
+    /// Send errors which aren't transient to an asynchronous logger
+    ///
+    /// Same as [`log_warnings`](#method.log_warnings) but the logger can't
+    /// block the accept loop. It's either a closure returning a future,
+    /// which is spawned as a separate task, or a channel sender (see
+    /// [`AsyncLogger`](trait.AsyncLogger.html)). Useful when warnings are
+    /// shipped to a remote logging service.
+    ///
+    /// Logger receives a copy of the error containing OS error code (if
+    /// any) or kind and message of the original error.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::TcpListener;
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_std::channel;
+    /// use async_listen::{ListenExt, error_hint};
+    ///
+    /// let (tx, rx) = channel::bounded(100);
+    /// task::spawn(async move {
+    ///     while let Ok(e) = rx.recv().await {
+    ///         // ship to remote syslog here
+    ///         eprintln!("Accept error: {}. {}", e, error_hint(&e));
+    ///     }
+    /// });
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .log_warnings_async(tx)
+    ///     .handle_errors(Duration::from_millis(500));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     // ...
+    /// }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn log_warnings_async<I, L>(self, logger: L)
+        -> log::LogWarningsAsync<Self, L>
+        where Self: Stream<Item=Result<I, io::Error>> + Sized,
+              L: log::AsyncLogger,
+    {
+        log::LogWarningsAsync::new(self, logger)
+    }
+
+    /// Emit `tracing` events for errors in the stream
+    ///
+    /// Non-transient errors are emitted as `WARN` events including the
+    /// [`error_hint`](fn.error_hint.html), transient ones as `DEBUG`
+    /// events. Items are passed through unchanged, so this is used in
+    /// place of (or in addition to) [`log_warnings`](#method.log_warnings).
+    /// Events have a `listener` field if the pipeline is
+    /// [`labeled`](#method.labeled).
+    ///
+    /// Only available with the `tracing` feature.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use tracing::Instrument;
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .trace_errors()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .trace_connections();
+    ///
+    /// while let Some((span, stream)) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream).instrument(span));
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    #[cfg(feature="tracing")]
+    fn trace_errors<I>(self) -> trace::TraceErrors<Self>
+        where Self: Stream<Item=Result<I, io::Error>> + Sized,
+    {
+        trace::TraceErrors::new(self)
+    }
+
+    /// Create a `tracing` span for each accepted connection
+    ///
+    /// The span is named `connection` and has `id` (sequential number
+    /// starting from `1`), `peer` (peer address) and `listener` (see
+    /// [`labeled`](#method.labeled)) fields. The stream
+    /// yields the span along with the connection, so that connection
+    /// handler can be instrumented with it (see `tracing::Instrument`).
+    ///
+    /// Only available with the `tracing` feature. See
+    /// [`trace_errors`](#method.trace_errors) for an example.
+    #[cfg(feature="tracing")]
+    fn trace_connections<I>(self) -> trace::TraceConnections<Self>
+        where Self: Stream<Item=I> + Sized,
+              I: HasPeerAddr,
+    {
+        trace::TraceConnections::new(self)
+    }
+
+    /// Call `callback` with peer address of every `n`th connection
+    ///
+    /// Logging every connection is too expensive at high accept rates, but
+    /// looking at a sample of them is often enough to see who is connecting
+    /// in production. Connections which don't have a peer address anymore
+    /// (i.e. peer has already disconnected) are counted but not reported.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .sample(1000, |peer| eprintln!("Sampled connection from {}", peer));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn sample<I, F>(self, n: u64, callback: F) -> log::Sample<Self, F>
+        where Self: Stream<Item=I> + Sized,
+              I: HasPeerAddr,
+              F: FnMut(&PeerAddr),
+    {
+        log::Sample::new(self, n, callback)
+    }
+
+    /// Attach a label (a listener name) to the pipeline
+    ///
+    /// While the stream is polled, the label is available to all the
+    /// combinators applied *before* this one, so this should usually be the
+    /// last combinator in the chain (before
+    /// [`serve`](#method.serve), if used). The label is included in events
+    /// of [`trace_errors`](#method.trace_errors) and spans of
+    /// [`trace_connections`](#method.trace_connections), and can be
+    /// obtained in any callback (i.e. in [`log_warnings`](#method.log_warnings),
+    /// [`sample`](#method.sample) or metrics hooks) using
+    /// [`current_label`](fn.current_label.html).
+    ///
+    /// This is useful to find out which listener has produced an error, when
+    /// there are multiple of them in a single process.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, current_label};
+    ///
+    /// let listener = TcpListener::bind("0.0.0.0:8080").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .log_warnings(|e| {
+    ///         let label = current_label();
+    ///         eprintln!("Error on {}: {}", label.as_deref().unwrap_or("?"), e);
+    ///     })
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .labeled("public-tcp");
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn labeled(self, label: &str) -> label::Labeled<Self>
+        where Self: Sized,
+    {
+        label::Labeled::new(self, label)
+    }
+
     /// Handle errors and return infallible stream
     ///
     /// There are two types of errors:
@@ -68,6 +297,16 @@ pub trait ListenExt: Stream {
     /// We ignore transient errors entirely, and timeout for a `sleep_amount`
     /// on stick ones.
     ///
+    /// The `sleep_on_warning` is either a `Duration` (sleep the same amount
+    /// after each error) or a [`Backoff`](struct.Backoff.html) policy, which
+    /// increases sleep time on consecutive errors.
+    ///
+    /// By default errors are retried forever. Use
+    /// [`give_up_after`](wrapper_types/struct.HandleErrors.html#method.give_up_after)
+    /// or
+    /// [`give_up_after_time`](wrapper_types/struct.HandleErrors.html#method.give_up_after_time)
+    /// to end the stream when a listener is persistently broken.
+    ///
     /// One example of warning is `EMFILE: too many open files`. In this
     /// case, if we sleep for some amount, so there is a chance that other
     /// connection or some file descriptor is closed in the meantime and we
@@ -97,28 +336,46 @@ pub trait ListenExt: Stream {
     ///
     /// # Example
     ///
-    fn handle_errors<I>(self, sleep_on_warning: Duration)
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, Backoff};
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Backoff::exponential(
+    ///         Duration::from_millis(10), Duration::from_secs(2)));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn handle_errors<I, B>(self, sleep_on_warning: B)
         -> sleep::HandleErrors<Self>
         where Self: Stream<Item=Result<I, io::Error>> + Sized,
+              B: Into<sleep::Backoff>,
     {
-        sleep::HandleErrors::new(self, sleep_on_warning)
+        sleep::HandleErrors::new(self, sleep_on_warning.into())
     }
 
-    /// Apply a fixed backpressure to the the stream
-    ///
-    /// The output stream yields pairs of (token, stream). The token must
-    /// be kept alive as long as connection is still alive.
+    /// Handle errors according to a custom policy
     ///
-    /// See [`backpressure_wrapper`](#method.backpressure_wrapper) method for
-    /// a simple way of handling backpressure in a common case.
+    /// Unlike [`handle_errors`](#method.handle_errors), which has the
+    /// split into transient and sleep-worthy errors hardcoded, this
+    /// method calls the policy for every error and the policy decides
+    /// whether to ignore the error, to sleep for some time, or to
+    /// stop accepting connections (the stream ends in this case).
     ///
-    /// `stream.backpressure(10)` is equivalent of:
-    /// ```ignore
-    /// let (tx, rx) = backpressure::new(10);
-    /// stream
-    ///     .apply_backpressure(rx)
-    ///     .map(|conn| (tx.token(), conn))
-    /// ```
+    /// The policy is either a closure `FnMut(&io::Error) -> ErrorAction`
+    /// or any type implementing [`ErrorPolicy`](trait.ErrorPolicy.html).
     ///
     /// # Example
     ///
@@ -129,122 +386,103 @@ pub trait ListenExt: Stream {
     /// # use async_std::task;
     /// # fn main() -> std::io::Result<()> { task::block_on(async {
     /// #
-    /// use async_listen::ListenExt;
+    /// use std::io;
+    /// use async_listen::{ListenExt, ErrorAction, is_transient_error};
     ///
     /// let listener = TcpListener::bind("127.0.0.1:0").await?;
     /// let mut incoming = listener.incoming()
-    ///     .handle_errors(Duration::from_millis(100))
-    ///     .backpressure(100);
-    ///
-    /// while let Some((token, stream)) = incoming.next().await {
-    ///     task::spawn(async {
-    ///         connection_loop(stream).await;
-    ///         drop(token);
+    ///     .handle_errors_with(|e: &io::Error| {
+    ///         if is_transient_error(e) {
+    ///             ErrorAction::Ignore
+    ///         } else if e.kind() == io::ErrorKind::InvalidInput {
+    ///             ErrorAction::Stop
+    ///         } else {
+    ///             ErrorAction::Sleep(Duration::from_millis(500))
+    ///         }
     ///     });
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
     /// }
     /// # async fn connection_loop(_stream: TcpStream) {
     /// # }
     /// #
     /// # Ok(()) }) }
     /// ```
-    ///
-    /// *Note:* the `drop` there is not needed you can use either:
-    ///
-    /// * `let _token = token;` inside `async` block, or
-    /// * `connection_loop(&token, stream)`,
-    ///
-    /// To achieve the same result. But `drop(token)` makes it explicit that
-    /// token is dropped only at that point, which is an important property to
-    /// achieve.
-    fn backpressure<I>(self, limit: usize)
-        -> backpressure::BackpressureToken<Self>
-        where Self: Stream<Item=I> + Sized,
+    fn handle_errors_with<I, P>(self, policy: P)
+        -> sleep::HandleErrorsWith<Self, P>
+        where Self: Stream<Item=Result<I, io::Error>> + Sized,
+              P: sleep::ErrorPolicy,
     {
-        let (_tx, rx) = backpressure::new(limit);
-        return backpressure::BackpressureToken::new(self, rx);
+        sleep::HandleErrorsWith::new(self, policy)
     }
 
-    /// Apply a backpressure object to a stream
-    ///
-    /// This method is different from [`backpressure`](#method.backpressure) in
-    /// two ways:
-    ///
-    /// 1. It doesn't modify stream output
-    /// 2. External backpressure object may be used to change limit at runtime
+    /// Measure how long connections wait before being accepted
     ///
-    /// With the greater power comes greater responsibility, though. Here are
-    /// some things to remember when using the method:
-    ///
-    /// 1. You must create a token for each connection (see example).
-    /// 2. Token *should* be created before yielding to a main loop, otherwise
-    ///    limit can be exhausted at times.
-    /// 2. Token should be kept alive as long as the connection is alive.
+    /// The `callback` is called for each item of the stream with the
+    /// (upper bound of) time the item was ready but not consumed. This
+    /// is the time since the stream was last polled, if the item was
+    /// available immediately, or zero if the stream had to wait for it.
     ///
-    /// See [`backpressure_wrapper`](#method.backpressure_wrapper) method for
-    /// a simple way of handling backpressure in a common case.
+    /// When placed right after the listener and before
+    /// [`backpressure`](#method.backpressure) (or similar combinators),
+    /// this quantifies how much queuing the connection limit introduces:
+    /// while the limit is reached the listener isn't polled, and
+    /// connections wait in the listen backlog.
     ///
     /// # Example
     ///
     /// ```no_run
     /// # use std::time::Duration;
-    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::net::TcpListener;
     /// # use async_std::prelude::*;
     /// # use async_std::task;
     /// # fn main() -> std::io::Result<()> { task::block_on(async {
     /// #
-    /// use async_listen::ListenExt;
-    /// use async_listen::backpressure;
+    /// use async_listen::{ListenExt, ByteStream, backpressure};
     ///
+    /// let (_, bp) = backpressure::new(10);
     /// let listener = TcpListener::bind("127.0.0.1:0").await?;
-    /// let (tx, rx) = backpressure::new(10);
     /// let mut incoming = listener.incoming()
-    ///     .handle_errors(Duration::from_millis(100))
-    ///     .apply_backpressure(rx);
+    ///     .measure_accept_latency(|latency| {
+    ///         if latency > Duration::from_millis(100) {
+    ///             eprintln!("Connection queued for {:?}", latency);
+    ///         }
+    ///     })
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .backpressure_wrapper(bp);
     ///
     /// while let Some(stream) = incoming.next().await {
-    ///     let token = tx.token();  // should be created before spawn
-    ///     task::spawn(async {
-    ///         connection_loop(stream).await;
-    ///         drop(token);  // should be dropped after
-    ///     });
+    ///     task::spawn(connection_loop(stream));
     /// }
-    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # async fn connection_loop(_stream: ByteStream) {
     /// # }
     /// #
     /// # Ok(()) }) }
     /// ```
-    ///
-    /// *Note:* the `drop` there is not needed you can use either:
-    ///
-    /// * `let _token = token;` inside `async` block, or
-    /// * `connection_loop(&token, stream)`,
-
-    /// To achieve the same result. But `drop(token)` makes it explicit that
-    /// token is dropped only at that point, which is an important property to
-    /// achieve. Also don't create token in async block as it makes
-    /// backpressure enforcing unreliable.
-    fn apply_backpressure<I>(self, backpressure: backpressure::Receiver)
-        -> backpressure::Backpressure<Self>
-        where Self: Stream<Item=I> + Sized,
+    fn measure_accept_latency<F>(self, callback: F)
+        -> latency::AcceptLatency<Self, F>
+        where Self: Sized,
+              F: FnMut(Duration),
     {
-        return backpressure::Backpressure::new(self, backpressure);
+        latency::AcceptLatency::new(self, callback)
     }
 
-    /// Apply a backpressure object to a stream and yield ByteStream
+    /// Call a function when no connections are accepted for a while
     ///
-    /// This method simplifies backpressure handling by hiding the token
-    /// inside the [`ByteStream`](struct.ByteStream.html) structure, so
-    /// it's lifetime is tied to the lifetime of the structure
+    /// The `callback` is called with the time since the last item each
+    /// time the stream was idle for `timeout`, i.e. repeatedly until a
+    /// connection arrives. A listener that silently stopped receiving
+    /// connections (firewall misconfiguration after a deploy, a stale
+    /// load balancer target) otherwise looks exactly like no traffic.
     ///
-    /// The wrapper works for `TcpListener` and `UdpListener` and returns
-    /// the same `ByteStream` structure on both of them. This helps working
-    /// with both kinds of sockets in a uniform way.
+    /// Place it after [`handle_errors`](#method.handle_errors), so that
+    /// items that are errors don't count as activity, but before
+    /// [`backpressure`](#method.backpressure): while the connection
+    /// limit is reached the stream isn't polled and the timer doesn't
+    /// fire.
     ///
-    /// Wrapping streams might incur tiny performance cost (although, this cast
-    /// is much smaller than cost of system calls involved in working with
-    /// sockets nevertheless). See [`backpressure`](#method.backpressure) and
-    /// [`apply_backpressure`](#method.apply_backpressure) for a wrapper-less
-    /// way of applying backpressure.
+    /// Note: the timer only runs while the stream is polled.
     ///
     /// # Example
     ///
@@ -255,49 +493,1411 @@ pub trait ListenExt: Stream {
     /// # use async_std::task;
     /// # fn main() -> std::io::Result<()> { task::block_on(async {
     /// #
-    /// use async_listen::{ListenExt, ByteStream, backpressure};
+    /// use async_listen::ListenExt;
     ///
     /// let listener = TcpListener::bind("127.0.0.1:0").await?;
-    /// let (_, rx) = backpressure::new(10);
     /// let mut incoming = listener.incoming()
-    ///     .handle_errors(Duration::from_millis(100))
-    ///     .backpressure_wrapper(rx);
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .watchdog(Duration::from_secs(300), |idle| {
+    ///         eprintln!("No connections for {:?}", idle);
+    ///     });
     ///
     /// while let Some(stream) = incoming.next().await {
     ///     task::spawn(connection_loop(stream));
     /// }
-    /// # async fn connection_loop(_stream: ByteStream) {
+    /// # async fn connection_loop(_stream: TcpStream) {
     /// # }
     /// #
     /// # Ok(()) }) }
     /// ```
+    fn watchdog<F>(self, timeout: Duration, callback: F)
+        -> watchdog::Watchdog<Self, F>
+        where Self: Sized,
+              F: FnMut(Duration),
+    {
+        watchdog::Watchdog::new(self, timeout, callback)
+    }
+
+    /// Inject artificial delays and errors into the stream (for testing)
     ///
-    /// # Notes
+    /// This is a helper for application test suites: put it right after
+    /// the listener to exercise handling of slow accept loops, accept errors
+    /// and saturation without exhausting real resources.
     ///
-    /// The following examples are equivalent:
+    /// See [`Faults`](struct.Faults.html) for available faults.
     ///
-    /// ```ignore
-    /// let (_, bp) = backpressure::new(100);
-    /// stream.backpressure_wrapper(bp)
-    /// ```
+    /// # Example
     ///
-    /// ```ignore
-    /// let (tx, rx) = backpressure::new(100);
-    /// stream.apply_backpressure(rx)
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, Faults};
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .inject_faults(Faults::new()
+    ///         .delay(Duration::from_millis(50))
+    ///         // EMFILE, "Too many open files" on Linux
+    ///         .os_error_every(10, 24))
+    ///     .handle_errors(Duration::from_millis(100));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn inject_faults<I>(self, faults: Faults) -> fault::InjectFaults<Self>
+        where Self: Stream<Item=io::Result<I>> + Sized,
+    {
+        fault::InjectFaults::new(self, faults)
+    }
+
+    /// Limit the rate at which connections are accepted
+    ///
+    /// This is a token bucket limiter: at most `burst` connections are
+    /// accepted at once, and the bucket is replenished at `rate` connections
+    /// per second. When the bucket is empty the listener is not polled, so
+    /// new connections are kept in the kernel backlog.
+    ///
+    /// This is independent of the concurrency limit applied by
+    /// [`backpressure`](#method.backpressure) and is useful to smooth out
+    /// reconnect storms, when each connection is handled quickly but
+    /// accepting them at full speed still overloads the server.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rate` is not a positive finite number.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .throttle_accepts(100.0, 20)
+    ///     .backpressure(1000);
+    ///
+    /// while let Some((token, stream)) = incoming.next().await {
+    ///     task::spawn(async {
+    ///         connection_loop(stream).await;
+    ///         drop(token);
+    ///     });
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn throttle_accepts(self, rate: f64, burst: u32)
+        -> throttle::ThrottleAccepts<Self>
+        where Self: Sized,
+    {
+        throttle::ThrottleAccepts::new(self, rate, burst)
+    }
+
+    /// Only yield connections from peers allowed by the policy
+    ///
+    /// Rejected connections are closed immediately, without spawning a task
+    /// for them. Connections whose peer address can't be determined (i.e.
+    /// when client has already disconnected) are also closed.
+    ///
+    /// The policy is either a closure `Fn(&PeerAddr) -> bool` or one of the
+    /// built-in lists: [`AllowList`](filter/struct.AllowList.html) or
+    /// [`DenyList`](filter/struct.DenyList.html).
+    ///
+    /// This must be applied to an infallible stream, i.e. after
+    /// [`handle_errors`](#method.handle_errors). When combined with
+    /// [`backpressure`](#method.backpressure), filtering should go first so
+    /// rejected connections don't hold tokens.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    /// use async_listen::filter::AllowList;
+    ///
+    /// let allow: AllowList = "127.0.0.0/8, 10.0.0.0/8".parse()?;
+    /// let listener = TcpListener::bind("0.0.0.0:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .filter_peers(allow)
+    ///     .backpressure(100);
+    ///
+    /// while let Some((token, stream)) = incoming.next().await {
+    ///     task::spawn(async {
+    ///         connection_loop(stream).await;
+    ///         drop(token);
+    ///     });
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn filter_peers<I, P>(self, policy: P) -> filter::FilterPeers<Self, P>
+        where Self: Stream<Item=I> + Sized,
+              I: HasPeerAddr,
+              P: filter::PeerPolicy,
+    {
+        filter::FilterPeers::new(self, policy)
+    }
+
+    /// Limit the number of simultaneous connections from a single IP address
+    ///
+    /// The output stream yields pairs of `(peer_token, stream)`. The token
+    /// must be kept alive as long as connection is still alive. When peer
+    /// already has `limit` connections open, new connections from it are
+    /// closed immediately.
+    ///
+    /// Connections over Unix sockets are not limited. Connections whose peer
+    /// address can't be determined are closed.
+    ///
+    /// This limit complements the global one applied by
+    /// [`backpressure`](#method.backpressure), so a single abusive client
+    /// can't consume the entire global budget.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .limit_per_peer(10)
+    ///     .backpressure(1000);
+    ///
+    /// while let Some((token, (peer_token, stream))) = incoming.next().await {
+    ///     task::spawn(async {
+    ///         connection_loop(stream).await;
+    ///         drop((token, peer_token));
+    ///     });
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn limit_per_peer<I>(self, limit: usize) -> filter::LimitPerPeer<Self>
+        where Self: Stream<Item=I> + Sized,
+              I: HasPeerAddr,
+    {
+        filter::LimitPerPeer::new(self, limit)
+    }
+
+    /// Reject connections from a peer reconnecting faster than `cooldown`
+    ///
+    /// Time of the last accepted connection is tracked for each IP address,
+    /// and new connections from the same address within the `cooldown` are
+    /// closed immediately. Buggy clients reconnecting in a tight loop
+    /// otherwise dominate accept throughput. Rejected connections don't
+    /// restart the cooldown, so the client gets through as soon as cooldown
+    /// passes.
+    ///
+    /// Connections over Unix sockets are not limited. Connections whose peer
+    /// address can't be determined are closed.
+    ///
+    /// Note: many clients behind a NAT share a single IP address, so
+    /// cooldown should be short if this is expected.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .reconnect_cooldown(Duration::from_millis(100))
+    ///     .backpressure(1000);
+    ///
+    /// while let Some((token, stream)) = incoming.next().await {
+    ///     task::spawn(async {
+    ///         connection_loop(stream).await;
+    ///         drop(token);
+    ///     });
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn reconnect_cooldown<I>(self, cooldown: Duration)
+        -> filter::ReconnectCooldown<Self>
+        where Self: Stream<Item=I> + Sized,
+              I: HasPeerAddr,
+    {
+        filter::ReconnectCooldown::new(self, cooldown)
+    }
+
+    /// Hold connections from flagged peers open instead of closing them
+    ///
+    /// Connections from peers the `policy` doesn't allow are put into
+    /// a tarpit: they are kept open in a background task, and a single
+    /// byte is written to them every 10 seconds until the peer disconnects
+    /// or 10 minutes pass (see
+    /// [`interval`](filter/struct.Tarpit.html#method.interval) and
+    /// [`duration`](filter/struct.Tarpit.html#method.duration)). This wastes
+    /// time of abusive clients and scanners, which would just reconnect
+    /// immediately if connection is closed.
+    ///
+    /// At most `capacity` connections are held in the tarpit, flagged
+    /// connections over the capacity are closed immediately, so the tarpit
+    /// itself can't be used to exhaust file descriptors. Allowed connections
+    /// are yielded as is.
+    ///
+    /// The policy is the same as for [`filter_peers`](#method.filter_peers).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    /// use async_listen::filter::DenyList;
+    ///
+    /// let flagged: DenyList = "192.0.2.0/24".parse()?;
+    /// let listener = TcpListener::bind("0.0.0.0:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .tarpit(flagged, 100)
+    ///     .backpressure(1000);
+    ///
+    /// while let Some((token, stream)) = incoming.next().await {
+    ///     task::spawn(async {
+    ///         connection_loop(stream).await;
+    ///         drop(token);
+    ///     });
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn tarpit<I, P>(self, policy: P, capacity: usize)
+        -> filter::Tarpit<Self, P>
+        where Self: Stream<Item=I> + Sized,
+              I: HasPeerAddr + async_std::io::Write + Unpin + Send + 'static,
+              P: filter::PeerPolicy,
+    {
+        filter::Tarpit::new(self, policy, capacity)
+    }
+
+    /// Attach connection id and accept timestamp to each connection
+    ///
+    /// The output stream yields pairs of
+    /// [`(ConnInfo, stream)`](struct.ConnInfo.html). Connection ids are
+    /// monotonically increasing and are useful for log correlation.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .with_conn_info();
+    ///
+    /// while let Some((info, stream)) = incoming.next().await {
+    ///     task::spawn(async move {
+    ///         eprintln!("Connection {} accepted", info.id());
+    ///         connection_loop(stream).await;
+    ///         eprintln!("Connection {} closed after {:?}",
+    ///                   info.id(), info.age());
+    ///     });
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn with_conn_info<I>(self) -> conn_info::WithConnInfo<Self>
+        where Self: Stream<Item=I> + Sized,
+    {
+        conn_info::WithConnInfo::new(self)
+    }
+
+    /// Apply socket options to each accepted connection
+    ///
+    /// Options such as `TCP_NODELAY`, keepalive, TTL and buffer sizes are
+    /// applied before connection is yielded, so handler code doesn't need
+    /// to repeat the setup. Options are not applied to Unix sockets.
+    ///
+    /// If setting options fails (usually it means that connection is already
+    /// reset by peer), the connection is yielded anyway. Use
+    /// [`on_error`](wrapper_types/struct.ConfigureSockets.html#method.on_error)
+    /// to log such errors.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, SocketOptions};
+    ///
+    /// let mut opts = SocketOptions::new();
+    /// opts.nodelay(true).keepalive(Duration::from_secs(60));
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .configure_sockets(opts)
+    ///     .on_error(|e| eprintln!("Can't set socket options: {}", e));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn configure_sockets<I>(self, options: SocketOptions)
+        -> socket_options::ConfigureSockets<Self>
+        where Self: Stream<Item=I> + Sized,
+              I: ConfigureSocket,
+    {
+        socket_options::ConfigureSockets::new(self, options)
+    }
+
+    /// End the stream when the `shutdown` future completes
+    ///
+    /// This makes `while let Some(conn) = incoming.next().await` loop
+    /// naturally exit on shutdown (i.e. on a signal), without selecting
+    /// between two futures in every server. Connections that are already
+    /// accepted are not affected.
+    ///
+    /// The output of the future is ignored. The listener itself is not closed
+    /// until the stream is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_std::channel;
+    /// use async_listen::ListenExt;
+    ///
+    /// let (stop_tx, stop_rx) = channel::bounded::<()>(1);
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .until(async move { stop_rx.recv().await.ok(); });
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// eprintln!("Stopped accepting connections");
+    /// # drop(stop_tx);
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn until<F>(self, shutdown: F) -> until::Until<Self, F>
+        where Self: Sized,
+              F: Future,
+    {
+        until::Until::new(self, shutdown)
+    }
+
+    /// End the stream after `n` connections are accepted
+    ///
+    /// Every item of the stream is counted, so this should usually be
+    /// applied after [`handle_errors`](#method.handle_errors) so that
+    /// only successfully accepted connections count. The listener itself is
+    /// not closed until the stream is dropped.
+    ///
+    /// This is useful for canary processes, fuzzing harnesses and controlled
+    /// handover to another process (i.e. on blue/green restarts).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .take_connections(1000);
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// eprintln!("Served 1000 connections, exiting");
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn take_connections(self, n: usize) -> until::TakeConnections<Self>
+        where Self: Sized,
+    {
+        until::TakeConnections::new(self, n)
+    }
+
+    /// Allow pausing and resuming accepting connections externally
+    ///
+    /// Returns the stream and a [`PauseHandle`](struct.PauseHandle.html).
+    /// While paused, the underlying stream isn't polled, so connections wait
+    /// in the listen backlog, but the connections that are already accepted
+    /// continue to be served. This is independent of the backpressure, so
+    /// can be used for operator commands like "stop accepting but keep
+    /// serving".
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let (mut incoming, handle) = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .pausable();
+    /// task::spawn(admin_interface(handle));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// # async fn admin_interface(_handle: async_listen::PauseHandle) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn pausable(self) -> (pause::Pausable<Self>, PauseHandle)
+        where Self: Sized,
+    {
+        pause::Pausable::new(self)
+    }
+
+    /// Apply a fixed backpressure to the the stream
+    ///
+    /// The output stream yields pairs of (token, stream). The token must
+    /// be kept alive as long as connection is still alive.
+    ///
+    /// See [`backpressure_wrapper`](#method.backpressure_wrapper) method for
+    /// a simple way of handling backpressure in a common case.
+    ///
+    /// `stream.backpressure(10)` is equivalent of:
+    /// ```ignore
+    /// let (tx, rx) = backpressure::new(10);
+    /// stream
+    ///     .apply_backpressure(rx)
+    ///     .map(|conn| (tx.token(), conn))
+    /// ```
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .backpressure(100);
+    ///
+    /// while let Some((token, stream)) = incoming.next().await {
+    ///     task::spawn(async {
+    ///         connection_loop(stream).await;
+    ///         drop(token);
+    ///     });
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    ///
+    /// *Note:* the `drop` there is not needed you can use either:
+    ///
+    /// * `let _token = token;` inside `async` block, or
+    /// * `connection_loop(&token, stream)`,
+    ///
+    /// To achieve the same result. But `drop(token)` makes it explicit that
+    /// token is dropped only at that point, which is an important property to
+    /// achieve.
+    fn backpressure<I>(self, limit: usize)
+        -> backpressure::BackpressureToken<Self>
+        where Self: Stream<Item=I> + Sized,
+    {
+        let (_tx, rx) = backpressure::new(limit);
+        backpressure::BackpressureToken::new(self, rx)
+    }
+
+    /// Apply a backpressure object to a stream
+    ///
+    /// This method is different from [`backpressure`](#method.backpressure) in
+    /// two ways:
+    ///
+    /// 1. It doesn't modify stream output
+    /// 2. External backpressure object may be used to change limit at runtime
+    ///
+    /// With the greater power comes greater responsibility, though. Here are
+    /// some things to remember when using the method:
+    ///
+    /// 1. You must create a token for each connection (see example).
+    /// 2. Token *should* be created before yielding to a main loop, otherwise
+    ///    limit can be exhausted at times.
+    /// 2. Token should be kept alive as long as the connection is alive.
+    ///
+    /// See [`backpressure_wrapper`](#method.backpressure_wrapper) method for
+    /// a simple way of handling backpressure in a common case.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    /// use async_listen::backpressure;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let (tx, rx) = backpressure::new(10);
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .apply_backpressure(rx);
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     let token = tx.token();  // should be created before spawn
+    ///     task::spawn(async {
+    ///         connection_loop(stream).await;
+    ///         drop(token);  // should be dropped after
+    ///     });
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    ///
+    /// *Note:* the `drop` there is not needed you can use either:
+    ///
+    /// * `let _token = token;` inside `async` block, or
+    /// * `connection_loop(&token, stream)`,
+    ///
+    /// To achieve the same result. But `drop(token)` makes it explicit that
+    /// token is dropped only at that point, which is an important property to
+    /// achieve. Also don't create token in async block as it makes
+    /// backpressure enforcing unreliable.
+    fn apply_backpressure<I>(self, backpressure: backpressure::Receiver)
+        -> backpressure::Backpressure<Self>
+        where Self: Stream<Item=I> + Sized,
+    {
+        backpressure::Backpressure::new(self, backpressure)
+    }
+
+    /// Apply a backpressure object to a stream and yield ByteStream
+    ///
+    /// This method simplifies backpressure handling by hiding the token
+    /// inside the [`ByteStream`](struct.ByteStream.html) structure, so
+    /// it's lifetime is tied to the lifetime of the structure
+    ///
+    /// The wrapper works for `TcpListener` and `UdpListener` and returns
+    /// the same `ByteStream` structure on both of them. This helps working
+    /// with both kinds of sockets in a uniform way.
+    ///
+    /// Wrapping streams might incur tiny performance cost (although, this cast
+    /// is much smaller than cost of system calls involved in working with
+    /// sockets nevertheless). See [`backpressure`](#method.backpressure) and
+    /// [`apply_backpressure`](#method.apply_backpressure) for a wrapper-less
+    /// way of applying backpressure.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, ByteStream, backpressure};
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let (_, rx) = backpressure::new(10);
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .backpressure_wrapper(rx);
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: ByteStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    ///
+    /// # Notes
+    ///
+    /// The following examples are equivalent:
+    ///
+    /// ```ignore
+    /// let (_, bp) = backpressure::new(100);
+    /// stream.backpressure_wrapper(bp)
+    /// ```
+    ///
+    /// ```ignore
+    /// let (tx, rx) = backpressure::new(100);
+    /// stream.apply_backpressure(rx)
     ///     .map(|stream| ByteStream::from((tx.token(), stream)))
     /// ```
     ///
-    /// ```ignore
-    /// stream.backpressure(100)
-    ///     .map(ByteStream::from)
+    /// ```ignore
+    /// stream.backpressure(100)
+    ///     .map(ByteStream::from)
+    /// ```
+    ///
+    fn backpressure_wrapper<I>(self, backpressure: backpressure::Receiver)
+        -> backpressure::BackpressureWrapper<Self>
+        where Self: Stream<Item=I> + Sized,
+              ByteStream: From<(Token, I)>,
+    {
+        backpressure::BackpressureWrapper::new(self, backpressure)
+    }
+
+    /// Apply a backpressure object to a stream and yield a user type
+    ///
+    /// This is a generalization of
+    /// [`backpressure_wrapper`](#method.backpressure_wrapper) for any type
+    /// `W` that implements `From<(Token, I)>`, so a token can be embedded
+    /// into application's own connection structure at the time connection
+    /// is accepted.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, backpressure::{self, Token}};
+    ///
+    /// struct Connection {
+    ///     stream: TcpStream,
+    ///     token: Token,
+    /// }
+    ///
+    /// impl From<(Token, TcpStream)> for Connection {
+    ///     fn from((token, stream): (Token, TcpStream)) -> Connection {
+    ///         Connection { stream, token }
+    ///     }
+    /// }
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let (_, rx) = backpressure::new(10);
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .backpressure_wrap::<Connection>(rx);
+    ///
+    /// while let Some(conn) = incoming.next().await {
+    ///     task::spawn(connection_loop(conn));
+    /// }
+    /// # async fn connection_loop(_conn: Connection) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn backpressure_wrap<W>(self, backpressure: backpressure::Receiver)
+        -> backpressure::BackpressureWrap<Self, W>
+        where Self: Sized,
+              W: From<(Token, Self::Item)>,
+    {
+        backpressure::BackpressureWrap::new(self, backpressure)
+    }
+
+    /// Reject connections over the limit with a canned response
+    ///
+    /// Unlike other backpressure methods, this one doesn't pause accepting
+    /// connections when the limit is reached. Instead, connections over the
+    /// limit are accepted, the `response` (i.e. an HTTP 503 or
+    /// a protocol-specific "busy" message) is written to them in
+    /// background and connection is closed. So clients get fast and explicit
+    /// rejection instead of hanging in the listen backlog.
+    ///
+    /// Accepted connections are yielded along with the backpressure token,
+    /// the same way as [`backpressure`](#method.backpressure) does.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::TcpListener;
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, ByteStream, backpressure};
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let (tx, _) = backpressure::new(100);
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .reject_over_limit(&tx,
+    ///         &b"HTTP/1.0 503 Service Unavailable\r\n\r\n"[..]);
+    ///
+    /// while let Some(pair) = incoming.next().await {
+    ///     task::spawn(connection_loop(ByteStream::from(pair)));
+    /// }
+    /// # async fn connection_loop(_stream: ByteStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn reject_over_limit<I, R>(self, sender: &backpressure::Sender,
+        response: R)
+        -> backpressure::RejectOverLimit<Self>
+        where Self: Stream<Item=I> + Sized,
+              I: async_std::io::Write + Unpin + Send + 'static,
+              R: Into<Vec<u8>>,
+    {
+        backpressure::RejectOverLimit::new(self, sender, response.into())
+    }
+
+    /// Apply a backpressure to a stream using a `Sender` and yield ByteStream
+    ///
+    /// This works exactly like
+    /// [`backpressure_wrapper`](#method.backpressure_wrapper) but derives
+    /// the receiver from the sender internally. So the sender can be kept for
+    /// changing the limit and for metrics, and the receiver part of the
+    /// [`backpressure::new`](backpressure/fn.new.html) pair may be ignored.
+    ///
+    /// Several streams may be throttled using the same sender (or its
+    /// clones), in which case they share the sender and its limit, see
+    /// [`Sender::receiver`](backpressure/struct.Sender.html#method.receiver).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::TcpListener;
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, ByteStream, backpressure};
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let (tx, _) = backpressure::new(10);
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(100))
+    ///     .backpressure_wrapper_with(&tx);
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     println!("Active connections: {}", tx.get_active_tokens());
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: ByteStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn backpressure_wrapper_with<I>(self, sender: &backpressure::Sender)
+        -> backpressure::BackpressureWrapper<Self>
+        where Self: Stream<Item=I> + Sized,
+              ByteStream: From<(Token, I)>,
+    {
+        let receiver = sender.receiver();
+        backpressure::BackpressureWrapper::new(self, receiver)
+    }
+
+    /// Limit lifetime of each connection
+    ///
+    /// Each connection is wrapped into a [`Deadline`](struct.Deadline.html)
+    /// which closes the connection after `lifetime` since it was accepted:
+    /// reads and writes start failing with `io::ErrorKind::TimedOut`, the
+    /// socket is closed and backpressure token held by the connection is
+    /// released.
+    ///
+    /// This protects from long-forgotten connections (i.e. from clients
+    /// behind NAT which have lost their state) holding the resources
+    /// forever.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::TcpListener;
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, ByteStream, Deadline, backpressure};
+    ///
+    /// let (_, bp) = backpressure::new(10);
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .backpressure_wrapper(bp)
+    ///     .with_deadline(Duration::from_secs(3600));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: Deadline<ByteStream>) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn with_deadline<I>(self, lifetime: Duration)
+        -> deadline::WithDeadline<Self>
+        where Self: Stream<Item=I> + Sized,
+    {
+        deadline::WithDeadline::new(self, lifetime)
+    }
+
+    /// Close connections which are idle for too long
+    ///
+    /// Each connection is wrapped into an
+    /// [`IdleTimeout`](struct.IdleTimeout.html) which tracks the last
+    /// successful read or write. When there was no activity for `timeout`,
+    /// the connection is closed: pending and further reads and writes fail
+    /// with `io::ErrorKind::TimedOut`, the socket is closed and
+    /// backpressure token held by the connection is released.
+    ///
+    /// Unlike [`with_deadline`](#method.with_deadline), active connections
+    /// may live as long as they need.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::TcpListener;
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, ByteStream, IdleTimeout, backpressure};
+    ///
+    /// let (_, bp) = backpressure::new(10);
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .backpressure_wrapper(bp)
+    ///     .idle_timeout(Duration::from_secs(300));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: IdleTimeout<ByteStream>) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
     /// ```
+    fn idle_timeout<I>(self, timeout: Duration)
+        -> idle::WithIdleTimeout<Self>
+        where Self: Stream<Item=I> + Sized,
+    {
+        idle::WithIdleTimeout::new(self, timeout)
+    }
+
+    /// Limit read and write throughput of each connection
     ///
-    fn backpressure_wrapper<I>(self, backpressure: backpressure::Receiver)
-        -> backpressure::BackpressureWrapper<Self>
+    /// Each connection is wrapped into a
+    /// [`Throttled`](struct.Throttled.html) which allows at most the
+    /// configured number of bytes per second in each direction, so a single
+    /// greedy client can't saturate the uplink. Limits are applied to each
+    /// connection independently, see [`Bandwidth`](struct.Bandwidth.html).
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::TcpListener;
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, ByteStream, Bandwidth, Throttled};
+    /// use async_listen::backpressure;
+    ///
+    /// let (_, bp) = backpressure::new(10);
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .backpressure_wrapper(bp)
+    ///     .limit_bandwidth(Bandwidth::new().write(1 << 20));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: Throttled<ByteStream>) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn limit_bandwidth<I>(self, limit: Bandwidth)
+        -> bandwidth::WithBandwidthLimit<Self>
         where Self: Stream<Item=I> + Sized,
-              ByteStream: From<(Token, I)>,
     {
-        return backpressure::BackpressureWrapper::new(self, backpressure);
+        bandwidth::WithBandwidthLimit::new(self, limit)
+    }
+
+    /// Perform TLS handshake on each connection
+    ///
+    /// Each connection is converted into a `ByteStream` and TLS handshake
+    /// is done using the `acceptor`. The yielded `ByteStream` reads and
+    /// writes decrypted data, so the same connection handler may serve both
+    /// plain and encrypted listeners. Backpressure token of the original
+    /// connection (if any) is kept by the yielded stream.
+    ///
+    /// Handshakes are run concurrently and connections whose handshake fails
+    /// or times out are dropped. Default handshake timeout is 10 seconds,
+    /// use [`handshake_timeout`](tls/struct.TlsAccept.html#method.handshake_timeout)
+    /// to change it. The number of concurrent handshakes can be limited with
+    /// [`concurrency_limit`](tls/struct.TlsAccept.html#method.concurrency_limit).
+    ///
+    /// Socket options should be applied (see
+    /// [`configure_sockets`](#method.configure_sockets)) before this
+    /// combinator.
+    ///
+    /// Only available with the `rustls` feature.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::sync::Arc;
+    /// # use std::time::Duration;
+    /// # use async_std::net::TcpListener;
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, ByteStream, backpressure};
+    /// use async_listen::tls::{TlsAcceptor, rustls, pki_types};
+    /// use pki_types::pem::PemObject;
+    ///
+    /// let certs = pki_types::CertificateDer::pem_file_iter("cert.pem")?
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    /// let key = pki_types::PrivateKeyDer::from_pem_file("key.pem")?;
+    /// let config = rustls::ServerConfig::builder()
+    ///     .with_no_client_auth()
+    ///     .with_single_cert(certs, key)?;
+    ///
+    /// let (_, bp) = backpressure::new(10);
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .backpressure_wrapper(bp)
+    ///     .tls(TlsAcceptor::from(Arc::new(config)))
+    ///     .handshake_timeout(Duration::from_secs(5));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: ByteStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    #[cfg(feature="rustls")]
+    fn tls<I>(self, acceptor: tls::TlsAcceptor) -> tls::TlsAccept<Self>
+        where Self: Stream<Item=I> + Sized,
+              I: Into<ByteStream>,
+    {
+        tls::TlsAccept::new(self, acceptor)
+    }
+
+    /// Decode PROXY protocol header on each connection
+    ///
+    /// Reads the header sent by a load balancer and yields it along with
+    /// the connection. The header contains the address of the original
+    /// client, and [`HasPeerAddr`](trait.HasPeerAddr.html) is implemented
+    /// for the yielded pair, so [`filter_peers`](#method.filter_peers) and
+    /// [`limit_per_peer`](#method.limit_per_peer) applied after this
+    /// combinator work with the real client address.
+    ///
+    /// Both v1 (text) and v2 (binary) headers are supported. Headers are
+    /// read concurrently, connections with an invalid header or not
+    /// sending the header in 10 seconds are dropped. Use
+    /// [`header_timeout`](proxy/struct.ProxyProtocol.html#method.header_timeout)
+    /// to change the timeout, and
+    /// [`concurrency_limit`](proxy/struct.ProxyProtocol.html#method.concurrency_limit)
+    /// to limit the number of connections waiting for the header.
+    ///
+    /// **Warning:** only use this for listeners that are reachable
+    /// exclusively by the load balancer, otherwise clients can spoof their
+    /// address.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    /// use async_listen::proxy::ProxyHeader;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .proxy_protocol()
+    ///     .header_timeout(Duration::from_secs(3));
+    ///
+    /// while let Some((header, stream)) = incoming.next().await {
+    ///     task::spawn(connection_loop(header, stream));
+    /// }
+    /// # async fn connection_loop(_header: ProxyHeader, _stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn proxy_protocol<I>(self) -> proxy::ProxyProtocol<Self, I>
+        where Self: Stream<Item=I> + Sized,
+              I: async_std::io::Read + Unpin + Send + 'static,
+    {
+        proxy::ProxyProtocol::new(self)
+    }
+
+    /// Drop connections which haven't sent anything within the timeout
+    ///
+    /// Connection is yielded only after at least one byte of data is
+    /// received on it. The data isn't consumed, so the connection handler
+    /// reads it as usual. Connections are waited for concurrently, so a
+    /// slow client doesn't delay connections accepted after it.
+    ///
+    /// This is a defense against slow-loris style clients which otherwise
+    /// hold a backpressure token (and a connection slot of the handler)
+    /// while doing nothing. Note that the token is held while waiting for
+    /// the data, so put this combinator after
+    /// [`backpressure_wrapper`](#method.backpressure_wrapper).
+    ///
+    /// Only use this for protocols where client talks first. Use
+    /// [`handshake`](#method.handshake) for more complex preludes.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::TcpListener;
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, ByteStream, backpressure};
+    ///
+    /// let (_, bp) = backpressure::new(10);
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .backpressure_wrapper(bp)
+    ///     .first_byte_timeout(Duration::from_secs(5));
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: ByteStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn first_byte_timeout<I>(self, timeout: Duration)
+        -> handshake::Handshake<Self, I, ByteStream>
+        where Self: Stream<Item=I> + Sized,
+              I: Into<ByteStream> + 'static,
+    {
+        handshake::Handshake::first_byte(self, timeout)
+    }
+
+    /// Run an async handshake on each connection with a timeout
+    ///
+    /// The function `f` is called for each connection and returns a future
+    /// that does the protocol prelude (authentication, negotiation, reading
+    /// a request header) and returns a value (usually including the
+    /// connection itself) which is yielded from the stream. Handshakes are
+    /// run concurrently.
+    ///
+    /// Connections for which the future fails or doesn't complete within
+    /// the `timeout` are dropped, so clients that connect and do nothing
+    /// never reach the connection handler. Use
+    /// [`concurrency_limit`](wrapper_types/struct.Handshake.html#method.concurrency_limit)
+    /// to limit the number of handshakes running at once.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .handshake(Duration::from_secs(5), |mut conn: TcpStream| async {
+    ///         let mut magic = [0u8; 4];
+    ///         conn.read_exact(&mut magic).await?;
+    ///         Ok((magic, conn))
+    ///     });
+    ///
+    /// while let Some((magic, stream)) = incoming.next().await {
+    ///     task::spawn(connection_loop(magic, stream));
+    /// }
+    /// # async fn connection_loop(_magic: [u8; 4], _stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn handshake<I, T, F, Fut>(self, timeout: Duration, f: F)
+        -> handshake::Handshake<Self, I, T>
+        where Self: Stream<Item=I> + Sized,
+              I: 'static,
+              T: 'static,
+              F: FnMut(I) -> Fut + Send + 'static,
+              Fut: Future<Output=io::Result<T>> + Send + 'static,
+    {
+        handshake::Handshake::with_timeout(self, timeout, f)
+    }
+
+    /// Run an async transform on each connection with limited concurrency
+    ///
+    /// The function `f` is called for each connection and the returned
+    /// future (i.e. TLS handshake, protocol negotiation, authentication
+    /// preamble) is run concurrently with other ones, but at most `limit`
+    /// of them run at the same time. When the limit is reached, no new
+    /// connections are accepted until one of the futures is finished.
+    ///
+    /// Only successful results are yielded, connections for which the future
+    /// returned an error are dropped. Unlike
+    /// [`handshake`](#method.handshake) there is no timeout, so the future
+    /// should apply its own timeout if client can stall it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is zero.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_std::future::timeout;
+    /// use async_listen::ListenExt;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let mut incoming = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .and_then_limited(100, |mut conn: TcpStream| async {
+    ///         let mut password = [0u8; 8];
+    ///         timeout(Duration::from_secs(5), conn.read_exact(&mut password))
+    ///             .await
+    ///             .map_err(|_| std::io::ErrorKind::TimedOut)??;
+    ///         if &password != b"swordfsh" {
+    ///             return Err(std::io::ErrorKind::PermissionDenied.into());
+    ///         }
+    ///         Ok(conn)
+    ///     });
+    ///
+    /// while let Some(stream) = incoming.next().await {
+    ///     task::spawn(connection_loop(stream));
+    /// }
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn and_then_limited<I, T, F, Fut>(self, limit: usize, f: F)
+        -> handshake::Handshake<Self, I, T>
+        where Self: Stream<Item=I> + Sized,
+              I: 'static,
+              T: 'static,
+              F: FnMut(I) -> Fut + Send + 'static,
+              Fut: Future<Output=io::Result<T>> + Send + 'static,
+    {
+        handshake::Handshake::new(self, f).concurrency_limit(limit)
+    }
+
+    /// Spawn a task for each connection and wait for all of them
+    ///
+    /// The `handler` is called for each connection and the returned future
+    /// is spawned as a separate task. If the future returns an error, it is
+    /// passed to the [`on_error`](wrapper_types/struct.Serve.html#method.on_error)
+    /// hook (by default it's printed to stderr).
+    /// Panics in handlers are caught and passed to the
+    /// [`on_panic`](wrapper_types/struct.Serve.html#method.on_panic) hook.
+    ///
+    /// The returned future resolves when the stream ends (see
+    /// [`until`](#method.until)) and all connection tasks have finished.
+    /// Use `task::spawn(incoming.serve(..))` to get a handle to await
+    /// completion later.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::TcpListener;
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::{ListenExt, ByteStream, backpressure};
+    ///
+    /// let (_, bp) = backpressure::new(10);
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .backpressure_wrapper(bp)
+    ///     .serve(connection_loop)
+    ///     .on_error(|e| eprintln!("Error: {}", e))
+    ///     .await;
+    ///
+    /// async fn connection_loop(mut stream: ByteStream)
+    ///     -> Result<(), std::io::Error>
+    /// {
+    ///     stream.write_all(b"hello\n").await?;
+    ///     Ok(())
+    /// }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    fn serve<F, Fut, E>(self, handler: F) -> serve::Serve<Self, F, E>
+        where Self: Sized,
+              F: FnMut(Self::Item) -> Fut,
+              Fut: Future<Output=Result<(), E>> + Send + 'static,
+              E: std::fmt::Display + Send + 'static,
+    {
+        serve::Serve::new(self, handler)
+    }
+
+    /// Distribute connections between `n` workers
+    ///
+    /// Returns a future which does the distribution and a receiver for
+    /// each worker. Each receiver is a bounded channel of `capacity`
+    /// connections, when a channel is full, accepting connections is paused
+    /// until the worker takes a connection from it. The future must be
+    /// awaited (or spawned) for connections to be distributed, it resolves
+    /// when the stream ends.
+    ///
+    /// The `strategy` chooses a worker for each connection, see the
+    /// [`shard`](shard/index.html) module for built-in strategies.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use async_std::net::{TcpListener, TcpStream};
+    /// # use async_std::prelude::*;
+    /// # use async_std::task;
+    /// # fn main() -> std::io::Result<()> { task::block_on(async {
+    /// #
+    /// use async_listen::ListenExt;
+    /// use async_listen::shard::PeerHash;
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:0").await?;
+    /// let (dispatcher, workers) = listener.incoming()
+    ///     .handle_errors(Duration::from_millis(500))
+    ///     .shard(4, 16, PeerHash::new());
+    /// for mut worker in workers {
+    ///     task::spawn(async move {
+    ///         while let Some(stream) = worker.next().await {
+    ///             connection_loop(stream).await;
+    ///         }
+    ///     });
+    /// }
+    /// dispatcher.await;
+    /// # async fn connection_loop(_stream: TcpStream) {
+    /// # }
+    /// #
+    /// # Ok(()) }) }
+    /// ```
+    #[allow(clippy::type_complexity)]
+    fn shard<P>(self, n: usize, capacity: usize, strategy: P)
+        -> (shard::Shard<Self, Self::Item, P>, Vec<shard::Receiver<Self::Item>>)
+        where Self: Sized,
+              P: shard::ShardStrategy<Self::Item>,
+    {
+        shard::Shard::new(self, n, capacity, strategy)
     }
 }
 
@@ -0,0 +1,197 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+use std::time::{Instant, SystemTime};
+
+use crate::byte_stream::PeerAddr;
+use crate::error::{ErrorHint, error_hint, bind_error_hint};
+use crate::error::unix_bind_error_hint;
+use crate::label::current_label;
+
+
+/// A phase of the listener lifecycle where an error has happened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorPhase {
+    /// Creating and binding a listening socket
+    Bind,
+    /// Accepting a connection
+    Accept,
+    /// Connection handshake (i.e. TLS or PROXY protocol header)
+    Handshake,
+}
+
+/// An I/O error of a listener with the context needed for alerts
+///
+/// Plain `io::Error` contains only the error code, so it's hard to find out
+/// which listener has failed, when, and what to do about it. This structure
+/// wraps the error together with the listener label and address, the
+/// [`ErrorPhase`](enum.ErrorPhase.html), timestamps and the
+/// [`ErrorHint`](wrapper_types/struct.ErrorHint.html) computed for the phase.
+///
+/// # Example
+///
+/// ```
+/// # use std::io;
+/// use async_listen::{ListenerError, ErrorPhase};
+///
+/// let e = io::Error::from(io::ErrorKind::ConnectionRefused);
+/// let err = ListenerError::new(ErrorPhase::Accept, e)
+///     .with_label("public-tcp");
+/// assert_eq!(err.label(), Some("public-tcp"));
+/// eprintln!("{}", err);
+/// ```
+pub struct ListenerError {
+    error: io::Error,
+    phase: ErrorPhase,
+    label: Option<Arc<str>>,
+    address: Option<PeerAddr>,
+    occurred_at: Instant,
+    system_time: SystemTime,
+    hint: ErrorHint,
+}
+
+impl ListenerError {
+    /// Wrap an error that happened in the specified phase
+    ///
+    /// The label is initialized from
+    /// [`current_label`](fn.current_label.html), so errors created
+    /// inside of the [`labeled`](trait.ListenExt.html#method.labeled)
+    /// pipeline get the label automatically. The hint is computed by
+    /// [`bind_error_hint`](fn.bind_error_hint.html) for the `Bind` phase and
+    /// by [`error_hint`](fn.error_hint.html) otherwise.
+    pub fn new(phase: ErrorPhase, error: io::Error) -> ListenerError {
+        let hint = match phase {
+            ErrorPhase::Bind => bind_error_hint(&error),
+            _ => error_hint(&error),
+        };
+        ListenerError {
+            phase,
+            label: current_label(),
+            address: None,
+            occurred_at: Instant::now(),
+            system_time: SystemTime::now(),
+            hint,
+            error,
+        }
+    }
+
+    /// Set the label of the listener
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Set the address of the listener
+    ///
+    /// For the `Bind` phase and a Unix socket address the hint is replaced
+    /// by [`unix_bind_error_hint`](fn.unix_bind_error_hint.html).
+    pub fn with_address(mut self, address: PeerAddr) -> Self {
+        if let (ErrorPhase::Bind, PeerAddr::Unix(_)) = (self.phase, &address) {
+            self.hint = unix_bind_error_hint(&self.error);
+        }
+        self.address = Some(address);
+        self
+    }
+
+    /// Returns the phase where the error has happened
+    pub fn phase(&self) -> ErrorPhase {
+        self.phase
+    }
+
+    /// Returns the label of the listener, if known
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// Returns the address of the listener, if known
+    pub fn address(&self) -> Option<&PeerAddr> {
+        self.address.as_ref()
+    }
+
+    /// Returns the moment when the error has happened
+    pub fn occurred_at(&self) -> Instant {
+        self.occurred_at
+    }
+
+    /// Returns the wall-clock time when the error has happened
+    ///
+    /// Unlike [`occurred_at`](#method.occurred_at) this can be printed in
+    /// logs and alerts.
+    pub fn system_time(&self) -> SystemTime {
+        self.system_time
+    }
+
+    /// Returns the hint for the error
+    pub fn hint(&self) -> &ErrorHint {
+        &self.hint
+    }
+
+    /// Returns the underlying I/O error
+    pub fn io_error(&self) -> &io::Error {
+        &self.error
+    }
+
+    /// Returns the kind of the underlying I/O error
+    pub fn kind(&self) -> io::ErrorKind {
+        self.error.kind()
+    }
+
+    /// Consumes the wrapper, returning the underlying I/O error
+    pub fn into_io_error(self) -> io::Error {
+        self.error
+    }
+}
+
+impl fmt::Display for ErrorPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorPhase::Bind => f.write_str("bind"),
+            ErrorPhase::Accept => f.write_str("accept"),
+            ErrorPhase::Handshake => f.write_str("handshake"),
+        }
+    }
+}
+
+impl fmt::Debug for ListenerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ListenerError")
+            .field("error", &self.error)
+            .field("phase", &self.phase)
+            .field("label", &self.label)
+            .field("address", &self.address)
+            .field("system_time", &self.system_time)
+            .field("hint", &self.hint)
+            .finish()
+    }
+}
+
+impl fmt::Display for ListenerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} error", self.phase)?;
+        if let Some(label) = &self.label {
+            write!(f, " on {}", label)?;
+        }
+        if let Some(address) = &self.address {
+            write!(f, " ({})", address)?;
+        }
+        write!(f, ": {}", self.error)?;
+        if !self.hint.is_empty() {
+            write!(f, ". {}", self.hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ListenerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+impl From<ListenerError> for io::Error {
+    fn from(err: ListenerError) -> io::Error {
+        io::Error::new(err.kind(), err)
+    }
+}
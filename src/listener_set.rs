@@ -0,0 +1,381 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+#[cfg(unix)] use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use async_std::future::Future;
+use async_std::net::TcpListener;
+#[cfg(unix)] use async_std::os::unix::net::UnixListener;
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+
+use crate::byte_stream::{ByteStream, PeerAddr};
+#[cfg(unix)] use crate::byte_stream::unix_peer_addr;
+use crate::pause::PauseHandle;
+
+
+type Accept = Pin<Box<dyn Future<Output=io::Result<ByteStream>> + Send>>;
+
+#[derive(Debug, Clone)]
+enum Listener {
+    Tcp(Arc<TcpListener>),
+    #[cfg(unix)]
+    Unix(Arc<UnixListener>),
+}
+
+#[derive(Debug, Clone)]
+enum Address {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+    #[cfg(any(target_os="linux", target_os="android"))]
+    UnixAbstract(Vec<u8>),
+}
+
+struct Slot {
+    listener: Option<Listener>,
+    address: Option<Address>,
+    accept: Option<Accept>,
+    priority: u32,
+}
+
+/// A stream of connections accepted from multiple listeners
+///
+/// Combines any number of TCP and Unix listeners into a single stream of
+/// `io::Result<ByteStream>`, so the same pipeline of error handling and
+/// backpressure can be applied to all of them at once. Listeners are polled
+/// in round-robin order, so a busy listener doesn't starve the others.
+///
+/// Listeners may also be given a priority using
+/// [`add_tcp_with_priority`](#method.add_tcp_with_priority): a connection
+/// from a listener with higher priority is always accepted before ones of
+/// lower priority. This keeps the control plane (i.e. admin Unix socket)
+/// responsive exactly when the public socket is flooded.
+///
+/// Yielded streams have no backpressure token attached, use
+/// [`ListenExt::backpressure_wrapper`](trait.ListenExt.html#method.backpressure_wrapper)
+/// to attach one.
+///
+/// The stream ends immediately if the set is empty, and never ends
+/// otherwise.
+///
+/// Accepting can be paused using [`pause_handle`](#method.pause_handle),
+/// including closing listening sockets while paused.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use async_std::net::TcpListener;
+/// # use async_std::prelude::*;
+/// # use async_std::task;
+/// # fn main() -> std::io::Result<()> { task::block_on(async {
+/// #
+/// use async_listen::{ListenExt, ListenerSet, ByteStream, backpressure};
+///
+/// let (_, bp) = backpressure::new(100);
+/// let mut listeners = ListenerSet::new();
+/// listeners.add_tcp(TcpListener::bind("127.0.0.1:8080").await?);
+/// listeners.add_tcp(TcpListener::bind("[::1]:8080").await?);
+/// let mut incoming = listeners
+///     .handle_errors(Duration::from_millis(500))
+///     .backpressure_wrapper(bp);
+///
+/// while let Some(stream) = incoming.next().await {
+///     task::spawn(connection_loop(stream));
+/// }
+/// # async fn connection_loop(_stream: ByteStream) {
+/// # }
+/// #
+/// # Ok(()) }) }
+/// ```
+#[derive(Default)]
+pub struct ListenerSet {
+    slots: Vec<Slot>,
+    // distinct priorities of the slots, highest first
+    priorities: Vec<u32>,
+    next: usize,
+    pause: Option<PauseHandle>,
+}
+
+impl fmt::Debug for ListenerSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ListenerSet")
+            .field("listeners", &self.slots.iter()
+                .map(|s| &s.listener).collect::<Vec<_>>())
+            .field("paused", &self.pause.as_ref().map(|p| p.is_paused()))
+            .finish()
+    }
+}
+
+impl Listener {
+    fn accept(&self) -> Accept {
+        match self {
+            Listener::Tcp(listener) => {
+                let listener = listener.clone();
+                Box::pin(async move {
+                    let (stream, addr) = listener.accept().await?;
+                    let mut stream = ByteStream::new_tcp_detached(stream);
+                    stream.set_peer_addr(PeerAddr::Tcp(addr));
+                    Ok(stream)
+                })
+            }
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                let listener = listener.clone();
+                Box::pin(async move {
+                    let (stream, addr) = listener.accept().await?;
+                    let mut stream = ByteStream::new_unix_detached(stream);
+                    stream.set_peer_addr(unix_peer_addr(&addr));
+                    Ok(stream)
+                })
+            }
+        }
+    }
+}
+
+impl Listener {
+    fn address(&self) -> io::Result<Address> {
+        match self {
+            Listener::Tcp(listener) => listener.local_addr().map(Address::Tcp),
+            #[cfg(unix)]
+            Listener::Unix(listener) => {
+                match unix_peer_addr(&listener.local_addr()?) {
+                    PeerAddr::Unix(Some(path)) => Ok(Address::Unix(path)),
+                    #[cfg(any(target_os="linux", target_os="android"))]
+                    PeerAddr::UnixAbstract(name) => {
+                        Ok(Address::UnixAbstract(name))
+                    }
+                    _ => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                        "unnamed unix socket can't be bound again")),
+                }
+            }
+        }
+    }
+}
+
+impl Address {
+    fn bind(&self) -> io::Result<Listener> {
+        match self {
+            Address::Tcp(addr) => {
+                let listener = std::net::TcpListener::bind(addr)?;
+                listener.set_nonblocking(true)?;
+                Ok(Listener::Tcp(Arc::new(listener.into())))
+            }
+            #[cfg(unix)]
+            Address::Unix(path) => {
+                // the socket file is left after the listener is closed
+                std::fs::remove_file(path).ok();
+                let listener = std::os::unix::net::UnixListener::bind(path)?;
+                listener.set_nonblocking(true)?;
+                Ok(Listener::Unix(Arc::new(listener.into())))
+            }
+            #[cfg(any(target_os="linux", target_os="android"))]
+            Address::UnixAbstract(name) => {
+                let listener = crate::listen_addr::bind_abstract(name)?;
+                Ok(Listener::Unix(Arc::new(listener)))
+            }
+        }
+    }
+}
+
+impl ListenerSet {
+    /// Create an empty set of listeners
+    pub fn new() -> ListenerSet {
+        ListenerSet::default()
+    }
+
+    /// Add a TCP listener to the set
+    pub fn add_tcp(&mut self, listener: TcpListener) -> &mut ListenerSet {
+        self.add(Listener::Tcp(Arc::new(listener)), 0)
+    }
+
+    /// Add a Unix listener to the set
+    #[cfg(unix)]
+    pub fn add_unix(&mut self, listener: UnixListener) -> &mut ListenerSet {
+        self.add(Listener::Unix(Arc::new(listener)), 0)
+    }
+
+    /// Add a listener of any kind to the set
+    ///
+    /// Useful with listeners created by
+    /// [`ListenAddr::bind`](enum.ListenAddr.html#method.bind).
+    pub fn add_listener(&mut self, listener: crate::Listener)
+        -> &mut ListenerSet
+    {
+        match listener {
+            crate::Listener::Tcp(listener) => self.add_tcp(listener),
+            #[cfg(unix)]
+            crate::Listener::Unix(listener) => self.add_unix(listener),
+        }
+    }
+
+    /// Add a TCP listener with the specified priority
+    ///
+    /// Connections are accepted from listeners with higher `priority` first,
+    /// lower priority listeners are only served when all the higher ones
+    /// have no pending connections. Listeners of the same priority are
+    /// polled round-robin. Default priority is `0`.
+    pub fn add_tcp_with_priority(&mut self, listener: TcpListener,
+        priority: u32)
+        -> &mut ListenerSet
+    {
+        self.add(Listener::Tcp(Arc::new(listener)), priority)
+    }
+
+    /// Add a Unix listener with the specified priority
+    ///
+    /// See [`add_tcp_with_priority`](#method.add_tcp_with_priority).
+    #[cfg(unix)]
+    pub fn add_unix_with_priority(&mut self, listener: UnixListener,
+        priority: u32)
+        -> &mut ListenerSet
+    {
+        self.add(Listener::Unix(Arc::new(listener)), priority)
+    }
+
+    fn add(&mut self, listener: Listener, priority: u32) -> &mut ListenerSet {
+        self.slots.push(Slot {
+            listener: Some(listener),
+            address: None,
+            accept: None,
+            priority,
+        });
+        if let Err(pos) = self.priorities
+            .binary_search_by(|p| priority.cmp(p))
+        {
+            self.priorities.insert(pos, priority);
+        }
+        self
+    }
+
+    /// Returns a handle to pause and resume accepting connections
+    ///
+    /// Unlike [`ListenExt::pausable`](trait.ListenExt.html#method.pausable),
+    /// this handle also supports
+    /// [`pause_and_close`](struct.PauseHandle.html#method.pause_and_close),
+    /// which closes listening sockets while paused, so clients get
+    /// "connection refused" immediately instead of waiting in the listen
+    /// backlog. Sockets are bound again to the same addresses on resume.
+    /// For unix sockets the socket file is removed before binding.
+    ///
+    /// All calls return handles to the same pause state.
+    pub fn pause_handle(&mut self) -> PauseHandle {
+        self.pause.get_or_insert_with(PauseHandle::new).clone()
+    }
+
+    fn close_listeners(&mut self) {
+        for slot in &mut self.slots {
+            if let Some(listener) = &slot.listener {
+                // keep sockets which we wouldn't be able to bind again
+                if let Ok(address) = listener.address() {
+                    slot.accept = None;
+                    slot.listener = None;
+                    slot.address = Some(address);
+                }
+            }
+        }
+    }
+
+    fn reopen_listeners(&mut self) -> io::Result<()> {
+        for slot in &mut self.slots {
+            if slot.listener.is_none() {
+                if let Some(address) = &slot.address {
+                    slot.listener = Some(address.bind()?);
+                    slot.address = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Make listening sockets inheritable and return their descriptors
+    #[cfg(unix)]
+    pub(crate) fn inheritable_fds(&self)
+        -> io::Result<Vec<(i32, crate::ListenAddr)>>
+    {
+        use std::os::fd::{AsFd, AsRawFd};
+        use rustix::io::{fcntl_setfd, FdFlags};
+
+        let mut result = Vec::with_capacity(self.slots.len());
+        for slot in &self.slots {
+            let listener = match &slot.listener {
+                Some(listener) => listener,
+                None => continue,
+            };
+            let (fd, addr) = match (listener, listener.address()?) {
+                (Listener::Tcp(l), Address::Tcp(addr)) => {
+                    (l.as_fd(), crate::ListenAddr::Tcp(addr))
+                }
+                (Listener::Unix(l), Address::Unix(path)) => {
+                    (l.as_fd(), crate::ListenAddr::Unix(path))
+                }
+                #[cfg(any(target_os="linux", target_os="android"))]
+                (Listener::Unix(l), Address::UnixAbstract(name)) => {
+                    (l.as_fd(), crate::ListenAddr::UnixAbstract(name))
+                }
+                _ => unreachable!("address matches listener"),
+            };
+            fcntl_setfd(fd, FdFlags::empty())?;
+            result.push((fd.as_raw_fd(), addr));
+        }
+        Ok(result)
+    }
+
+    /// Returns number of listeners in the set
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Returns true if there are no listeners in the set
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+impl Stream for ListenerSet {
+    type Item = io::Result<ByteStream>;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let num = self.slots.len();
+        if num == 0 {
+            return Poll::Ready(None);
+        }
+        if let Some(pause) = &self.pause {
+            if pause.register(cx) {
+                if pause.wants_close() {
+                    self.close_listeners();
+                }
+                return Poll::Pending;
+            }
+            if let Err(e) = self.reopen_listeners() {
+                return Poll::Ready(Some(Err(e)));
+            }
+        }
+        let start = self.next % num;
+        for pidx in 0..self.priorities.len() {
+            let priority = self.priorities[pidx];
+            for idx in (start..num).chain(0..start) {
+                let Slot { listener, accept, priority: prio, .. }
+                    = &mut self.slots[idx];
+                if *prio != priority {
+                    continue;
+                }
+                let listener = match listener {
+                    Some(listener) => listener,
+                    None => continue,
+                };
+                let fut = accept.get_or_insert_with(|| listener.accept());
+                if let Poll::Ready(res) = fut.as_mut().poll(cx) {
+                    *accept = None;
+                    self.next = idx + 1;
+                    return Poll::Ready(Some(res));
+                }
+            }
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,179 @@
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+use async_std::task::sleep;
+
+use crate::backpressure::Sender;
+
+
+/// The system pressure signal used by [`LoadLimiter`](struct.LoadLimiter.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadSource {
+    /// One-minute load average divided by the number of CPUs
+    ///
+    /// Value of `1.0` means all CPUs are busy. Read from `/proc/loadavg`,
+    /// so only supported on Linux.
+    LoadAverage,
+    /// Linux Pressure Stall Information for CPU
+    ///
+    /// This is `some avg10` value from `/proc/pressure/cpu` divided by 100,
+    /// so `1.0` means some tasks were stalled on CPU all the time during
+    /// last 10 seconds. Requires Linux 4.20+ with PSI enabled.
+    CpuPressure,
+}
+
+/// Adjusts backpressure limit based on the system load
+///
+/// When the host is overloaded, accepting more connections usually makes
+/// things worse. This structure periodically reads a system pressure signal
+/// (see [`LoadSource`](enum.LoadSource.html)) and lowers the limit of the
+/// backpressure [`Sender`](struct.Sender.html) accordingly:
+///
+/// * when pressure is below `low` threshold limit is `max_limit`
+/// * when pressure is above `high` threshold limit is `min_limit`
+/// * in between the limit is interpolated linearly
+///
+/// If the signal can't be read (i.e. on unsupported platform), the limit is
+/// reset to `max_limit` so the limiter never makes things worse than plain
+/// fixed-limit backpressure.
+///
+/// # Example
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// use async_std::task;
+/// use async_listen::backpressure::{self, LoadLimiter, LoadSource};
+///
+/// let (tx, rx) = backpressure::new(1000);
+/// let limiter = LoadLimiter::new(tx, 1000)
+///     .min_limit(100)
+///     .source(LoadSource::CpuPressure)
+///     .thresholds(0.2, 0.8)
+///     .interval(Duration::from_secs(5));
+/// task::spawn(limiter.run());
+/// ```
+#[derive(Debug)]
+pub struct LoadLimiter {
+    sender: Sender,
+    min_limit: usize,
+    max_limit: usize,
+    source: LoadSource,
+    low: f64,
+    high: f64,
+    interval: Duration,
+}
+
+impl LoadLimiter {
+    /// Create a limiter which controls `sender` and never sets limit higher
+    /// than `max_limit`
+    ///
+    /// Defaults are: `min_limit` of 1, `LoadAverage` source, thresholds
+    /// of `0.7` and `1.5`, and interval of one second.
+    pub fn new(sender: Sender, max_limit: usize) -> LoadLimiter {
+        LoadLimiter {
+            sender,
+            min_limit: 1.min(max_limit),
+            max_limit,
+            source: LoadSource::LoadAverage,
+            low: 0.7,
+            high: 1.5,
+            interval: Duration::from_secs(1),
+        }
+    }
+
+    /// Set the lowest limit used when the host is overloaded
+    pub fn min_limit(mut self, min_limit: usize) -> LoadLimiter {
+        self.min_limit = min_limit.min(self.max_limit);
+        self
+    }
+
+    /// Set the pressure signal to use
+    pub fn source(mut self, source: LoadSource) -> LoadLimiter {
+        self.source = source;
+        self
+    }
+
+    /// Set pressure values at which limit starts to decrease and at which
+    /// limit reaches `min_limit`
+    pub fn thresholds(mut self, low: f64, high: f64) -> LoadLimiter {
+        assert!(low < high, "low threshold must be lower than high one");
+        self.low = low;
+        self.high = high;
+        self
+    }
+
+    /// Set how often the pressure signal is read
+    pub fn interval(mut self, interval: Duration) -> LoadLimiter {
+        self.interval = interval;
+        self
+    }
+
+    /// Returns the limit that corresponds to the specified pressure value
+    pub fn limit_for(&self, pressure: f64) -> usize {
+        if pressure.is_nan() || pressure <= self.low {
+            return self.max_limit;
+        }
+        if pressure >= self.high {
+            return self.min_limit;
+        }
+        let ratio = (pressure - self.low) / (self.high - self.low);
+        let range = (self.max_limit - self.min_limit) as f64;
+        self.max_limit - (range * ratio).round() as usize
+    }
+
+    /// Read pressure signal once and update the limit
+    ///
+    /// Returns the pressure value read. On error limit is set to
+    /// `max_limit`.
+    pub fn update(&self) -> io::Result<f64> {
+        match read_pressure(self.source) {
+            Ok(pressure) => {
+                self.sender.set_limit(self.limit_for(pressure));
+                Ok(pressure)
+            }
+            Err(e) => {
+                self.sender.set_limit(self.max_limit);
+                Err(e)
+            }
+        }
+    }
+
+    /// Run the limiter forever
+    ///
+    /// Usually this future is spawned as a separate task.
+    pub async fn run(self) {
+        loop {
+            self.update().ok();
+            sleep(self.interval).await;
+        }
+    }
+}
+
+fn read_pressure(source: LoadSource) -> io::Result<f64> {
+    match source {
+        LoadSource::LoadAverage => {
+            let text = fs::read_to_string("/proc/loadavg")?;
+            let load = parse_float(text.split_whitespace().next())?;
+            let cpus = std::thread::available_parallelism()
+                .map(|n| n.get()).unwrap_or(1);
+            Ok(load / cpus as f64)
+        }
+        LoadSource::CpuPressure => {
+            let text = fs::read_to_string("/proc/pressure/cpu")?;
+            let avg10 = text.lines()
+                .find(|line| line.starts_with("some "))
+                .and_then(|line| {
+                    line.split_whitespace()
+                        .find_map(|f| f.strip_prefix("avg10="))
+                });
+            Ok(parse_float(avg10)? / 100.0)
+        }
+    }
+}
+
+fn parse_float(value: Option<&str>) -> io::Result<f64> {
+    value.and_then(|v| v.parse().ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+                                      "can't parse pressure value"))
+}
@@ -1,20 +1,32 @@
 use std::io;
 use std::fmt;
 use std::pin::Pin;
+use std::time::Duration;
 
+use async_std::channel;
+use async_std::future::Future;
 use async_std::stream::Stream;
-use async_std::task::{Poll, Context};
+use async_std::task::{self, Poll, Context};
+use pin_project_lite::pin_project;
 
-use crate::is_transient_error;
+use crate::burst::Burst;
+use crate::error::{Transient, TransientPolicy, default_transient};
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
 
-/// A stream adapter that logs errors which aren't transient
-///
-/// See
-/// [`ListenExt::log_warnings`](../trait.ListenExt.html#method.log_warnings)
-/// for more info.
-pub struct LogWarnings<S, F> {
-    stream: S,
-    logger: F,
+pin_project! {
+    /// A stream adapter that logs errors which aren't transient
+    ///
+    /// See
+    /// [`ListenExt::log_warnings`](../trait.ListenExt.html#method.log_warnings)
+    /// for more info.
+    #[project = LogWarningsProj]
+    pub struct LogWarnings<S, F> {
+        #[pin]
+        stream: S,
+        logger: F,
+        transient: Transient,
+        burst: Option<Burst>,
+    }
 }
 
 impl<S: fmt::Debug, F> fmt::Debug for LogWarnings<S, F> {
@@ -25,16 +37,49 @@ impl<S: fmt::Debug, F> fmt::Debug for LogWarnings<S, F> {
     }
 }
 
-impl<S: Unpin, F> Unpin for LogWarnings<S, F> {}
-
 impl<S, F> LogWarnings<S, F> {
     pub(crate) fn new(stream: S, f: F) -> LogWarnings<S, F> {
         LogWarnings {
             stream,
             logger: f,
+            transient: default_transient(),
+            burst: None,
         }
     }
 
+    /// Use a custom policy to decide which errors are transient
+    ///
+    /// By default
+    /// [`is_transient_error`](../fn.is_transient_error.html) is used.
+    /// See [`TransientPolicy`](../trait.TransientPolicy.html).
+    pub fn transient_policy<P>(mut self, policy: P) -> Self
+        where P: TransientPolicy + Send + 'static,
+    {
+        self.transient = Box::new(policy);
+        self
+    }
+
+    /// Call the function when transient errors come in a burst
+    ///
+    /// Transient errors (like connection reset) are expected in small
+    /// numbers, but a sudden spike usually means a SYN flood or a broken
+    /// load balancer. The function is called with the last error and the
+    /// number of errors when `threshold` transient errors occur within the
+    /// `window`. It's called once per burst: not again until there is a
+    /// window with fewer errors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` is zero.
+    pub fn on_burst<C>(mut self, threshold: usize, window: Duration,
+                       callback: C)
+        -> Self
+        where C: FnMut(&io::Error, usize) + Send + 'static,
+    {
+        self.burst = Some(Burst::new(threshold, window, callback));
+        self
+    }
+
     /// Acquires a reference to the underlying stream that this adapter is
     /// pulling from.
     pub fn get_ref(&self) -> &S {
@@ -54,19 +99,258 @@ impl<S, F> LogWarnings<S, F> {
 }
 
 impl<I, S, F> Stream for LogWarnings<S, F>
-    where S: Stream<Item=Result<I, io::Error>> + Unpin,
+    where S: Stream<Item=Result<I, io::Error>>,
           F: FnMut(&io::Error),
 {
     type Item = Result<I, io::Error>;
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
         -> Poll<Option<Self::Item>>
     {
-        let res = Pin::new(&mut self.stream).poll_next(cx);
+        let this = self.project();
+        let res = this.stream.poll_next(cx);
         match &res {
-            Poll::Ready(Some(Err(e))) if !is_transient_error(e)
-            => (self.get_mut().logger)(e),
+            Poll::Ready(Some(Err(e))) if !this.transient.is_transient(e)
+            => (this.logger)(e),
+            Poll::Ready(Some(Err(e))) => {
+                if let Some(ref mut burst) = this.burst {
+                    burst.error(e);
+                }
+            }
             _ => {}
         };
-        return res;
+        res
+    }
+}
+
+/// An asynchronous receiver of warnings
+///
+/// Used by
+/// [`ListenExt::log_warnings_async`](trait.ListenExt.html#method.log_warnings_async).
+/// The trait is implemented for:
+///
+/// * closures `FnMut(io::Error) -> impl Future<Output=()>`, the returned
+///   future is spawned as a separate task
+/// * `async_std::channel::Sender<io::Error>`, errors are dropped if the
+///   channel is full or closed, so the accept loop is never blocked
+pub trait AsyncLogger {
+    /// Ship the error to the logger, must not block
+    fn log(&mut self, error: io::Error);
+}
+
+pin_project! {
+    /// A stream adapter that sends errors which aren't transient to a logger
+    ///
+    /// See
+    /// [`ListenExt::log_warnings_async`](../trait.ListenExt.html#method.log_warnings_async)
+    /// for more info.
+    #[project = LogWarningsAsyncProj]
+    pub struct LogWarningsAsync<S, L> {
+        #[pin]
+        stream: S,
+        logger: L,
+        transient: Transient,
+        burst: Option<Burst>,
+    }
+}
+
+impl<F, Fut> AsyncLogger for F
+    where F: FnMut(io::Error) -> Fut,
+          Fut: Future<Output=()> + Send + 'static,
+{
+    fn log(&mut self, error: io::Error) {
+        task::spawn(self(error));
+    }
+}
+
+impl AsyncLogger for channel::Sender<io::Error> {
+    fn log(&mut self, error: io::Error) {
+        self.try_send(error).ok();
+    }
+}
+
+/// Create an owned copy of the error
+///
+/// Only OS error code or kind and message are preserved
+pub(crate) fn copy_error(e: &io::Error) -> io::Error {
+    match e.raw_os_error() {
+        Some(code) => io::Error::from_raw_os_error(code),
+        None => io::Error::new(e.kind(), e.to_string()),
+    }
+}
+
+impl<S: fmt::Debug, L> fmt::Debug for LogWarningsAsync<S, L> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LogWarningsAsync")
+            .field("stream", &self.stream)
+            .finish()
+    }
+}
+
+impl<S, L> LogWarningsAsync<S, L> {
+    pub(crate) fn new(stream: S, logger: L) -> LogWarningsAsync<S, L> {
+        LogWarningsAsync {
+            stream,
+            logger,
+            transient: default_transient(),
+            burst: None,
+        }
+    }
+
+    /// Use a custom policy to decide which errors are transient
+    ///
+    /// By default
+    /// [`is_transient_error`](../fn.is_transient_error.html) is used.
+    /// See [`TransientPolicy`](../trait.TransientPolicy.html).
+    pub fn transient_policy<P>(mut self, policy: P) -> Self
+        where P: TransientPolicy + Send + 'static,
+    {
+        self.transient = Box::new(policy);
+        self
+    }
+
+    /// Call the function when transient errors come in a burst
+    ///
+    /// Transient errors (like connection reset) are expected in small
+    /// numbers, but a sudden spike usually means a SYN flood or a broken
+    /// load balancer. The function is called with the last error and the
+    /// number of errors when `threshold` transient errors occur within the
+    /// `window`. It's called once per burst: not again until there is a
+    /// window with fewer errors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` is zero.
+    pub fn on_burst<C>(mut self, threshold: usize, window: Duration,
+                       callback: C)
+        -> Self
+        where C: FnMut(&io::Error, usize) + Send + 'static,
+    {
+        self.burst = Some(Burst::new(threshold, window, callback));
+        self
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S, L> Stream for LogWarningsAsync<S, L>
+    where S: Stream<Item=Result<I, io::Error>>,
+          L: AsyncLogger,
+{
+    type Item = Result<I, io::Error>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        let res = this.stream.poll_next(cx);
+        match &res {
+            Poll::Ready(Some(Err(e))) if !this.transient.is_transient(e)
+            => this.logger.log(copy_error(e)),
+            Poll::Ready(Some(Err(e))) => {
+                if let Some(ref mut burst) = this.burst {
+                    burst.error(e);
+                }
+            }
+            _ => {}
+        };
+        res
+    }
+}
+
+pin_project! {
+    /// A stream adapter that reports peer address of every Nth connection
+    ///
+    /// See
+    /// [`ListenExt::sample`](../trait.ListenExt.html#method.sample)
+    /// for more info.
+    #[project = SampleProj]
+    pub struct Sample<S, F> {
+        #[pin]
+        stream: S,
+        every: u64,
+        counter: u64,
+        callback: F,
+    }
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for Sample<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sample")
+            .field("stream", &self.stream)
+            .field("every", &self.every)
+            .field("counter", &self.counter)
+            .finish()
+    }
+}
+
+impl<S, F> Sample<S, F> {
+    pub(crate) fn new(stream: S, every: u64, f: F) -> Sample<S, F> {
+        assert!(every > 0, "sampling interval must be positive");
+        Sample {
+            stream,
+            every,
+            counter: 0,
+            callback: f,
+        }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns total number of connections seen by the adapter
+    pub fn connections(&self) -> u64 {
+        self.counter
+    }
+}
+
+impl<I, S, F> Stream for Sample<S, F>
+    where S: Stream<Item=I>,
+          I: HasPeerAddr,
+          F: FnMut(&PeerAddr),
+{
+    type Item = I;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        let res = this.stream.poll_next(cx);
+        if let Poll::Ready(Some(conn)) = &res {
+            *this.counter += 1;
+            if this.counter.is_multiple_of(*this.every) {
+                // peer might have already disconnected, skip such sample
+                if let Ok(addr) = conn.get_peer_addr() {
+                    (this.callback)(&addr);
+                }
+            }
+        }
+        res
     }
 }
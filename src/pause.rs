@@ -0,0 +1,177 @@
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context, Waker};
+use pin_project_lite::pin_project;
+
+
+struct Inner {
+    paused: AtomicBool,
+    close: AtomicBool,
+    task: Mutex<Option<Waker>>,
+}
+
+pin_project! {
+    /// A stream adapter that can be paused externally
+    ///
+    /// See
+    /// [`ListenExt::pausable`](../trait.ListenExt.html#method.pausable)
+    /// for more info.
+    #[project = PausableProj]
+    pub struct Pausable<S> {
+        #[pin]
+        stream: S,
+        handle: PauseHandle,
+    }
+}
+
+/// A handle which pauses and resumes accepting connections
+///
+/// Returned by
+/// [`ListenExt::pausable`](trait.ListenExt.html#method.pausable). The handle
+/// can be cloned and sent to other tasks (i.e. to an admin interface).
+#[derive(Clone)]
+pub struct PauseHandle {
+    inner: Arc<Inner>,
+}
+
+fn lock(inner: &Inner) -> MutexGuard<'_, Option<Waker>> {
+    inner.task.lock().expect("pause lock is not poisoned")
+}
+
+impl<S: fmt::Debug> fmt::Debug for Pausable<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pausable")
+            .field("stream", &self.stream)
+            .field("paused", &self.handle.is_paused())
+            .finish()
+    }
+}
+
+impl fmt::Debug for PauseHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PauseHandle")
+            .field("paused", &self.is_paused())
+            .finish()
+    }
+}
+
+impl<S> Pausable<S> {
+    pub(crate) fn new(stream: S) -> (Pausable<S>, PauseHandle) {
+        let handle = PauseHandle::new();
+        (Pausable { stream, handle: handle.clone() }, handle)
+    }
+
+    /// Returns a new handle controlling this stream
+    pub fn handle(&self) -> PauseHandle {
+        self.handle.clone()
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl PauseHandle {
+    pub(crate) fn new() -> PauseHandle {
+        PauseHandle {
+            inner: Arc::new(Inner {
+                paused: AtomicBool::new(false),
+                close: AtomicBool::new(false),
+                task: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Stop accepting connections
+    ///
+    /// Connections that are already accepted are not affected. New ones
+    /// wait in the listen backlog until [`resume`](#method.resume) is
+    /// called.
+    pub fn pause(&self) {
+        self.inner.paused.store(true, Ordering::SeqCst);
+        self.wake();
+    }
+
+    /// Stop accepting connections and close listening sockets
+    ///
+    /// Unlike [`pause`](#method.pause), clients get "connection refused"
+    /// immediately instead of waiting in the listen backlog (connections
+    /// that are already in the backlog are reset). Sockets are bound again
+    /// to the same addresses on [`resume`](#method.resume).
+    ///
+    /// This only works for the handle returned from
+    /// [`ListenerSet::pause_handle`](struct.ListenerSet.html#method.pause_handle),
+    /// as other streams don't know how to rebind the socket. For other
+    /// handles this is the same as `pause`.
+    pub fn pause_and_close(&self) {
+        self.inner.close.store(true, Ordering::SeqCst);
+        self.pause();
+    }
+
+    /// Continue accepting connections
+    ///
+    /// If sockets were closed by [`pause_and_close`](#method.pause_and_close)
+    /// they are bound again. Binding errors are yielded from the stream of
+    /// connections and binding is retried on next poll (i.e. after the
+    /// sleep in [`handle_errors`](trait.ListenExt.html#method.handle_errors)).
+    pub fn resume(&self) {
+        self.inner.close.store(false, Ordering::SeqCst);
+        self.inner.paused.store(false, Ordering::SeqCst);
+        self.wake();
+    }
+
+    /// Returns true if accepting connections is paused
+    pub fn is_paused(&self) -> bool {
+        self.inner.paused.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn wants_close(&self) -> bool {
+        self.inner.close.load(Ordering::SeqCst)
+    }
+
+    /// Registers the task to wake up on pause and resume
+    ///
+    /// Returns true if stream is paused.
+    pub(crate) fn register(&self, cx: &Context) -> bool {
+        *lock(&self.inner) = Some(cx.waker().clone());
+        // re-check in case state was changed before waker was stored
+        self.is_paused()
+    }
+
+    fn wake(&self) {
+        if let Some(waker) = lock(&self.inner).take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<I, S> Stream for Pausable<S>
+    where S: Stream<Item=I>,
+{
+    type Item = I;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        if self.handle.is_paused() && self.handle.register(cx) {
+            return Poll::Pending;
+        }
+        self.as_mut().project().stream.poll_next(cx)
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::IpAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
+use crate::filter::normalize;
+
+
+type Counters = Arc<Mutex<HashMap<IpAddr, usize>>>;
+
+pin_project! {
+    /// A stream adapter that limits number of connections per peer IP
+    ///
+    /// See
+    /// [`ListenExt::limit_per_peer`](../trait.ListenExt.html#method.limit_per_peer)
+    /// for more info.
+    #[project = LimitPerPeerProj]
+    pub struct LimitPerPeer<S> {
+        #[pin]
+        stream: S,
+        limit: usize,
+        counters: Counters,
+    }
+}
+
+/// The token which holds a slot of per-peer connection limit
+///
+/// Must be kept alive as long as connection is alive.
+pub struct PeerToken {
+    counters: Counters,
+    addr: Option<IpAddr>,
+}
+
+fn lock(counters: &Counters) -> MutexGuard<'_, HashMap<IpAddr, usize>> {
+    counters.lock().expect("peer limit lock is not poisoned")
+}
+
+impl<S: fmt::Debug> fmt::Debug for LimitPerPeer<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LimitPerPeer")
+            .field("stream", &self.stream)
+            .field("limit", &self.limit)
+            .field("peers", &lock(&self.counters).len())
+            .finish()
+    }
+}
+
+impl fmt::Debug for PeerToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.addr {
+            Some(addr) => write!(f, "<PeerToken {}>", addr),
+            None => write!(f, "<PeerToken>"),
+        }
+    }
+}
+
+impl<S> LimitPerPeer<S> {
+    pub(crate) fn new(stream: S, limit: usize) -> LimitPerPeer<S> {
+        LimitPerPeer {
+            stream,
+            limit,
+            counters: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns number of active connections from the IP address
+    pub fn get_active(&self, addr: IpAddr) -> usize {
+        lock(&self.counters).get(&normalize(addr)).copied().unwrap_or(0)
+    }
+
+    fn acquire(&self, peer: PeerAddr) -> Option<PeerToken> {
+        let addr = match peer {
+            PeerAddr::Tcp(addr) => normalize(addr.ip()),
+            PeerAddr::Unix(_) | PeerAddr::UnixAbstract(_) => {
+                return Some(PeerToken {
+                    counters: self.counters.clone(),
+                    addr: None,
+                });
+            }
+        };
+        let mut counters = lock(&self.counters);
+        // check before inserting, so rejected peers don't leave zero entries
+        if counters.get(&addr).copied().unwrap_or(0) >= self.limit {
+            return None;
+        }
+        *counters.entry(addr).or_insert(0) += 1;
+        Some(PeerToken {
+            counters: self.counters.clone(),
+            addr: Some(addr),
+        })
+    }
+}
+
+impl PeerToken {
+    /// The IP address this token is counted for
+    ///
+    /// Returns `None` for Unix sockets, which aren't limited.
+    pub fn addr(&self) -> Option<IpAddr> {
+        self.addr
+    }
+}
+
+impl Drop for PeerToken {
+    fn drop(&mut self) {
+        if let Some(addr) = self.addr {
+            let mut counters = lock(&self.counters);
+            if let Some(counter) = counters.get_mut(&addr) {
+                *counter -= 1;
+                if *counter == 0 {
+                    counters.remove(&addr);
+                }
+            }
+        }
+    }
+}
+
+impl<T: HasPeerAddr> HasPeerAddr for (PeerToken, T) {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.1.get_peer_addr()
+    }
+}
+
+impl<I, S> Stream for LimitPerPeer<S>
+    where S: Stream<Item=I>,
+          I: HasPeerAddr,
+{
+    type Item = (PeerToken, I);
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        loop {
+            match self.as_mut().project().stream.poll_next(cx) {
+                Poll::Ready(Some(conn)) => {
+                    let token = conn.get_peer_addr().ok()
+                        .and_then(|peer| self.acquire(peer));
+                    match token {
+                        Some(token) => return Poll::Ready(Some((token, conn))),
+                        // connection is closed on drop
+                        None => continue,
+                    }
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
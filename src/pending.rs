@@ -0,0 +1,146 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Wake, Waker};
+
+use async_std::future::Future;
+use async_std::task::{Poll, Context};
+
+
+type Task<T> = Pin<Box<dyn Future<Output=T> + Send>>;
+
+/// A set of futures polled only when woken
+///
+/// Polling all the futures on every wakeup is quadratic when there are
+/// many of them (i.e. thousands of slow handshakes), so each future gets
+/// its own waker which queues the future and wakes the parent task.
+pub(crate) struct Pending<T> {
+    slots: Vec<Option<Slot<T>>>,
+    free: Vec<usize>,
+    len: usize,
+    queue: Arc<Queue>,
+}
+
+struct Slot<T> {
+    task: Task<T>,
+    waker: Arc<TaskWaker>,
+}
+
+#[derive(Default)]
+struct Queue {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    ready: VecDeque<usize>,
+    parent: Option<Waker>,
+}
+
+struct TaskWaker {
+    index: usize,
+    queued: AtomicBool,
+    queue: Arc<Queue>,
+}
+
+impl Queue {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("pending queue lock is not poisoned")
+    }
+}
+
+impl Wake for TaskWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref()
+    }
+    fn wake_by_ref(self: &Arc<Self>) {
+        if self.queued.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let parent = {
+            let mut state = self.queue.lock();
+            state.ready.push_back(self.index);
+            state.parent.clone()
+        };
+        if let Some(waker) = parent {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Pending<T> {
+    pub(crate) fn new() -> Pending<T> {
+        Pending {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            queue: Arc::new(Queue::default()),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn push(&mut self, task: Task<T>) {
+        let index = self.free.pop().unwrap_or(self.slots.len());
+        let waker = Arc::new(TaskWaker {
+            index,
+            queued: AtomicBool::new(false),
+            queue: self.queue.clone(),
+        });
+        // new task must be polled at least once
+        waker.wake_by_ref();
+        let slot = Some(Slot { task, waker });
+        if index == self.slots.len() {
+            self.slots.push(slot);
+        } else {
+            self.slots[index] = slot;
+        }
+        self.len += 1;
+    }
+
+    /// Polls woken futures until one of them is complete
+    ///
+    /// Returns `Ready(None)` if there are no futures left.
+    pub(crate) fn poll_next(&mut self, cx: &mut Context) -> Poll<Option<T>> {
+        let mut ready = {
+            let mut state = self.queue.lock();
+            if !state.parent.iter().any(|w| w.will_wake(cx.waker())) {
+                state.parent = Some(cx.waker().clone());
+            }
+            mem::take(&mut state.ready)
+        };
+        let mut result = None;
+        while let Some(index) = ready.pop_front() {
+            // the index may be stale if the task is already complete
+            let slot = match self.slots.get_mut(index) {
+                Some(Some(slot)) => slot,
+                _ => continue,
+            };
+            slot.waker.queued.store(false, Ordering::Release);
+            let waker = Waker::from(slot.waker.clone());
+            let mut task_cx = Context::from_waker(&waker);
+            if let Poll::Ready(value) = slot.task.as_mut().poll(&mut task_cx) {
+                self.slots[index] = None;
+                self.free.push(index);
+                self.len -= 1;
+                result = Some(value);
+                break;
+            }
+        }
+        if !ready.is_empty() {
+            // put the rest back in front of tasks woken in the meantime
+            let mut state = self.queue.lock();
+            ready.append(&mut state.ready);
+            state.ready = ready;
+        }
+        match result {
+            Some(value) => Poll::Ready(Some(value)),
+            None if self.len == 0 => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+}
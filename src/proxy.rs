@@ -0,0 +1,348 @@
+//! PROXY protocol support
+//!
+//! Servers behind a load balancer (i.e. HAProxy, AWS NLB) see the address
+//! of the balancer as a peer address. With the
+//! [PROXY protocol](https://www.haproxy.org/download/2.0/doc/proxy-protocol.txt)
+//! the balancer sends the original client address in a header before any
+//! other data.
+//!
+//! The usual way to decode the header is the
+//! [`ListenExt::proxy_protocol`](../trait.ListenExt.html#method.proxy_protocol)
+//! method. Both text (v1) and binary (v2) versions of the protocol are
+//! supported.
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::str;
+use std::time::Duration;
+
+use async_std::future::timeout;
+use async_std::io::{Read, ReadExt};
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
+use crate::pending::Pending;
+use crate::socket_options::{ConfigureSocket, SocketOptions};
+
+
+const V2_SIGNATURE: &[u8; 12] = b"\r\n\r\n\0\r\nQUIT\n";
+const V1_MAX_LENGTH: usize = 107;
+
+/// Default time allowed for a client to send the PROXY header
+pub const DEFAULT_HEADER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Decoded PROXY protocol header
+///
+/// Addresses are `None` if the balancer doesn't know them (`UNKNOWN` in v1,
+/// `LOCAL` command or non-inet address family in v2). In this case the
+/// peer address of the connection itself should be used, which is what
+/// [`HasPeerAddr`](../trait.HasPeerAddr.html) implementation for
+/// `(ProxyHeader, T)` does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyHeader {
+    version: u8,
+    source: Option<SocketAddr>,
+    destination: Option<SocketAddr>,
+}
+
+pin_project! {
+    /// A stream adapter that decodes PROXY protocol header of each connection
+    ///
+    /// Headers are read concurrently, so a slow client doesn't delay
+    /// connections accepted after it. Connections with an invalid header, or
+    /// which haven't sent the header within the timeout, are dropped.
+    ///
+    /// The number of connections waiting for the header is only bounded by
+    /// the header timeout and the rate of accepted connections, unless
+    /// [`concurrency_limit`](#method.concurrency_limit) is set.
+    ///
+    /// See
+    /// [`ListenExt::proxy_protocol`](../trait.ListenExt.html#method.proxy_protocol)
+    /// for more info.
+    #[project = ProxyProtocolProj]
+    pub struct ProxyProtocol<S, I> {
+        #[pin]
+        stream: S,
+        header_timeout: Duration,
+        pending: Pending<io::Result<(ProxyHeader, I)>>,
+        max_pending: Option<usize>,
+        stream_done: bool,
+    }
+}
+
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+impl ProxyHeader {
+    /// Protocol version the header was sent with (`1` or `2`)
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Address of the original client
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.source
+    }
+
+    /// Address the original client has connected to
+    pub fn destination(&self) -> Option<SocketAddr> {
+        self.destination
+    }
+
+    /// Read PROXY protocol header from the connection
+    ///
+    /// Reads exactly the header bytes, so the rest of the data can be read
+    /// from the connection afterwards.
+    pub async fn read<R: Read + Unpin>(conn: &mut R) -> io::Result<ProxyHeader> {
+        let mut buf = [0u8; 12];
+        conn.read_exact(&mut buf).await?;
+        if &buf == V2_SIGNATURE {
+            return read_v2(conn).await;
+        }
+        if !buf.starts_with(b"PROXY ") {
+            return Err(invalid("no PROXY protocol header"));
+        }
+        let mut line = buf.to_vec();
+        while !line.ends_with(b"\r\n") {
+            let len = line.len();
+            let more = v1_remaining(&line);
+            if len + more > V1_MAX_LENGTH {
+                return Err(invalid("PROXY header is too long"));
+            }
+            line.resize(len + more, 0);
+            conn.read_exact(&mut line[len..]).await?;
+        }
+        parse_v1(&line[..line.len()-2])
+    }
+}
+
+/// Returns the minimum number of bytes left until the end of v1 header
+///
+/// Reading more than that could consume data sent after the header.
+fn v1_remaining(line: &[u8]) -> usize {
+    if line.ends_with(b"\r") {
+        return 1;
+    }
+    let spaces = line.iter().filter(|&&b| b == b' ').count();
+    // anything may follow `UNKNOWN` up to the end of line
+    if spaces < 2 || line.split(|&b| b == b' ').nth(1) == Some(b"UNKNOWN") {
+        return 2;
+    }
+    // each of the addresses and ports left is at least a space and a digit
+    let fields = 5_usize.saturating_sub(spaces);
+    let current = if line.ends_with(b" ") { 1 } else { 0 };
+    2 * fields + current + 2
+}
+
+fn parse_v1(line: &[u8]) -> io::Result<ProxyHeader> {
+    let err = || invalid("invalid PROXY v1 header");
+    let line = str::from_utf8(line).map_err(|_| err())?;
+    let mut parts = line.split(' ');
+    if parts.next() != Some("PROXY") {
+        return Err(err());
+    }
+    let proto = parts.next().ok_or_else(err)?;
+    if proto == "UNKNOWN" {
+        return Ok(ProxyHeader { version: 1, source: None, destination: None });
+    }
+    let src: IpAddr = parts.next().ok_or_else(err)?.parse().map_err(|_| err())?;
+    let dst: IpAddr = parts.next().ok_or_else(err)?.parse().map_err(|_| err())?;
+    let sport: u16 = parts.next().ok_or_else(err)?.parse().map_err(|_| err())?;
+    let dport: u16 = parts.next().ok_or_else(err)?.parse().map_err(|_| err())?;
+    if parts.next().is_some() {
+        return Err(err());
+    }
+    match (proto, src, dst) {
+        ("TCP4", IpAddr::V4(_), IpAddr::V4(_)) => {}
+        ("TCP6", IpAddr::V6(_), IpAddr::V6(_)) => {}
+        _ => return Err(err()),
+    }
+    Ok(ProxyHeader {
+        version: 1,
+        source: Some(SocketAddr::new(src, sport)),
+        destination: Some(SocketAddr::new(dst, dport)),
+    })
+}
+
+async fn read_v2<R: Read + Unpin>(conn: &mut R) -> io::Result<ProxyHeader> {
+    let mut head = [0u8; 4];
+    conn.read_exact(&mut head).await?;
+    if head[0] >> 4 != 2 {
+        return Err(invalid("unsupported PROXY protocol version"));
+    }
+    let command = head[0] & 0x0F;
+    let family = head[1];
+    let len = u16::from_be_bytes([head[2], head[3]]) as usize;
+    let mut data = vec![0u8; len];
+    conn.read_exact(&mut data).await?;
+    let header = ProxyHeader { version: 2, source: None, destination: None };
+    match command {
+        // LOCAL: connection from the balancer itself (i.e. health check)
+        0 => return Ok(header),
+        1 => {}
+        _ => return Err(invalid("unsupported PROXY v2 command")),
+    }
+    let port = |at: usize| u16::from_be_bytes([data[at], data[at+1]]);
+    match family >> 4 {
+        // AF_INET
+        1 if len >= 12 => {
+            let mut src = [0u8; 4];
+            let mut dst = [0u8; 4];
+            src.copy_from_slice(&data[0..4]);
+            dst.copy_from_slice(&data[4..8]);
+            Ok(ProxyHeader {
+                source: Some((Ipv4Addr::from(src), port(8)).into()),
+                destination: Some((Ipv4Addr::from(dst), port(10)).into()),
+                .. header
+            })
+        }
+        // AF_INET6
+        2 if len >= 36 => {
+            let mut src = [0u8; 16];
+            let mut dst = [0u8; 16];
+            src.copy_from_slice(&data[0..16]);
+            dst.copy_from_slice(&data[16..32]);
+            Ok(ProxyHeader {
+                source: Some((Ipv6Addr::from(src), port(32)).into()),
+                destination: Some((Ipv6Addr::from(dst), port(34)).into()),
+                .. header
+            })
+        }
+        1 | 2 => Err(invalid("PROXY v2 address block is too short")),
+        // AF_UNSPEC and AF_UNIX
+        _ => Ok(header),
+    }
+}
+
+impl<T: HasPeerAddr> HasPeerAddr for (ProxyHeader, T) {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        match self.0.source {
+            Some(addr) => Ok(PeerAddr::Tcp(addr)),
+            None => self.1.get_peer_addr(),
+        }
+    }
+}
+
+impl<T: ConfigureSocket> ConfigureSocket for (ProxyHeader, T) {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        self.1.configure(options)
+    }
+}
+
+impl<S: fmt::Debug, I> fmt::Debug for ProxyProtocol<S, I> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProxyProtocol")
+            .field("stream", &self.stream)
+            .field("header_timeout", &self.header_timeout)
+            .field("pending", &self.pending.len())
+            .field("max_pending", &self.max_pending)
+            .finish()
+    }
+}
+
+impl<S, I> ProxyProtocol<S, I> {
+    pub(crate) fn new(stream: S) -> ProxyProtocol<S, I> {
+        ProxyProtocol {
+            stream,
+            header_timeout: DEFAULT_HEADER_TIMEOUT,
+            pending: Pending::new(),
+            max_pending: None,
+            stream_done: false,
+        }
+    }
+
+    /// Set time allowed for a client to send the header
+    ///
+    /// Default is [`DEFAULT_HEADER_TIMEOUT`](constant.DEFAULT_HEADER_TIMEOUT.html).
+    pub fn header_timeout(mut self, value: Duration) -> Self {
+        self.header_timeout = value;
+        self
+    }
+
+    /// Limit the number of connections waiting for the header
+    ///
+    /// When the limit is reached no new connections are pulled from the
+    /// underlying stream (i.e. they wait in the listen backlog) until one
+    /// of the pending headers is read or times out.
+    pub fn concurrency_limit(mut self, limit: usize) -> Self {
+        assert!(limit > 0, "concurrency limit must be positive");
+        self.max_pending = Some(limit);
+        self
+    }
+
+    fn is_full(&self) -> bool {
+        self.max_pending.map(|max| self.pending.len() >= max).unwrap_or(false)
+    }
+
+    /// Returns number of connections which haven't sent the header yet
+    pub fn pending_headers(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    ///
+    /// Connections which haven't sent the header yet are dropped.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S> Stream for ProxyProtocol<S, I>
+    where S: Stream<Item=I>,
+          I: Read + Unpin + Send + 'static,
+{
+    type Item = (ProxyHeader, I);
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        loop {
+            while !self.stream_done && !self.is_full() {
+                let this = self.as_mut().project();
+                match this.stream.poll_next(cx) {
+                    Poll::Ready(Some(mut conn)) => {
+                        let limit = *this.header_timeout;
+                        this.pending.push(Box::pin(async move {
+                            let read = ProxyHeader::read(&mut conn);
+                            let header = match timeout(limit, read).await {
+                                Ok(res) => res?,
+                                Err(_) => {
+                                    return Err(io::ErrorKind::TimedOut.into());
+                                }
+                            };
+                            Ok((header, conn))
+                        }));
+                    }
+                    Poll::Ready(None) => *this.stream_done = true,
+                    Poll::Pending => break,
+                }
+            }
+            let this = self.as_mut().project();
+            match this.pending.poll_next(cx) {
+                Poll::Ready(Some(Ok(item))) => return Poll::Ready(Some(item)),
+                // connection is closed on drop, and there might be a free
+                // slot for the next one now
+                Poll::Ready(Some(Err(_))) => continue,
+                Poll::Ready(None) if *this.stream_done => {
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
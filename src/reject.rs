@@ -0,0 +1,122 @@
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_std::future::timeout;
+use async_std::io::{Write, WriteExt};
+use async_std::stream::Stream;
+use async_std::task::{self, Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::backpressure::{Sender, Token};
+
+
+// Default time allowed for writing the rejection response
+const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+pin_project! {
+    /// A stream adapter that rejects connections over the limit
+    ///
+    /// See
+    /// [`ListenExt::reject_over_limit`](../trait.ListenExt.html#method.reject_over_limit)
+    /// for more info.
+    #[project = RejectOverLimitProj]
+    pub struct RejectOverLimit<S> {
+        #[pin]
+        stream: S,
+        sender: Sender,
+        response: Arc<[u8]>,
+        write_timeout: Duration,
+        rejected: u64,
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for RejectOverLimit<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RejectOverLimit")
+            .field("stream", &self.stream)
+            .field("limit", &self.sender.get_limit())
+            .field("response_len", &self.response.len())
+            .field("rejected", &self.rejected)
+            .finish()
+    }
+}
+
+impl<S> RejectOverLimit<S> {
+    pub(crate) fn new(stream: S, sender: &Sender, response: Vec<u8>)
+        -> RejectOverLimit<S>
+    {
+        RejectOverLimit {
+            stream,
+            sender: sender.clone(),
+            response: response.into(),
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            rejected: 0,
+        }
+    }
+
+    /// Set time allowed for writing the response to a rejected connection
+    ///
+    /// Connection is closed after the timeout even if the response isn't
+    /// fully written. Default is 1 second.
+    pub fn write_timeout(mut self, value: Duration) -> Self {
+        self.write_timeout = value;
+        self
+    }
+
+    /// Returns number of connections rejected so far
+    pub fn rejected(&self) -> u64 {
+        self.rejected
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S> Stream for RejectOverLimit<S>
+    where S: Stream<Item=I>,
+          I: Write + Unpin + Send + 'static,
+{
+    type Item = (Token, I);
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let mut this = self.project();
+        loop {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(mut conn)) => {
+                    if let Some(token) = this.sender.try_token() {
+                        return Poll::Ready(Some((token, conn)));
+                    }
+                    *this.rejected += 1;
+                    let response = this.response.clone();
+                    let write_timeout = *this.write_timeout;
+                    task::spawn(async move {
+                        // connection is closed on drop
+                        timeout(write_timeout, async {
+                            conn.write_all(&response).await?;
+                            conn.flush().await
+                        }).await.ok();
+                    });
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
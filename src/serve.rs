@@ -0,0 +1,208 @@
+use std::any::Any;
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_std::future::Future;
+use async_std::stream::Stream;
+use async_std::task::{self, Poll, Context, Waker};
+use pin_project_lite::pin_project;
+
+
+type ErrorHook<E> = Arc<dyn Fn(&E) + Send + Sync + 'static>;
+type PanicHook = Arc<dyn Fn(&str) + Send + Sync + 'static>;
+
+struct Tracker {
+    active: AtomicUsize,
+    waker: Mutex<Option<Waker>>,
+}
+
+struct TaskGuard {
+    tracker: Arc<Tracker>,
+}
+
+struct CatchUnwind<F> {
+    future: Option<Pin<Box<F>>>,
+}
+
+pin_project! {
+    /// A future that accepts connections and spawns a task for each
+    ///
+    /// See [`ListenExt::serve`](../trait.ListenExt.html#method.serve)
+    /// for more info.
+    #[project = ServeProj]
+    pub struct Serve<S, F, E> {
+        #[pin]
+        stream: S,
+        handler: F,
+        on_error: ErrorHook<E>,
+        on_panic: PanicHook,
+        tracker: Arc<Tracker>,
+        stream_done: bool,
+    }
+}
+
+impl Tracker {
+    fn lock_waker(&self) -> std::sync::MutexGuard<'_, Option<Waker>> {
+        self.waker.lock().expect("serve lock is not poisoned")
+    }
+}
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        let old = self.tracker.active.fetch_sub(1, Ordering::SeqCst);
+        if old == 1 {
+            if let Some(waker) = self.tracker.lock_waker().take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let future = self.future.as_mut().expect("future polled after ready");
+        match catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(value)) => {
+                self.future = None;
+                Poll::Ready(Ok(value))
+            }
+            Err(panic) => {
+                // drop connection (and its tokens) before reporting
+                self.future = None;
+                Poll::Ready(Err(panic))
+            }
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> &str {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        s
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s
+    } else {
+        "<unknown panic>"
+    }
+}
+
+impl<S: fmt::Debug, F, E> fmt::Debug for Serve<S, F, E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Serve")
+            .field("stream", &self.stream)
+            .field("active", &self.active())
+            .field("stream_done", &self.stream_done)
+            .finish()
+    }
+}
+
+impl<S, F, E: fmt::Display> Serve<S, F, E> {
+    pub(crate) fn new(stream: S, handler: F) -> Serve<S, F, E> {
+        Serve {
+            stream,
+            handler,
+            on_error: Arc::new(|e: &E| eprintln!("Connection error: {}", e)),
+            on_panic: Arc::new(|msg: &str| {
+                eprintln!("Connection handler panicked: {}", msg)
+            }),
+            tracker: Arc::new(Tracker {
+                active: AtomicUsize::new(0),
+                waker: Mutex::new(None),
+            }),
+            stream_done: false,
+        }
+    }
+}
+
+impl<S, F, E> Serve<S, F, E> {
+    /// Set a function to call when connection handler returns an error
+    ///
+    /// By default errors are printed to stderr.
+    pub fn on_error<H>(mut self, hook: H) -> Self
+        where H: Fn(&E) + Send + Sync + 'static,
+    {
+        self.on_error = Arc::new(hook);
+        self
+    }
+
+    /// Set a function to call when connection handler panics
+    ///
+    /// Panics in connection handlers are caught, so that a single bad
+    /// connection doesn't tear down the task silently: the connection (and
+    /// backpressure token held by it) is dropped and the hook is called with
+    /// the panic message. By default message is printed to stderr.
+    ///
+    /// Note: the panic hook of the standard library still runs when
+    /// panic occurs.
+    pub fn on_panic<H>(mut self, hook: H) -> Self
+        where H: Fn(&str) + Send + Sync + 'static,
+    {
+        self.on_panic = Arc::new(hook);
+        self
+    }
+
+    /// Returns number of connection tasks currently running
+    pub fn active(&self) -> usize {
+        self.tracker.active.load(Ordering::SeqCst)
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
+
+impl<I, S, F, Fut, E> Future for Serve<S, F, E>
+    where S: Stream<Item=I>,
+          F: FnMut(I) -> Fut,
+          Fut: Future<Output=Result<(), E>> + Send + 'static,
+          E: Send + 'static,
+{
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let mut this = self.as_mut().project();
+        while !*this.stream_done {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(conn)) => {
+                    let fut = CatchUnwind {
+                        future: Some(Box::pin((this.handler)(conn))),
+                    };
+                    let on_error = this.on_error.clone();
+                    let on_panic = this.on_panic.clone();
+                    this.tracker.active.fetch_add(1, Ordering::SeqCst);
+                    let guard = TaskGuard { tracker: this.tracker.clone() };
+                    task::spawn(async move {
+                        match fut.await {
+                            Ok(Ok(())) => {}
+                            Ok(Err(e)) => on_error(&e),
+                            Err(panic) => on_panic(panic_message(&*panic)),
+                        }
+                        drop(guard);
+                    });
+                }
+                Poll::Ready(None) => *this.stream_done = true,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        if self.active() == 0 {
+            return Poll::Ready(());
+        }
+        *self.tracker.lock_waker() = Some(cx.waker().clone());
+        // recheck in case the last task finished before waker was stored
+        if self.active() == 0 {
+            return Poll::Ready(());
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,189 @@
+//! Distributing connections between workers
+//!
+//! The [`ListenExt::shard`](../trait.ListenExt.html#method.shard) method
+//! splits a stream of connections into a number of bounded channels, one
+//! per worker. Connection is sent to a worker chosen by a
+//! [`ShardStrategy`](trait.ShardStrategy.html):
+//! * [`RoundRobin`](struct.RoundRobin.html)
+//! * [`PeerHash`](struct.PeerHash.html)
+//! * or a closure `FnMut(&I, usize) -> usize`
+//!
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::net::IpAddr;
+use std::pin::Pin;
+
+use async_std::channel::{bounded, Sender, SendError};
+use async_std::future::Future;
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
+use crate::filter::normalize;
+
+pub use async_std::channel::Receiver;
+
+type Sending<I> = Pin<Box<dyn Future<Output=Result<(), SendError<I>>>
+                         + Send>>;
+
+/// A strategy for choosing a shard for a connection
+///
+/// The trait is implemented for closures `FnMut(&I, usize) -> usize`
+/// which receive the connection and number of shards.
+pub trait ShardStrategy<I> {
+    /// Returns index of the shard for the connection
+    ///
+    /// The value is taken modulo number of shards.
+    fn select(&mut self, conn: &I, shards: usize) -> usize;
+}
+
+/// Send connections to each shard in turn
+#[derive(Debug, Clone, Default)]
+pub struct RoundRobin {
+    next: usize,
+}
+
+/// Send all connections from the same peer IP to the same shard
+///
+/// Connections with unknown peer address and Unix socket connections are
+/// sent to the first shard.
+#[derive(Debug, Clone, Default)]
+pub struct PeerHash {
+    _private: (),
+}
+
+pin_project! {
+    /// A future that distributes connections between shards
+    ///
+    /// Resolves when the stream ends, which in turn closes all the channels.
+    ///
+    /// See [`ListenExt::shard`](../trait.ListenExt.html#method.shard)
+    /// for more info.
+    #[project = ShardProj]
+    pub struct Shard<S, I, P> {
+        #[pin]
+        stream: S,
+        strategy: P,
+        senders: Vec<Sender<I>>,
+        sending: Option<Sending<I>>,
+    }
+}
+
+impl RoundRobin {
+    /// Create a round-robin strategy starting from the first shard
+    pub fn new() -> RoundRobin {
+        RoundRobin::default()
+    }
+}
+
+impl PeerHash {
+    /// Create a strategy sharding by peer IP address
+    pub fn new() -> PeerHash {
+        PeerHash::default()
+    }
+}
+
+impl<I> ShardStrategy<I> for RoundRobin {
+    fn select(&mut self, _conn: &I, shards: usize) -> usize {
+        let idx = self.next % shards;
+        self.next = idx + 1;
+        idx
+    }
+}
+
+impl<I: HasPeerAddr> ShardStrategy<I> for PeerHash {
+    fn select(&mut self, conn: &I, shards: usize) -> usize {
+        let ip: IpAddr = match conn.get_peer_addr() {
+            Ok(PeerAddr::Tcp(addr)) => normalize(addr.ip()),
+            _ => return 0,
+        };
+        let mut hasher = DefaultHasher::new();
+        ip.hash(&mut hasher);
+        (hasher.finish() % shards as u64) as usize
+    }
+}
+
+impl<I, F: FnMut(&I, usize) -> usize> ShardStrategy<I> for F {
+    fn select(&mut self, conn: &I, shards: usize) -> usize {
+        (self)(conn, shards)
+    }
+}
+
+impl<S: fmt::Debug, I, P: fmt::Debug> fmt::Debug for Shard<S, I, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Shard")
+            .field("stream", &self.stream)
+            .field("strategy", &self.strategy)
+            .field("shards", &self.senders.len())
+            .finish()
+    }
+}
+
+impl<S, I, P> Shard<S, I, P> {
+    pub(crate) fn new(stream: S, shards: usize, capacity: usize, strategy: P)
+        -> (Shard<S, I, P>, Vec<Receiver<I>>)
+    {
+        assert!(shards > 0, "number of shards must be positive");
+        let (senders, receivers) = (0..shards)
+            .map(|_| bounded(capacity))
+            .unzip();
+        let shard = Shard { stream, strategy, senders, sending: None };
+        (shard, receivers)
+    }
+
+    /// Returns number of connections queued in each shard
+    pub fn queue_lengths(&self) -> Vec<usize> {
+        self.senders.iter().map(|s| s.len()).collect()
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+}
+
+impl<I, S, P> Future for Shard<S, I, P>
+    where S: Stream<Item=I>,
+          I: Send + 'static,
+          P: ShardStrategy<I>,
+{
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<()> {
+        let mut this = self.project();
+        loop {
+            if let Some(sending) = this.sending.as_mut() {
+                match sending.as_mut().poll(cx) {
+                    // if worker is gone, connection is closed on drop
+                    Poll::Ready(_) => *this.sending = None,
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(conn)) => {
+                    let shards = this.senders.len();
+                    let idx = this.strategy.select(&conn, shards) % shards;
+                    let sender = this.senders[idx].clone();
+                    *this.sending = Some(Box::pin(async move {
+                        sender.send(conn).await
+                    }));
+                }
+                Poll::Ready(None) => {
+                    for sender in this.senders.iter() {
+                        sender.close();
+                    }
+                    return Poll::Ready(());
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
@@ -1,41 +1,415 @@
+use std::collections::hash_map::RandomState;
 use std::fmt;
+use std::hash::{BuildHasher, Hasher};
 use std::io;
 use std::pin::Pin;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
 
+use async_std::channel;
 use async_std::future::Future;
 use async_std::stream::Stream;
 use async_std::task::{sleep, Context, Poll};
+use pin_project_lite::pin_project;
 
-use crate::is_transient_error;
+use crate::burst::Burst;
+use crate::error::{Transient, TransientPolicy, default_transient};
+use crate::log::copy_error;
 
-/// A stream adapter that retries on error
+type FatalCallback = Box<dyn FnMut(&io::Error) + Send + 'static>;
+type PauseCallback =
+    Box<dyn FnMut(PauseEvent, &io::Error, Duration) + Send + 'static>;
+
+/// Whether the listener has just paused or resumed accepting connections
+///
+/// Passed to the callback set by
+/// [`HandleErrors::on_pause`](wrapper_types/struct.HandleErrors.html#method.on_pause).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseEvent {
+    /// Error occurred and the stream starts sleeping
+    Started,
+    /// Sleep is over and the stream resumes accepting connections
+    Finished,
+}
+
+/// Counters of errors swallowed by the accept loop
+///
+/// Returned by
+/// [`HandleErrors::error_stats`](wrapper_types/struct.HandleErrors.html#method.error_stats).
+/// This is a shared handle: clones observe the same counters, so it can be
+/// passed to a health endpoint or a metrics task, while the stream itself
+/// is owned by the accept loop.
+#[derive(Debug, Clone, Default)]
+pub struct ErrorStats {
+    inner: Arc<StatsInner>,
+}
+
+#[derive(Debug, Default)]
+struct StatsInner {
+    transient: AtomicU64,
+    non_transient: AtomicU64,
+    last_error: Mutex<Option<io::Error>>,
+}
+
+/// A policy of sleeping after errors in accept loop
+///
+/// Used by [`ListenExt::handle_errors`](trait.ListenExt.html#method.handle_errors).
+/// The sleep time starts at `initial` and is multiplied by `multiplier`
+/// after each consecutive error up to `max`. Sleep time is reset to
+/// `initial` as soon as a connection is accepted successfully.
+///
+/// Plain `Duration` converts into a fixed backoff (no growth and no jitter),
+/// so `handle_errors(Duration::from_millis(500))` sleeps 500ms after each
+/// error.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// use async_listen::Backoff;
+///
+/// let backoff = Backoff::exponential(
+///         Duration::from_millis(10), Duration::from_secs(5))
+///     .multiplier(2.0)
+///     .jitter(0.1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    multiplier: f64,
+    jitter: f64,
+}
+
+/// An action to take on an error in accept loop
+///
+/// Returned by [`ErrorPolicy`](trait.ErrorPolicy.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorAction {
+    /// Skip the error and accept next connection immediately
+    Ignore,
+    /// Sleep for the specified time before accepting next connection
+    Sleep(Duration),
+    /// End the stream
+    Stop,
+}
+
+/// A policy which decides what to do on an error in accept loop
+///
+/// The trait is implemented for closures `FnMut(&io::Error) -> ErrorAction`.
 ///
 /// See
-/// [`ListenExt::sleep_on_error`](../trait.ListenExt.html#method.sleep_on_error)
+/// [`ListenExt::handle_errors_with`](trait.ListenExt.html#method.handle_errors_with)
 /// for more info.
-pub struct HandleErrors<S> {
-    stream: S,
-    sleep_on_warning: Duration,
-    timeout: Option<Pin<Box<dyn Future<Output=()> + 'static + Send>>>,
+pub trait ErrorPolicy {
+    /// Returns the action to take on the error
+    fn on_error(&mut self, err: &io::Error) -> ErrorAction;
+}
+
+pin_project! {
+    /// A stream adapter that retries on error
+    ///
+    /// See
+    /// [`ListenExt::handle_errors`](../trait.ListenExt.html#method.handle_errors)
+    /// for more info.
+    #[project = HandleErrorsProj]
+    pub struct HandleErrors<S> {
+        #[pin]
+        stream: S,
+        backoff: Backoff,
+        failures: u32,
+        failing_since: Option<Instant>,
+        max_failures: Option<u32>,
+        max_failing_time: Option<Duration>,
+        on_fatal: Option<FatalCallback>,
+        on_pause: Option<PauseCallback>,
+        paused_by: Option<(io::Error, Duration)>,
+        errors: Option<channel::Sender<io::Error>>,
+        stats: ErrorStats,
+        transient: Transient,
+        burst: Option<Burst>,
+        stopped: bool,
+        random: RandomState,
+        timeout: Option<Pin<Box<dyn Future<Output=()> + 'static + Send>>>,
+    }
+}
+
+pin_project! {
+    /// A stream adapter that handles errors according to a policy
+    ///
+    /// See
+    /// [`ListenExt::handle_errors_with`](../trait.ListenExt.html#method.handle_errors_with)
+    /// for more info.
+    #[project = HandleErrorsWithProj]
+    pub struct HandleErrorsWith<S, P> {
+        #[pin]
+        stream: S,
+        policy: P,
+        stopped: bool,
+        timeout: Option<Pin<Box<dyn Future<Output=()> + 'static + Send>>>,
+    }
+}
+
+impl<F: FnMut(&io::Error) -> ErrorAction> ErrorPolicy for F {
+    fn on_error(&mut self, err: &io::Error) -> ErrorAction {
+        (self)(err)
+    }
+}
+
+impl Backoff {
+    /// Always sleep for the same amount of time
+    pub fn fixed(duration: Duration) -> Backoff {
+        Backoff {
+            initial: duration,
+            max: duration,
+            multiplier: 1.0,
+            jitter: 0.0,
+        }
+    }
+
+    /// Sleep time grows exponentially from `initial` to `max`
+    ///
+    /// Default multiplier is `2.0` and jitter is `0.1`.
+    pub fn exponential(initial: Duration, max: Duration) -> Backoff {
+        Backoff {
+            initial,
+            max: max.max(initial),
+            multiplier: 2.0,
+            jitter: 0.1,
+        }
+    }
+
+    /// Set the factor by which sleep time is multiplied after each error
+    ///
+    /// # Panics
+    ///
+    /// Panics if `multiplier` is less than `1.0` or NaN.
+    pub fn multiplier(mut self, multiplier: f64) -> Backoff {
+        assert!(multiplier >= 1.0, "multiplier must be at least 1.0");
+        self.multiplier = multiplier;
+        self
+    }
+
+    /// Set random jitter as a fraction of the sleep time
+    ///
+    /// For example, with `0.1` the actual sleep time is randomly chosen
+    /// within ±10% of the computed one (never exceeding `max`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `jitter` is not within `0..=1` (or NaN).
+    pub fn jitter(mut self, jitter: f64) -> Backoff {
+        assert!((0.0..=1.0).contains(&jitter), "jitter must be within 0..1");
+        self.jitter = jitter;
+        self
+    }
+
+    /// Returns the sleep time after `failures` consecutive errors
+    ///
+    /// `random` is a value within `0..1` used to apply jitter.
+    pub fn delay(&self, failures: u32, random: f64) -> Duration {
+        let exp = failures.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let base = self.initial.as_secs_f64() * self.multiplier.powi(exp);
+        let base = base.min(self.max.as_secs_f64());
+        let factor = 1.0 + self.jitter * (2.0 * random - 1.0);
+        let delay = (base * factor).min(self.max.as_secs_f64());
+        // `max` may not be representable exactly as `f64` (e.g.
+        // `Duration::MAX`), so converting back can overflow
+        Duration::try_from_secs_f64(delay.max(0.0))
+            .map_or(self.max, |delay| delay.min(self.max))
+    }
+}
+
+impl From<Duration> for Backoff {
+    fn from(duration: Duration) -> Backoff {
+        Backoff::fixed(duration)
+    }
+}
+
+impl ErrorStats {
+    /// Number of transient errors (i.e. `ECONNRESET`) that were skipped
+    pub fn transient_errors(&self) -> u64 {
+        self.inner.transient.load(Ordering::Relaxed)
+    }
+
+    /// Number of non-transient errors the stream slept or gave up on
+    pub fn non_transient_errors(&self) -> u64 {
+        self.inner.non_transient.load(Ordering::Relaxed)
+    }
+
+    /// Returns a copy of the last non-transient error
+    ///
+    /// Only OS error code, or kind and message, are preserved.
+    pub fn last_error(&self) -> Option<io::Error> {
+        self.inner.last_error.lock().expect("stats lock")
+            .as_ref().map(copy_error)
+    }
+
+    fn transient(&self) {
+        self.inner.transient.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn non_transient(&self, e: &io::Error) {
+        self.inner.non_transient.fetch_add(1, Ordering::Relaxed);
+        *self.inner.last_error.lock().expect("stats lock")
+            = Some(copy_error(e));
+    }
 }
 
 impl<S: fmt::Debug> fmt::Debug for HandleErrors<S> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         f.debug_struct("HandleErrors")
             .field("stream", &self.stream)
-            .field("sleep_on_warning", &self.sleep_on_warning)
+            .field("backoff", &self.backoff)
+            .field("failures", &self.failures)
+            .field("max_failures", &self.max_failures)
+            .field("max_failing_time", &self.max_failing_time)
+            .field("stopped", &self.stopped)
             .finish()
     }
 }
 
-impl<S: Unpin> Unpin for HandleErrors<S> {}
-
 impl<S> HandleErrors<S> {
-    pub(crate) fn new(stream: S, sleep_on_warning: Duration)
-        -> HandleErrors<S>
+    pub(crate) fn new(stream: S, backoff: Backoff) -> HandleErrors<S> {
+        HandleErrors {
+            stream,
+            backoff,
+            failures: 0,
+            failing_since: None,
+            max_failures: None,
+            max_failing_time: None,
+            on_fatal: None,
+            on_pause: None,
+            paused_by: None,
+            errors: None,
+            stats: ErrorStats::default(),
+            transient: default_transient(),
+            burst: None,
+            stopped: false,
+            random: RandomState::new(),
+            timeout: None,
+        }
+    }
+
+    /// Use a custom policy to decide which errors are transient
+    ///
+    /// By default
+    /// [`is_transient_error`](../fn.is_transient_error.html) is used.
+    /// See [`TransientPolicy`](../trait.TransientPolicy.html).
+    pub fn transient_policy<P>(mut self, policy: P) -> Self
+        where P: TransientPolicy + Send + 'static,
     {
-        HandleErrors { stream, sleep_on_warning, timeout: None }
+        self.transient = Box::new(policy);
+        self
+    }
+
+    /// Call the function when transient errors come in a burst
+    ///
+    /// Transient errors (like connection reset) are expected in small
+    /// numbers, but a sudden spike usually means a SYN flood or a broken
+    /// load balancer. The function is called with the last error and the
+    /// number of errors when `threshold` transient errors occur within the
+    /// `window`. It's called once per burst: not again until there is a
+    /// window with fewer errors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` is zero.
+    pub fn on_burst<C>(mut self, threshold: usize, window: Duration,
+                       callback: C)
+        -> Self
+        where C: FnMut(&io::Error, usize) + Send + 'static,
+    {
+        self.burst = Some(Burst::new(threshold, window, callback));
+        self
+    }
+
+    /// End the stream after `failures` consecutive non-transient errors
+    ///
+    /// A listener that errors forever (i.e. socket has been closed behind
+    /// our back) would otherwise spin silently behind sleeps. Counter is
+    /// reset when a connection is accepted successfully.
+    pub fn give_up_after(mut self, failures: u32) -> Self {
+        assert!(failures > 0, "failures must be positive");
+        self.max_failures = Some(failures);
+        self
+    }
+
+    /// End the stream if non-transient errors persist for the `window`
+    ///
+    /// Time is counted from the first error in a row of consecutive
+    /// errors, i.e. any successful accept resets it.
+    pub fn give_up_after_time(mut self, window: Duration) -> Self {
+        self.max_failing_time = Some(window);
+        self
+    }
+
+    /// Call the function with the last error when giving up
+    ///
+    /// See [`give_up_after`](#method.give_up_after) and
+    /// [`give_up_after_time`](#method.give_up_after_time).
+    pub fn on_fatal<F>(mut self, callback: F) -> Self
+        where F: FnMut(&io::Error) + Send + 'static,
+    {
+        self.on_fatal = Some(Box::new(callback));
+        self
+    }
+
+    /// Call the function when the stream starts and stops sleeping
+    ///
+    /// The function receives the error that triggered the pause and
+    /// the chosen sleep duration, both when pause starts and when it's
+    /// finished. This is useful to keep a metric or a log of how long
+    /// the listener has been paused (i.e. due to `EMFILE`), which
+    /// [`log_warnings`](../trait.ListenExt.html#method.log_warnings)
+    /// alone can't provide.
+    pub fn on_pause<F>(mut self, callback: F) -> Self
+        where F: FnMut(PauseEvent, &io::Error, Duration) + Send + 'static,
+    {
+        self.on_pause = Some(Box::new(callback));
+        self
+    }
+
+    /// Send non-transient errors to a separate stream
+    ///
+    /// Returns this adapter and a receiving end that yields every error
+    /// that made the stream sleep (or give up), by value. This allows
+    /// consuming errors in a separate task: aggregating, alerting or
+    /// persisting them, which may not be done in a synchronous callback.
+    ///
+    /// At most `capacity` errors are buffered, the ones that don't fit
+    /// are dropped. The error stream ends when this adapter is dropped or
+    /// gives up. Transient errors are not sent, as usual.
+    pub fn split_errors(mut self, capacity: usize)
+        -> (Self, channel::Receiver<io::Error>)
+    {
+        let (tx, rx) = channel::bounded(capacity);
+        self.errors = Some(tx);
+        (self, rx)
+    }
+
+    /// Returns a handle to the counters of errors swallowed by the stream
+    ///
+    /// The handle may be cloned and read from another task.
+    pub fn error_stats(&self) -> ErrorStats {
+        self.stats.clone()
+    }
+
+    /// Returns true if the stream has given up on errors
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Returns true if the stream is sleeping after an error
+    pub fn is_paused(&self) -> bool {
+        self.timeout.is_some()
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
     }
 
     /// Acquires a mutable reference to the underlying stream that this
@@ -50,38 +424,243 @@ impl<S> HandleErrors<S> {
     }
 }
 
+impl<S> HandleErrors<S> {
+    /// Acquires a pinned mutable reference to the underlying stream that
+    /// this adapter is pulling from.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut S> {
+        self.project().stream
+    }
+}
+
+impl<S> HandleErrorsProj<'_, S> {
+    fn pause_finished(&mut self) {
+        if let Some((err, delay)) = self.paused_by.take() {
+            if let Some(callback) = self.on_pause.as_mut() {
+                callback(PauseEvent::Finished, &err, delay);
+            }
+        }
+    }
+
+    fn is_fatal(&self) -> bool {
+        if let Some(max) = *self.max_failures {
+            if *self.failures >= max {
+                return true;
+            }
+        }
+        if let (Some(max), Some(since)) =
+            (*self.max_failing_time, *self.failing_since)
+        {
+            if since.elapsed() >= max {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        *self.failures = self.failures.saturating_add(1);
+        let mut hasher = self.random.build_hasher();
+        hasher.write_u32(*self.failures);
+        let random = (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64;
+        self.backoff.delay(*self.failures, random)
+    }
+}
+
 impl<I, S> Stream for HandleErrors<S>
-    where S: Stream<Item=Result<I, io::Error>> + Unpin,
+    where S: Stream<Item=Result<I, io::Error>>,
 {
     type Item = I;
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
         -> Poll<Option<Self::Item>>
     {
-        if let Some(ref mut to) = self.timeout {
+        let mut this = self.project();
+        if *this.stopped {
+            return Poll::Ready(None);
+        }
+        if let Some(to) = this.timeout.as_mut() {
             match to.as_mut().poll(cx) {
                 Poll::Ready(_) => {}
                 Poll::Pending => return Poll::Pending,
             }
         }
-        self.timeout = None;
+        if this.timeout.take().is_some() {
+            this.pause_finished();
+        }
         loop {
-            match Pin::new(&mut self.stream).poll_next(cx) {
+            match this.stream.as_mut().poll_next(cx) {
                 Poll::Pending => return Poll::Pending,
-                Poll::Ready(Some(Ok(v))) => return Poll::Ready(Some(v)),
+                Poll::Ready(Some(Ok(v))) => {
+                    *this.failures = 0;
+                    *this.failing_since = None;
+                    return Poll::Ready(Some(v));
+                }
                 Poll::Ready(None) => return Poll::Ready(None),
                 Poll::Ready(Some(Err(ref e)))
-                if is_transient_error(e) => continue,
-                Poll::Ready(Some(Err(_))) => {
-                    let mut timeout = Box::pin(sleep(self.sleep_on_warning));
+                if this.transient.is_transient(e) => {
+                    this.stats.transient();
+                    if let Some(burst) = this.burst.as_mut() {
+                        burst.error(e);
+                    }
+                    continue;
+                }
+                Poll::Ready(Some(Err(e))) => {
+                    this.stats.non_transient(&e);
+                    let delay = this.next_delay();
+                    if this.failing_since.is_none() {
+                        *this.failing_since = Some(Instant::now());
+                    }
+                    if this.is_fatal() {
+                        *this.stopped = true;
+                        if let Some(callback) = this.on_fatal.as_mut() {
+                            callback(&e);
+                        }
+                        if let Some(errors) = this.errors.take() {
+                            errors.try_send(e).ok();
+                        }
+                        return Poll::Ready(None);
+                    }
+                    if let Some(callback) = this.on_pause.as_mut() {
+                        callback(PauseEvent::Started, &e, delay);
+                    }
+                    let e = match this.errors {
+                        Some(ref errors) => {
+                            let copy = copy_error(&e);
+                            errors.try_send(e).ok();
+                            copy
+                        }
+                        None => e,
+                    };
+                    if this.on_pause.is_some() {
+                        *this.paused_by = Some((e, delay));
+                    }
+                    let mut timeout = Box::pin(sleep(delay));
                     match timeout.as_mut().poll(cx) {
                         Poll::Pending => {
-                            self.timeout = Some(timeout);
+                            *this.timeout = Some(timeout);
                             return Poll::Pending;
                         }
-                        Poll::Ready(()) => continue,
+                        Poll::Ready(()) => {
+                            this.pause_finished();
+                            continue;
+                        }
                     }
                 }
             }
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.stopped {
+            return (0, Some(0));
+        }
+        // any number of items may be errors that are skipped
+        let (_, upper) = self.stream.size_hint();
+        (0, upper)
+    }
+}
+
+impl<S: fmt::Debug, P: fmt::Debug> fmt::Debug for HandleErrorsWith<S, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HandleErrorsWith")
+            .field("stream", &self.stream)
+            .field("policy", &self.policy)
+            .field("stopped", &self.stopped)
+            .finish()
+    }
+}
+
+impl<S, P> HandleErrorsWith<S, P> {
+    pub(crate) fn new(stream: S, policy: P) -> HandleErrorsWith<S, P> {
+        HandleErrorsWith { stream, policy, stopped: false, timeout: None }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns a mutable reference to the policy
+    pub fn policy_mut(&mut self) -> &mut P {
+        &mut self.policy
+    }
+
+    /// Returns true if the stream was stopped by the policy
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+}
+
+impl<S, P> HandleErrorsWith<S, P> {
+    /// Acquires a pinned mutable reference to the underlying stream that
+    /// this adapter is pulling from.
+    pub fn get_pin_mut(self: Pin<&mut Self>) -> Pin<&mut S> {
+        self.project().stream
+    }
+}
+
+impl<I, S, P> Stream for HandleErrorsWith<S, P>
+    where S: Stream<Item=Result<I, io::Error>>,
+          P: ErrorPolicy,
+{
+    type Item = I;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let mut this = self.project();
+        if *this.stopped {
+            return Poll::Ready(None);
+        }
+        if let Some(to) = this.timeout.as_mut() {
+            match to.as_mut().poll(cx) {
+                Poll::Ready(_) => {}
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        *this.timeout = None;
+        loop {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Some(Ok(v))) => return Poll::Ready(Some(v)),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Ready(Some(Err(e))) => match this.policy.on_error(&e) {
+                    ErrorAction::Ignore => continue,
+                    ErrorAction::Stop => {
+                        *this.stopped = true;
+                        return Poll::Ready(None);
+                    }
+                    ErrorAction::Sleep(delay) => {
+                        let mut timeout = Box::pin(sleep(delay));
+                        match timeout.as_mut().poll(cx) {
+                            Poll::Pending => {
+                                *this.timeout = Some(timeout);
+                                return Poll::Pending;
+                            }
+                            Poll::Ready(()) => continue,
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.stopped {
+            return (0, Some(0));
+        }
+        // any number of items may be errors that are skipped
+        let (_, upper) = self.stream.size_hint();
+        (0, upper)
+    }
 }
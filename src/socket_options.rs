@@ -0,0 +1,290 @@
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::time::Duration;
+
+use async_std::net::TcpStream;
+#[cfg(unix)] use async_std::os::unix::net::UnixStream;
+#[cfg(unix)] use async_std::os::unix::net::SocketAddr as UnixSocketAddr;
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+#[cfg(unix)] use rustix::net::sockopt;
+
+use crate::backpressure::Token;
+use crate::byte_stream::ByteStream;
+use crate::conn_info::ConnInfo;
+use crate::peer_limit::PeerToken;
+
+type ErrorCallback = Box<dyn FnMut(&io::Error) + Send + 'static>;
+
+
+/// Socket options applied to each accepted connection
+///
+/// All options are left intact unless set explicitly. Options are only
+/// applied to TCP sockets, for Unix sockets they are no-op.
+///
+/// Keepalive and buffer size options are only supported on Unix platforms,
+/// on other platforms they are ignored.
+///
+/// See
+/// [`ListenExt::configure_sockets`](trait.ListenExt.html#method.configure_sockets)
+/// for more info.
+///
+/// # Example
+///
+/// ```
+/// # use std::time::Duration;
+/// use async_listen::SocketOptions;
+///
+/// let mut opts = SocketOptions::new();
+/// opts.nodelay(true)
+///     .keepalive(Duration::from_secs(60))
+///     .send_buffer_size(256 << 10);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SocketOptions {
+    nodelay: Option<bool>,
+    ttl: Option<u32>,
+    keepalive: Option<Option<Duration>>,
+    recv_buffer_size: Option<usize>,
+    send_buffer_size: Option<usize>,
+}
+
+/// A connection whose socket options can be configured
+///
+/// This is implemented for async-std sockets,
+/// [`ByteStream`](struct.ByteStream.html), and for tuples yielded by
+/// other combinators of this crate.
+pub trait ConfigureSocket {
+    /// Apply socket options to the underlying socket
+    fn configure(&self, options: &SocketOptions) -> io::Result<()>;
+}
+
+pin_project! {
+    /// A stream adapter that applies socket options to each connection
+    ///
+    /// See
+    /// [`ListenExt::configure_sockets`](../trait.ListenExt.html#method.configure_sockets)
+    /// for more info.
+    #[project = ConfigureSocketsProj]
+    pub struct ConfigureSockets<S> {
+        #[pin]
+        stream: S,
+        options: SocketOptions,
+        on_error: Option<ErrorCallback>,
+    }
+}
+
+impl SocketOptions {
+    /// Create an empty set of options (nothing is changed)
+    pub fn new() -> SocketOptions {
+        SocketOptions::default()
+    }
+
+    /// Set `TCP_NODELAY` option
+    ///
+    /// See [`ByteStream::set_nodelay`](struct.ByteStream.html#method.set_nodelay).
+    pub fn nodelay(&mut self, value: bool) -> &mut SocketOptions {
+        self.nodelay = Some(value);
+        self
+    }
+
+    /// Set `IP_TTL` option
+    pub fn ttl(&mut self, value: u32) -> &mut SocketOptions {
+        self.ttl = Some(value);
+        self
+    }
+
+    /// Enable `SO_KEEPALIVE` with the specified idle time before probes
+    pub fn keepalive(&mut self, idle: Duration) -> &mut SocketOptions {
+        self.keepalive = Some(Some(idle));
+        self
+    }
+
+    /// Disable `SO_KEEPALIVE`
+    pub fn no_keepalive(&mut self) -> &mut SocketOptions {
+        self.keepalive = Some(None);
+        self
+    }
+
+    /// Set `SO_RCVBUF` option
+    pub fn recv_buffer_size(&mut self, size: usize) -> &mut SocketOptions {
+        self.recv_buffer_size = Some(size);
+        self
+    }
+
+    /// Set `SO_SNDBUF` option
+    pub fn send_buffer_size(&mut self, size: usize) -> &mut SocketOptions {
+        self.send_buffer_size = Some(size);
+        self
+    }
+
+    pub(crate) fn apply_tcp(&self, stream: &TcpStream) -> io::Result<()> {
+        if let Some(nodelay) = self.nodelay {
+            stream.set_nodelay(nodelay)?;
+        }
+        if let Some(ttl) = self.ttl {
+            stream.set_ttl(ttl)?;
+        }
+        #[cfg(unix)] {
+            match self.keepalive {
+                Some(Some(_idle)) => {
+                    sockopt::set_socket_keepalive(stream, true)?;
+                    #[cfg(not(any(target_os="haiku", target_os="nto",
+                                  target_os="openbsd")))]
+                    sockopt::set_tcp_keepidle(stream, _idle)?;
+                }
+                Some(None) => sockopt::set_socket_keepalive(stream, false)?,
+                None => {}
+            }
+            if let Some(size) = self.recv_buffer_size {
+                sockopt::set_socket_recv_buffer_size(stream, size)?;
+            }
+            if let Some(size) = self.send_buffer_size {
+                sockopt::set_socket_send_buffer_size(stream, size)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn set_tos(stream: &TcpStream, tos: u8) -> io::Result<()> {
+    #[cfg(all(unix, not(any(target_os="solaris", target_os="illumos",
+                            target_os="redox"))))]
+    {
+        if stream.local_addr()?.is_ipv6() {
+            sockopt::set_ipv6_tclass(stream, tos.into())?;
+        } else {
+            sockopt::set_ip_tos(stream, tos)?;
+        }
+    }
+    #[cfg(not(all(unix, not(any(target_os="solaris", target_os="illumos",
+                                target_os="redox")))))]
+    let _ = (stream, tos);
+    Ok(())
+}
+
+impl ConfigureSocket for TcpStream {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        options.apply_tcp(self)
+    }
+}
+
+#[cfg(unix)]
+impl ConfigureSocket for UnixStream {
+    fn configure(&self, _options: &SocketOptions) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl ConfigureSocket for ByteStream {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        match self.as_tcp() {
+            Some(stream) => options.apply_tcp(stream),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ConfigureSocket for (TcpStream, SocketAddr) {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        options.apply_tcp(&self.0)
+    }
+}
+
+#[cfg(unix)]
+impl ConfigureSocket for (UnixStream, UnixSocketAddr) {
+    fn configure(&self, _options: &SocketOptions) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: ConfigureSocket> ConfigureSocket for (Token, T) {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        self.1.configure(options)
+    }
+}
+
+impl<T: ConfigureSocket> ConfigureSocket for (PeerToken, T) {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        self.1.configure(options)
+    }
+}
+
+impl<T: ConfigureSocket> ConfigureSocket for (ConnInfo, T) {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        self.1.configure(options)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for ConfigureSockets<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConfigureSockets")
+            .field("stream", &self.stream)
+            .field("options", &self.options)
+            .finish()
+    }
+}
+
+impl<S> ConfigureSockets<S> {
+    pub(crate) fn new(stream: S, options: SocketOptions)
+        -> ConfigureSockets<S>
+    {
+        ConfigureSockets { stream, options, on_error: None }
+    }
+
+    /// Call the function when options can't be applied to a connection
+    ///
+    /// The connection is yielded anyway. Usually it means that connection
+    /// is already reset by peer, but an option unsupported by the platform
+    /// fails for every connection, so errors should be logged.
+    pub fn on_error<F>(mut self, callback: F) -> Self
+        where F: FnMut(&io::Error) + Send + 'static,
+    {
+        self.on_error = Some(Box::new(callback));
+        self
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S> Stream for ConfigureSockets<S>
+    where S: Stream<Item=I>,
+          I: ConfigureSocket,
+{
+    type Item = I;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        match this.stream.poll_next(cx) {
+            Poll::Ready(Some(conn)) => {
+                if let Err(e) = conn.configure(this.options) {
+                    if let Some(ref mut callback) = this.on_error {
+                        callback(&e);
+                    }
+                }
+                Poll::Ready(Some(conn))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Socket activation by systemd
+//!
+//! Only available with the `systemd` feature on Unix systems. With socket
+//! activation the service manager binds listening sockets (configured in a
+//! `.socket` unit) and passes them to the service process, so the service
+//! doesn't need privileges to bind ports, and connections aren't refused
+//! while the service restarts.
+//!
+//! Sockets are passed as file descriptors starting from `3`, described by
+//! `LISTEN_PID`, `LISTEN_FDS` and `LISTEN_FDNAMES` environment variables.
+//! Functions in this module read these variables and create listeners of
+//! the right kind (TCP or Unix). Descriptors are duplicated, so functions
+//! can be called more than once. Environment variables are left intact.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use std::time::Duration;
+//! # use async_std::prelude::*;
+//! # use async_std::task;
+//! # fn main() -> std::io::Result<()> { task::block_on(async {
+//! use async_listen::{ListenExt, systemd};
+//!
+//! let listeners = systemd::listener_set()?;
+//! if listeners.is_empty() {
+//!     eprintln!("No sockets passed, not started by systemd?");
+//!     std::process::exit(1);
+//! }
+//! let mut incoming = listeners.handle_errors(Duration::from_millis(500));
+//! while let Some(stream) = incoming.next().await {
+//!     // ...
+//! }
+//! # Ok(()) }) }
+//! ```
+use std::env;
+use std::io;
+
+use crate::listen_addr::{Listener, take_fd, listener_from_fd};
+use crate::listener_set::ListenerSet;
+
+const LISTEN_FDS_START: i32 = 3;
+
+
+/// Returns listeners passed by systemd
+///
+/// Returns an empty list if the process isn't socket-activated (variables
+/// aren't set or `LISTEN_PID` belongs to another process). Fails if any
+/// of the passed descriptors isn't a listening TCP or Unix socket.
+pub fn listeners() -> io::Result<Vec<Listener>> {
+    Ok(named_listeners()?.into_iter().map(|(_, l)| l).collect())
+}
+
+/// Returns listeners passed by systemd along with their names
+///
+/// Names are set by `FileDescriptorName=` in the socket unit, and default
+/// to the name of the unit (`LISTEN_FDNAMES`). If names aren't passed at
+/// all, `unknown` is used, the same as in `sd_listen_fds_with_names`.
+///
+/// See [`listeners`](fn.listeners.html) for details.
+pub fn named_listeners() -> io::Result<Vec<(String, Listener)>> {
+    let num = match listen_fds()? {
+        Some(num) => num,
+        None => return Ok(Vec::new()),
+    };
+    let names = env::var("LISTEN_FDNAMES").ok();
+    let mut names = names.as_deref().map(|n| n.split(':'));
+    let mut result = Vec::with_capacity(num as usize);
+    for fd in LISTEN_FDS_START..LISTEN_FDS_START + num {
+        let name = names.as_mut().and_then(|n| n.next())
+            .unwrap_or("unknown");
+        let listener = take_fd(fd).and_then(listener_from_fd)
+            .map_err(|e| match e.raw_os_error() {
+                Some(_) => e,
+                None => io::Error::new(e.kind(),
+                    format!("LISTEN_FDS descriptor {}: {}", fd, e)),
+            })?;
+        result.push((name.to_owned(), listener));
+    }
+    Ok(result)
+}
+
+/// Returns a set of all listeners passed by systemd
+///
+/// This is a shortcut to add all the [`listeners`](fn.listeners.html) to
+/// a [`ListenerSet`](../struct.ListenerSet.html), so they can be used as
+/// a single stream of connections.
+pub fn listener_set() -> io::Result<ListenerSet> {
+    let mut set = ListenerSet::new();
+    for listener in listeners()? {
+        set.add_listener(listener);
+    }
+    Ok(set)
+}
+
+fn listen_fds() -> io::Result<Option<i32>> {
+    fn invalid(msg: &'static str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, msg)
+    }
+    let pid = match env::var("LISTEN_PID") {
+        Ok(pid) => pid,
+        Err(_) => return Ok(None),
+    };
+    let pid: u32 = pid.parse().map_err(|_| invalid("invalid LISTEN_PID"))?;
+    if pid != std::process::id() {
+        return Ok(None);
+    }
+    let num = match env::var("LISTEN_FDS") {
+        Ok(num) => num,
+        Err(_) => return Ok(None),
+    };
+    let num = num.parse().ok().filter(|n| *n >= 0)
+        .ok_or_else(|| invalid("invalid LISTEN_FDS"))?;
+    Ok(Some(num))
+}
@@ -0,0 +1,183 @@
+use std::fmt;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use async_std::future::timeout;
+use async_std::io::{Write, WriteExt};
+use async_std::stream::Stream;
+use async_std::task::{self, Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::HasPeerAddr;
+use crate::filter::PeerPolicy;
+
+
+/// Default interval between writes to a tarpitted connection
+pub const DEFAULT_TARPIT_INTERVAL: Duration = Duration::from_secs(10);
+/// Default time a connection is held in the tarpit
+pub const DEFAULT_TARPIT_DURATION: Duration = Duration::from_secs(600);
+
+pin_project! {
+    /// A stream adapter that holds connections from flagged peers open
+    ///
+    /// See
+    /// [`ListenExt::tarpit`](../trait.ListenExt.html#method.tarpit)
+    /// for more info.
+    #[project = TarpitProj]
+    pub struct Tarpit<S, P> {
+        #[pin]
+        stream: S,
+        policy: P,
+        capacity: usize,
+        interval: Duration,
+        duration: Duration,
+        active: Arc<AtomicUsize>,
+    }
+}
+
+struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<S: fmt::Debug, P: fmt::Debug> fmt::Debug for Tarpit<S, P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tarpit")
+            .field("stream", &self.stream)
+            .field("policy", &self.policy)
+            .field("capacity", &self.capacity)
+            .field("interval", &self.interval)
+            .field("duration", &self.duration)
+            .field("active", &self.active.load(Ordering::SeqCst))
+            .finish()
+    }
+}
+
+impl<S, P> Tarpit<S, P> {
+    pub(crate) fn new(stream: S, policy: P, capacity: usize) -> Tarpit<S, P> {
+        Tarpit {
+            stream,
+            policy,
+            capacity,
+            interval: DEFAULT_TARPIT_INTERVAL,
+            duration: DEFAULT_TARPIT_DURATION,
+            active: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Set interval between the writes to a tarpitted connection
+    ///
+    /// Default is
+    /// [`DEFAULT_TARPIT_INTERVAL`](constant.DEFAULT_TARPIT_INTERVAL.html).
+    pub fn interval(mut self, value: Duration) -> Self {
+        self.interval = value;
+        self
+    }
+
+    /// Set maximum time a connection is held in the tarpit
+    ///
+    /// Default is
+    /// [`DEFAULT_TARPIT_DURATION`](constant.DEFAULT_TARPIT_DURATION.html).
+    pub fn duration(mut self, value: Duration) -> Self {
+        self.duration = value;
+        self
+    }
+
+    /// Returns number of connections currently held in the tarpit
+    pub fn tarpitted(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    ///
+    /// Connections already in the tarpit are held until they time out.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns a mutable reference to the policy (i.e. to update lists)
+    pub fn policy_mut(&mut self) -> &mut P {
+        &mut self.policy
+    }
+
+    fn try_slot(&self) -> Option<Slot> {
+        let mut active = self.active.load(Ordering::SeqCst);
+        loop {
+            if active >= self.capacity {
+                return None;
+            }
+            match self.active.compare_exchange_weak(active, active + 1,
+                Ordering::SeqCst, Ordering::SeqCst)
+            {
+                Ok(_) => return Some(Slot(self.active.clone())),
+                Err(value) => active = value,
+            }
+        }
+    }
+}
+
+async fn hold<I: Write + Unpin>(mut conn: I, interval: Duration) {
+    loop {
+        task::sleep(interval).await;
+        let res = async {
+            conn.write_all(b"\n").await?;
+            conn.flush().await
+        }.await;
+        if res.is_err() {
+            return;
+        }
+    }
+}
+
+impl<I, S, P> Stream for Tarpit<S, P>
+    where S: Stream<Item=I>,
+          I: HasPeerAddr + Write + Unpin + Send + 'static,
+          P: PeerPolicy,
+{
+    type Item = I;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        loop {
+            match self.as_mut().project().stream.poll_next(cx) {
+                Poll::Ready(Some(conn)) => {
+                    match conn.get_peer_addr() {
+                        Ok(addr) if self.policy.allow(&addr) => {
+                            return Poll::Ready(Some(conn));
+                        }
+                        Ok(_) => {}
+                        // connection is closed on drop
+                        Err(_) => continue,
+                    }
+                    // when tarpit is full, connection is closed on drop
+                    if let Some(slot) = self.try_slot() {
+                        let interval = self.interval;
+                        let duration = self.duration;
+                        task::spawn(async move {
+                            timeout(duration, hold(conn, interval)).await.ok();
+                            drop(slot);
+                        });
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use std::io;
+use std::time::Duration;
+
+use async_std::net::TcpStream;
+
+
+/// Kernel statistics of a TCP connection
+///
+/// Returned by
+/// [`ByteStream::tcp_info`](struct.ByteStream.html#method.tcp_info).
+/// This is a subset of Linux `struct tcp_info` that is most useful to find
+/// out why a connection was slow, for example to log it when connection is
+/// closed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpInfo {
+    rtt: Duration,
+    rtt_var: Duration,
+    retransmits: u32,
+    lost: u32,
+    send_cwnd: u32,
+    send_mss: u32,
+}
+
+impl TcpInfo {
+    /// Smoothed round trip time
+    pub fn rtt(&self) -> Duration {
+        self.rtt
+    }
+
+    /// Round trip time variance (mean deviation)
+    pub fn rtt_var(&self) -> Duration {
+        self.rtt_var
+    }
+
+    /// Total number of retransmitted segments during the connection lifetime
+    pub fn retransmits(&self) -> u32 {
+        self.retransmits
+    }
+
+    /// Number of segments currently considered lost
+    pub fn lost(&self) -> u32 {
+        self.lost
+    }
+
+    /// Congestion window, in segments
+    pub fn send_cwnd(&self) -> u32 {
+        self.send_cwnd
+    }
+
+    /// Maximum segment size for sending, in bytes
+    pub fn send_mss(&self) -> u32 {
+        self.send_mss
+    }
+}
+
+#[cfg(not(any(target_os="linux", target_os="android")))]
+pub(crate) fn query(_stream: &TcpStream) -> io::Result<Option<TcpInfo>> {
+    Ok(None)
+}
+
+// `getsockopt(TCP_INFO)` can't be called without unsafe code, so the same
+// `struct tcp_info` is requested using `sock_diag` netlink interface, which
+// only needs plain sockets.
+#[cfg(any(target_os="linux", target_os="android"))]
+pub(crate) fn query(stream: &TcpStream) -> io::Result<Option<TcpInfo>> {
+    use std::net::SocketAddr;
+    use rustix::net::{socket, send, recv, AddressFamily, SocketType};
+    use rustix::net::{SendFlags, RecvFlags, netlink};
+
+    const SOCK_DIAG_BY_FAMILY: u16 = 20;
+    const NLMSG_ERROR: u16 = 2;
+    const NLM_F_REQUEST: u16 = 1;
+    const IPPROTO_TCP: u8 = 6;
+    const INET_DIAG_INFO: u16 = 2;
+    const HEADER: usize = 16;  // struct nlmsghdr
+    const DIAG_MSG: usize = 72;  // struct inet_diag_msg
+    const REQUEST: usize = HEADER + 56;  // struct inet_diag_req_v2
+
+    fn addr_bytes(addr: &SocketAddr) -> [u8; 16] {
+        let mut bytes = [0u8; 16];
+        match addr {
+            SocketAddr::V4(a) => bytes[..4].copy_from_slice(&a.ip().octets()),
+            SocketAddr::V6(a) => bytes.copy_from_slice(&a.ip().octets()),
+        }
+        bytes
+    }
+    fn u32_at(buf: &[u8], offset: usize) -> u32 {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&buf[offset..offset+4]);
+        u32::from_ne_bytes(bytes)
+    }
+    fn u16_at(buf: &[u8], offset: usize) -> u16 {
+        u16::from_ne_bytes([buf[offset], buf[offset+1]])
+    }
+    fn invalid() -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData,
+                       "malformed sock_diag response")
+    }
+
+    let local = stream.local_addr()?;
+    let peer = stream.peer_addr()?;
+    let family = match local {
+        SocketAddr::V4(_) => AddressFamily::INET,
+        SocketAddr::V6(_) => AddressFamily::INET6,
+    };
+
+    let mut req = [0u8; REQUEST];
+    req[0..4].copy_from_slice(&(REQUEST as u32).to_ne_bytes());
+    req[4..6].copy_from_slice(&SOCK_DIAG_BY_FAMILY.to_ne_bytes());
+    req[6..8].copy_from_slice(&NLM_F_REQUEST.to_ne_bytes());
+    req[16] = family.as_raw() as u8;
+    req[17] = IPPROTO_TCP;
+    req[18] = 1 << (INET_DIAG_INFO - 1);
+    req[20..24].copy_from_slice(&u32::MAX.to_ne_bytes());  // all states
+    req[24..26].copy_from_slice(&local.port().to_be_bytes());
+    req[26..28].copy_from_slice(&peer.port().to_be_bytes());
+    req[28..44].copy_from_slice(&addr_bytes(&local));
+    req[44..60].copy_from_slice(&addr_bytes(&peer));
+    req[64..72].copy_from_slice(&[0xFF; 8]);  // INET_DIAG_NOCOOKIE
+
+    let sock = socket(AddressFamily::NETLINK, SocketType::DGRAM,
+                      Some(netlink::SOCK_DIAG))?;
+    send(&sock, &req, SendFlags::empty())?;
+    let mut buf = [0u8; 4096];
+    let (len, _) = recv(&sock, &mut buf[..], RecvFlags::empty())?;
+    let buf = &buf[..len];
+
+    if buf.len() < HEADER {
+        return Err(invalid());
+    }
+    match u16_at(buf, 4) {
+        NLMSG_ERROR if buf.len() >= HEADER + 4 => {
+            let errno = u32_at(buf, HEADER) as i32;
+            return Err(io::Error::from_raw_os_error(-errno));
+        }
+        SOCK_DIAG_BY_FAMILY => {}
+        _ => return Err(invalid()),
+    }
+    let msg_len = (u32_at(buf, 0) as usize).min(buf.len());
+    let mut offset = HEADER + DIAG_MSG;
+    while offset + 4 <= msg_len {
+        let attr_len = u16_at(buf, offset) as usize;
+        if attr_len < 4 || offset + attr_len > msg_len {
+            return Err(invalid());
+        }
+        if u16_at(buf, offset + 2) == INET_DIAG_INFO {
+            let info = &buf[offset+4..offset+attr_len];
+            if info.len() < 104 {
+                return Err(invalid());
+            }
+            return Ok(Some(TcpInfo {
+                rtt: Duration::from_micros(u32_at(info, 68).into()),
+                rtt_var: Duration::from_micros(u32_at(info, 72).into()),
+                retransmits: u32_at(info, 100),
+                lost: u32_at(info, 32),
+                send_cwnd: u32_at(info, 80),
+                send_mss: u32_at(info, 16),
+            }));
+        }
+        offset += (attr_len + 3) & !3;
+    }
+    Ok(None)
+}
@@ -0,0 +1,114 @@
+use std::fmt;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use async_std::future::Future;
+use async_std::stream::Stream;
+use async_std::task::{sleep, Context, Poll};
+use pin_project_lite::pin_project;
+
+pin_project! {
+    /// A stream adapter that limits the rate of accepted connections
+    ///
+    /// See
+    /// [`ListenExt::throttle_accepts`](../trait.ListenExt.html#method.throttle_accepts)
+    /// for more info.
+    #[project = ThrottleAcceptsProj]
+    pub struct ThrottleAccepts<S> {
+        #[pin]
+        stream: S,
+        rate: f64,
+        burst: f64,
+        available: f64,
+        updated: Instant,
+        timeout: Option<Pin<Box<dyn Future<Output=()> + 'static + Send>>>,
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for ThrottleAccepts<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ThrottleAccepts")
+            .field("stream", &self.stream)
+            .field("rate", &self.rate)
+            .field("burst", &self.burst)
+            .field("available", &self.available)
+            .finish()
+    }
+}
+
+impl<S> ThrottleAccepts<S> {
+    pub(crate) fn new(stream: S, rate: f64, burst: u32) -> ThrottleAccepts<S> {
+        assert!(rate > 0.0 && rate.is_finite(),
+            "rate must be positive and finite");
+        let burst = f64::from(burst.max(1));
+        ThrottleAccepts {
+            stream,
+            rate,
+            burst,
+            available: burst,
+            updated: Instant::now(),
+            timeout: None,
+        }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> ThrottleAcceptsProj<'_, S> {
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(*self.updated).as_secs_f64();
+        *self.available = (*self.available + elapsed * *self.rate)
+            .min(*self.burst);
+        *self.updated = now;
+    }
+}
+
+impl<I, S> Stream for ThrottleAccepts<S>
+    where S: Stream<Item=I>,
+{
+    type Item = I;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let mut this = self.project();
+        loop {
+            if let Some(ref mut to) = this.timeout {
+                match to.as_mut().poll(cx) {
+                    Poll::Ready(_) => {}
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            *this.timeout = None;
+            this.refill();
+            if *this.available >= 1.0 {
+                break;
+            }
+            // with a tiny rate the wait may not fit into `Duration`
+            let wait = Duration::try_from_secs_f64(
+                (1.0 - *this.available) / *this.rate)
+                .unwrap_or(Duration::MAX);
+            *this.timeout = Some(Box::pin(sleep(wait)));
+        }
+        let res = this.stream.poll_next(cx);
+        if let Poll::Ready(Some(_)) = res {
+            *this.available -= 1.0;
+        }
+        res
+    }
+}
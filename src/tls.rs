@@ -0,0 +1,175 @@
+//! TLS termination using rustls
+//!
+//! Only available with the `rustls` feature. The usual way to accept
+//! TLS connections is the
+//! [`ListenExt::tls`](../trait.ListenExt.html#method.tls) method.
+//!
+//! Types from [`futures-rustls`](https://docs.rs/futures-rustls) needed
+//! to configure an acceptor are reexported in this module.
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::time::Duration;
+
+use async_std::future::{timeout, Future};
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+use crate::byte_stream::ByteStream;
+use crate::pending::Pending;
+
+pub use futures_rustls::TlsAcceptor;
+pub use futures_rustls::rustls;
+pub use futures_rustls::pki_types;
+
+type Handshake = Pin<Box<dyn Future<Output=io::Result<ByteStream>> + Send>>;
+
+/// Default time allowed for a TLS handshake
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pin_project! {
+    /// A stream adapter that performs TLS handshake on each connection
+    ///
+    /// Handshakes run concurrently, so a slow client doesn't delay
+    /// connections accepted after it. Connections whose handshake fails or
+    /// doesn't complete within the timeout are dropped.
+    ///
+    /// The number of concurrent handshakes is only bounded by the handshake
+    /// timeout and the rate of accepted connections, unless
+    /// [`concurrency_limit`](#method.concurrency_limit) is set.
+    ///
+    /// See [`ListenExt::tls`](../trait.ListenExt.html#method.tls)
+    /// for more info.
+    #[project = TlsAcceptProj]
+    pub struct TlsAccept<S> {
+        #[pin]
+        stream: S,
+        acceptor: TlsAcceptor,
+        handshake_timeout: Duration,
+        handshakes: Pending<io::Result<ByteStream>>,
+        max_handshakes: Option<usize>,
+        stream_done: bool,
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for TlsAccept<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TlsAccept")
+            .field("stream", &self.stream)
+            .field("handshake_timeout", &self.handshake_timeout)
+            .field("handshakes", &self.handshakes.len())
+            .field("max_handshakes", &self.max_handshakes)
+            .finish()
+    }
+}
+
+impl<S> TlsAccept<S> {
+    pub(crate) fn new(stream: S, acceptor: TlsAcceptor) -> TlsAccept<S> {
+        TlsAccept {
+            stream,
+            acceptor,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            handshakes: Pending::new(),
+            max_handshakes: None,
+            stream_done: false,
+        }
+    }
+
+    /// Set time allowed for a TLS handshake
+    ///
+    /// Default is [`DEFAULT_HANDSHAKE_TIMEOUT`](constant.DEFAULT_HANDSHAKE_TIMEOUT.html).
+    pub fn handshake_timeout(mut self, value: Duration) -> Self {
+        self.handshake_timeout = value;
+        self
+    }
+
+    /// Limit the number of handshakes running concurrently
+    ///
+    /// When the limit is reached no new connections are pulled from the
+    /// underlying stream (i.e. they wait in the listen backlog) until one
+    /// of the running handshakes is finished.
+    pub fn concurrency_limit(mut self, limit: usize) -> Self {
+        assert!(limit > 0, "concurrency limit must be positive");
+        self.max_handshakes = Some(limit);
+        self
+    }
+
+    fn is_full(&self) -> bool {
+        self.max_handshakes
+            .map(|max| self.handshakes.len() >= max).unwrap_or(false)
+    }
+
+    /// Returns number of handshakes in progress
+    pub fn pending_handshakes(&self) -> usize {
+        self.handshakes.len()
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    ///
+    /// Connections with handshake in progress are dropped.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> TlsAcceptProj<'_, S> {
+    fn handshake(&self, conn: ByteStream) -> Handshake {
+        let accept = self.acceptor.accept(conn);
+        let handshake_timeout = *self.handshake_timeout;
+        Box::pin(async move {
+            match timeout(handshake_timeout, accept).await {
+                Ok(Ok(stream)) => Ok(ByteStream::new_tls(stream)),
+                Ok(Err(e)) => Err(e),
+                Err(_) => Err(io::ErrorKind::TimedOut.into()),
+            }
+        })
+    }
+}
+
+impl<I, S> Stream for TlsAccept<S>
+    where S: Stream<Item=I>,
+          I: Into<ByteStream>,
+{
+    type Item = ByteStream;
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        loop {
+            while !self.stream_done && !self.is_full() {
+                let mut this = self.as_mut().project();
+                match this.stream.as_mut().poll_next(cx) {
+                    Poll::Ready(Some(conn)) => {
+                        let handshake = this.handshake(conn.into());
+                        this.handshakes.push(handshake);
+                    }
+                    Poll::Ready(None) => *this.stream_done = true,
+                    Poll::Pending => break,
+                }
+            }
+            let this = self.as_mut().project();
+            match this.handshakes.poll_next(cx) {
+                Poll::Ready(Some(Ok(conn))) => return Poll::Ready(Some(conn)),
+                // connection is closed on drop, and there might be a free
+                // slot for the next one now
+                Poll::Ready(Some(Err(_))) => continue,
+                Poll::Ready(None) if *this.stream_done => {
+                    return Poll::Ready(None);
+                }
+                Poll::Ready(None) | Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
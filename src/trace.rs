@@ -0,0 +1,183 @@
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+use tracing::{Span, debug, debug_span, warn};
+
+use crate::byte_stream::{HasPeerAddr, PeerAddr};
+use crate::error::error_hint;
+use crate::error::{Transient, TransientPolicy, default_transient};
+use crate::label::current_label;
+use crate::socket_options::{ConfigureSocket, SocketOptions};
+
+
+pin_project! {
+    /// A stream adapter that emits `tracing` events for accept errors
+    ///
+    /// See
+    /// [`ListenExt::trace_errors`](../trait.ListenExt.html#method.trace_errors)
+    /// for more info.
+    #[project = TraceErrorsProj]
+    pub struct TraceErrors<S> {
+        #[pin]
+        stream: S,
+        transient: Transient,
+    }
+}
+
+pin_project! {
+    /// A stream adapter that creates a `tracing` span for each connection
+    ///
+    /// See
+    /// [`ListenExt::trace_connections`](../trait.ListenExt.html#method.trace_connections)
+    /// for more info.
+    #[project = TraceConnectionsProj]
+    pub struct TraceConnections<S> {
+        #[pin]
+        stream: S,
+        next_id: u64,
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for TraceErrors<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TraceErrors")
+            .field("stream", &self.stream)
+            .finish()
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for TraceConnections<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TraceConnections")
+            .field("stream", &self.stream)
+            .field("next_id", &self.next_id)
+            .finish()
+    }
+}
+
+impl<S> TraceErrors<S> {
+    pub(crate) fn new(stream: S) -> TraceErrors<S> {
+        TraceErrors { stream, transient: default_transient() }
+    }
+
+    /// Use a custom policy to decide which errors are transient
+    ///
+    /// By default
+    /// [`is_transient_error`](../fn.is_transient_error.html) is used.
+    /// See [`TransientPolicy`](../trait.TransientPolicy.html).
+    pub fn transient_policy<P>(mut self, policy: P) -> Self
+        where P: TransientPolicy + Send + 'static,
+    {
+        self.transient = Box::new(policy);
+        self
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> TraceConnections<S> {
+    pub(crate) fn new(stream: S) -> TraceConnections<S> {
+        TraceConnections { stream, next_id: 1 }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S> Stream for TraceErrors<S>
+    where S: Stream<Item=Result<I, io::Error>>,
+{
+    type Item = Result<I, io::Error>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        let res = this.stream.poll_next(cx);
+        match &res {
+            Poll::Ready(Some(Err(e))) if this.transient.is_transient(e) => {
+                debug!(error = %e, listener = current_label().as_deref(),
+                       "transient accept error");
+            }
+            Poll::Ready(Some(Err(e))) => {
+                warn!(error = %e, hint = %error_hint(e),
+                      listener = current_label().as_deref(), "accept error");
+            }
+            _ => {}
+        };
+        res
+    }
+}
+
+impl<I, S> Stream for TraceConnections<S>
+    where S: Stream<Item=I>,
+          I: HasPeerAddr,
+{
+    type Item = (Span, I);
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        match this.stream.poll_next(cx) {
+            Poll::Ready(Some(conn)) => {
+                let id = *this.next_id;
+                *this.next_id += 1;
+                let label = current_label();
+                let listener = label.as_deref();
+                let span = match conn.get_peer_addr() {
+                    Ok(peer) => debug_span!("connection",
+                                            id, peer = %peer, listener),
+                    Err(_) => debug_span!("connection", id, listener),
+                };
+                debug!(parent: &span, "connection accepted");
+                Poll::Ready(Some((span, conn)))
+            }
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T: HasPeerAddr> HasPeerAddr for (Span, T) {
+    fn get_peer_addr(&self) -> io::Result<PeerAddr> {
+        self.1.get_peer_addr()
+    }
+}
+
+impl<T: ConfigureSocket> ConfigureSocket for (Span, T) {
+    fn configure(&self, options: &SocketOptions) -> io::Result<()> {
+        self.1.configure(options)
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+use std::pin::Pin;
+
+use async_std::future::Future;
+use async_std::stream::Stream;
+use async_std::task::{Poll, Context};
+use pin_project_lite::pin_project;
+
+pin_project! {
+    /// A stream adapter that ends the stream when a future completes
+    ///
+    /// See
+    /// [`ListenExt::until`](../trait.ListenExt.html#method.until)
+    /// for more info.
+    #[project = UntilProj]
+    pub struct Until<S, F> {
+        #[pin]
+        stream: S,
+        shutdown: Option<Pin<Box<F>>>,
+    }
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for Until<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Until")
+            .field("stream", &self.stream)
+            .field("finished", &self.shutdown.is_none())
+            .finish()
+    }
+}
+
+impl<S, F> Until<S, F> {
+    pub(crate) fn new(stream: S, shutdown: F) -> Until<S, F> {
+        Until { stream, shutdown: Some(Box::pin(shutdown)) }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns true if the shutdown future has completed
+    pub fn is_finished(&self) -> bool {
+        self.shutdown.is_none()
+    }
+}
+
+impl<I, S, F> Stream for Until<S, F>
+    where S: Stream<Item=I>,
+          F: Future,
+{
+    type Item = I;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        match this.shutdown {
+            Some(ref mut fut) => {
+                if fut.as_mut().poll(cx).is_ready() {
+                    *this.shutdown = None;
+                    return Poll::Ready(None);
+                }
+            }
+            None => return Poll::Ready(None),
+        }
+        this.stream.poll_next(cx)
+    }
+}
+
+pin_project! {
+    /// A stream adapter that ends the stream after a number of connections
+    ///
+    /// See
+    /// [`ListenExt::take_connections`](../trait.ListenExt.html#method.take_connections)
+    /// for more info.
+    #[project = TakeConnectionsProj]
+    pub struct TakeConnections<S> {
+        #[pin]
+        stream: S,
+        remaining: usize,
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for TakeConnections<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TakeConnections")
+            .field("stream", &self.stream)
+            .field("remaining", &self.remaining)
+            .finish()
+    }
+}
+
+impl<S> TakeConnections<S> {
+    pub(crate) fn new(stream: S, limit: usize) -> TakeConnections<S> {
+        TakeConnections { stream, remaining: limit }
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Returns number of connections left before the stream ends
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+}
+
+impl<I, S> Stream for TakeConnections<S>
+    where S: Stream<Item=I>,
+{
+    type Item = I;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        if *this.remaining == 0 {
+            return Poll::Ready(None);
+        }
+        let res = this.stream.poll_next(cx);
+        match res {
+            Poll::Ready(Some(_)) => *this.remaining -= 1,
+            Poll::Ready(None) => *this.remaining = 0,
+            Poll::Pending => {}
+        }
+        res
+    }
+}
@@ -0,0 +1,138 @@
+//! Passing listening sockets to a new process for zero-downtime restart
+//!
+//! Only available on Unix systems. The usual way to upgrade a server
+//! without refusing connections is to `exec()` the new binary keeping the
+//! listening sockets open: connections queued in the listen backlog are
+//! accepted by the new process, and the old one may finish serving
+//! connections it has already accepted (drain) in a child process.
+//!
+//! [`export`](fn.export.html) makes the sockets of a
+//! [`ListenerSet`](../struct.ListenerSet.html) inheritable and returns a
+//! description of them to pass in the [`ENV_VAR`](constant.ENV_VAR.html)
+//! environment variable. The new process reads it using
+//! [`Inherited::from_env`](struct.Inherited.html#method.from_env) and takes
+//! inherited sockets by address, binding only the addresses that weren't
+//! inherited (i.e. added to the configuration).
+//!
+//! Descriptors are duplicated when imported, so the original descriptors
+//! stay open in the new process until it exits.
+//!
+//! # Example
+//!
+//! ```no_run
+//! # use async_std::task;
+//! # fn main() -> std::io::Result<()> { task::block_on(async {
+//! use std::os::unix::process::CommandExt;
+//! use std::process::Command;
+//! use async_listen::{ListenAddr, ListenerSet};
+//! use async_listen::upgrade::{self, Inherited};
+//!
+//! let addrs = vec!["tcp://0.0.0.0:8080".parse::<ListenAddr>()?];
+//! let mut inherited = Inherited::from_env()?;
+//! let mut listeners = ListenerSet::new();
+//! for addr in &addrs {
+//!     match inherited.take(addr) {
+//!         Some(listener) => listeners.add_listener(listener),
+//!         None => listeners.add_listener(addr.bind().await?),
+//!     };
+//! }
+//!
+//! // ... later, on upgrade signal
+//! let err = Command::new(std::env::current_exe()?)
+//!     .env(upgrade::ENV_VAR, upgrade::export(&listeners)?)
+//!     .exec();
+//! eprintln!("Can't upgrade: {}", err);
+//! # Ok(()) }) }
+//! ```
+use std::fmt::Write;
+use std::io;
+
+use crate::listen_addr::{ListenAddr, Listener, take_fd, listener_from_fd};
+use crate::listener_set::ListenerSet;
+
+/// Environment variable used to pass sockets to the new process
+pub const ENV_VAR: &str = "ASYNC_LISTEN_FDS";
+
+
+/// Listening sockets inherited from the previous process
+#[derive(Debug, Default)]
+pub struct Inherited {
+    listeners: Vec<(ListenAddr, Listener)>,
+}
+
+/// Make sockets of the set inheritable and describe them
+///
+/// The returned string should be passed to the new process in
+/// [`ENV_VAR`](constant.ENV_VAR.html). It has the form of
+/// `FD:ADDRESS` entries separated by semicolons, for example
+/// `3:tcp://0.0.0.0:8080;4:unix:/run/app.sock`.
+///
+/// Sockets are inherited by every process spawned after this call, so
+/// call it right before `exec()`. Listeners closed by
+/// [`pause_and_close`](../struct.PauseHandle.html#method.pause_and_close)
+/// are skipped.
+pub fn export(set: &ListenerSet) -> io::Result<String> {
+    let mut result = String::new();
+    for (fd, addr) in set.inheritable_fds()? {
+        if !result.is_empty() {
+            result.push(';');
+        }
+        write!(result, "{}:{}", fd, addr).expect("can write to string");
+    }
+    Ok(result)
+}
+
+impl Inherited {
+    /// Import sockets described in the [`ENV_VAR`](constant.ENV_VAR.html)
+    ///
+    /// Returns an empty set if the variable isn't set, i.e. on the first
+    /// start of the server.
+    pub fn from_env() -> io::Result<Inherited> {
+        match std::env::var(ENV_VAR) {
+            Ok(value) => Inherited::parse(&value),
+            Err(_) => Ok(Inherited::default()),
+        }
+    }
+
+    /// Import sockets described by the string returned from
+    /// [`export`](fn.export.html)
+    pub fn parse(value: &str) -> io::Result<Inherited> {
+        let mut listeners = Vec::new();
+        for item in value.split(';').filter(|s| !s.is_empty()) {
+            let (fd, addr) = item.split_once(':')
+                .and_then(|(fd, addr)| Some((fd.parse().ok()?, addr)))
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData,
+                    format!("invalid inherited socket {:?}", item)))?;
+            let addr = addr.parse()?;
+            listeners.push((addr, listener_from_fd(take_fd(fd)?)?));
+        }
+        Ok(Inherited { listeners })
+    }
+
+    /// Take the inherited listener bound to the address
+    ///
+    /// Returns `None` if there is no such listener, so the address should
+    /// be bound as usual.
+    pub fn take(&mut self, addr: &ListenAddr) -> Option<Listener> {
+        let idx = self.listeners.iter().position(|(a, _)| a == addr)?;
+        Some(self.listeners.remove(idx).1)
+    }
+
+    /// Returns number of listeners not taken yet
+    pub fn len(&self) -> usize {
+        self.listeners.len()
+    }
+
+    /// Returns true if all listeners are taken (or nothing was inherited)
+    pub fn is_empty(&self) -> bool {
+        self.listeners.is_empty()
+    }
+
+    /// Returns all listeners not taken yet with their addresses
+    ///
+    /// Useful to close sockets that are removed from the configuration,
+    /// or to listen on everything that's inherited.
+    pub fn into_listeners(self) -> Vec<(ListenAddr, Listener)> {
+        self.listeners
+    }
+}
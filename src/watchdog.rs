@@ -0,0 +1,116 @@
+use std::fmt;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use async_std::future::Future;
+use async_std::stream::Stream;
+use async_std::task::{sleep, Context, Poll};
+use pin_project_lite::pin_project;
+
+
+pin_project! {
+    /// A stream adapter that reports periods of no incoming connections
+    ///
+    /// See
+    /// [`ListenExt::watchdog`](../trait.ListenExt.html#method.watchdog)
+    /// for more info.
+    #[project = WatchdogProj]
+    pub struct Watchdog<S, F> {
+        #[pin]
+        stream: S,
+        callback: F,
+        timeout: Duration,
+        last_item: Instant,
+        deadline: Instant,
+        timer: Option<Pin<Box<dyn Future<Output=()> + 'static + Send>>>,
+    }
+}
+
+impl<S: fmt::Debug, F> fmt::Debug for Watchdog<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("stream", &self.stream)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl<S, F> Watchdog<S, F> {
+    pub(crate) fn new(stream: S, timeout: Duration, callback: F)
+        -> Watchdog<S, F>
+    {
+        let now = Instant::now();
+        Watchdog {
+            stream,
+            callback,
+            timeout,
+            last_item: now,
+            deadline: now + timeout,
+            timer: None,
+        }
+    }
+
+    /// Returns time elapsed since the last item of the stream
+    ///
+    /// Before the first item, this is the time since the adapter was
+    /// created.
+    pub fn idle_time(&self) -> Duration {
+        self.last_item.elapsed()
+    }
+
+    /// Acquires a reference to the underlying stream that this adapter is
+    /// pulling from.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Acquires a mutable reference to the underlying stream that this
+    /// adapter is pulling from.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consumes this adapter, returning the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<I, S, F> Stream for Watchdog<S, F>
+    where S: Stream<Item=I>,
+          F: FnMut(Duration),
+{
+    type Item = I;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context)
+        -> Poll<Option<Self::Item>>
+    {
+        let this = self.project();
+        match this.stream.poll_next(cx) {
+            Poll::Ready(Some(v)) => {
+                let now = Instant::now();
+                *this.last_item = now;
+                *this.deadline = now + *this.timeout;
+                *this.timer = None;
+                return Poll::Ready(Some(v));
+            }
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => {}
+        }
+        loop {
+            if this.timer.is_none() {
+                let delay = this.deadline
+                    .saturating_duration_since(Instant::now());
+                *this.timer = Some(Box::pin(sleep(delay)));
+            }
+            match this.timer.as_mut().expect("timer").as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(()) => {
+                    *this.timer = None;
+                    let idle = this.last_item.elapsed();
+                    (this.callback)(idle);
+                    *this.deadline = Instant::now() + *this.timeout;
+                }
+            }
+        }
+    }
+}
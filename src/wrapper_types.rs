@@ -1,6 +1,22 @@
 //! This module exports all the public wrapper types that library uses
 //!
 //! Usually we don't need to import these types, but they have to be public.
-pub use crate::log::LogWarnings;
-pub use crate::sleep::HandleErrors;
+pub use crate::log::{LogWarnings, LogWarningsAsync, Sample};
+pub use crate::sleep::{HandleErrors, HandleErrorsWith};
+pub use crate::throttle::ThrottleAccepts;
+pub use crate::conn_info::WithConnInfo;
+pub use crate::socket_options::ConfigureSockets;
+pub use crate::until::{Until, TakeConnections};
+pub use crate::pause::Pausable;
+pub use crate::deadline::WithDeadline;
+pub use crate::idle::WithIdleTimeout;
+pub use crate::bandwidth::WithBandwidthLimit;
+pub use crate::handshake::Handshake;
+pub use crate::label::Labeled;
+pub use crate::latency::AcceptLatency;
+pub use crate::watchdog::Watchdog;
+pub use crate::fault::InjectFaults;
+pub use crate::serve::Serve;
+#[cfg(feature="tracing")]
+pub use crate::trace::{TraceErrors, TraceConnections};
 pub use crate::error::ErrorHint;
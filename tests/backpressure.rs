@@ -4,10 +4,12 @@ use std::time::Duration;
 
 use rand::{Rng, thread_rng};
 
+use async_std::future;
 use async_std::stream::{from_iter, Stream, StreamExt};
 use async_std::task;
 
 use async_listen::{ListenExt, backpressure};
+use async_listen::backpressure::LoadLimiter;
 
 fn collect<S: Stream + Unpin>(mut stream: S) -> Vec<S::Item> {
     task::block_on(async {
@@ -119,3 +121,211 @@ fn test_change_limit() {
     println!("Top capacity {}", top);
     assert!(5 < top && top <= 81);
 }
+
+#[test]
+fn test_shared_token() {
+    let (tx, _rx) = backpressure::new(10);
+    let token = tx.token();
+    let cloned = token.clone();
+    assert_eq!(tx.get_active_tokens(), 2);
+    drop(cloned);
+    assert_eq!(tx.get_active_tokens(), 1);
+    let shared = token.share();
+    let copies = (0..5).map(|_| shared.clone()).collect::<Vec<_>>();
+    assert_eq!(tx.get_active_tokens(), 1);
+    drop(shared);
+    assert_eq!(tx.get_active_tokens(), 1);
+    drop(copies);
+    assert_eq!(tx.get_active_tokens(), 0);
+}
+
+#[test]
+fn test_duration_histogram() {
+    let (tx, _rx) = backpressure::new(10);
+    assert_eq!(tx.get_duration_histogram().quantile(0.5), None);
+    let short = tx.token();
+    let long = tx.token().share();
+    let long2 = long.clone();
+    drop(short);
+    std::thread::sleep(Duration::from_millis(20));
+    drop(long);
+    drop(long2);
+    let hist = tx.get_duration_histogram();
+    assert_eq!(hist.count(), 2);
+    assert!(hist.quantile(0.5).unwrap() <= Duration::from_millis(16));
+    assert!(hist.quantile(1.0).unwrap() >= Duration::from_millis(32));
+    assert_eq!(hist.buckets().map(|(_, n)| n).sum::<u64>(), 2);
+}
+
+#[test]
+fn test_load_limiter() {
+    let (tx, _rx) = backpressure::new(100);
+    let limiter = LoadLimiter::new(tx, 100)
+        .min_limit(10)
+        .thresholds(1.0, 2.0);
+    assert_eq!(limiter.limit_for(0.0), 100);
+    assert_eq!(limiter.limit_for(1.0), 100);
+    assert_eq!(limiter.limit_for(1.5), 55);
+    assert_eq!(limiter.limit_for(2.0), 10);
+    assert_eq!(limiter.limit_for(10.0), 10);
+    assert_eq!(limiter.limit_for(f64::NAN), 100);
+}
+
+#[test]
+fn test_pause_metrics() {
+    let (tx, rx) = backpressure::new(2);
+    let tx1 = tx.clone();
+    let tokens = collect(
+        from_iter(0..4)
+        .apply_backpressure(rx)
+        .map(|index| {
+            let token = tx1.token();
+            if index == 1 {
+                let tx = tx1.clone();
+                task::spawn(async move {
+                    task::sleep(Duration::from_millis(50)).await;
+                    tx.set_limit(10);
+                });
+            }
+            token
+        }));
+    assert_eq!(tokens.len(), 4);
+    assert_eq!(tx.get_pause_count(), 1);
+    assert!(!tx.is_paused());
+    assert!(tx.get_paused_time() >= Duration::from_millis(40));
+}
+
+#[test]
+fn test_drop_paused() {
+    let (tx, rx) = backpressure::new(1);
+    let _token = tx.token();
+    task::block_on(async {
+        let mut stream = from_iter(0..10).apply_backpressure(rx);
+        let next = future::timeout(Duration::from_millis(10), stream.next());
+        assert!(next.await.is_err());
+        assert!(tx.is_paused());
+        drop(stream);
+    });
+    assert!(!tx.is_paused());
+    let paused = tx.get_paused_time();
+    task::block_on(task::sleep(Duration::from_millis(20)));
+    assert_eq!(tx.get_paused_time(), paused);
+}
+
+#[test]
+fn test_shared_sender() {
+    use async_std::io::Cursor;
+    use async_listen::ByteStream;
+
+    let (tx, _rx) = backpressure::new(2);
+    let (other, _) = backpressure::new(10);
+    let tokens = vec![tx.token(), tx.token()];
+    let tasks = (0..2).map(|_| {
+        let other = other.clone();
+        let mut stream = from_iter(0..1)
+            .map(move |_| {
+                ByteStream::new_boxed(other.token(), Cursor::new(Vec::new()))
+            })
+            .backpressure_wrapper_with(&tx);
+        task::spawn(async move { stream.next().await.is_some() })
+    }).collect::<Vec<_>>();
+    task::block_on(async {
+        task::sleep(Duration::from_millis(20)).await;
+        assert!(tx.is_paused());
+        drop(tokens);
+        for task in tasks {
+            let resumed = future::timeout(Duration::from_secs(5), task).await;
+            assert_eq!(resumed, Ok(true));
+        }
+    });
+    assert!(!tx.is_paused());
+}
+
+#[test]
+fn test_set_receiver() {
+    let (tx, rx) = backpressure::new(3);
+    let mut stream = from_iter(0..10).backpressure(1);
+    task::block_on(async {
+        let (first, _) = stream.next().await.unwrap();
+        stream.set_receiver(rx);
+        let mut tokens = Vec::new();
+        for _ in 0..3 {
+            tokens.push(stream.next().await.unwrap());
+        }
+        assert_eq!(tx.get_active_tokens(), 3);
+        drop(first);
+    });
+}
+
+#[test]
+fn test_drain() {
+    let (tx, _rx) = backpressure::new(10);
+    let tokens = (0..5).map(|_| tx.token()).collect::<Vec<_>>();
+    task::block_on(async {
+        assert!(tx.drain_timeout(Duration::from_millis(10)).await.is_err());
+        for token in tokens {
+            task::spawn(async move {
+                task::sleep(random_delay()).await;
+                drop(token);
+            });
+        }
+        tx.drain().await;
+        assert_eq!(tx.get_active_tokens(), 0);
+    });
+}
+
+#[test]
+fn test_backpressure_wrap() {
+    struct Conn {
+        value: u32,
+        _token: backpressure::Token,
+    }
+    impl From<(backpressure::Token, u32)> for Conn {
+        fn from((token, value): (backpressure::Token, u32)) -> Conn {
+            Conn { value, _token: token }
+        }
+    }
+    let (tx, rx) = backpressure::new(10);
+    let conns = collect(from_iter(vec![1u32, 2, 3])
+        .backpressure_wrap::<Conn>(rx));
+    assert_eq!(conns.iter().map(|c| c.value).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert_eq!(tx.get_active_tokens(), 3);
+    drop(conns);
+    assert_eq!(tx.get_active_tokens(), 0);
+}
+
+#[test]
+fn test_reject_over_limit() {
+    use async_std::io::ReadExt;
+    use async_std::net::{TcpListener, TcpStream};
+
+    task::block_on(async {
+        let (tx, _) = backpressure::new(1);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .reject_over_limit(&tx, "busy\n");
+        let _c1 = TcpStream::connect(addr).await.unwrap();
+        let (token, _conn) = incoming.next().await.unwrap();
+        assert_eq!(tx.get_active_tokens(), 1);
+
+        let mut c2 = TcpStream::connect(addr).await.unwrap();
+        let mut c3 = TcpStream::connect(addr).await.unwrap();
+        let pending = async_std::future::timeout(Duration::from_millis(100),
+            incoming.next()).await;
+        assert!(pending.is_err());
+        assert_eq!(incoming.rejected(), 2);
+        let mut buf = String::new();
+        c2.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "busy\n");
+        buf.clear();
+        c3.read_to_string(&mut buf).await.unwrap();
+        assert_eq!(buf, "busy\n");
+
+        drop(token);
+        let _c4 = TcpStream::connect(addr).await.unwrap();
+        let (_token, _conn) = incoming.next().await.unwrap();
+        assert_eq!(incoming.rejected(), 2);
+    });
+}
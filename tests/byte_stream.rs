@@ -0,0 +1,667 @@
+use async_std::io::ReadExt;
+use async_std::net::{TcpListener, TcpStream};
+use async_std::task;
+
+use async_listen::{ByteStream, backpressure};
+
+
+async fn pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let client = TcpStream::connect(listener.local_addr().unwrap())
+        .await.unwrap();
+    let (server, _) = listener.accept().await.unwrap();
+    (client, server)
+}
+
+#[cfg(unix)]
+#[test]
+fn test_raw_fd() {
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+    use async_std::io::Cursor;
+
+    task::block_on(async {
+        let (sender, _) = backpressure::new(1);
+        let (mut client, server) = pair().await;
+        let fd = server.as_raw_fd();
+        let stream = ByteStream::new_tcp(sender.token(), server);
+        assert_eq!(stream.as_raw_fd(), fd);
+        assert_eq!(stream.try_as_raw_fd().unwrap(), fd);
+        assert_eq!(sender.get_active_tokens(), 1);
+
+        let owned = stream.try_into_raw_fd().unwrap();
+        assert_eq!(sender.get_active_tokens(), 0);
+        let mut std_stream = std::net::TcpStream::from(owned);
+        std_stream.write_all(b"hello").unwrap();
+        drop(std_stream);
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hello");
+
+        let boxed = ByteStream::new_boxed(sender.token(),
+                                          Cursor::new(Vec::new()));
+        assert_eq!(boxed.as_raw_fd(), -1);
+        assert!(boxed.try_as_raw_fd().is_err());
+        assert!(boxed.try_into_raw_fd().is_err());
+    });
+}
+
+#[test]
+fn test_from_detached() {
+    use async_std::io::WriteExt;
+
+    async fn greet<S: Into<ByteStream>>(stream: S) {
+        let mut stream = stream.into();
+        stream.write_all(b"hi").await.unwrap();
+    }
+
+    task::block_on(async {
+        let (mut client, server) = pair().await;
+        greet(server).await;
+        let mut buf = [0u8; 2];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hi");
+    });
+}
+
+#[test]
+fn test_boxed() {
+    use std::io;
+    use async_std::io::Cursor;
+
+    task::block_on(async {
+        let (sender, _) = backpressure::new(1);
+        let cursor = Cursor::new(b"hello".to_vec());
+        let mut stream = ByteStream::new_boxed(sender.token(), cursor);
+        assert_eq!(sender.get_active_tokens(), 1);
+        let mut buf = Vec::new();
+        stream.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hello");
+        assert_eq!(stream.peer_addr().unwrap_err().kind(),
+                   io::ErrorKind::Unsupported);
+        drop(stream);
+        assert_eq!(sender.get_active_tokens(), 0);
+    });
+}
+
+#[test]
+fn test_read_timeout() {
+    use std::io;
+    use std::time::{Duration, Instant};
+    use async_std::io::WriteExt;
+
+    task::block_on(async {
+        let (mut client, server) = pair().await;
+        let mut stream = ByteStream::from(server);
+        assert_eq!(stream.read_timeout(), None);
+        stream.set_read_timeout(Some(Duration::from_millis(50)));
+        let mut buf = [0u8; 4];
+        let started = Instant::now();
+        let err = stream.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+
+        // timer is per operation, not per connection
+        let writer = task::spawn(async move {
+            for _ in 0..3 {
+                task::sleep(Duration::from_millis(30)).await;
+                client.write_all(b"x").await.unwrap();
+            }
+            client
+        });
+        for _ in 0..3 {
+            assert_eq!(stream.read(&mut buf).await.unwrap(), 1);
+        }
+        let _client = writer.await;
+        let clone = stream.duplicate();
+        assert_eq!(clone.read_timeout(), Some(Duration::from_millis(50)));
+        let err = (&clone).read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    });
+}
+
+#[test]
+fn test_peer_addr_from_str() {
+    use std::path::PathBuf;
+    use async_listen::PeerAddr;
+
+    let addrs = vec![
+        PeerAddr::Tcp("127.0.0.1:80".parse().unwrap()),
+        PeerAddr::Tcp("[::1]:8080".parse().unwrap()),
+        PeerAddr::Unix(None),
+        PeerAddr::Unix(Some(PathBuf::from("/run/app.sock"))),
+        PeerAddr::UnixAbstract(b"app".to_vec()),
+    ];
+    for addr in addrs {
+        assert_eq!(addr.to_string().parse::<PeerAddr>().unwrap(), addr);
+    }
+    assert!("localhost:80".parse::<PeerAddr>().is_err());
+}
+
+#[cfg(feature="serde")]
+#[test]
+fn test_peer_addr_serde() {
+    use async_listen::PeerAddr;
+
+    let addr = PeerAddr::Tcp("127.0.0.1:80".parse().unwrap());
+    let json = serde_json::to_string(&addr).unwrap();
+    assert_eq!(json, r#""127.0.0.1:80""#);
+    assert_eq!(serde_json::from_str::<PeerAddr>(&json).unwrap(), addr);
+    assert!(serde_json::from_str::<PeerAddr>(r#""nope""#).is_err());
+}
+
+#[test]
+fn test_token() {
+    task::block_on(async {
+        let (sender, _) = backpressure::new(2);
+        let (_, first) = pair().await;
+        let (_, second) = pair().await;
+        let mut old = ByteStream::new_tcp(sender.token(), first);
+        let mut new = ByteStream::from(second);
+        assert!(old.token().is_some());
+        assert!(new.token().is_none());
+        assert!(new.set_token(old.take_token().unwrap()).is_none());
+        drop(old);
+        assert_eq!(sender.get_active_tokens(), 1);
+        let replaced = new.set_token(sender.token().share());
+        assert_eq!(sender.get_active_tokens(), 2);
+        drop(replaced);
+        assert_eq!(sender.get_active_tokens(), 1);
+        drop(new);
+        assert_eq!(sender.get_active_tokens(), 0);
+    });
+}
+
+#[test]
+fn test_into_tcp() {
+    use async_std::io::WriteExt;
+
+    task::block_on(async {
+        let (sender, _) = backpressure::new(1);
+        let (mut client, server) = pair().await;
+        let stream = ByteStream::new_tcp(sender.token(), server);
+        #[cfg(unix)]
+        let stream = stream.into_unix().unwrap_err();
+        let (token, mut tcp) = stream.into_tcp().unwrap();
+        assert_eq!(sender.get_active_tokens(), 1);
+        drop(token);
+        assert_eq!(sender.get_active_tokens(), 0);
+        tcp.write_all(b"ok").await.unwrap();
+        let mut buf = [0u8; 2];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ok");
+    });
+}
+
+#[test]
+fn test_set_peer_addr() {
+    use async_listen::{PeerAddr, HasPeerAddr};
+
+    task::block_on(async {
+        let (client, server) = pair().await;
+        let mut stream = ByteStream::from(server);
+        assert_eq!(stream.peer_addr().unwrap(),
+                   PeerAddr::Tcp(client.local_addr().unwrap()));
+        let real = PeerAddr::Tcp("203.0.113.7:4000".parse().unwrap());
+        stream.set_peer_addr(real.clone());
+        assert_eq!(stream.peer_addr().unwrap(), real);
+        assert_eq!(stream.duplicate().get_peer_addr().unwrap(), real);
+    });
+}
+
+#[test]
+fn test_buffered() {
+    use std::time::Duration;
+    use async_std::io::{BufReadExt, WriteExt};
+    use async_std::stream::StreamExt;
+    use async_listen::{ListenExt, BufferedByteStream};
+
+    task::block_on(async {
+        let (sender, bp) = backpressure::new(1);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .backpressure_wrap::<BufferedByteStream>(bp);
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let mut conn = incoming.next().await.unwrap();
+        assert_eq!(sender.get_active_tokens(), 1);
+
+        client.write_all(b"hello\nworld\n").await.unwrap();
+        let mut line = String::new();
+        conn.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "hello\n");
+        assert_eq!(conn.read_buffer(), b"world\n");
+
+        conn.write_all(b"one ").await.unwrap();
+        conn.write_all(b"two").await.unwrap();
+        assert_eq!(conn.write_buffer(), b"one two");
+        conn.flush().await.unwrap();
+        assert!(conn.write_buffer().is_empty());
+        let mut buf = [0u8; 7];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"one two");
+        drop(conn);
+        assert_eq!(sender.get_active_tokens(), 0);
+    });
+}
+
+#[test]
+fn test_graceful_close() {
+    use std::io;
+    use std::time::Duration;
+    use async_std::io::WriteExt;
+
+    task::block_on(async {
+        let (mut client, server) = pair().await;
+        let mut stream = ByteStream::from(server);
+        client.write_all(b"unread request").await.unwrap();
+        stream.write_all(b"response").await.unwrap();
+        let closing = task::spawn(
+            stream.graceful_close(Duration::from_secs(5)));
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"response");
+        drop(client);
+        closing.await.unwrap();
+
+        // peer doesn't close its side
+        let (_client, server) = pair().await;
+        let err = ByteStream::from(server)
+            .graceful_close(Duration::from_millis(50)).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    });
+}
+
+#[test]
+fn test_id() {
+    task::block_on(async {
+        let (_, first) = pair().await;
+        let (_, second) = pair().await;
+        let first = ByteStream::from(first);
+        let second = ByteStream::from(second);
+        assert!(second.id() > first.id());
+        assert_eq!(first.duplicate().id(), first.id());
+        let (sender, _) = backpressure::new(1);
+        let rewrapped = ByteStream::from((sender.token(), second.duplicate()));
+        assert_eq!(rewrapped.id(), second.id());
+    });
+}
+
+#[test]
+fn test_age() {
+    use std::time::{Duration, Instant};
+
+    task::block_on(async {
+        let before = Instant::now();
+        let (_, server) = pair().await;
+        let stream = ByteStream::from(server);
+        assert!(stream.accepted_at() >= before);
+        task::sleep(Duration::from_millis(20)).await;
+        assert!(stream.age() >= Duration::from_millis(20));
+        assert_eq!(stream.duplicate().accepted_at(), stream.accepted_at());
+    });
+}
+
+#[test]
+fn test_duplicate() {
+    task::block_on(async {
+        let (sender, _) = backpressure::new(10);
+        let (_, server) = pair().await;
+        let stream = ByteStream::new_tcp(sender.token(), server);
+        let shared = stream.duplicate();
+        assert_eq!(sender.get_active_tokens(), 1);
+        let counted = stream.duplicate_with_new_token();
+        assert_eq!(sender.get_active_tokens(), 2);
+        assert_eq!(counted.id(), stream.id());
+        drop(stream);
+        assert_eq!(sender.get_active_tokens(), 2);
+        drop(shared);
+        assert_eq!(sender.get_active_tokens(), 1);
+        drop(counted);
+        assert_eq!(sender.get_active_tokens(), 0);
+
+        let (_, server) = pair().await;
+        let detached = ByteStream::from(server);
+        assert!(detached.duplicate_with_new_token().token().is_none());
+    });
+}
+
+#[test]
+fn test_cache_peer_addr() {
+    use std::time::Duration;
+    use async_std::stream::StreamExt;
+    use async_listen::{ListenExt, ListenerSet, PeerAddr};
+
+    task::block_on(async {
+        let (client, server) = pair().await;
+        let mut stream = ByteStream::from(server);
+        assert!(stream.cached_peer_addr().is_none());
+        let expected = PeerAddr::Tcp(client.local_addr().unwrap());
+        assert_eq!(stream.cache_peer_addr().unwrap(), &expected);
+        assert_eq!(stream.cached_peer_addr(), Some(&expected));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut listeners = ListenerSet::new();
+        listeners.add_tcp(listener);
+        let mut incoming = listeners.handle_errors(Duration::from_millis(10));
+        let client = TcpStream::connect(addr).await.unwrap();
+        let conn = incoming.next().await.unwrap();
+        assert_eq!(conn.cached_peer_addr(),
+                   Some(&PeerAddr::Tcp(client.local_addr().unwrap())));
+    });
+}
+
+#[cfg(feature="socket2")]
+#[test]
+fn test_with_socket() {
+    use std::time::Duration;
+
+    task::block_on(async {
+        let (_client, server) = pair().await;
+        let stream = ByteStream::new_tcp_detached(server);
+        stream.with_socket(|s| s.set_keepalive(true)).unwrap();
+        assert!(stream.with_socket(|s| s.keepalive()).unwrap());
+        stream.with_socket(|s| s.set_linger(Some(Duration::from_secs(1))))
+            .unwrap();
+        assert_eq!(stream.with_socket(|s| s.linger()).unwrap(),
+                   Some(Duration::from_secs(1)));
+    });
+}
+
+#[cfg(all(target_os="linux", feature="socket2"))]
+#[test]
+fn test_tos() {
+    task::block_on(async {
+        let (_client, server) = pair().await;
+        let stream = ByteStream::new_tcp_detached(server);
+        stream.set_dscp(46).unwrap();
+        assert_eq!(stream.with_socket(|s| s.tos()).unwrap(), 46 << 2);
+        stream.set_tos(0x10).unwrap();
+        assert_eq!(stream.with_socket(|s| s.tos()).unwrap(), 0x10);
+    });
+}
+
+#[test]
+fn test_invalid_dscp() {
+    task::block_on(async {
+        let (_client, server) = pair().await;
+        let stream = ByteStream::new_tcp_detached(server);
+        let err = stream.set_dscp(64).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    });
+}
+
+#[test]
+fn test_tcp_info() {
+    use async_std::io::WriteExt;
+
+    task::block_on(async {
+        let (mut client, server) = pair().await;
+        let mut stream = ByteStream::new_tcp_detached(server);
+        stream.write_all(b"hello").await.unwrap();
+        let mut buf = [0u8; 5];
+        client.read_exact(&mut buf).await.unwrap();
+        let info = stream.tcp_info().unwrap();
+        if cfg!(target_os="linux") {
+            let info = info.expect("tcp info");
+            assert!(info.send_cwnd() > 0);
+            assert!(info.send_mss() > 0);
+            assert_eq!(info.retransmits(), 0);
+        } else {
+            assert!(info.is_none());
+        }
+    });
+}
+
+#[test]
+fn test_sniff() {
+    use std::time::{Duration, Instant};
+    use async_std::io::WriteExt;
+    use async_listen::looks_like_tls;
+
+    task::block_on(async {
+        let (mut client, server) = pair().await;
+        let mut stream = ByteStream::new_tcp_detached(server);
+        let timeout = Duration::from_millis(100);
+        let err = stream.sniff(3, timeout).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+
+        client.write_all(b"G").await.unwrap();
+        let start = Instant::now();
+        assert_eq!(stream.sniff(3, timeout).await.unwrap(), b"G");
+        assert!(start.elapsed() >= timeout);
+
+        client.write_all(b"ET / HTTP/1.0\r\n\r\n").await.unwrap();
+        let prefix = stream.sniff(3, timeout).await.unwrap();
+        assert_eq!(prefix, b"GET");
+        assert!(!looks_like_tls(&prefix));
+        let mut buf = [0u8; 4];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"GET ");
+
+        drop(client);
+        let (client, server) = pair().await;
+        drop(client);
+        let stream = ByteStream::new_tcp_detached(server);
+        assert_eq!(stream.sniff(3, timeout).await.unwrap(), b"");
+    });
+}
+
+#[test]
+fn test_looks_like_tls() {
+    use async_listen::looks_like_tls;
+
+    assert!(looks_like_tls(b"\x16"));
+    assert!(looks_like_tls(b"\x16\x03\x01\x02\x00"));
+    assert!(!looks_like_tls(b"\x16\x03\x07"));
+    assert!(!looks_like_tls(b"\x16\x02"));
+    assert!(!looks_like_tls(b"*1\r\n"));
+    assert!(!looks_like_tls(b""));
+}
+
+#[test]
+fn test_into_std() {
+    use std::io::{Read, Write};
+    use async_listen::StdStream;
+
+    task::block_on(async {
+        let (sender, _) = backpressure::new(1);
+        let (mut client, server) = pair().await;
+        let stream = ByteStream::new_tcp(sender.token(), server);
+        let (token, std_stream) = stream.into_std().unwrap();
+        assert!(token.is_some());
+        assert_eq!(sender.get_active_tokens(), 1);
+        drop(token);
+        assert_eq!(sender.get_active_tokens(), 0);
+        let mut std_stream = match std_stream {
+            StdStream::Tcp(s) => s,
+            #[cfg(unix)]
+            other => panic!("unexpected stream {:?}", other),
+        };
+        let reader = std::thread::spawn(move || {
+            let mut buf = [0u8; 4];
+            std_stream.read_exact(&mut buf).unwrap();
+            std_stream.write_all(b"pong").unwrap();
+            buf
+        });
+        async_std::io::WriteExt::write_all(&mut client, b"ping").await
+            .unwrap();
+        let mut buf = [0u8; 4];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+        assert_eq!(&reader.join().unwrap(), b"ping");
+    });
+}
+
+#[test]
+fn test_send_file() {
+    use std::io::Write;
+
+    let path = std::env::temp_dir()
+        .join(format!("async-listen-send-file-{}", std::process::id()));
+    let data: Vec<u8> = (0..200_000u32).map(|x| x as u8).collect();
+    std::fs::File::create(&path).unwrap().write_all(&data).unwrap();
+    let file = std::fs::File::open(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    task::block_on(async {
+        let (client, server) = pair().await;
+        let mut stream = ByteStream::new_tcp_detached(server);
+        let reader = task::spawn(async move {
+            let mut client = client;
+            let mut buf = Vec::new();
+            client.read_to_end(&mut buf).await.unwrap();
+            buf
+        });
+        let sent = stream.send_file(&file, 10..150_000).await.unwrap();
+        assert_eq!(sent, 149_990);
+        // range past the end of file
+        let sent = stream.send_file(&file, 199_990..300_000).await.unwrap();
+        assert_eq!(sent, 10);
+        drop(stream);
+        let buf = reader.await;
+        assert_eq!(&buf[..149_990], &data[10..150_000]);
+        assert_eq!(&buf[149_990..], &data[199_990..]);
+
+        // buffered fallback
+        let (sender, _) = backpressure::new(1);
+        let mut stream = ByteStream::new_boxed(sender.token(),
+            async_std::io::Cursor::new(Vec::new()));
+        let sent = stream.send_file(&file, 0..100_000).await.unwrap();
+        assert_eq!(sent, 100_000);
+    });
+}
+
+#[test]
+fn test_proxy_to() {
+    use async_std::io::WriteExt;
+    use async_std::net::Shutdown;
+
+    async fn check(mut a: ByteStream, mut client: TcpStream,
+                   mut b: ByteStream, mut backend: TcpStream)
+    {
+        let proxy = task::spawn(async move {
+            let res = a.proxy_to(&mut b).await;
+            drop((a, b));
+            res
+        });
+        client.write_all(b"ping").await.unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let mut buf = Vec::new();
+        backend.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"ping");
+        // the other direction still works after half-close
+        backend.write_all(b"pong").await.unwrap();
+        drop(backend);
+        let mut buf = Vec::new();
+        client.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"pong");
+        assert_eq!(proxy.await.unwrap(), (4, 4));
+    }
+
+    task::block_on(async {
+        let (client, a) = pair().await;
+        let (backend, b) = pair().await;
+        check(ByteStream::new_tcp_detached(a), client,
+              ByteStream::new_tcp_detached(b), backend).await;
+
+        // generic copy path
+        let (sender, _) = backpressure::new(1);
+        let (client, a) = pair().await;
+        let (backend, b) = pair().await;
+        check(ByteStream::new_boxed(sender.token(), a), client,
+              ByteStream::new_tcp_detached(b), backend).await;
+    });
+}
+
+#[test]
+fn test_close_write() {
+    use async_std::io::WriteExt;
+
+    task::block_on(async {
+        let (client, server) = pair().await;
+        let mut stream = ByteStream::new_tcp_detached(client);
+        let server = task::spawn(async move {
+            let mut server = server;
+            let mut request = Vec::new();
+            server.read_to_end(&mut request).await.unwrap();
+            server.write_all(b"reply to ").await.unwrap();
+            server.write_all(&request).await.unwrap();
+        });
+        stream.write_all(b"request").await.unwrap();
+        stream.close_write().await.unwrap();
+        assert_eq!(stream.read_remaining().await.unwrap(),
+                   b"reply to request");
+        server.await;
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn test_pass_fd() {
+    use std::io::{self, Read, Seek, Write};
+    use async_std::io::WriteExt;
+    use async_std::os::unix::net::UnixStream;
+
+    task::block_on(async {
+        let (a, b) = UnixStream::pair().unwrap();
+        let mut a = ByteStream::new_unix_detached(a);
+        let b = ByteStream::new_unix_detached(b);
+
+        let path = std::env::temp_dir()
+            .join(format!("async-listen-pass-fd-{}", std::process::id()));
+        let mut file = std::fs::OpenOptions::new()
+            .read(true).write(true).create(true).truncate(true)
+            .open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        file.write_all(b"passed").unwrap();
+        a.send_fd(&file).await.unwrap();
+        drop(file);
+
+        let mut file = std::fs::File::from(b.recv_fd().await.unwrap());
+        file.rewind().unwrap();
+        let mut data = String::new();
+        file.read_to_string(&mut data).unwrap();
+        assert_eq!(data, "passed");
+
+        a.write_all(b"x").await.unwrap();
+        assert_eq!(b.recv_fd().await.unwrap_err().kind(),
+                   io::ErrorKind::InvalidData);
+        drop(a);
+        assert_eq!(b.recv_fd().await.unwrap_err().kind(),
+                   io::ErrorKind::UnexpectedEof);
+
+        let (_client, server) = pair().await;
+        let tcp = ByteStream::new_tcp_detached(server);
+        assert_eq!(tcp.recv_fd().await.unwrap_err().kind(),
+                   io::ErrorKind::Unsupported);
+    });
+}
+
+#[test]
+fn test_extensions() {
+    #[derive(Clone, Debug, PartialEq)]
+    struct User(&'static str);
+
+    task::block_on(async {
+        let (_client, server) = pair().await;
+        let mut stream = ByteStream::new_tcp_detached(server);
+        assert!(stream.extensions().is_empty());
+        assert_eq!(stream.extensions_mut().insert(User("guest")), None);
+        assert_eq!(stream.extensions_mut().insert(User("admin")),
+                   Some(User("guest")));
+        stream.extensions_mut().insert(42u32);
+        assert_eq!(stream.extensions().len(), 2);
+        *stream.extensions_mut().get_mut::<u32>().unwrap() += 1;
+
+        let dup = stream.duplicate();
+        assert_eq!(dup.extensions().get::<User>(), Some(&User("admin")));
+        assert_eq!(stream.extensions_mut().remove::<u32>(), Some(43));
+        assert_eq!(stream.extensions().get::<u32>(), None);
+        assert_eq!(dup.extensions().get::<u32>(), Some(&43));
+        stream.extensions_mut().clear();
+        assert!(stream.extensions().is_empty());
+    });
+}
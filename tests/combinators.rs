@@ -0,0 +1,617 @@
+use std::time::{Duration, Instant};
+
+use async_std::net::{TcpListener, TcpStream};
+use async_std::stream::{from_iter, Stream, StreamExt};
+use async_std::task;
+
+use async_listen::{ListenExt, ListenerSet, SocketOptions, backpressure};
+
+fn collect<S: Stream + Unpin>(mut stream: S) -> Vec<S::Item> {
+    task::block_on(async {
+        let mut result = Vec::new();
+        while let Some(item) = stream.next().await {
+            result.push(item);
+        }
+        result
+    })
+}
+
+#[test]
+fn test_throttle_accepts() {
+    let start = Instant::now();
+    let result = collect(from_iter(0..15).throttle_accepts(100.0, 5));
+    assert_eq!(result, (0..15).collect::<Vec<_>>());
+    // 5 items are yielded immediately, 10 more at 10ms each
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(90), "{:?}", elapsed);
+    assert!(elapsed < Duration::from_millis(1000), "{:?}", elapsed);
+}
+
+#[test]
+fn test_throttle_tiny_rate() {
+    let mut stream = from_iter(0..2).throttle_accepts(1e-300, 1);
+    task::block_on(async {
+        assert_eq!(stream.next().await, Some(0));
+        let next = async_std::future::timeout(
+            Duration::from_millis(50), stream.next()).await;
+        assert!(next.is_err());
+    });
+}
+
+#[test]
+#[should_panic(expected = "rate must be positive and finite")]
+fn test_throttle_nan_rate() {
+    let _ = from_iter(0..2).throttle_accepts(f64::NAN, 1);
+}
+
+#[test]
+fn test_conn_info() {
+    let result = collect(from_iter(vec!["a", "b", "c"]).with_conn_info());
+    let ids = result.iter().map(|(info, _)| info.id()).collect::<Vec<_>>();
+    assert_eq!(ids, vec![1, 2, 3]);
+    assert_eq!(result[2].0.to_string(), "#3");
+    assert!(result[0].0.accepted_at() <= result[2].0.accepted_at());
+}
+
+#[test]
+fn test_configure_sockets() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut opts = SocketOptions::new();
+        opts.nodelay(true).ttl(42).keepalive(Duration::from_secs(30));
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .configure_sockets(opts);
+        let _client = TcpStream::connect(addr).await.unwrap();
+        let stream = incoming.next().await.unwrap();
+        assert!(stream.nodelay().unwrap());
+        assert_eq!(stream.ttl().unwrap(), 42);
+    });
+}
+
+#[test]
+fn test_configure_sockets_error() {
+    use std::sync::{Arc, Mutex};
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut opts = SocketOptions::new();
+        opts.ttl(1000);  // invalid
+        let errors = Arc::new(Mutex::new(0));
+        let counter = errors.clone();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .configure_sockets(opts)
+            .on_error(move |_| *counter.lock().unwrap() += 1);
+        let client = TcpStream::connect(addr).await.unwrap();
+        // connection is not dropped
+        let stream = incoming.next().await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), client.local_addr().unwrap());
+        assert_eq!(*errors.lock().unwrap(), 1);
+    });
+}
+
+#[test]
+fn test_until() {
+    let stream = from_iter(0..)
+        .throttle_accepts(1000.0, 1)
+        .until(task::sleep(Duration::from_millis(50)));
+    let result = collect(stream);
+    assert!(result.len() > 5 && result.len() < 200, "{}", result.len());
+}
+
+#[test]
+fn test_listener_set() {
+    assert_eq!(collect(ListenerSet::new()).len(), 0);
+    task::block_on(async {
+        let (sender, bp) = backpressure::new(10);
+        let first = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let second = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addrs = [first.local_addr().unwrap(), second.local_addr().unwrap()];
+        let mut listeners = ListenerSet::new();
+        listeners.add_tcp(first).add_tcp(second);
+        assert_eq!(listeners.len(), 2);
+        let mut incoming = listeners
+            .handle_errors(Duration::from_millis(10))
+            .backpressure_wrapper(bp);
+        let _c1 = TcpStream::connect(addrs[1]).await.unwrap();
+        let _c2 = TcpStream::connect(addrs[0]).await.unwrap();
+        let s1 = incoming.next().await.unwrap();
+        let s2 = incoming.next().await.unwrap();
+        assert_eq!(sender.get_active_tokens(), 2);
+        drop(s1);
+        drop(s2);
+        assert_eq!(sender.get_active_tokens(), 0);
+    });
+}
+
+#[test]
+fn test_listener_priority() {
+    use async_listen::PeerAddr;
+
+    task::block_on(async {
+        let public = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let admin = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addrs = [public.local_addr().unwrap(), admin.local_addr().unwrap()];
+        let mut listeners = ListenerSet::new();
+        listeners.add_tcp(public).add_tcp_with_priority(admin, 1);
+        let mut incoming = listeners.handle_errors(Duration::from_millis(10));
+        let mut clients = Vec::new();
+        for _ in 0..3 {
+            clients.push(TcpStream::connect(addrs[0]).await.unwrap());
+        }
+        let admin_client = TcpStream::connect(addrs[1]).await.unwrap();
+        task::sleep(Duration::from_millis(20)).await;
+        let first = incoming.next().await.unwrap();
+        assert_eq!(first.peer_addr().unwrap(),
+                   PeerAddr::Tcp(admin_client.local_addr().unwrap()));
+        for _ in 0..3 {
+            let conn = incoming.next().await.unwrap();
+            assert!(clients.iter().any(|c| {
+                conn.peer_addr().unwrap() ==
+                    PeerAddr::Tcp(c.local_addr().unwrap())
+            }));
+        }
+    });
+}
+
+#[test]
+fn test_serve() {
+    use std::sync::{Arc, Mutex};
+
+    let errors = Arc::new(Mutex::new(Vec::new()));
+    let errors2 = errors.clone();
+    let started = Instant::now();
+    task::block_on(
+        from_iter(vec![1u64, 2, 3, 4])
+        .serve(|x| async move {
+            task::sleep(Duration::from_millis(x * 20)).await;
+            if x % 2 == 0 {
+                return Err(format!("error {}", x));
+            }
+            Ok(())
+        })
+        .on_error(move |e| errors2.lock().unwrap().push(e.clone()))
+    );
+    assert!(started.elapsed() >= Duration::from_millis(80));
+    let mut errors = errors.lock().unwrap().clone();
+    errors.sort();
+    assert_eq!(errors, vec!["error 2", "error 4"]);
+}
+
+#[test]
+fn test_serve_panic() {
+    use std::sync::{Arc, Mutex};
+
+    let (sender, _) = backpressure::new(10);
+    let panics = Arc::new(Mutex::new(Vec::new()));
+    let panics2 = panics.clone();
+    task::block_on(
+        from_iter(vec![1u32, 2, 3])
+        .map(|x| (sender.token(), x))
+        .serve(|(_token, x)| async move {
+            if x == 2 {
+                panic!("bad connection {}", x);
+            }
+            Ok::<(), String>(())
+        })
+        .on_panic(move |msg| panics2.lock().unwrap().push(msg.to_string()))
+    );
+    assert_eq!(*panics.lock().unwrap(), vec!["bad connection 2"]);
+    assert_eq!(sender.get_active_tokens(), 0);
+}
+
+#[test]
+fn test_deadline() {
+    use async_std::io::ReadExt;
+
+    task::block_on(async {
+        let (sender, _) = backpressure::new(10);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .backpressure_wrapper_with(&sender)
+            .with_deadline(Duration::from_millis(50));
+        let _client = TcpStream::connect(addr).await.unwrap();
+        let mut conn = incoming.next().await.unwrap();
+        assert!(!conn.is_expired());
+        assert_eq!(sender.get_active_tokens(), 1);
+        let started = Instant::now();
+        let mut buf = [0u8; 16];
+        let err = conn.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert!(started.elapsed() >= Duration::from_millis(40));
+        assert!(conn.is_expired());
+        assert_eq!(sender.get_active_tokens(), 0);
+    });
+}
+
+#[test]
+fn test_idle_timeout() {
+    use async_std::io::{ReadExt, WriteExt};
+
+    task::block_on(async {
+        let (sender, _) = backpressure::new(10);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .backpressure_wrapper_with(&sender)
+            .idle_timeout(Duration::from_millis(100));
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let mut conn = incoming.next().await.unwrap();
+        let started = Instant::now();
+        let mut buf = [0u8; 16];
+        for _ in 0..3 {
+            task::sleep(Duration::from_millis(60)).await;
+            client.write_all(b"x").await.unwrap();
+            assert_eq!(conn.read(&mut buf).await.unwrap(), 1);
+        }
+        assert!(started.elapsed() >= Duration::from_millis(150));
+        let err = conn.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+        assert!(conn.last_activity().elapsed() >= Duration::from_millis(90));
+        assert!(conn.is_expired());
+        assert_eq!(sender.get_active_tokens(), 0);
+    });
+}
+
+#[test]
+fn test_limit_bandwidth() {
+    use async_std::io::{ReadExt, WriteExt};
+    use async_listen::Bandwidth;
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .limit_bandwidth(Bandwidth::new().write(1000).burst(100));
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let mut conn = incoming.next().await.unwrap();
+        let started = Instant::now();
+        conn.write_all(&[0u8; 300]).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(190));
+        assert!(started.elapsed() < Duration::from_millis(1000));
+        let mut buf = [0u8; 300];
+        client.read_exact(&mut buf).await.unwrap();
+
+        // reading is not limited
+        client.write_all(&[1u8; 300]).await.unwrap();
+        let started = Instant::now();
+        conn.read_exact(&mut buf).await.unwrap();
+        assert!(started.elapsed() < Duration::from_millis(100));
+    });
+}
+
+#[test]
+fn test_bandwidth_burst_first() {
+    use async_std::io::WriteExt;
+    use async_listen::Bandwidth;
+
+    assert_eq!(Bandwidth::new().burst(100).read(1000),
+               Bandwidth::new().read(1000).burst(100));
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .limit_bandwidth(Bandwidth::new().burst(100).write(1000));
+        let _client = TcpStream::connect(addr).await.unwrap();
+        let mut conn = incoming.next().await.unwrap();
+        let started = Instant::now();
+        conn.write_all(&[0u8; 300]).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(190));
+    });
+}
+
+#[test]
+fn test_shard() {
+    use async_listen::shard::RoundRobin;
+
+    task::block_on(async {
+        let (dispatcher, workers) = from_iter(0..10u32)
+            .shard(3, 10, RoundRobin::new());
+        dispatcher.await;
+        let mut result = Vec::new();
+        for mut worker in workers {
+            let mut items = Vec::new();
+            while let Some(x) = worker.next().await {
+                items.push(x);
+            }
+            result.push(items);
+        }
+        assert_eq!(result, vec![
+            vec![0, 3, 6, 9],
+            vec![1, 4, 7],
+            vec![2, 5, 8],
+        ]);
+
+        // bounded channel pauses distribution
+        let (dispatcher, mut workers) = from_iter(0..10u32)
+            .shard(2, 1, |x: &u32, _| if *x < 5 { 0 } else { 1 });
+        let dispatcher = task::spawn(dispatcher);
+        let mut first = workers.remove(0);
+        let mut second = workers.remove(0);
+        task::sleep(Duration::from_millis(10)).await;
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 0);
+        let mut got = Vec::new();
+        for _ in 0..5 {
+            got.push(first.next().await.unwrap());
+        }
+        assert_eq!(got, vec![0, 1, 2, 3, 4]);
+        assert_eq!(second.next().await, Some(5));
+        // items to a closed worker are dropped
+        drop(second);
+        dispatcher.await;
+        assert_eq!(first.next().await, None);
+    });
+}
+
+#[test]
+fn test_accept_latency() {
+    use std::sync::{Arc, Mutex};
+
+    let latencies = Arc::new(Mutex::new(Vec::new()));
+    let latencies2 = latencies.clone();
+    let mut stream = from_iter(vec![1u32, 2])
+        .measure_accept_latency(move |lat| latencies2.lock().unwrap().push(lat))
+        .backpressure(1);
+    task::block_on(async {
+        let (token, _) = stream.next().await.unwrap();
+        // backpressure pauses the listener until the token is dropped
+        task::spawn(async move {
+            task::sleep(Duration::from_millis(50)).await;
+            drop(token);
+        });
+        stream.next().await.unwrap();
+    });
+    let latencies = latencies.lock().unwrap();
+    assert_eq!(latencies.len(), 2);
+    assert!(latencies[0] < Duration::from_millis(10));
+    assert!(latencies[1] >= Duration::from_millis(40));
+}
+
+#[test]
+fn test_watchdog() {
+    use std::sync::{Arc, Mutex};
+    use async_std::channel;
+
+    let idle = Arc::new(Mutex::new(Vec::new()));
+    let idle2 = idle.clone();
+    let (tx, rx) = channel::unbounded();
+    let mut stream = rx.watchdog(Duration::from_millis(20),
+        move |dur| idle2.lock().unwrap().push(dur));
+    task::block_on(async {
+        task::spawn(async move {
+            task::sleep(Duration::from_millis(70)).await;
+            tx.send(1u32).await.unwrap();
+        });
+        assert_eq!(stream.next().await, Some(1));
+    });
+    assert!(stream.idle_time() < Duration::from_millis(20));
+    let idle = idle.lock().unwrap();
+    assert!(idle.len() >= 2 && idle.len() <= 3);
+    assert!(idle[0] >= Duration::from_millis(20));
+    assert!(idle[1] >= Duration::from_millis(40));
+}
+
+#[test]
+fn test_not_unpin() {
+    use std::io;
+    use std::pin::pin;
+
+    let items: Vec<io::Result<u32>> =
+        vec![Ok(1), Err(io::ErrorKind::Other.into()), Ok(2)];
+    // the future of an async block is not `Unpin`, and neither is the stream
+    let stream = futures_lite::stream::unfold(items.into_iter(),
+        |mut items| async move { items.next().map(|item| (item, items)) });
+    let stream = stream
+        .handle_errors(Duration::from_millis(1))
+        .take_connections(5);
+    assert_eq!(collect(pin!(stream)), vec![1, 2]);
+}
+
+#[test]
+fn test_take_connections() {
+    let stream = from_iter(1..10u32).take_connections(3);
+    assert_eq!(stream.remaining(), 3);
+    assert_eq!(collect(stream), vec![1, 2, 3]);
+    assert_eq!(collect(from_iter(1..3u32).take_connections(5)), vec![1, 2]);
+    assert_eq!(collect(from_iter(1..3u32).take_connections(0)), vec![]);
+}
+
+#[test]
+fn test_sample() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut sampled = Vec::new();
+        let mut clients = Vec::new();
+        {
+            let mut incoming = listener.incoming()
+                .handle_errors(Duration::from_millis(10))
+                .sample(2, |peer| sampled.push(peer.to_string()));
+            for _ in 0..4 {
+                let client = TcpStream::connect(addr).await.unwrap();
+                incoming.next().await.unwrap();
+                clients.push(client.local_addr().unwrap().to_string());
+            }
+            assert_eq!(incoming.connections(), 4);
+        }
+        assert_eq!(sampled, vec![clients[1].clone(), clients[3].clone()]);
+    });
+}
+
+#[test]
+fn test_first_byte_timeout() {
+    use async_std::io::{ReadExt, WriteExt};
+
+    task::block_on(async {
+        let (sender, bp) = backpressure::new(10);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .backpressure_wrapper(bp)
+            .first_byte_timeout(Duration::from_millis(100));
+        let mut silent = TcpStream::connect(addr).await.unwrap();
+        let mut active = TcpStream::connect(addr).await.unwrap();
+        active.write_all(b"hello").await.unwrap();
+        let mut stream = incoming.next().await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap().to_string(),
+                   active.local_addr().unwrap().to_string());
+        let mut buf = [0u8; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+        // silent connection is closed after timeout
+        let next = async_std::future::timeout(
+            Duration::from_millis(300), incoming.next()).await;
+        assert!(next.is_err());
+        assert!(matches!(silent.read(&mut buf).await, Ok(0) | Err(_)));
+        assert_eq!(incoming.pending_handshakes(), 0);
+        assert_eq!(sender.get_active_tokens(), 1);
+    });
+}
+
+#[cfg(unix)]
+#[test]
+fn test_first_byte_timeout_unix() {
+    use async_std::io::{ReadExt, WriteExt};
+    use async_std::os::unix::net::{UnixListener, UnixStream};
+
+    task::block_on(async {
+        let path = std::env::temp_dir()
+            .join(format!("async-listen-first-byte-{}.sock", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let listener = UnixListener::bind(&path).await.unwrap();
+        let mut listeners = ListenerSet::new();
+        listeners.add_unix(listener);
+        let mut incoming = listeners
+            .handle_errors(Duration::from_millis(10))
+            .first_byte_timeout(Duration::from_secs(5));
+        let mut client = UnixStream::connect(&path).await.unwrap();
+        client.write_all(b"x").await.unwrap();
+        let mut stream = incoming.next().await.unwrap();
+        let mut buf = [0u8; 1];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"x");
+        std::fs::remove_file(&path).ok();
+    });
+}
+
+#[test]
+fn test_and_then_limited() {
+    use std::io;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let running = Arc::new(AtomicUsize::new(0));
+    let max_running = Arc::new(AtomicUsize::new(0));
+    let (r, m) = (running.clone(), max_running.clone());
+    let stream = from_iter(1..7u32)
+        .and_then_limited(2, move |x| {
+            let (r, m) = (r.clone(), m.clone());
+            async move {
+                m.fetch_max(r.fetch_add(1, Ordering::SeqCst) + 1,
+                            Ordering::SeqCst);
+                task::sleep(Duration::from_millis(10)).await;
+                r.fetch_sub(1, Ordering::SeqCst);
+                if x == 3 {
+                    return Err(io::ErrorKind::Other.into());
+                }
+                Ok(x * 10)
+            }
+        });
+    let mut result = collect(stream);
+    result.sort();
+    assert_eq!(result, vec![10, 20, 40, 50, 60]);
+    assert_eq!(max_running.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_pausable() {
+    use async_std::future::timeout;
+
+    task::block_on(async {
+        let (mut incoming, handle) = from_iter(1..4u32).pausable();
+        assert_eq!(incoming.next().await, Some(1));
+        handle.pause();
+        assert!(handle.is_paused());
+        let next = timeout(Duration::from_millis(50), incoming.next()).await;
+        assert!(next.is_err());
+        let resume = incoming.handle();
+        task::spawn(async move {
+            task::sleep(Duration::from_millis(20)).await;
+            resume.resume();
+        });
+        assert_eq!(incoming.next().await, Some(2));
+        assert!(!handle.is_paused());
+        assert_eq!(incoming.next().await, Some(3));
+    });
+}
+
+#[test]
+fn test_pause_and_close() {
+    use async_std::future::timeout;
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut listeners = ListenerSet::new();
+        listeners.add_tcp(listener);
+        let handle = listeners.pause_handle();
+        let mut incoming = listeners.handle_errors(Duration::from_millis(10));
+        handle.pause_and_close();
+        let next = timeout(Duration::from_millis(50), incoming.next()).await;
+        assert!(next.is_err());
+        assert!(TcpStream::connect(addr).await.is_err());
+        handle.resume();
+        let accept = task::spawn(async move {
+            incoming.next().await.unwrap()
+        });
+        let mut client = None;
+        for _ in 0..100 {
+            if let Ok(conn) = TcpStream::connect(addr).await {
+                client = Some(conn);
+                break;
+            }
+            task::sleep(Duration::from_millis(10)).await;
+        }
+        let client = client.expect("listener is bound again");
+        let stream = accept.await;
+        assert_eq!(stream.peer_addr().unwrap().to_string(),
+                   client.local_addr().unwrap().to_string());
+    });
+}
+
+#[test]
+fn test_accept_tuples() {
+    use async_listen::filter::AllowList;
+
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = TcpStream::connect(addr).await.unwrap();
+        let accepted = listener.accept().await;
+        let (sender, bp) = backpressure::new(10);
+        let mut opts = SocketOptions::new();
+        opts.nodelay(true);
+        let mut sampled = Vec::new();
+        let mut incoming = from_iter(vec![accepted])
+            .handle_errors(Duration::from_millis(10))
+            .filter_peers("127.0.0.0/8".parse::<AllowList>().unwrap())
+            .sample(1, |peer| sampled.push(peer.to_string()))
+            .configure_sockets(opts)
+            .backpressure_wrapper(bp);
+        let stream = incoming.next().await.unwrap();
+        assert!(stream.nodelay().unwrap());
+        assert_eq!(sender.get_active_tokens(), 1);
+        drop(incoming);
+        assert_eq!(sampled, vec![client.local_addr().unwrap().to_string()]);
+    });
+}
@@ -0,0 +1,26 @@
+// Diagnostics are enabled globally, so this test lives in its own binary
+use std::io;
+
+use async_listen::{error_hint, set_fd_diagnostics};
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_fd_diagnostics() {
+    let e = io::Error::from_raw_os_error(24);
+    assert!(error_hint(&e).fd_usage().is_none());
+
+    set_fd_diagnostics(true);
+    let hint = error_hint(&e);
+    let usage = hint.fd_usage().expect("diagnostics are supported");
+    assert!(usage.open().unwrap() >= 3);  // at least stdio
+    assert!(usage.soft_limit().is_some());
+    let text = hint.to_string();
+    assert!(text.starts_with("Increase per-process open file limit (open: "),
+            "{}", text);
+    assert!(text.ends_with(") https://bit.ly/async-err#EMFILE"), "{}", text);
+
+    // only EMFILE has diagnostics
+    let e = io::Error::from_raw_os_error(23);
+    assert!(error_hint(&e).fd_usage().is_none());
+    set_fd_diagnostics(false);
+}
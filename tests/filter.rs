@@ -0,0 +1,153 @@
+use std::net::IpAddr;
+use std::time::Duration;
+
+use async_std::net::{TcpListener, TcpStream};
+use async_std::prelude::*;
+use async_std::future::timeout;
+use async_std::task;
+
+use async_listen::{ListenExt, PeerAddr};
+use async_listen::filter::{AllowList, Cidr, DenyList, PeerPolicy};
+
+fn ip(s: &str) -> IpAddr {
+    s.parse().unwrap()
+}
+
+#[test]
+fn test_cidr() {
+    let net: Cidr = "10.1.0.0/16".parse().unwrap();
+    assert!(net.contains(ip("10.1.2.3")));
+    assert!(net.contains(ip("::ffff:10.1.2.3")));
+    assert!(!net.contains(ip("10.2.0.1")));
+    assert!(!net.contains(ip("::1")));
+    let net: Cidr = "2001:db8::/32".parse().unwrap();
+    assert!(net.contains(ip("2001:db8::1")));
+    assert!(!net.contains(ip("2001:db9::1")));
+    let net: Cidr = "0.0.0.0/0".parse().unwrap();
+    assert!(net.contains(ip("192.0.2.1")));
+    let single: Cidr = "192.0.2.1".parse().unwrap();
+    assert_eq!(single.to_string(), "192.0.2.1/32");
+    assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    assert!("10.0.0/8".parse::<Cidr>().is_err());
+}
+
+#[test]
+fn test_lists() {
+    let peer = |s: &str| PeerAddr::Tcp(s.parse().unwrap());
+    let allow: AllowList = "127.0.0.0/8, ::1".parse().unwrap();
+    assert!(allow.allow(&peer("127.0.0.1:1234")));
+    assert!(allow.allow(&peer("[::1]:1234")));
+    assert!(!allow.allow(&peer("192.0.2.1:1234")));
+    assert!(allow.allow(&PeerAddr::Unix(None)));
+    let deny: DenyList = "192.0.2.0/24".parse().unwrap();
+    assert!(deny.allow(&peer("127.0.0.1:1234")));
+    assert!(!deny.allow(&peer("192.0.2.1:1234")));
+}
+
+#[test]
+fn test_filter_peers() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut denied = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .filter_peers("127.0.0.0/8".parse::<DenyList>().unwrap());
+        let client = TcpStream::connect(addr).await.unwrap();
+        let accept = timeout(Duration::from_millis(100), denied.next()).await;
+        assert!(accept.is_err());
+        drop(client);
+    });
+}
+
+#[test]
+fn test_limit_per_peer() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .limit_per_peer(2);
+        let mut clients = Vec::new();
+        for _ in 0..3 {
+            clients.push(TcpStream::connect(addr).await.unwrap());
+        }
+        let (first, _) = incoming.next().await.unwrap();
+        let (_second, _) = incoming.next().await.unwrap();
+        assert_eq!(incoming.get_active(ip("127.0.0.1")), 2);
+        // third connection is closed by the limiter
+        let next = timeout(Duration::from_millis(100), incoming.next()).await;
+        assert!(next.is_err());
+        let mut buf = [0u8; 1];
+        assert_eq!(clients[2].read(&mut buf).await.unwrap(), 0);
+        drop(first);
+        assert_eq!(incoming.get_active(ip("127.0.0.1")), 1);
+        let _client = TcpStream::connect(addr).await.unwrap();
+        let (_third, _) = incoming.next().await.unwrap();
+        assert_eq!(incoming.get_active(ip("127.0.0.1")), 2);
+    });
+}
+
+#[test]
+fn test_limit_per_peer_zero() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .limit_per_peer(0);
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        let next = timeout(Duration::from_millis(100), incoming.next()).await;
+        assert!(next.is_err());
+        let mut buf = [0u8; 1];
+        assert_eq!(client.read(&mut buf).await.unwrap(), 0);
+        // rejected peers must not leave entries in the map
+        assert!(format!("{:?}", incoming).ends_with("peers: 0 }"));
+    });
+}
+
+#[test]
+fn test_tarpit() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .tarpit("127.0.0.0/8".parse::<DenyList>().unwrap(), 1)
+            .interval(Duration::from_millis(20));
+        let mut first = TcpStream::connect(addr).await.unwrap();
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        let next = timeout(Duration::from_millis(100), incoming.next()).await;
+        assert!(next.is_err());
+        assert_eq!(incoming.tarpitted(), 1);
+        // first connection is held open and receives tiny writes
+        let mut buf = [0u8; 1];
+        assert_eq!(first.read(&mut buf).await.unwrap(), 1);
+        // second one doesn't fit into the tarpit and is closed
+        assert_eq!(second.read(&mut buf).await.unwrap(), 0);
+        drop(first);
+        task::sleep(Duration::from_millis(100)).await;
+        assert_eq!(incoming.tarpitted(), 0);
+    });
+}
+
+#[test]
+fn test_reconnect_cooldown() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .reconnect_cooldown(Duration::from_millis(200));
+        let _first = TcpStream::connect(addr).await.unwrap();
+        incoming.next().await.unwrap();
+        let mut second = TcpStream::connect(addr).await.unwrap();
+        let next = timeout(Duration::from_millis(50), incoming.next()).await;
+        assert!(next.is_err());
+        let mut buf = [0u8; 1];
+        assert_eq!(second.read(&mut buf).await.unwrap(), 0);
+        assert_eq!(incoming.rejected(), 1);
+        task::sleep(Duration::from_millis(200)).await;
+        let _third = TcpStream::connect(addr).await.unwrap();
+        incoming.next().await.unwrap();
+    });
+}
@@ -0,0 +1,99 @@
+// The socket must be the standard input, so tests run the test binary
+// itself as a child process, the same way a super-server would start it
+#![cfg(unix)]
+
+use std::env;
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::os::fd::OwnedFd;
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use async_std::io::WriteExt;
+use async_std::prelude::*;
+use async_std::task;
+
+use async_listen::inetd::{self, Socket};
+use async_listen::{ListenerSet, PeerAddr};
+
+const CHILD_ENV: &str = "ASYNC_LISTEN_INETD_TEST";
+
+fn spawn_child(test: &str, stdin: Stdio) -> Child {
+    Command::new(env::current_exe().unwrap())
+        .args(["--exact", test, "--test-threads=1"])
+        .env(CHILD_ENV, test)
+        .stdin(stdin)
+        .stdout(Stdio::null())
+        .spawn().unwrap()
+}
+
+fn is_child(test: &str) -> bool {
+    env::var(CHILD_ENV).map(|v| v == test).unwrap_or(false)
+}
+
+#[test]
+fn test_wait_mode() {
+    if is_child("test_wait_mode") {
+        task::block_on(async {
+            let listener = match inetd::socket().unwrap() {
+                Socket::Listener(listener) => listener,
+                Socket::Connection(_) => panic!("expected listener"),
+            };
+            let mut set = ListenerSet::new();
+            set.add_listener(listener);
+            let mut conn = set.next().await.unwrap().unwrap();
+            conn.write_all(b"hello").await.unwrap();
+        });
+        return;
+    }
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let stdin = Stdio::from(OwnedFd::from(listener));
+    let mut child = spawn_child("test_wait_mode", stdin);
+    let mut client = TcpStream::connect(addr).unwrap();
+    let mut buf = String::new();
+    client.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "hello");
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn test_nowait_mode() {
+    if is_child("test_nowait_mode") {
+        task::block_on(async {
+            let mut conn = inetd::connection().unwrap();
+            assert!(matches!(conn.peer_addr().unwrap(), PeerAddr::Tcp(_)));
+            conn.write_all(b"hello").await.unwrap();
+            drop(conn);
+            // peer sees the end of stream before the process exits, i.e.
+            // standard input doesn't keep the connection open
+            std::thread::sleep(Duration::from_secs(30));
+        });
+        return;
+    }
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap())
+        .unwrap();
+    let (server, _) = listener.accept().unwrap();
+    let stdin = Stdio::from(OwnedFd::from(server));
+    let mut child = spawn_child("test_nowait_mode", stdin);
+    client.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    let mut buf = String::new();
+    client.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, "hello");
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_not_a_socket() {
+    if is_child("test_not_a_socket") {
+        let err = inetd::socket().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        assert!(inetd::listener().is_err());
+        assert!(inetd::connection().is_err());
+        return;
+    }
+    let mut child = spawn_child("test_not_a_socket", Stdio::null());
+    assert!(child.wait().unwrap().success());
+}
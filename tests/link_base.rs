@@ -0,0 +1,26 @@
+// The link base is global, so this test lives in its own binary to avoid
+// affecting hints checked by other tests
+use std::io;
+
+use async_listen::{error_hint, set_link_base};
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_link_base() {
+    let e = io::Error::from_raw_os_error(24);
+    assert_eq!(error_hint(&e).link_base(), "https://bit.ly/async-err");
+    set_link_base("https://wiki.example.org/listen");
+    let hint = error_hint(&e);
+    assert_eq!(hint.link_base(), "https://wiki.example.org/listen");
+    assert_eq!(hint.default_link_base(), "https://bit.ly/async-err");
+    assert_eq!(hint.link(), "https://wiki.example.org/listen#EMFILE");
+    assert_eq!(hint.to_string(),
+        "Increase per-process open file limit \
+         https://wiki.example.org/listen#EMFILE");
+
+    // owned strings, i.e. from a config file
+    set_link_base(format!("https://{}/listen", "docs.example.org"));
+    assert_eq!(error_hint(&e).link(), "https://docs.example.org/listen#EMFILE");
+    // hints keep the base they were created with
+    assert_eq!(hint.link_base(), "https://wiki.example.org/listen");
+}
@@ -0,0 +1,337 @@
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use async_std::net::TcpStream;
+use async_std::prelude::*;
+use async_std::task;
+
+use async_listen::{ListenAddr, Listener, ListenerSet};
+
+#[test]
+fn test_parse() {
+    let addr: SocketAddr = "0.0.0.0:8080".parse().unwrap();
+    assert_eq!("tcp://0.0.0.0:8080".parse::<ListenAddr>().unwrap(),
+               ListenAddr::Tcp(addr));
+    assert_eq!("0.0.0.0:8080".parse::<ListenAddr>().unwrap(),
+               ListenAddr::Tcp(addr));
+    assert_eq!("unix:/run/app.sock".parse::<ListenAddr>().unwrap(),
+               ListenAddr::Unix(PathBuf::from("/run/app.sock")));
+    assert_eq!("unix:///run/app.sock".parse::<ListenAddr>().unwrap(),
+               ListenAddr::Unix(PathBuf::from("/run/app.sock")));
+    assert_eq!("unix:app.sock".parse::<ListenAddr>().unwrap(),
+               ListenAddr::Unix(PathBuf::from("app.sock")));
+    assert_eq!("./app.sock".parse::<ListenAddr>().unwrap(),
+               ListenAddr::Unix(PathBuf::from("./app.sock")));
+    assert_eq!("fd://3".parse::<ListenAddr>().unwrap(), ListenAddr::Fd(3));
+    assert_eq!("unix:@app".parse::<ListenAddr>().unwrap(),
+               ListenAddr::UnixAbstract(b"app".to_vec()));
+    assert_eq!("@app".parse::<ListenAddr>().unwrap(),
+               ListenAddr::UnixAbstract(b"app".to_vec()));
+
+    for bad in &["tcp://localhost:80", "unix:", "fd://x", "fd://-1", "80"] {
+        assert!(bad.parse::<ListenAddr>().is_err(), "{}", bad);
+    }
+    for s in &["tcp://[::1]:80", "unix:/run/app.sock", "unix:@app", "fd://3"]
+    {
+        assert_eq!(s.parse::<ListenAddr>().unwrap().to_string(), *s);
+    }
+}
+
+#[test]
+#[cfg(feature="serde")]
+fn test_serde() {
+    let addr: ListenAddr = "tcp://127.0.0.1:80".parse().unwrap();
+    let json = serde_json::to_string(&addr).unwrap();
+    assert_eq!(json, r#""tcp://127.0.0.1:80""#);
+    assert_eq!(serde_json::from_str::<ListenAddr>(&json).unwrap(), addr);
+    assert!(serde_json::from_str::<ListenAddr>(r#""nope""#).is_err());
+}
+
+async fn check_accept(listener: Listener, connect: SocketAddr) {
+    let mut set = ListenerSet::new();
+    set.add_listener(listener);
+    let client = task::spawn(async move {
+        let mut client = TcpStream::connect(connect).await.unwrap();
+        client.write_all(b"hello").await.unwrap();
+    });
+    let mut stream = set.next().await.unwrap().unwrap();
+    let mut buf = Vec::new();
+    stream.read_to_end(&mut buf).await.unwrap();
+    assert_eq!(buf, b"hello");
+    client.await;
+}
+
+#[test]
+fn test_bind_tcp() {
+    task::block_on(async {
+        let addr: ListenAddr = "tcp://127.0.0.1:0".parse().unwrap();
+        let listener = addr.bind().await.unwrap();
+        let local = match listener.local_addr().unwrap() {
+            ListenAddr::Tcp(addr) => addr,
+            other => panic!("unexpected address {}", other),
+        };
+        assert_ne!(local.port(), 0);
+        check_accept(listener, local).await;
+    })
+}
+
+#[test]
+#[cfg(unix)]
+fn test_bind_unix() {
+    let path = std::env::temp_dir()
+        .join(format!("async-listen-addr-{}.sock", std::process::id()));
+    task::block_on(async {
+        let addr = ListenAddr::Unix(path.clone());
+        let listener = addr.bind().await.unwrap();
+        assert_eq!(listener.local_addr().unwrap(), addr);
+        // the file isn't removed automatically
+        assert!(addr.bind().await.is_err());
+    });
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_bind_unix_abstract() {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixStream};
+    use std::time::Duration;
+    use async_listen::{ByteStream, ListenExt, PeerAddr};
+
+    let name = format!("async-listen-abstract-{}", std::process::id());
+    task::block_on(async {
+        let addr: ListenAddr = format!("@{}", name).parse().unwrap();
+        let listener = addr.bind().await.unwrap();
+        assert_eq!(listener.local_addr().unwrap(), addr);
+        // name is taken until the socket is closed
+        assert!(addr.bind().await.is_err());
+
+        let mut set = ListenerSet::new();
+        set.add_listener(listener);
+        let mut incoming = set.handle_errors(Duration::from_millis(1));
+        let server = SocketAddr::from_abstract_name(&name).unwrap();
+        let client = UnixStream::connect_addr(&server).unwrap();
+        let client = ByteStream::from(
+            async_std::os::unix::net::UnixStream::from(client));
+        assert_eq!(client.peer_addr().unwrap(),
+                   PeerAddr::UnixAbstract(name.clone().into_bytes()));
+        let stream = incoming.next().await.unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), PeerAddr::Unix(None));
+    });
+    // no file is left, the name is free once the listener is closed
+    task::block_on(async {
+        ListenAddr::UnixAbstract(name.into_bytes()).bind().await.unwrap();
+    });
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_bind_fd() {
+    use std::os::fd::AsRawFd;
+
+    task::block_on(async {
+        let std_listener = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap();
+        let local = std_listener.local_addr().unwrap();
+        let addr = ListenAddr::Fd(std_listener.as_raw_fd());
+        let listener = addr.bind().await.unwrap();
+        assert_eq!(listener.local_addr().unwrap(), ListenAddr::Tcp(local));
+        check_accept(listener, local).await;
+
+        // not a listening socket
+        let file = std::fs::File::open("/dev/null").unwrap();
+        let addr = ListenAddr::Fd(file.as_raw_fd());
+        assert!(addr.bind().await.is_err());
+    })
+}
+
+#[test]
+#[cfg(unix)]
+fn test_bind_all() {
+    use std::time::Duration;
+    use async_std::os::unix::net::UnixStream;
+    use async_listen::{ListenExt, PeerAddr, bind_all};
+
+    let path = std::env::temp_dir()
+        .join(format!("async-listen-bind-all-{}.sock", std::process::id()));
+    task::block_on(async {
+        let addrs = vec![
+            "tcp://127.0.0.1:0".parse().unwrap(),
+            ListenAddr::Unix(path.clone()),
+        ];
+        let (listeners, bound) = bind_all(&addrs).await.unwrap();
+        assert_eq!(bound.len(), 2);
+        assert_eq!(bound[1], addrs[1]);
+        let tcp = match bound[0] {
+            ListenAddr::Tcp(addr) => addr,
+            ref other => panic!("unexpected address {}", other),
+        };
+        assert_ne!(tcp.port(), 0);
+
+        let mut incoming = listeners.handle_errors(Duration::from_millis(1));
+        let c1 = TcpStream::connect(tcp).await.unwrap();
+        let c2 = UnixStream::connect(&path).await.unwrap();
+        let mut kinds = Vec::new();
+        for _ in 0..2 {
+            let stream = incoming.next().await.unwrap();
+            kinds.push(match stream.peer_addr().unwrap() {
+                PeerAddr::Tcp(_) => "tcp",
+                PeerAddr::Unix(_) | PeerAddr::UnixAbstract(_) => "unix",
+            });
+        }
+        kinds.sort();
+        assert_eq!(kinds, vec!["tcp", "unix"]);
+        drop((c1, c2));
+
+        // second instance fails on the TCP port, and doesn't leave the
+        // socket file behind
+        let path2 = path.with_extension("2.sock");
+        let addrs = vec![
+            ListenAddr::Unix(path2.clone()),
+            bound[0].clone(),
+        ];
+        let err = bind_all(&addrs).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AddrInUse);
+        assert_eq!(err.address(), Some(&PeerAddr::Tcp(tcp)));
+        assert!(!path2.exists());
+    });
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_upgrade() {
+    use async_listen::upgrade::{export, Inherited};
+
+    let path = std::env::temp_dir()
+        .join(format!("async-listen-upgrade-{}.sock", std::process::id()));
+    task::block_on(async {
+        let tcp_addr: ListenAddr = "tcp://127.0.0.1:0".parse().unwrap();
+        let tcp = tcp_addr.bind().await.unwrap();
+        let tcp_addr = tcp.local_addr().unwrap();
+        let unix_addr = ListenAddr::Unix(path.clone());
+        let mut set = ListenerSet::new();
+        set.add_listener(tcp);
+        set.add_listener(unix_addr.bind().await.unwrap());
+
+        let exported = export(&set).unwrap();
+        assert_eq!(exported.split(';').count(), 2);
+        assert!(exported.contains(&format!(":{};", tcp_addr)), "{}", exported);
+
+        let mut inherited = Inherited::parse(&exported).unwrap();
+        assert_eq!(inherited.len(), 2);
+        let other: ListenAddr = "tcp://127.0.0.1:1".parse().unwrap();
+        assert!(inherited.take(&other).is_none());
+        let listener = inherited.take(&tcp_addr).unwrap();
+        assert_eq!(inherited.len(), 1);
+        let (addr, _) = inherited.into_listeners().pop().unwrap();
+        assert_eq!(addr, unix_addr);
+
+        // the old set stops accepting, the new listener gets connections
+        drop(set);
+        match tcp_addr {
+            ListenAddr::Tcp(addr) => check_accept(listener, addr).await,
+            _ => unreachable!(),
+        }
+
+        assert!(Inherited::parse("3").is_err());
+        assert!(Inherited::parse("").unwrap().is_empty());
+    });
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_bind_reuseport() {
+    use async_listen::bind_reuseport;
+
+    task::block_on(async {
+        let listeners = bind_reuseport("127.0.0.1:0".parse().unwrap(), 3)
+            .unwrap();
+        assert_eq!(listeners.len(), 3);
+        let addr = listeners[0].local_addr().unwrap();
+        assert_ne!(addr.port(), 0);
+        let mut set = ListenerSet::new();
+        for listener in listeners {
+            assert_eq!(listener.local_addr().unwrap(), addr);
+            set.add_tcp(listener);
+        }
+        for _ in 0..10 {
+            let _client = TcpStream::connect(addr).await.unwrap();
+            set.next().await.unwrap().unwrap();
+        }
+        // plain bind to the same address fails
+        assert!(std::net::TcpListener::bind(addr).is_err());
+    })
+}
+
+#[test]
+fn test_bind_dual_stack() {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use async_listen::bind_dual_stack;
+    use async_listen::filter::{AllowList, PeerPolicy};
+
+    task::block_on(async {
+        let (mut set, addrs) = bind_dual_stack(0).unwrap();
+        assert!(!addrs.is_empty() && addrs.len() <= 2);
+        let port = addrs[0].port();
+        assert_ne!(port, 0);
+        assert!(addrs.iter().all(|a| a.port() == port));
+        assert!(addrs.iter().all(|a| a.ip().is_unspecified()));
+
+        let localhost = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let _client = TcpStream::connect(localhost).await.unwrap();
+        let stream = set.next().await.unwrap().unwrap();
+        // IPv4 peer is either plain or mapped into IPv6 by dual-stack socket
+        let allow: AllowList = "127.0.0.1/32".parse().unwrap();
+        assert!(allow.allow(&stream.peer_addr().unwrap()));
+
+        if addrs.iter().any(|a| a.is_ipv6()) {
+            let ipv6 = SocketAddr::from((Ipv6Addr::LOCALHOST, port));
+            if let Ok(_client) = TcpStream::connect(ipv6).await {
+                let stream = set.next().await.unwrap().unwrap();
+                let allow: AllowList = "::1".parse().unwrap();
+                assert!(allow.allow(&stream.peer_addr().unwrap()));
+            }
+        }
+    })
+}
+
+#[test]
+#[cfg(feature="socket2")]
+fn test_listener_builder() {
+    use std::time::Duration;
+    use async_listen::{ByteStream, ListenerBuilder};
+
+    task::block_on(async {
+        let mut builder = ListenerBuilder::new();
+        builder.keepalive(Duration::from_secs(60))
+            .recv_buffer_size(128 << 10)
+            .backlog(16);
+        #[cfg(target_os="linux")]
+        builder.reuse_port(true);
+        let listener = builder.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert_ne!(addr.port(), 0);
+        #[cfg(target_os="linux")]
+        builder.bind(addr).unwrap();
+
+        let _client = TcpStream::connect(addr).await.unwrap();
+        let (conn, _) = listener.accept().await.unwrap();
+        let conn = ByteStream::from(conn);
+        // keepalive is inherited by accepted connections
+        if cfg!(target_os="linux") {
+            assert!(conn.with_socket(|s| s.keepalive()).unwrap());
+            assert_eq!(conn.with_socket(|s| s.keepalive_time()).unwrap(),
+                       Duration::from_secs(60));
+        }
+
+        let mut v6 = ListenerBuilder::new();
+        v6.only_v6(true);
+        if let Ok(listener) = v6.bind("[::]:0".parse().unwrap()) {
+            let port = listener.local_addr().unwrap().port();
+            let v4 = SocketAddr::from(([127, 0, 0, 1], port));
+            assert!(TcpStream::connect(v4).await.is_err());
+        }
+    })
+}
@@ -1,9 +1,11 @@
 use std::io;
+use std::time::Duration;
 
 use async_std::stream::{from_iter, Stream, StreamExt};
 use async_std::task;
 
-use async_listen::{ListenExt, error_hint};
+use async_listen::{ListenExt, Backoff, ErrorAction, PauseEvent, error_hint};
+use async_listen::{register_hint, bind_error_hint, HintKind};
 
 fn collect<S: Stream + Unpin>(mut stream: S) -> Vec<S::Item> {
     task::block_on(async {
@@ -49,8 +51,562 @@ fn test_hint() {
         "Error: Too many open files in system (os error 23). \
          Increase system open file limit \
          https://bit.ly/async-err#ENFILE");
-    let e = io::ErrorKind::Other.into();
+    let e = io::Error::from_raw_os_error(98);
+    assert_eq!(
+        format!("Error: {}. {}", e, error_hint(&e)),
+        "Error: Address already in use (os error 98). \
+         Make sure no other process is listening on the same address \
+         https://bit.ly/async-err#EADDRINUSE");
+    let e = io::Error::from_raw_os_error(105);
+    assert_eq!(
+        format!("Error: {}. {}", e, error_hint(&e)),
+        "Error: No buffer space available (os error 105). \
+         Increase network buffer memory limits \
+         https://bit.ly/async-err#ENOBUFS");
+    let e = io::Error::from_raw_os_error(12);
+    assert_eq!(error_hint(&e).link_hash(), "ENOMEM");
+    assert_eq!(error_hint(&e).kind(), Some(HintKind::Enomem));
+    let e = io::Error::from_raw_os_error(99);
+    assert_eq!(
+        format!("Error: {}. {}", e, error_hint(&e)),
+        "Error: Cannot assign requested address (os error 99). \
+         Bind to an address of existing network interface \
+         or to 0.0.0.0 / [::] \
+         https://bit.ly/async-err#EADDRNOTAVAIL");
+    let e = io::Error::from_raw_os_error(1);
+    assert_eq!(
+        format!("Error: {}. {}", e, error_hint(&e)),
+        "Error: Operation not permitted (os error 1). \
+         Check firewall and seccomp rules \
+         https://bit.ly/async-err#EPERM");
+    let e = io::Error::from_raw_os_error(71);
+    assert_eq!(error_hint(&e).kind(), Some(HintKind::Eproto));
+    assert_eq!(error_hint(&e).link_hash(), "EPROTO");
+    let e = io::Error::other("other os error");
     assert_eq!(
         format!("Error: {}. {}", e, error_hint(&e)),
         "Error: other os error. ");
 }
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_transient_codes() {
+    use async_listen::is_transient_error;
+
+    // EINTR, ECONNABORTED, ENETDOWN, ENETUNREACH, EHOSTUNREACH, EPROTO
+    for code in &[4, 103, 100, 101, 113, 71] {
+        let e = io::Error::from_raw_os_error(*code);
+        assert!(is_transient_error(&e), "{} must be transient", e);
+    }
+    // EMFILE, ENFILE, ENOBUFS
+    for code in &[24, 23, 105] {
+        let e = io::Error::from_raw_os_error(*code);
+        assert!(!is_transient_error(&e), "{} must not be transient", e);
+    }
+}
+
+#[test]
+#[cfg(any(target_os="macos", target_os="freebsd"))]
+fn test_transient_codes() {
+    use async_listen::is_transient_error;
+
+    // EINTR, ECONNABORTED, ENETDOWN, ENETUNREACH, EHOSTUNREACH
+    for code in &[4, 53, 50, 51, 65] {
+        let e = io::Error::from_raw_os_error(*code);
+        assert!(is_transient_error(&e), "{} must be transient", e);
+    }
+    // EMFILE, ENFILE
+    for code in &[24, 23] {
+        let e = io::Error::from_raw_os_error(*code);
+        assert!(!is_transient_error(&e), "{} must not be transient", e);
+    }
+}
+
+#[test]
+#[cfg(windows)]
+fn test_windows_codes() {
+    use async_listen::is_transient_error;
+
+    let e = io::Error::from_raw_os_error(10024);  // WSAEMFILE
+    assert_eq!(error_hint(&e).kind(), Some(HintKind::Emfile));
+    let e = io::Error::from_raw_os_error(10055);  // WSAENOBUFS
+    assert_eq!(error_hint(&e).kind(), Some(HintKind::Enobufs));
+    let e = io::Error::from_raw_os_error(10054);  // WSAECONNRESET
+    assert!(is_transient_error(&e));
+    let e = io::Error::from_raw_os_error(10050);  // WSAENETDOWN
+    assert!(is_transient_error(&e));
+    let e = io::Error::from_raw_os_error(10065);  // WSAEHOSTUNREACH
+    assert!(is_transient_error(&e));
+    assert!(error_hint(&e).is_empty());
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_bind_hint() {
+    let e = io::Error::from_raw_os_error(13);
+    assert!(error_hint(&e).is_empty());
+    assert_eq!(
+        format!("Error: {}. {}", e, bind_error_hint(&e)),
+        "Error: Permission denied (os error 13). \
+         Use port 1024 or above, or allow binding privileged ports \
+         https://bit.ly/async-err#EACCES");
+    let e = io::Error::from_raw_os_error(2);
+    assert_eq!(bind_error_hint(&e).kind(), Some(HintKind::Enoent));
+    let e = io::Error::from_raw_os_error(98);
+    assert_eq!(bind_error_hint(&e).kind(), Some(HintKind::Eaddrinuse));
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_unix_bind_hint() {
+    use async_listen::{unix_bind_error_hint, ListenerError, ErrorPhase};
+    use async_listen::PeerAddr;
+
+    let e = io::Error::from_raw_os_error(98);
+    assert_eq!(
+        format!("Error: {}. {}", e, unix_bind_error_hint(&e)),
+        "Error: Address already in use (os error 98). \
+         Remove stale socket file if no other process is listening on it \
+         https://bit.ly/async-err#UNIX-EADDRINUSE");
+    let e = io::Error::from_raw_os_error(13);
+    assert_eq!(unix_bind_error_hint(&e).kind(), Some(HintKind::UnixEacces));
+    let e = io::Error::from_raw_os_error(2);
+    assert_eq!(unix_bind_error_hint(&e).kind(), Some(HintKind::Enoent));
+
+    let e = io::Error::from_raw_os_error(98);
+    let err = ListenerError::new(ErrorPhase::Bind, e)
+        .with_address(PeerAddr::Unix(Some("/run/app.sock".into())));
+    assert_eq!(err.hint().kind(), Some(HintKind::UnixEaddrinuse));
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_severity() {
+    use async_listen::Severity;
+
+    let e = io::Error::from_raw_os_error(23);  // ENFILE
+    assert_eq!(error_hint(&e).severity(), Severity::Critical);
+    let e = io::Error::from_raw_os_error(1);  // EPERM
+    assert_eq!(error_hint(&e).severity(), Severity::Warning);
+    let e = io::Error::from_raw_os_error(103);  // ECONNABORTED
+    assert_eq!(error_hint(&e).severity(), Severity::Info);
+    let e = io::Error::from_raw_os_error(2);  // ENOENT
+    assert_eq!(error_hint(&e).severity(), Severity::Warning);
+    assert_eq!(bind_error_hint(&e).severity(), Severity::Critical);
+    assert!(Severity::Critical > Severity::Warning);
+    assert_eq!(Severity::Info.to_string(), "info");
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_hint_fields() {
+    let e = io::Error::from_raw_os_error(24);
+    let hint = error_hint(&e);
+    assert_eq!(hint.code(), Some(24));
+    assert_eq!(hint.link(), "https://bit.ly/async-err#EMFILE");
+    let e = io::ErrorKind::Other.into();
+    assert_eq!(error_hint(&e).code(), None);
+    assert_eq!(error_hint(&e).link(), "");
+}
+
+#[test]
+#[cfg(all(feature="serde", target_os="linux"))]
+fn test_hint_serde() {
+    use serde_json::json;
+
+    let e = io::Error::from_raw_os_error(24);
+    assert_eq!(serde_json::to_value(error_hint(&e)).unwrap(), json!({
+        "code": 24,
+        "hint_text": "Increase per-process open file limit",
+        "link": "https://bit.ly/async-err#EMFILE",
+        "severity": "critical",
+    }));
+    let e = io::ErrorKind::ConnectionReset.into();
+    assert_eq!(serde_json::to_value(error_hint(&e)).unwrap(), json!({
+        "code": null,
+        "hint_text": null,
+        "link": null,
+        "severity": "info",
+    }));
+}
+
+#[test]
+fn test_register_hint() {
+    let e = io::Error::from_raw_os_error(4242);
+    assert!(error_hint(&e).is_empty());
+    register_hint(4242, "Restart the frobnicator",
+                  "https://wiki.example.org/frob");
+    let hint = error_hint(&e);
+    assert!(!hint.is_empty());
+    assert_eq!(hint.to_string(),
+               "Restart the frobnicator https://wiki.example.org/frob");
+    assert_eq!(hint.link_hash(), "");
+    register_hint(4242, "Call the frobnicator team",
+                  "https://wiki.example.org/runbook#frob");
+    assert_eq!(error_hint(&e).hint_text(), "Call the frobnicator team");
+    assert_eq!(error_hint(&e).link_hash(), "frob");
+    assert_eq!(error_hint(&e).kind(), None);
+
+    let e = io::Error::new(io::ErrorKind::TimedOut, "backend timed out");
+    register_hint(io::ErrorKind::TimedOut, "Check the backend",
+                  "https://wiki.example.org/backend");
+    assert_eq!(error_hint(&e).hint_text(), "Check the backend");
+
+    // owned strings, i.e. from a config file
+    let e = io::Error::from_raw_os_error(4243);
+    register_hint(4243, format!("Check {}", "the queue"),
+                  String::from("https://wiki.example.org/queue#full"));
+    assert_eq!(error_hint(&e).to_string(),
+               "Check the queue https://wiki.example.org/queue#full");
+    assert_eq!(error_hint(&e).link_hash(), "full");
+
+    // built-in hints take precedence
+    register_hint(24, "Custom", "https://example.org");
+    let e = io::Error::from_raw_os_error(24);
+    assert_eq!(error_hint(&e).link_hash(), "EMFILE");
+    assert_eq!(error_hint(&e).kind(), Some(HintKind::Emfile));
+}
+
+#[test]
+#[cfg(target_os="linux")]
+fn test_listener_error() {
+    use std::error::Error;
+    use async_listen::{ListenerError, ErrorPhase, PeerAddr};
+
+    let addr: PeerAddr = "127.0.0.1:80".parse().unwrap();
+    let e = io::Error::from_raw_os_error(13);
+    let err = ListenerError::new(ErrorPhase::Bind, e)
+        .with_label("public")
+        .with_address(addr.clone());
+    assert_eq!(err.phase(), ErrorPhase::Bind);
+    assert_eq!(err.label(), Some("public"));
+    assert_eq!(err.address(), Some(&addr));
+    assert_eq!(err.kind(), io::ErrorKind::PermissionDenied);
+    assert_eq!(err.hint().kind(), Some(HintKind::Eacces));
+    assert_eq!(err.source().unwrap().to_string(),
+        "Permission denied (os error 13)");
+    assert_eq!(err.to_string(),
+        "bind error on public (127.0.0.1:80): \
+         Permission denied (os error 13). \
+         Use port 1024 or above, or allow binding privileged ports \
+         https://bit.ly/async-err#EACCES");
+
+    // same code isn't a bind hint when accepting
+    let e = io::Error::from_raw_os_error(13);
+    let err = ListenerError::new(ErrorPhase::Accept, e);
+    assert_eq!(err.label(), None);
+    assert!(err.hint().is_empty());
+    assert_eq!(err.to_string(),
+        "accept error: Permission denied (os error 13)");
+    let e: io::Error = err.into();
+    assert_eq!(e.kind(), io::ErrorKind::PermissionDenied);
+}
+
+#[test]
+fn test_backoff() {
+    let ms = Duration::from_millis;
+    let backoff = Backoff::exponential(ms(10), ms(100)).jitter(0.0);
+    assert_eq!(backoff.delay(1, 0.5), ms(10));
+    assert_eq!(backoff.delay(2, 0.5), ms(20));
+    assert_eq!(backoff.delay(4, 0.5), ms(80));
+    assert_eq!(backoff.delay(5, 0.5), ms(100));
+    assert_eq!(backoff.delay(1000, 0.5), ms(100));
+    let backoff = backoff.jitter(0.5);
+    assert_eq!(backoff.delay(1, 0.0), ms(5));
+    assert_eq!(backoff.delay(1, 1.0), ms(15));
+    assert_eq!(backoff.delay(10, 1.0), ms(100));
+    assert_eq!(Backoff::from(ms(500)).delay(7, 0.9), ms(500));
+    let backoff = Backoff::exponential(ms(10), Duration::MAX);
+    assert_eq!(backoff.delay(u32::MAX, 1.0), Duration::MAX);
+    assert_eq!(backoff.delay(u32::MAX, f64::NAN), Duration::MAX);
+}
+
+#[test]
+fn test_error_policy() {
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::ConnectionReset.into()),
+        Ok(2),
+        Err(io::ErrorKind::Other.into()),
+        Ok(3),
+        Err(io::ErrorKind::InvalidInput.into()),
+        Ok(4),
+    ]);
+    let mut errors = 0;
+    let stream = s.handle_errors_with(|e: &io::Error| {
+        errors += 1;
+        match e.kind() {
+            io::ErrorKind::ConnectionReset => ErrorAction::Ignore,
+            io::ErrorKind::InvalidInput => ErrorAction::Stop,
+            _ => ErrorAction::Sleep(Duration::from_millis(10)),
+        }
+    });
+    assert_eq!(collect(stream), vec![1, 2, 3]);
+    assert_eq!(errors, 3);
+}
+
+#[test]
+fn test_transient_policy() {
+    use async_listen::is_transient_error;
+
+    fn is_transient(e: &io::Error) -> bool {
+        is_transient_error(e) || e.kind() == io::ErrorKind::InvalidInput
+    }
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::InvalidInput.into()),
+        Ok(2),
+        Err(io::ErrorKind::Other.into()),
+        Ok(3),
+    ]);
+    let mut logged = Vec::new();
+    let stream = s.log_warnings(|e| logged.push(e.kind()))
+        .transient_policy(is_transient);
+    assert_eq!(collect(stream).len(), 5);
+    assert_eq!(logged, vec![io::ErrorKind::Other]);
+
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::Other.into()),
+        Ok(2),
+        Err(io::ErrorKind::InvalidInput.into()),
+        Ok(3),
+    ]);
+    let stream = s.handle_errors(Duration::from_millis(1))
+        .transient_policy(|e: &io::Error| e.kind() == io::ErrorKind::Other)
+        .give_up_after(1);
+    assert_eq!(collect(stream), vec![1, 2]);
+}
+
+#[test]
+fn test_on_burst() {
+    use std::sync::{Arc, Mutex};
+
+    let mut errors = vec![Ok(1u32)];
+    for _ in 0..10 {
+        errors.push(Err(io::ErrorKind::ConnectionReset.into()));
+    }
+    errors.push(Err(io::ErrorKind::Other.into()));
+    errors.push(Ok(2));
+    let bursts = Arc::new(Mutex::new(Vec::new()));
+    let bursts2 = bursts.clone();
+    let stream = from_iter(errors)
+        .log_warnings(|_| {})
+        .on_burst(5, Duration::from_secs(10), move |e, n| {
+            bursts2.lock().unwrap().push((e.kind(), n));
+        });
+    assert_eq!(collect(stream).len(), 13);
+    // reported once per burst
+    assert_eq!(*bursts.lock().unwrap(),
+               vec![(io::ErrorKind::ConnectionReset, 5)]);
+
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::ConnectionAborted.into()),
+        Err(io::ErrorKind::ConnectionAborted.into()),
+        Ok(2),
+    ]);
+    let bursts = Arc::new(Mutex::new(0));
+    let bursts2 = bursts.clone();
+    let stream = s.handle_errors(Duration::from_millis(1))
+        .on_burst(2, Duration::from_secs(10), move |_, _| {
+            *bursts2.lock().unwrap() += 1;
+        });
+    assert_eq!(collect(stream), vec![1, 2]);
+    assert_eq!(*bursts.lock().unwrap(), 1);
+}
+
+#[test]
+fn test_give_up() {
+    use std::sync::{Arc, Mutex};
+
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::Other.into()),
+        Ok(2),
+        Err(io::ErrorKind::Other.into()),
+        Err(io::ErrorKind::ConnectionReset.into()),
+        Err(io::ErrorKind::InvalidInput.into()),
+        Ok(3),
+    ]);
+    let fatal = Arc::new(Mutex::new(None));
+    let fatal2 = fatal.clone();
+    let stream = s.handle_errors(Duration::from_millis(1))
+        .give_up_after(2)
+        .on_fatal(move |e| *fatal2.lock().unwrap() = Some(e.kind()));
+    assert_eq!(collect(stream), vec![1, 2]);
+    assert_eq!(*fatal.lock().unwrap(), Some(io::ErrorKind::InvalidInput));
+
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::Other.into()),
+        Err(io::ErrorKind::Other.into()),
+        Err(io::ErrorKind::Other.into()),
+        Ok(2),
+    ]);
+    let stream = s.handle_errors(Duration::from_millis(20))
+        .give_up_after_time(Duration::from_millis(30));
+    assert_eq!(collect(stream), vec![1]);
+}
+
+#[test]
+fn test_accessors() {
+    use std::pin::Pin;
+
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::Other.into()),
+        Ok(2),
+    ]);
+    let mut stream = s.handle_errors(Duration::from_millis(1));
+    assert_eq!(stream.size_hint(), (0, stream.get_ref().size_hint().1));
+    let _ = Pin::new(&mut stream).get_pin_mut();
+    assert_eq!(collect(stream), vec![1, 2]);
+
+    let s = from_iter(vec![Err(io::ErrorKind::Other.into()), Ok(1u32)]);
+    let mut stream = s.handle_errors_with(|_: &io::Error| ErrorAction::Stop);
+    assert_eq!(task::block_on(stream.next()), None);
+    assert_eq!(stream.size_hint(), (0, Some(0)));
+}
+
+#[test]
+fn test_error_stats() {
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::ConnectionReset.into()),
+        Err(io::ErrorKind::Other.into()),
+        Err(io::ErrorKind::ConnectionAborted.into()),
+        Err(io::Error::from_raw_os_error(24)),
+        Ok(2),
+    ]);
+    let stream = s.handle_errors(Duration::from_millis(1));
+    let stats = stream.error_stats();
+    assert_eq!(stats.non_transient_errors(), 0);
+    assert!(stats.last_error().is_none());
+    assert_eq!(collect(stream), vec![1, 2]);
+    assert_eq!(stats.transient_errors(), 2);
+    assert_eq!(stats.non_transient_errors(), 2);
+    assert_eq!(stats.last_error().unwrap().raw_os_error(), Some(24));
+}
+
+#[test]
+fn test_on_pause() {
+    use std::sync::{Arc, Mutex};
+
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::Other.into()),
+        Err(io::ErrorKind::ConnectionReset.into()),
+        Ok(2),
+    ]);
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let events2 = events.clone();
+    let stream = s.handle_errors(Duration::from_millis(10))
+        .on_pause(move |ev, e, dur| {
+            events2.lock().unwrap().push((ev, e.kind(), dur))
+        });
+    assert_eq!(collect(stream), vec![1, 2]);
+    assert_eq!(*events.lock().unwrap(), vec![
+        (PauseEvent::Started, io::ErrorKind::Other, Duration::from_millis(10)),
+        (PauseEvent::Finished, io::ErrorKind::Other, Duration::from_millis(10)),
+    ]);
+}
+
+#[test]
+fn test_split_errors() {
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::Other.into()),
+        Err(io::ErrorKind::ConnectionReset.into()),
+        Ok(2),
+        Err(io::ErrorKind::InvalidInput.into()),
+        Err(io::ErrorKind::PermissionDenied.into()),
+        Ok(3),
+    ]);
+    let (stream, errors) = s.handle_errors(Duration::from_millis(1))
+        .split_errors(1);
+    assert_eq!(collect(stream), vec![1, 2, 3]);
+    let errors: Vec<_> = collect(errors).iter().map(|e| e.kind()).collect();
+    assert_eq!(errors, vec![io::ErrorKind::Other]);
+
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::Other.into()),
+        Err(io::ErrorKind::InvalidInput.into()),
+        Ok(2),
+    ]);
+    let (stream, errors) = s.handle_errors(Duration::from_millis(1))
+        .give_up_after(2)
+        .split_errors(10);
+    let consumer = task::spawn(async move {
+        let mut kinds = Vec::new();
+        let mut errors = errors;
+        while let Some(e) = errors.next().await {
+            kinds.push(e.kind());
+        }
+        kinds
+    });
+    assert_eq!(collect(stream), vec![1]);
+    assert_eq!(task::block_on(consumer), vec![
+        io::ErrorKind::Other,
+        io::ErrorKind::InvalidInput,
+    ]);
+}
+
+#[test]
+fn test_inject_faults() {
+    use std::time::Instant;
+    use async_listen::Faults;
+
+    let started = Instant::now();
+    let s = from_iter(vec![Ok::<_, io::Error>(1u32), Ok(2), Ok(3), Ok(4)])
+        .inject_faults(Faults::new()
+            .delay(Duration::from_millis(5))
+            .os_error_every(3, 24));
+    let items = collect(s).into_iter()
+        .map(|r| r.map_err(|e| e.raw_os_error()))
+        .collect::<Vec<_>>();
+    assert_eq!(items, vec![
+        Ok(1), Ok(2), Err(Some(24)), Ok(3), Ok(4), Err(Some(24)),
+    ]);
+    assert!(started.elapsed() >= Duration::from_millis(30));
+}
+
+#[test]
+fn test_labeled() {
+    use async_listen::current_label;
+
+    let mut labels = Vec::new();
+    let s = from_iter(vec![Ok(1u32), Err(io::ErrorKind::Other.into())])
+        .log_warnings(|_| labels.push(current_label().map(|l| l.to_string())))
+        .labeled("public-tcp");
+    assert_eq!(s.label(), "public-tcp");
+    assert_eq!(collect(s).len(), 2);
+    assert_eq!(labels, vec![Some("public-tcp".to_string())]);
+    assert_eq!(current_label(), None);
+}
+
+#[test]
+fn test_log_async() {
+    use async_std::channel;
+
+    let (tx, rx) = channel::bounded(10);
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::ConnectionReset.into()),
+        Err(io::Error::from_raw_os_error(24)),
+    ]);
+    assert_eq!(collect(s.log_warnings_async(tx)).len(), 3);
+    let err = task::block_on(rx.recv()).unwrap();
+    assert_eq!(err.raw_os_error(), Some(24));
+    assert!(rx.try_recv().is_err());
+
+    let (tx, rx) = channel::bounded(10);
+    let s = from_iter(vec![Err::<u32, _>(io::ErrorKind::Other.into())]);
+    let logger = move |e: io::Error| {
+        let tx = tx.clone();
+        async move { tx.send(e.kind()).await.unwrap(); }
+    };
+    assert_eq!(collect(s.log_warnings_async(logger)).len(), 1);
+    assert_eq!(task::block_on(rx.recv()).unwrap(), io::ErrorKind::Other);
+}
@@ -0,0 +1,145 @@
+use std::io;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use async_std::future::timeout;
+use async_std::io::{Cursor, Read, ReadExt, WriteExt};
+use async_std::net::{TcpListener, TcpStream};
+use async_std::stream::StreamExt;
+use async_std::task;
+
+use async_std::task::{Context, Poll};
+
+use async_listen::{ListenExt, HasPeerAddr, PeerAddr};
+use async_listen::proxy::ProxyHeader;
+
+struct CountReads<R> {
+    inner: R,
+    reads: usize,
+}
+
+impl<R: Read + Unpin> Read for CountReads<R> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context, buf: &mut [u8])
+        -> Poll<io::Result<usize>>
+    {
+        self.reads += 1;
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+fn read_v1(data: &[u8]) -> (ProxyHeader, usize, Vec<u8>) {
+    task::block_on(async {
+        let mut conn = CountReads { inner: Cursor::new(data), reads: 0 };
+        let header = ProxyHeader::read(&mut conn).await.unwrap();
+        let mut rest = Vec::new();
+        conn.inner.read_to_end(&mut rest).await.unwrap();
+        (header, conn.reads, rest)
+    })
+}
+
+#[test]
+fn test_proxy_protocol() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .proxy_protocol()
+            .header_timeout(Duration::from_millis(200));
+
+        let mut v1 = TcpStream::connect(addr).await.unwrap();
+        v1.write_all(b"PROXY TCP4 192.0.2.1 198.51.100.2 56324 443\r\nhello")
+            .await.unwrap();
+        let (header, mut conn) = incoming.next().await.unwrap();
+        assert_eq!(header.version(), 1);
+        assert_eq!(header.source(), Some("192.0.2.1:56324".parse().unwrap()));
+        assert_eq!(header.destination(),
+                   Some("198.51.100.2:443".parse().unwrap()));
+        assert_eq!((header, conn.clone()).get_peer_addr().unwrap(),
+                   PeerAddr::Tcp("192.0.2.1:56324".parse().unwrap()));
+        let mut buf = [0u8; 5];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        let mut v2 = TcpStream::connect(addr).await.unwrap();
+        let mut data = b"\r\n\r\n\0\r\nQUIT\n\x21\x21\x00\x24".to_vec();
+        let mut src = [0u8; 16];
+        src[15] = 1;
+        data.extend_from_slice(&src);
+        data.extend_from_slice(&[0u8; 16]);
+        data.extend_from_slice(&[0x1F, 0x90, 0x00, 0x50]);
+        data.extend_from_slice(b"data");
+        v2.write_all(&data).await.unwrap();
+        let (header, mut conn) = incoming.next().await.unwrap();
+        assert_eq!(header.version(), 2);
+        assert_eq!(header.source(), Some("[::1]:8080".parse().unwrap()));
+        assert_eq!(header.destination(), Some("[::]:80".parse().unwrap()));
+        let mut buf = [0u8; 4];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"data");
+
+        let mut local = TcpStream::connect(addr).await.unwrap();
+        local.write_all(b"\r\n\r\n\0\r\nQUIT\n\x20\x00\x00\x00").await.unwrap();
+        let (header, conn) = incoming.next().await.unwrap();
+        assert_eq!(header.source(), None);
+        assert_eq!((header, conn).get_peer_addr().unwrap(),
+                   PeerAddr::Tcp(local.local_addr().unwrap()));
+
+        // invalid and missing headers are dropped
+        let mut bad = TcpStream::connect(addr).await.unwrap();
+        bad.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        let _silent = TcpStream::connect(addr).await.unwrap();
+        assert!(timeout(Duration::from_millis(300), incoming.next())
+                .await.is_err());
+        assert_eq!(incoming.pending_headers(), 0);
+        // closed, possibly with reset because of unread data
+        assert!(matches!(bad.read(&mut [0u8; 1]).await, Ok(0) | Err(_)));
+    });
+}
+
+#[test]
+fn test_proxy_v1_exact() {
+    let (header, reads, rest) = read_v1(
+        b"PROXY TCP4 192.0.2.1 198.51.100.2 56324 443\r\nhello");
+    assert_eq!(header.source(), Some("192.0.2.1:56324".parse().unwrap()));
+    assert_eq!(rest, b"hello");
+    assert!(reads < 10, "{} reads", reads);
+
+    let (header, _, rest) = read_v1(b"PROXY TCP6 ::1 ::2 1 2\r\nx");
+    assert_eq!(header.destination(), Some("[::2]:2".parse().unwrap()));
+    assert_eq!(rest, b"x");
+
+    let (header, _, rest) = read_v1(b"PROXY UNKNOWN\r\ny");
+    assert_eq!(header.source(), None);
+    assert_eq!(rest, b"y");
+
+    let (header, _, rest) = read_v1(b"PROXY UNKNOWN ::1 ::2 1 2\r\nz");
+    assert_eq!(header.source(), None);
+    assert_eq!(rest, b"z");
+}
+
+#[test]
+fn test_proxy_concurrency_limit() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .proxy_protocol()
+            .header_timeout(Duration::from_millis(200))
+            .concurrency_limit(1);
+
+        let _silent = TcpStream::connect(addr).await.unwrap();
+        assert!(timeout(Duration::from_millis(50), incoming.next())
+                .await.is_err());
+        assert_eq!(incoming.pending_headers(), 1);
+
+        let started = Instant::now();
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_all(b"PROXY UNKNOWN\r\n").await.unwrap();
+        // the connection is accepted only after the silent one times out
+        let (header, _) = incoming.next().await.unwrap();
+        assert_eq!(header.version(), 1);
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    });
+}
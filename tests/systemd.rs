@@ -0,0 +1,44 @@
+// Environment variables are global, so this test lives in its own binary
+#![cfg(all(feature="systemd", target_os="linux"))]
+
+use std::env;
+use std::os::fd::AsRawFd;
+
+use async_listen::{systemd, Listener};
+
+#[test]
+fn test_listen_fds() {
+    env::remove_var("LISTEN_PID");
+    assert!(systemd::listeners().unwrap().is_empty());
+
+    // descriptors are passed starting from 3, which is usually the first
+    // free descriptor in a fresh test process
+    let tcp = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    if tcp.as_raw_fd() != 3 {
+        eprintln!("descriptor 3 is busy, skipping the test");
+        return;
+    }
+    env::set_var("LISTEN_FDS", "1");
+    env::set_var("LISTEN_PID", "1");  // another process
+    assert!(systemd::listeners().unwrap().is_empty());
+
+    env::set_var("LISTEN_PID", std::process::id().to_string());
+    env::set_var("LISTEN_FDNAMES", "web");
+    let listeners = systemd::named_listeners().unwrap();
+    assert_eq!(listeners.len(), 1);
+    assert_eq!(listeners[0].0, "web");
+    match &listeners[0].1 {
+        Listener::Tcp(listener) => {
+            assert_eq!(listener.local_addr().unwrap(),
+                       tcp.local_addr().unwrap());
+        }
+        other => panic!("unexpected listener {:?}", other),
+    }
+    assert_eq!(systemd::listener_set().unwrap().len(), 1);
+
+    env::remove_var("LISTEN_FDNAMES");
+    assert_eq!(systemd::named_listeners().unwrap()[0].0, "unknown");
+
+    env::set_var("LISTEN_FDS", "2");  // descriptor 4 is not passed
+    assert!(systemd::listeners().is_err());
+}
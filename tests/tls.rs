@@ -0,0 +1,158 @@
+#![cfg(feature="rustls")]
+
+use std::convert::TryFrom;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_std::io::{ReadExt, WriteExt};
+use async_std::net::{TcpListener, TcpStream};
+use async_std::stream::StreamExt;
+use async_std::task;
+use futures_rustls::TlsConnector;
+
+use async_listen::{ListenExt, backpressure};
+use async_listen::tls::{TlsAcceptor, rustls, pki_types};
+use pki_types::pem::PemObject;
+use pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+
+const CERT: &[u8] = include_bytes!("tls/cert.pem");
+const KEY: &[u8] = include_bytes!("tls/key.pem");
+
+fn acceptor() -> TlsAcceptor {
+    let certs = vec![CertificateDer::from_pem_slice(CERT).unwrap()];
+    let key = PrivateKeyDer::from_pem_slice(KEY).unwrap();
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .unwrap();
+    TlsAcceptor::from(Arc::new(config))
+}
+
+fn connector() -> TlsConnector {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(CertificateDer::from_pem_slice(CERT).unwrap()).unwrap();
+    let config = rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+#[test]
+fn test_tls() {
+    task::block_on(async {
+        let (sender, _) = backpressure::new(10);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .backpressure_wrapper_with(&sender)
+            .tls(acceptor())
+            .handshake_timeout(Duration::from_millis(200));
+
+        // a client which never does the handshake doesn't block others
+        let _stale = TcpStream::connect(addr).await.unwrap();
+        let client = task::spawn(async move {
+            let tcp = TcpStream::connect(addr).await.unwrap();
+            let name = ServerName::try_from("localhost").unwrap();
+            let mut tls = connector().connect(name, tcp).await.unwrap();
+            tls.write_all(b"ping").await.unwrap();
+            let mut buf = [0u8; 4];
+            tls.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"pong");
+        });
+
+        let mut conn = incoming.next().await.unwrap();
+        assert!(conn.is_tls());
+        assert!(conn.peer_addr().is_ok());
+        assert_eq!(conn.tls_server_name().as_deref(), Some("localhost"));
+        assert_eq!(conn.tls_alpn_protocol(), None);
+        assert!(conn.tls_protocol_version().is_some());
+        let mut buf = [0u8; 4];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+        conn.write_all(b"pong").await.unwrap();
+        conn.flush().await.unwrap();
+        client.await;
+
+        // stale connection is dropped after handshake timeout
+        task::sleep(Duration::from_millis(300)).await;
+        let _ = async_std::future::timeout(Duration::from_millis(10),
+            incoming.next()).await;
+        assert_eq!(incoming.pending_handshakes(), 0);
+        assert_eq!(sender.get_active_tokens(), 1);
+        drop(conn);
+        assert_eq!(sender.get_active_tokens(), 0);
+    });
+}
+
+#[test]
+fn test_tls_alpn() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let certs = vec![CertificateDer::from_pem_slice(CERT).unwrap()];
+        let key = PrivateKeyDer::from_pem_slice(KEY).unwrap();
+        let mut config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap();
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .tls(TlsAcceptor::from(Arc::new(config)));
+
+        let client = task::spawn(async move {
+            let mut roots = rustls::RootCertStore::empty();
+            roots.add(CertificateDer::from_pem_slice(CERT).unwrap()).unwrap();
+            let mut config = rustls::ClientConfig::builder()
+                .with_root_certificates(roots)
+                .with_no_client_auth();
+            config.alpn_protocols = vec![b"http/1.1".to_vec()];
+            let tcp = TcpStream::connect(addr).await.unwrap();
+            let name = ServerName::try_from("localhost").unwrap();
+            let mut tls = TlsConnector::from(Arc::new(config))
+                .connect(name, tcp).await.unwrap();
+            tls.write_all(b"x").await.unwrap();
+        });
+        let mut conn = incoming.next().await.unwrap();
+        let mut buf = [0u8; 1];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(conn.tls_alpn_protocol().as_deref(), Some(&b"http/1.1"[..]));
+        assert_eq!(conn.tls_protocol_version(),
+                   Some(rustls::ProtocolVersion::TLSv1_3));
+        client.await;
+    });
+}
+
+#[test]
+fn test_tls_concurrency_limit() {
+    task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut incoming = listener.incoming()
+            .handle_errors(Duration::from_millis(10))
+            .tls(acceptor())
+            .handshake_timeout(Duration::from_millis(200))
+            .concurrency_limit(1);
+
+        let _stale = TcpStream::connect(addr).await.unwrap();
+        let next = async_std::future::timeout(Duration::from_millis(50),
+            incoming.next()).await;
+        assert!(next.is_err());
+        assert_eq!(incoming.pending_handshakes(), 1);
+
+        let started = Instant::now();
+        let client = task::spawn(async move {
+            let tcp = TcpStream::connect(addr).await.unwrap();
+            let name = ServerName::try_from("localhost").unwrap();
+            let mut tls = connector().connect(name, tcp).await.unwrap();
+            tls.write_all(b"x").await.unwrap();
+        });
+        // the client is accepted only after stale handshake times out
+        let mut conn = incoming.next().await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        let mut buf = [0u8; 1];
+        conn.read_exact(&mut buf).await.unwrap();
+        client.await;
+    });
+}
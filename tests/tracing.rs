@@ -0,0 +1,98 @@
+#![cfg(feature="tracing")]
+
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use async_std::stream::{from_iter, Stream, StreamExt};
+use async_std::net::{TcpListener, TcpStream};
+use async_std::task;
+use tracing::{Event, Id, Metadata, Subscriber};
+use tracing::span::{Attributes, Record};
+
+use async_listen::{ListenExt, ByteStream};
+
+#[derive(Default)]
+struct Records {
+    next_id: AtomicU64,
+    spans: Mutex<Vec<String>>,
+    events: Mutex<Vec<String>>,
+}
+
+#[derive(Default, Clone)]
+struct Recorder(Arc<Records>);
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata) -> bool { true }
+    fn new_span(&self, span: &Attributes) -> Id {
+        self.0.spans.lock().unwrap().push(format!("{:?}", span.values()));
+        Id::from_u64(self.0.next_id.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+    fn record(&self, _: &Id, _: &Record) {}
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+    fn event(&self, event: &Event) {
+        self.0.events.lock().unwrap().push(format!("{} {:?}",
+            event.metadata().level(), event.fields().collect::<Vec<_>>()));
+    }
+    fn enter(&self, _: &Id) {}
+    fn exit(&self, _: &Id) {}
+}
+
+fn collect<S: Stream + Unpin>(mut stream: S) -> Vec<S::Item> {
+    task::block_on(async {
+        let mut result = Vec::new();
+        while let Some(item) = stream.next().await {
+            result.push(item);
+        }
+        result
+    })
+}
+
+#[test]
+fn test_trace() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+    let s = from_iter(vec![
+        Ok(1u32),
+        Err(io::ErrorKind::ConnectionReset.into()),
+        Err(io::ErrorKind::Other.into()),
+        Ok(2),
+    ]);
+    assert_eq!(collect(s.trace_errors()).len(), 4);
+    let events = recorder.0.events.lock().unwrap().clone();
+    assert_eq!(events.len(), 2);
+    assert!(events[0].starts_with("DEBUG"));
+    assert!(events[1].starts_with("WARN"));
+
+    let (a, _b) = task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let client = TcpStream::connect(listener.local_addr()?).await?;
+        let (server, _) = listener.accept().await?;
+        Ok::<_, io::Error>((server, client))
+    }).unwrap();
+    let conns = vec![ByteStream::new_tcp_detached(a)];
+    let result = collect(from_iter(conns).trace_connections());
+    assert_eq!(result.len(), 1);
+    let spans = recorder.0.spans.lock().unwrap().clone();
+    assert_eq!(spans.len(), 1);
+    assert!(spans[0].contains("id: 1"), "{}", spans[0]);
+    assert!(spans[0].contains("peer: 127.0.0.1:"), "{}", spans[0]);
+    assert_eq!(recorder.0.events.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn test_trace_labeled() {
+    let recorder = Recorder::default();
+    let _guard = tracing::subscriber::set_default(recorder.clone());
+    let (a, _b) = task::block_on(async {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let client = TcpStream::connect(listener.local_addr()?).await?;
+        let (server, _) = listener.accept().await?;
+        Ok::<_, io::Error>((server, client))
+    }).unwrap();
+    let conns = vec![ByteStream::new_tcp_detached(a)];
+    let result = collect(from_iter(conns).trace_connections().labeled("web"));
+    assert_eq!(result.len(), 1);
+    let spans = recorder.0.spans.lock().unwrap().clone();
+    assert!(spans[0].contains("listener: \"web\""), "{}", spans[0]);
+}